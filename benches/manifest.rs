@@ -0,0 +1,159 @@
+//! Benchmarks of editing manifests: opening, inserting, removing and writing dependencies, on a
+//! small and a medium manifest from the test fixtures and on a generated one with 5000
+//! dependencies.
+//!
+//! Run them with `cargo bench --bench manifest`. Criterion keeps the results of the last run in
+//! `target/criterion` and reports each benchmark that got slower since, so run them before and
+//! after a change to the manifest editing code.
+extern crate cargo_edit;
+#[macro_use]
+extern crate criterion;
+extern crate tempdir;
+
+use std::fs;
+use std::path::PathBuf;
+
+use cargo_edit::{DepKind, DepTable, Dependency, Manifest};
+use criterion::Criterion;
+use tempdir::TempDir;
+
+/// Number of dependencies of the generated manifest
+const GENERATED_DEPENDENCIES: usize = 5000;
+
+/// A manifest to benchmark, and a dependency of it to remove
+struct Input {
+    name: &'static str,
+    contents: String,
+    existing: String,
+}
+
+/// Generate a manifest with `count` dependencies, spread over the sections and written in the
+/// different styles: plain requirements, inline tables, and a few per target.
+fn generate_manifest(count: usize) -> String {
+    let sections = [
+        "[dependencies]",
+        "[dev-dependencies]",
+        "[build-dependencies]",
+        "[target.'cfg(unix)'.dependencies]",
+    ];
+    let mut entries: Vec<Vec<String>> = vec![Vec::new(); sections.len()];
+    for i in 0..count {
+        let name = format!("crate-{:04}", i);
+        let entry = match i % 3 {
+            0 => format!("{} = \"{}.{}\"", name, i % 7, i % 11),
+            1 => format!("{} = {{ version = \"0.{}\", default-features = false }}", name, i % 13),
+            _ => format!(
+                "{} = {{ version = \"1.{}\", features = [\"std\", \"derive\"] }}",
+                name,
+                i % 5
+            ),
+        };
+        // Most dependencies are normal ones.
+        let section = match i % 20 {
+            9 => 2,
+            19 => 3,
+            n if n % 10 < 7 => 0,
+            _ => 1,
+        };
+        entries[section].push(entry);
+    }
+
+    let mut manifest = String::from("[package]\nname = \"generated\"\nversion = \"0.1.0\"\n");
+    for (section, entries) in sections.iter().zip(entries) {
+        manifest.push_str(&format!("\n{}\n{}\n", section, entries.join("\n")));
+    }
+    manifest
+}
+
+fn inputs() -> Vec<Input> {
+    vec![
+        Input {
+            name: "small",
+            contents: include_str!("../tests/fixtures/rm/Cargo.toml.sample").to_owned(),
+            existing: "docopt".to_owned(),
+        },
+        Input {
+            name: "medium",
+            contents: include_str!("../tests/fixtures/upgrade/Cargo.toml.target").to_owned(),
+            existing: "docopt".to_owned(),
+        },
+        Input {
+            name: "generated",
+            contents: generate_manifest(GENERATED_DEPENDENCIES),
+            existing: format!("crate-{:04}", GENERATED_DEPENDENCIES / 2),
+        },
+    ]
+}
+
+/// Write the manifest of an input to a temporary directory, for the benchmarks that use the disk.
+fn write_input(dir: &TempDir, input: &Input) -> PathBuf {
+    let path = dir.path().join(format!("Cargo.toml.{}", input.name));
+    fs::write(&path, &input.contents).expect("temporary manifest is written");
+    path
+}
+
+fn parse(contents: &str) -> Manifest {
+    contents.parse().expect("benchmark manifest is valid")
+}
+
+fn open(c: &mut Criterion) {
+    let dir = TempDir::new("cargo-edit-bench").expect("temporary directory is created");
+    for input in inputs() {
+        let path = Some(write_input(&dir, &input));
+        c.bench_function(&format!("open {}", input.name), move |b| {
+            b.iter(|| Manifest::open(&path).expect("benchmark manifest is valid"))
+        });
+    }
+}
+
+fn insert(c: &mut Criterion) {
+    let section = DepTable::new().set_kind(DepKind::Development);
+    for input in inputs() {
+        let section = section.clone();
+        let contents = input.contents;
+        c.bench_function(&format!("insert {}", input.name), move |b| {
+            let dep = Dependency::new("new-crate").set_version("1.0");
+            b.iter_with_setup(
+                || parse(&contents),
+                |mut manifest| {
+                    manifest
+                        .insert_into_table(&section, &dep)
+                        .expect("dependency is inserted");
+                    manifest
+                },
+            )
+        });
+    }
+}
+
+fn remove(c: &mut Criterion) {
+    for input in inputs() {
+        let contents = input.contents;
+        let existing = input.existing;
+        c.bench_function(&format!("remove {}", input.name), move |b| {
+            b.iter_with_setup(
+                || parse(&contents),
+                |mut manifest| {
+                    manifest
+                        .remove_from_table(&DepTable::new(), &existing)
+                        .expect("dependency is removed");
+                    manifest
+                },
+            )
+        });
+    }
+}
+
+fn write(c: &mut Criterion) {
+    let dir = TempDir::new("cargo-edit-bench").expect("temporary directory is created");
+    for input in inputs() {
+        let path = Some(write_input(&dir, &input));
+        let manifest = parse(&input.contents);
+        c.bench_function(&format!("write {}", input.name), move |b| {
+            b.iter(|| manifest.write_to_path(&path).expect("manifest is written"))
+        });
+    }
+}
+
+criterion_group!(benches, open, insert, remove, write);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+//! Read arbitrary input as a crate specifier of `cargo add` (e.g. `serde+derive@1.0`, a git URL,
+//! `gh:owner/repo` or a path). None of the parts must panic, and a specifier with a version must
+//! read the same again when it is put back together from its parts. Specifiers are not resolved,
+//! as that needs the network or the file system.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate cargo_edit;
+
+use std::str;
+
+use cargo_edit::{expand_git_shorthand, CrateName};
+
+fuzz_target!(|data: &[u8]| {
+    let input = match str::from_utf8(data) {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+
+    let spec = CrateName::new(input);
+    let _ = spec.name();
+    let _ = spec.version_operator();
+    let _ = spec.is_url_or_path();
+    let _ = spec.git_url();
+    let features = spec.features();
+
+    if let Some(url) = expand_git_shorthand(input) {
+        assert_eq!(CrateName::new(&url).git_url(), Some(url.clone()));
+    }
+
+    let dep = match spec.parse_as_version() {
+        Ok(Some(dep)) => dep,
+        Ok(None) | Err(_) => return,
+    };
+    let version = dep.version().expect("a specifier with a version has one");
+    let mut rebuilt = dep.name.clone();
+    for feature in &features {
+        rebuilt.push('+');
+        rebuilt.push_str(feature);
+    }
+    rebuilt.push('@');
+    rebuilt.push_str(version);
+
+    let again = CrateName::new(&rebuilt)
+        .parse_as_version()
+        .expect("a rebuilt specifier is valid")
+        .expect("a rebuilt specifier has a version");
+    assert_eq!(again.name, dep.name);
+    assert_eq!(again.version(), Some(version));
+    assert_eq!(CrateName::new(&rebuilt).features(), features);
+});
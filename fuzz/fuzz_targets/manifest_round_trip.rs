@@ -0,0 +1,29 @@
+//! Parse arbitrary input as a manifest, like `Manifest::open` does with the contents of a file.
+//! Parsing must not panic, and a manifest that parses must be written back exactly as it was read
+//! (without its byte order mark and with `\n` line endings), and parse again to the same.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate cargo_edit;
+
+use std::str;
+
+use cargo_edit::Manifest;
+
+fuzz_target!(|data: &[u8]| {
+    let input = match str::from_utf8(data) {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+    let manifest: Manifest = match input.parse() {
+        Ok(manifest) => manifest,
+        Err(_) => return,
+    };
+
+    let written = manifest.to_string();
+    let expected = input.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    assert_eq!(written, expected, "the manifest is written as it was read");
+
+    let reparsed: Manifest = written.parse().expect("a written manifest parses again");
+    assert_eq!(reparsed.to_string(), written);
+});
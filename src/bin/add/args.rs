@@ -1,11 +1,30 @@
 //! Handle `cargo add` arguments
 
-use cargo_edit::Dependency;
-use cargo_edit::{get_latest_dependency, CrateName};
+use atty;
+use cargo_edit::{self, DepKind, DepTable, Dependency, GitReference};
+use cargo_edit::{checklist, expand_git_shorthand, find, find_local_source, find_lock_file,
+                 find_workspace_root, get_latest_dependencies_with_warnings,
+                 get_latest_dependency_with_max_age, get_latest_dependency_with_warnings,
+                 git_packages, latest_locked_version, parse_crate_file_name, prerelease_crates,
+                 read_lock_file, workspace_members, ChecklistItem, ConflictSide, CrateName,
+                 LocalSource, LockedVersions, Prereleases, SectionPlacement, WarningKind,
+                 Warnings, WorkspaceMember};
 use semver;
-use std::path::PathBuf;
+use serde_derive::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
 
-use errors::*;
+use super::errors::*;
+
+/// How to report the result of `cargo add`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum OutputFormat {
+    /// Human readable messages on stdout
+    Human,
+    /// A JSON document on stdout, messages on stderr
+    Json,
+}
 
 #[derive(Debug, Deserialize)]
 /// Docopts input args.
@@ -18,100 +37,769 @@ pub struct Args {
     pub flag_dev: bool,
     /// build-dependency
     pub flag_build: bool,
+    /// '--dev-crates'
+    pub flag_dev_crates: Option<String>,
+    /// '--build-crates'
+    pub flag_build_crates: Option<String>,
     /// Version
     pub flag_vers: Option<String>,
     /// Git repo Path
     pub flag_git: Option<String>,
+    /// Git branch
+    pub flag_branch: Option<String>,
+    /// Git tag
+    pub flag_tag: Option<String>,
+    /// Git revision
+    pub flag_rev: Option<String>,
+    /// Package of the git repository
+    pub flag_crate: Option<String>,
     /// Crate directory path
     pub flag_path: Option<PathBuf>,
+    /// '--canonicalize'
+    pub flag_canonicalize: bool,
+    /// '--path-to-crate'
+    pub flag_path_to_crate: Option<PathBuf>,
     /// Crate directory path
     pub flag_target: Option<String>,
     /// Optional dependency
     pub flag_optional: bool,
+    /// '--emulate-optional-dev'
+    pub flag_emulate_optional_dev: bool,
     /// `Cargo.toml` path
     pub flag_manifest_path: Option<PathBuf>,
+    /// '--members'
+    pub flag_members: Option<String>,
+    /// '--include-excluded'
+    pub flag_include_excluded: bool,
+    /// '--include-nested'
+    pub flag_include_nested: bool,
+    /// '--group'
+    pub flag_group: Option<String>,
     /// `--version`
     pub flag_version: bool,
+    /// `--json`
+    pub flag_json: bool,
     /// `---upgrade`
     pub flag_upgrade: Option<String>,
     /// '--fetch-prereleases'
     pub flag_allow_prerelease: bool,
+    /// '--prerelease'
+    pub flag_prerelease: Option<String>,
+    /// '--offline'
+    pub flag_offline: bool,
+    /// '--locked'
+    pub flag_locked: bool,
+    /// '--max-age'
+    pub flag_max_age: Option<u64>,
+    /// '--provenance'
+    pub flag_provenance: bool,
+    /// '--jobs'
+    pub flag_jobs: Option<usize>,
+    /// '--features'
+    pub flag_features: Option<String>,
+    /// '--no-default-features'
+    pub flag_no_default_features: bool,
+    /// '--no-verify-features'
+    pub flag_no_verify_features: bool,
+    /// '--interactive-features'
+    pub flag_interactive_features: bool,
     /// '--quiet'
     pub flag_quiet: bool,
+    /// '--verbose'
+    pub flag_verbose: bool,
+    /// '--bump-msrv'
+    pub flag_bump_msrv: bool,
+    /// '--compat-edition'
+    pub flag_compat_edition: bool,
+    /// '--follow-renames'
+    pub flag_follow_renames: bool,
+    /// '--explain'
+    pub flag_explain: bool,
+    /// '--dry-run'
+    pub flag_dry_run: bool,
+    /// '--output'
+    pub flag_output: OutputFormat,
+    /// '--section-placement'
+    pub flag_section_placement: Option<String>,
+    /// '--ours'
+    pub flag_ours: bool,
+    /// '--theirs'
+    pub flag_theirs: bool,
+    /// '--record'
+    pub flag_record: Option<PathBuf>,
+    /// '--reason'
+    pub flag_reason: Option<String>,
 }
 
 impl Args {
-    /// Get dependency section
-    pub fn get_section(&self) -> Vec<String> {
-        if self.flag_dev {
-            vec!["dev-dependencies".to_owned()]
+    /// Get dependency section, for the first target given with `--target` (see
+    /// `expand_targets` for the others)
+    pub fn get_section(&self) -> Result<DepTable> {
+        // With `--emulate-optional-dev`, the crate goes to the normal dependencies.
+        let dev = self.flag_dev && !self.flag_emulate_optional_dev;
+        if self.is_optional() && (dev || self.flag_build) {
+            return Err(Error::OptionalDevOrBuild { dev: dev });
+        }
+
+        let section = if dev {
+            DepTable::new().set_kind(DepKind::Development)
         } else if self.flag_build {
-            vec!["build-dependencies".to_owned()]
-        } else if let Some(ref target) = self.flag_target {
-            if target.is_empty() {
-                panic!("Target specification may not be empty");
-            }
-            vec![
-                "target".to_owned(),
-                target.clone(),
-                "dependencies".to_owned(),
-            ]
+            DepTable::new().set_kind(DepKind::Build)
         } else {
-            vec!["dependencies".to_owned()]
+            DepTable::new()
+        };
+
+        Ok(match self.get_targets()?.into_iter().next() {
+            Some(target) => section.set_target(target),
+            None => section,
+        })
+    }
+
+    /// Whether the dependencies are added as optional ones, with `--optional` or
+    /// `--emulate-optional-dev`.
+    pub fn is_optional(&self) -> bool {
+        self.flag_optional || self.flag_emulate_optional_dev
+    }
+
+    /// Get the targets given with `--target`, separated by commas outside of parentheses (e.g.
+    /// `cfg(unix), cfg(any(windows, target_os = "wasi"))`).
+    pub fn get_targets(&self) -> Result<Vec<String>> {
+        let targets = match self.flag_target {
+            Some(ref targets) => split_targets(targets),
+            None => return Ok(vec![]),
+        };
+        if targets.iter().any(|target| target.is_empty()) {
+            return Err(Error::EmptyTarget);
+        }
+        Ok(targets)
+    }
+
+    /// Repeat the dependencies passed as <crate> or <crates> (the first group of
+    /// `get_dependency_groups`) for each further target given with `--target`, right after them.
+    pub fn expand_targets(
+        &self,
+        groups: &[(DepTable, Vec<Dependency>)],
+    ) -> Result<Vec<(DepTable, Vec<Dependency>)>> {
+        let mut expanded = groups.to_vec();
+        if !self.has_crates() {
+            return Ok(expanded);
+        }
+        if let Some(&(ref section, ref deps)) = groups.first() {
+            for (i, target) in self.get_targets()?.into_iter().enumerate().skip(1) {
+                expanded.insert(i, (section.clone().set_target(target), deps.clone()));
+            }
+        }
+        Ok(expanded)
+    }
+
+    /// Whether dependencies are passed other than with `--dev-crates` and `--build-crates`: as
+    /// <crate> or <crates>, or with `--git` or `--path-to-crate`.
+    fn has_crates(&self) -> bool {
+        !self.arg_crate.is_empty()
+            || !self.arg_crates.is_empty()
+            || self.flag_git.is_some()
+            || self.flag_path_to_crate.is_some()
+    }
+
+    /// Get the dependencies to add, grouped by the section they go to: the ones passed as
+    /// <crate> or <crates>, then the ones passed with `--dev-crates` and `--build-crates`, which
+    /// may also be given on their own.
+    pub fn get_dependency_groups(
+        &self,
+        warnings: &mut Warnings,
+    ) -> Result<Vec<(DepTable, Vec<Dependency>)>> {
+        let mut groups = Vec::new();
+        if self.has_crates() {
+            groups.push((self.get_section()?, self.parse_dependencies(warnings)?));
+        }
+
+        let lists = [
+            (&self.flag_dev_crates, DepKind::Development),
+            (&self.flag_build_crates, DepKind::Build),
+        ];
+        for &(list, kind) in &lists {
+            if let Some(ref list) = *list {
+                let crate_names = split_list(list);
+                if !crate_names.is_empty() {
+                    let deps = self.parse_registry_crates(&crate_names, warnings)?;
+                    groups.push((DepTable::new().set_kind(kind), deps));
+                }
+            }
+        }
+
+        if groups.is_empty() {
+            return Err(Error::NoCrates);
         }
+        Ok(groups)
     }
 
     /// Build dependencies from arguments
-    pub fn parse_dependencies(&self) -> Result<Vec<Dependency>> {
+    pub fn parse_dependencies(&self, warnings: &mut Warnings) -> Result<Vec<Dependency>> {
         if !self.arg_crates.is_empty() {
-            return self.arg_crates
-                .iter()
-                .map(|crate_name| {
-                    Ok(
-                        if let Some(krate) = CrateName::new(crate_name).parse_as_version()? {
-                            krate
-                        } else {
-                            get_latest_dependency(crate_name, self.flag_allow_prerelease)?
-                        }.set_optional(self.flag_optional),
-                    )
+            if self.get_git_reference()?.is_some() {
+                return Err(Error::GitReferenceWithoutGit);
+            }
+            if self.flag_crate.is_some() {
+                return Err(Error::CrateWithoutGitRepository);
+            }
+
+            return Ok(self.parse_registry_crates(&self.arg_crates, warnings)?
+                .into_iter()
+                .map(|dep| {
+                    let inline = dep.features().map(<[String]>::to_vec).unwrap_or_default();
+                    dep.set_optional(self.is_optional())
+                        .set_default_features(!self.flag_no_default_features)
+                        .set_features(self.get_features_with(inline))
+                        .set_removed_features(self.get_removed_features())
                 })
-                .collect();
+                .collect());
         }
 
         let crate_name = CrateName::new(&self.arg_crate);
+        if self.flag_crate.is_some() && !self.arg_crate.is_empty() && crate_name.git_url().is_none()
+        {
+            return Err(Error::CrateWithoutGitRepository);
+        }
 
-        let dependency = if let Some(krate) = crate_name.parse_as_version()? {
+        let dependency = if let Some(ref crate_file) = self.flag_path_to_crate {
+            // `cargo add --path-to-crate <file>`
+            self.crate_file_dependency(crate_file, warnings)?
+        } else if self.arg_crate.is_empty() {
+            // `cargo add --git <uri> --crate <member>`
+            let repo = self.flag_git.as_ref().ok_or(Error::NoCrates)?;
+            let url = expand_git_shorthand(repo).unwrap_or_else(|| repo.clone());
+            let dependency = self.git_dependency(&CrateName::new(&url), &url)?;
+            self.set_flag_version(dependency)?
+        } else if let Some(krate) = crate_name.parse_as_version()? {
             krate
         } else if !crate_name.is_url_or_path() {
-            let dependency = Dependency::new(&self.arg_crate);
-
-            if let Some(ref version) = self.flag_vers {
-                semver::VersionReq::parse(version)
-                    .chain_err(|| "Invalid dependency version requirement")?;
-                dependency.set_version(version)
-            } else if let Some(ref repo) = self.flag_git {
-                dependency.set_git(repo)
+            // Only `--git` makes a name a git dependency, so fail before looking the name up.
+            if self.flag_git.is_none() && self.get_git_reference()?.is_some() {
+                return Err(Error::GitReferenceWithoutGit);
+            }
+            let name = crate_name.name();
+            let dependency = Dependency::new(name);
+
+            let dependency = if let Some(ref repo) = self.flag_git {
+                dependency.set_git(&expand_git_shorthand(repo).unwrap_or_else(|| repo.clone()))
             } else if let Some(ref path) = self.flag_path {
-                dependency.set_path(path.to_str().unwrap())
-            } else {
-                let dep = get_latest_dependency(&self.arg_crate, self.flag_allow_prerelease)?;
+                dependency.set_path(&self.get_path(path)?)
+            } else if let Some(member) = self.get_workspace_members()?
+                .into_iter()
+                .find(|member| member.name == name)
+            {
+                self.member_dependency(&member)?
+            } else if self.flag_vers.is_none() {
+                let dep = if let Some((source, versions)) = self.get_local_versions()? {
+                    self.local_dependency(&source, &versions, name)?
+                } else if self.flag_offline {
+                    let locked = self.get_locked_versions()?;
+                    self.locked_dependency(&locked, name, warnings)?
+                } else {
+                    self.latest_dependency(name, warnings)?
+                };
+                let prefix = match crate_name.version_operator() {
+                    Some(operator) => operator,
+                    None => self.get_upgrade_prefix(warnings).unwrap_or(""),
+                };
                 let v = format!(
                     "{prefix}{version}",
-                    prefix = self.get_upgrade_prefix().unwrap_or(""),
-                    // If version is unavailable `get_latest_dependency` must have
-                    // returned `Err(FetchVersionError::GetVersion)`
-                    version = dep.version().unwrap_or_else(|| unreachable!())
+                    prefix = prefix,
+                    version = dep.version().ok_or(cargo_edit::Error::NoVersionsAvailable)?
                 );
                 dep.set_version(&v)
-            }
+            } else {
+                dependency
+            };
+            self.set_flag_version(dependency)?
+        } else if let Some(url) = crate_name.git_url() {
+            let dependency = self.git_dependency(&crate_name, &url)?;
+            self.set_flag_version(dependency)?
         } else {
-            crate_name.parse_crate_name_from_uri()?
-        }.set_optional(self.flag_optional);
+            let dependency = crate_name.parse_crate_name_from_uri()?;
+            let path = match dependency.path() {
+                Some(path) => Some(self.get_path(Path::new(path))?),
+                None => None,
+            };
+            let dependency = match path {
+                Some(path) => dependency.set_path(&path),
+                None => dependency,
+            };
+            self.set_flag_version(dependency)?
+        };
+
+        let dependency = match self.get_git_reference()? {
+            Some(reference) => {
+                if dependency.git().is_none() {
+                    return Err(Error::GitReferenceWithoutGit);
+                }
+                dependency.set_git_reference(reference)
+            }
+            None => dependency,
+        }.set_optional(self.is_optional())
+            .set_default_features(!self.flag_no_default_features)
+            .set_features(self.get_features_with(crate_name.features()))
+            .set_removed_features(self.get_removed_features());
 
         Ok(vec![dependency])
     }
 
-    fn get_upgrade_prefix(&self) -> Option<&'static str> {
+    /// Build registry dependencies from crate names, which may carry a version (`foo@1.0`). The
+    /// crates without a version are looked up at once, and required with the operator after their
+    /// name (`foo@=`) or the one of `--upgrade`; other members of the workspace become path
+    /// dependencies.
+    fn parse_registry_crates(
+        &self,
+        crate_names: &[String],
+        warnings: &mut Warnings,
+    ) -> Result<Vec<Dependency>> {
+        let members = self.get_workspace_members()?;
+        let krates = crate_names
+            .iter()
+            .map(|crate_name| {
+                let crate_name = CrateName::new(crate_name);
+                match members.iter().find(|member| member.name == crate_name.name()) {
+                    Some(member) => self.member_dependency(member).map(Some),
+                    None => Ok(crate_name.parse_as_version()?),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let unversioned: Vec<_> = crate_names
+            .iter()
+            .zip(&krates)
+            .filter(|&(_, krate)| krate.is_none())
+            .map(|(crate_name, _)| CrateName::new(crate_name).name())
+            .collect();
+        let latest = if let Some((source, versions)) = self.get_local_versions()? {
+            unversioned
+                .iter()
+                .map(|crate_name| self.local_dependency(&source, &versions, crate_name))
+                .collect::<Result<Vec<_>>>()?
+        } else if self.flag_offline {
+            let locked = self.get_locked_versions()?;
+            unversioned
+                .iter()
+                .map(|crate_name| self.locked_dependency(&locked, crate_name, warnings))
+                .collect::<Result<Vec<_>>>()?
+        } else if self.flag_max_age.is_some() {
+            // Publication dates are not in the index, so each crate is looked up on its own.
+            unversioned
+                .iter()
+                .map(|crate_name| self.latest_dependency(crate_name, warnings))
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            get_latest_dependencies_with_warnings(
+                &unversioned,
+                self.get_prereleases()?,
+                &self.get_prerelease_crates()?,
+                self.flag_jobs,
+                warnings,
+            )?
+        };
+        let mut latest = latest.into_iter();
+        let default_prefix = self.get_upgrade_prefix(warnings).unwrap_or("");
+
+        krates
+            .into_iter()
+            .zip(crate_names)
+            .map(|(krate, crate_name)| {
+                let crate_name = CrateName::new(crate_name);
+                let dep = match krate {
+                    Some(dep) => dep,
+                    None => {
+                        let dep = latest.next().expect("one lookup per unversioned crate");
+                        let version = format!(
+                            "{}{}",
+                            crate_name.version_operator().unwrap_or(default_prefix),
+                            dep.version().ok_or(cargo_edit::Error::NoVersionsAvailable)?
+                        );
+                        dep.set_version(&version)
+                    }
+                };
+                let features = crate_name.features();
+                Ok(if features.is_empty() {
+                    dep
+                } else {
+                    dep.set_features(Some(features))
+                })
+            })
+            .collect()
+    }
+
+    /// Look up the latest version of a crate on crates.io, or the latest one old enough with
+    /// `--max-age`.
+    pub fn latest_dependency(
+        &self,
+        crate_name: &str,
+        warnings: &mut Warnings,
+    ) -> Result<Dependency> {
+        let dep = match self.flag_max_age {
+            Some(days) => get_latest_dependency_with_max_age(
+                crate_name,
+                self.get_prereleases_of(crate_name)?,
+                days,
+                warnings,
+            )?,
+            None => get_latest_dependency_with_warnings(
+                crate_name,
+                self.get_prereleases_of(crate_name)?,
+                warnings,
+            )?,
+        };
+        Ok(dep)
+    }
+
+    /// Read the versions recorded in the lock file of the package, for `--offline` and
+    /// `--locked`.
+    pub fn get_locked_versions(&self) -> Result<LockedVersions> {
+        let manifest = find(&self.flag_manifest_path)?;
+        Ok(read_lock_file(&find_lock_file(&manifest))?)
+    }
+
+    /// Build a dependency on the latest version of a crate recorded in the lock file (e.g. as a
+    /// dependency of another crate), noting that the version did not come from the registry.
+    fn locked_dependency(
+        &self,
+        locked: &LockedVersions,
+        crate_name: &str,
+        warnings: &mut Warnings,
+    ) -> Result<Dependency> {
+        let prereleases = self.get_prereleases_of(crate_name)?;
+        let version = latest_locked_version(locked, crate_name, prereleases)
+            .ok_or_else(|| Error::NotInLockFile(crate_name.to_owned()))?;
+        warnings.push(
+            WarningKind::LockedVersion,
+            format!(
+                "using version {} of `{}` from Cargo.lock, not from the registry",
+                version, crate_name
+            ),
+        );
+        Ok(Dependency::new(crate_name).set_version(&version.to_string()))
+    }
+
+    /// Read the versions of the crates in the local registry or directory source that replaces
+    /// crates.io for the package (see `.cargo/config.toml`), if any.
+    fn get_local_versions(&self) -> Result<Option<(LocalSource, LockedVersions)>> {
+        let manifest = find(&self.flag_manifest_path)?;
+        match find_local_source(&manifest)? {
+            Some(source) => {
+                let versions = source.versions()?;
+                Ok(Some((source, versions)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Build a dependency on the latest version of a crate in the local source that replaces
+    /// crates.io, which is the only one cargo can build with.
+    fn local_dependency(
+        &self,
+        source: &LocalSource,
+        versions: &LockedVersions,
+        crate_name: &str,
+    ) -> Result<Dependency> {
+        let prereleases = self.get_prereleases_of(crate_name)?;
+        let version = latest_locked_version(versions, crate_name, prereleases).ok_or_else(|| {
+            Error::NotInLocalSource {
+                name: crate_name.to_owned(),
+                source_name: source.name.clone(),
+                path: source.path.display().to_string(),
+            }
+        })?;
+        Ok(Dependency::new(crate_name).set_version(&version.to_string()))
+    }
+
+    /// Build a dependency on the crate of a `.crate` archive (`--path-to-crate`), named after the
+    /// crate and its version like the archives of registries. The entry is a registry dependency on
+    /// that version, which cargo finds in the local registry or directory source that holds the
+    /// archive (see `.cargo/config.toml`).
+    fn crate_file_dependency(&self, path: &Path, warnings: &mut Warnings) -> Result<Dependency> {
+        fs::metadata(path).map_err(|e| Error::InvalidPath {
+            path: path.display().to_string(),
+            source: e,
+        })?;
+        let (name, version) = path.file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(parse_crate_file_name)
+            .ok_or_else(|| Error::InvalidCrateFile(path.display().to_string()))?;
+        let prefix = self.get_upgrade_prefix(warnings).unwrap_or("");
+        Ok(Dependency::new(&name).set_version(&format!("{}{}", prefix, version)))
+    }
+
+    /// Get the branch, tag or revision passed with `--branch`, `--tag` or `--rev`, if any
+    fn get_git_reference(&self) -> Result<Option<GitReference>> {
+        let references: Vec<_> = vec![
+            self.flag_branch.clone().map(GitReference::Branch),
+            self.flag_tag.clone().map(GitReference::Tag),
+            self.flag_rev.clone().map(GitReference::Rev),
+        ].into_iter()
+            .filter_map(|r| r)
+            .collect();
+
+        match references.len() {
+            0 => Ok(None),
+            1 => Ok(references.into_iter().next()),
+            _ => Err(Error::ConflictingGitReferences),
+        }
+    }
+
+    /// Apply the version passed with `--vers`, if any. For git and path dependencies, the version
+    /// is kept next to the source (e.g. for publishing).
+    fn set_flag_version(&self, dependency: Dependency) -> Result<Dependency> {
+        match self.flag_vers {
+            Some(ref version) => {
+                semver::VersionReq::parse(version).map_err(Error::InvalidVersion)?;
+                Ok(dependency.set_version(version))
+            }
+            None => Ok(dependency),
+        }
+    }
+
+    /// Get the path to write for a path dependency. By default, this is the path as typed (even if
+    /// it leads through symlinks or into a git submodule). With `--canonicalize`, symlinks are
+    /// resolved and the path is made relative to the manifest.
+    fn get_path(&self, path: &Path) -> Result<String> {
+        if !self.flag_canonicalize {
+            return Ok(path.to_string_lossy().into_owned());
+        }
+
+        let canonicalize = |path: &Path| {
+            fs::canonicalize(path).map_err(|e| Error::InvalidPath {
+                path: path.display().to_string(),
+                source: e,
+            })
+        };
+        let manifest = find(&self.flag_manifest_path)?;
+        let manifest_dir = match manifest.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+
+        let relative = relative_path(&canonicalize(path)?, &canonicalize(manifest_dir)?);
+        Ok(relative.to_string_lossy().into_owned())
+    }
+
+    /// Get the other members of the workspace the manifest belongs to, if any. With `--members`,
+    /// the dependencies are added to several manifests, so no member is treated specially.
+    fn get_workspace_members(&self) -> Result<Vec<WorkspaceMember>> {
+        if self.flag_members.is_some() {
+            return Ok(vec![]);
+        }
+        let manifest = find(&self.flag_manifest_path)?;
+        let root = match find_workspace_root(&manifest) {
+            Ok(root) => root,
+            Err(cargo_edit::Error::WorkspaceRootNotFound) => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let manifest = fs::canonicalize(&manifest)?;
+        Ok(workspace_members(&Some(root))?
+            .into_iter()
+            .filter(|member| {
+                fs::canonicalize(&member.manifest_path)
+                    .map(|path| path != manifest)
+                    .unwrap_or(true)
+            })
+            .collect())
+    }
+
+    /// Build a dependency on a package of the git repository at `url`, found by fetching its
+    /// manifests with `git`: the one named with `--crate`, or else the only one. If the
+    /// repository has several packages, the user picks one. Without `git`, the name is taken from
+    /// `--crate` or guessed from the manifest of the default branch on GitHub or GitLab.
+    fn git_dependency(&self, crate_name: &CrateName, url: &str) -> Result<Dependency> {
+        let packages = match git_packages(url, self.get_git_reference()?.as_ref()) {
+            Ok(packages) => packages,
+            Err(cargo_edit::Error::Io { ref source, .. })
+                if source.kind() == io::ErrorKind::NotFound =>
+            {
+                return match self.flag_crate {
+                    Some(ref member) => Ok(Dependency::new(member).set_git(url)),
+                    None => Ok(crate_name.parse_crate_name_from_uri()?),
+                };
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(ref member) = self.flag_crate {
+            return match packages.iter().find(|package| package.name == *member) {
+                Some(package) => Ok(Dependency::new(&package.name).set_git(url)),
+                None => Err(Error::UnknownGitPackage {
+                    url: url.to_owned(),
+                    name: member.clone(),
+                    packages: packages.into_iter().map(|package| package.name).collect(),
+                }),
+            };
+        }
+        if packages.len() == 1 {
+            return Ok(Dependency::new(&packages[0].name).set_git(url));
+        }
+
+        if atty::is(atty::Stream::Stdin) {
+            let items: Vec<_> = packages
+                .iter()
+                .map(|package| {
+                    if package.path.is_empty() {
+                        ChecklistItem::new(url, &package.name)
+                    } else {
+                        ChecklistItem::new(url, &format!("{} ({})", package.name, package.path))
+                    }
+                })
+                .collect();
+            let picked = checklist("Package to add (pick one)", &items)?;
+            if picked.len() == 1 {
+                return Ok(Dependency::new(&packages[picked[0]].name).set_git(url));
+            }
+        }
+        Err(Error::AmbiguousGitPackage {
+            url: url.to_owned(),
+            packages: packages.into_iter().map(|package| package.name).collect(),
+        })
+    }
+
+    /// Build a dependency on another member of the workspace: its path, relative to the manifest,
+    /// and its version, so the package stays publishable.
+    fn member_dependency(&self, member: &WorkspaceMember) -> Result<Dependency> {
+        let manifest = fs::canonicalize(find(&self.flag_manifest_path)?)?;
+        let manifest_dir = manifest.parent().unwrap_or_else(|| Path::new(""));
+        let member_dir = member
+            .manifest_path
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        let member_dir = fs::canonicalize(member_dir)?;
+        let path = relative_path(&member_dir, manifest_dir);
+        Ok(Dependency::new(&member.name)
+            .set_path(&path.to_string_lossy())
+            .set_version(&member.version))
+    }
+
+    /// Get the features passed with `--features`, which may be separated by commas or spaces.
+    /// A leading `+` is allowed (e.g. `+std`); features with a leading `-` are removed instead (see
+    /// `get_removed_features`). `None` if no feature is enabled.
+    fn get_features(&self) -> Option<Vec<String>> {
+        let features: Vec<_> = self.flag_features
+            .as_ref()
+            .map(|features| split_list(features))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|feature| !feature.starts_with('-'))
+            .map(|feature| feature.trim_start_matches('+').to_owned())
+            .collect();
+        if features.is_empty() {
+            None
+        } else {
+            Some(features)
+        }
+    }
+
+    /// Get the features passed with a leading `-` to `--features` (e.g. `--features=-std`), which
+    /// are removed from an existing entry of the dependency.
+    fn get_removed_features(&self) -> Vec<String> {
+        self.flag_features
+            .as_ref()
+            .map(|features| split_list(features))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|feature| feature.starts_with('-'))
+            .map(|feature| feature[1..].to_owned())
+            .collect()
+    }
+
+    /// Combine the features given after the crate name (e.g. `serde+derive`) with the ones passed
+    /// with `--features`.
+    fn get_features_with(&self, inline: Vec<String>) -> Option<Vec<String>> {
+        let flag = self.get_features();
+        if inline.is_empty() {
+            return flag;
+        }
+        let mut features = inline;
+        for feature in flag.unwrap_or_default() {
+            if !features.contains(&feature) {
+                features.push(feature);
+            }
+        }
+        Some(features)
+    }
+
+    /// Get the prerelease versions that may be picked: those of the channel passed with
+    /// `--prerelease` and later ones, all of them with `--allow-prerelease`, or none.
+    pub fn get_prereleases(&self) -> Result<Prereleases> {
+        match self.flag_prerelease {
+            Some(ref channel) => Ok(Prereleases::Channel(channel.parse()?)),
+            None => Ok(Prereleases::from(self.flag_allow_prerelease)),
+        }
+    }
+
+    /// Get the prerelease versions that may be picked for `crate_name`: any, if the manifest or
+    /// the root manifest of its workspace lists the crate in
+    /// `package.metadata.cargo-edit.allow-prerelease` (or `workspace.metadata`).
+    pub fn get_prereleases_of(&self, crate_name: &str) -> Result<Prereleases> {
+        let prerelease_crates = self.get_prerelease_crates()?;
+        Ok(self.get_prereleases()?.for_crate(crate_name, &prerelease_crates))
+    }
+
+    /// Get the crates for which any prerelease may be picked (see `get_prereleases_of`).
+    fn get_prerelease_crates(&self) -> Result<Vec<String>> {
+        Ok(prerelease_crates(&find(&self.flag_manifest_path)?)?)
+    }
+
+    /// Get the crates passed to `cargo add`, for `--explain`, each with the prerelease versions
+    /// that may be picked for it. `None` marks crates whose version is not picked from the
+    /// registry: those passed with a version, other members of the workspace, and git or path
+    /// dependencies.
+    pub fn get_registry_lookups(&self) -> Result<Vec<(String, Option<Prereleases>)>> {
+        let mut lookups = Vec::new();
+        let mut crate_names = self.arg_crates.clone();
+        if crate_names.is_empty() && !self.arg_crate.is_empty() {
+            if self.flag_vers.is_some() || self.flag_git.is_some() || self.flag_path.is_some() {
+                lookups.push((CrateName::new(&self.arg_crate).name().to_owned(), None));
+            } else {
+                crate_names.push(self.arg_crate.clone());
+            }
+        }
+        for list in &[&self.flag_dev_crates, &self.flag_build_crates] {
+            if let Some(ref list) = **list {
+                crate_names.extend(split_list(list));
+            }
+        }
+
+        let members = self.get_workspace_members()?;
+        for crate_name in &crate_names {
+            let krate = CrateName::new(crate_name);
+            let name = krate.name();
+            let looked_up = !krate.is_url_or_path()
+                && krate.git_url().is_none()
+                && krate.parse_as_version()?.is_none()
+                && !members.iter().any(|member| member.name == name);
+            let prereleases = if looked_up {
+                Some(self.get_prereleases_of(name)?)
+            } else {
+                None
+            };
+            lookups.push((name.to_owned(), prereleases));
+        }
+        Ok(lookups)
+    }
+
+    /// Get where to put the dependency section, if it does not exist yet
+    pub fn get_section_placement(&self) -> Result<SectionPlacement> {
+        match self.flag_section_placement {
+            Some(ref placement) => Ok(placement.parse()?),
+            None => Ok(SectionPlacement::default()),
+        }
+    }
+
+    /// Get the side to prefer when resolving merge conflicts in the manifest
+    pub fn get_conflict_side(&self) -> Option<ConflictSide> {
+        if self.flag_ours {
+            Some(ConflictSide::Ours)
+        } else if self.flag_theirs {
+            Some(ConflictSide::Theirs)
+        } else {
+            None
+        }
+    }
+
+    fn get_upgrade_prefix(&self, warnings: &mut Warnings) -> Option<&'static str> {
         self.flag_upgrade
             .clone()
             .and_then(|flag| match flag.to_uppercase().as_ref() {
@@ -120,9 +808,9 @@ impl Args {
                 "MINOR" => Some("^"),
                 "ALL" => Some(">="),
                 _ => {
-                    println!(
-                        "WARN: cannot understand upgrade option \"{}\", using default",
-                        flag
+                    warnings.push(
+                        WarningKind::Other,
+                        format!("cannot understand upgrade option \"{}\", using default", flag),
                     );
                     None
                 }
@@ -130,6 +818,57 @@ impl Args {
     }
 }
 
+/// Split a list of names separated by commas or spaces.
+fn split_list(list: &str) -> Vec<String> {
+    list.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Split a list of targets at the commas outside of parentheses, e.g. `cfg(unix), cfg(any(windows,
+/// target_os = "wasi"))` into `cfg(unix)` and `cfg(any(windows, target_os = "wasi"))`.
+fn split_targets(list: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                targets.push(list[start..i].trim().to_owned());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    targets.push(list[start..].trim().to_owned());
+    targets
+}
+
+/// Express the absolute `path` relative to the absolute directory `base`. If they have nothing in
+/// common (e.g. they are on different drives), `path` is returned as is.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<_> = path.components().collect();
+    let base: Vec<_> = base.components().collect();
+    let common = path.iter()
+        .zip(&base)
+        .take_while(|&(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return path.iter().collect();
+    }
+
+    let mut relative: PathBuf = base[common..].iter().map(|_| Component::ParentDir).collect();
+    relative.extend(&path[common..]);
+    if relative.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        relative
+    }
+}
+
 impl Default for Args {
     fn default() -> Args {
         Args {
@@ -137,16 +876,52 @@ impl Default for Args {
             arg_crates: vec![],
             flag_dev: false,
             flag_build: false,
+            flag_dev_crates: None,
+            flag_build_crates: None,
             flag_vers: None,
             flag_git: None,
+            flag_branch: None,
+            flag_tag: None,
+            flag_rev: None,
+            flag_crate: None,
             flag_path: None,
+            flag_canonicalize: false,
+            flag_path_to_crate: None,
             flag_target: None,
             flag_optional: false,
+            flag_emulate_optional_dev: false,
             flag_manifest_path: None,
+            flag_members: None,
+            flag_include_excluded: false,
+            flag_include_nested: false,
+            flag_group: None,
             flag_version: false,
+            flag_json: false,
             flag_upgrade: None,
             flag_allow_prerelease: false,
+            flag_prerelease: None,
+            flag_offline: false,
+            flag_locked: false,
+            flag_max_age: None,
+            flag_provenance: false,
+            flag_jobs: None,
+            flag_features: None,
+            flag_no_default_features: false,
+            flag_no_verify_features: false,
+            flag_interactive_features: false,
             flag_quiet: false,
+            flag_verbose: false,
+            flag_bump_msrv: false,
+            flag_compat_edition: false,
+            flag_follow_renames: false,
+            flag_explain: false,
+            flag_dry_run: false,
+            flag_output: OutputFormat::Human,
+            flag_section_placement: None,
+            flag_ours: false,
+            flag_theirs: false,
+            flag_record: None,
+            flag_reason: None,
         }
     }
 }
@@ -154,6 +929,7 @@ impl Default for Args {
 #[cfg(test)]
 mod tests {
     use cargo_edit::Dependency;
+    use docopt::Docopt;
     use super::*;
 
     #[test]
@@ -164,11 +940,159 @@ mod tests {
         };
 
         assert_eq!(
-            args.parse_dependencies().unwrap(),
+            args.parse_dependencies(&mut Warnings::new()).unwrap(),
             vec![Dependency::new("demo").set_version("0.4.2")]
         );
     }
 
+    #[test]
+    fn test_features_parsing() {
+        let args = Args {
+            flag_vers: Some("0.4.2".to_owned()),
+            flag_features: Some("derive, std  alloc".to_owned()),
+            ..Args::default()
+        };
+
+        assert_eq!(
+            args.parse_dependencies(&mut Warnings::new()).unwrap(),
+            vec![
+                Dependency::new("demo")
+                    .set_version("0.4.2")
+                    .set_features(Some(vec!["derive".into(), "std".into(), "alloc".into()])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_optional_is_only_for_dependencies() {
+        let optional = Args {
+            flag_optional: true,
+            ..Args::default()
+        };
+        assert_eq!(optional.get_section().unwrap(), DepTable::new());
+
+        let optional_dev = Args {
+            flag_dev: true,
+            ..optional
+        };
+        assert!(optional_dev.get_section().is_err());
+
+        let emulated = Args {
+            flag_dev: true,
+            flag_emulate_optional_dev: true,
+            ..Args::default()
+        };
+        assert!(emulated.is_optional());
+        assert_eq!(emulated.get_section().unwrap(), DepTable::new());
+
+        let emulated_build = Args {
+            flag_build: true,
+            flag_emulate_optional_dev: true,
+            ..Args::default()
+        };
+        assert!(emulated_build.get_section().is_err());
+    }
+
+    #[test]
+    fn test_target_applies_to_all_kinds() {
+        let build = Args {
+            flag_build: true,
+            flag_target: Some("cfg(windows)".to_owned()),
+            ..Args::default()
+        };
+        assert_eq!(
+            build.get_section().unwrap().to_table(),
+            vec!["target", "cfg(windows)", "build-dependencies"]
+        );
+    }
+
+    #[test]
+    fn test_several_targets() {
+        let args = Args {
+            flag_target: Some("cfg(unix), cfg(any(windows, target_os = \"wasi\"))".to_owned()),
+            ..Args::default()
+        };
+        assert_eq!(
+            args.get_targets().unwrap(),
+            vec!["cfg(unix)", "cfg(any(windows, target_os = \"wasi\"))"]
+        );
+        let groups = vec![(
+            args.get_section().unwrap(),
+            vec![Dependency::new("libc").set_version("0.2")],
+        )];
+        let expanded = args.expand_targets(&groups).unwrap();
+        let targets: Vec<_> = expanded
+            .iter()
+            .map(|&(ref section, _)| section.target().unwrap())
+            .collect();
+        assert_eq!(
+            targets,
+            vec!["cfg(unix)", "cfg(any(windows, target_os = \"wasi\"))"]
+        );
+        assert_eq!(expanded[1].1, groups[0].1);
+
+        let trailing_comma = Args {
+            flag_target: Some("cfg(unix),".to_owned()),
+            ..Args::default()
+        };
+        assert!(trailing_comma.get_section().is_err());
+    }
+
+    #[test]
+    fn test_dependency_groups() {
+        let args = Args {
+            arg_crates: vec!["serde@1.0".to_owned()],
+            flag_dev_crates: Some("proptest@0.7, criterion@0.2".to_owned()),
+            flag_build_crates: Some("cc@1.0".to_owned()),
+            ..Args::default()
+        };
+
+        assert_eq!(
+            args.get_dependency_groups(&mut Warnings::new()).unwrap(),
+            vec![
+                (
+                    DepTable::new(),
+                    vec![Dependency::new("serde").set_version("1.0")],
+                ),
+                (
+                    DepTable::new().set_kind(DepKind::Development),
+                    vec![
+                        Dependency::new("proptest").set_version("0.7"),
+                        Dependency::new("criterion").set_version("0.2"),
+                    ],
+                ),
+                (
+                    DepTable::new().set_kind(DepKind::Build),
+                    vec![Dependency::new("cc").set_version("1.0")],
+                ),
+            ]
+        );
+
+        let dev_only = Args {
+            arg_crate: "".to_owned(),
+            flag_dev_crates: Some("proptest@0.7".to_owned()),
+            ..Args::default()
+        };
+        assert_eq!(
+            dev_only.get_dependency_groups(&mut Warnings::new()).unwrap(),
+            vec![
+                (
+                    DepTable::new().set_kind(DepKind::Development),
+                    vec![Dependency::new("proptest").set_version("0.7")],
+                ),
+            ]
+        );
+
+        let nothing = Args {
+            arg_crate: "".to_owned(),
+            ..Args::default()
+        };
+        match nothing.get_dependency_groups(&mut Warnings::new()) {
+            Err(Error::NoCrates) => {}
+            result => panic!("expected `NoCrates`, got {:?}", result),
+        }
+    }
+
     #[test]
     #[cfg(feature = "test-external-apis")]
     fn test_repo_as_arg_parsing() {
@@ -178,7 +1102,7 @@ mod tests {
             ..Args::default()
         };
         assert_eq!(
-            args_github.parse_dependencies().unwrap(),
+            args_github.parse_dependencies(&mut Warnings::new()).unwrap(),
             vec![Dependency::new("cargo-edit").set_git(github_url)]
         );
 
@@ -188,11 +1112,63 @@ mod tests {
             ..Args::default()
         };
         assert_eq!(
-            args_gitlab.parse_dependencies().unwrap(),
+            args_gitlab.parse_dependencies(&mut Warnings::new()).unwrap(),
             vec![Dependency::new("polly").set_git(gitlab_url)]
         );
     }
 
+    /// Feed a fixed grid of awkward arguments through docopt and the argument handling, which
+    /// have to report errors instead of panicking. With `--offline`, versions are read from the
+    /// lock file instead of the registry, so nothing is looked up.
+    #[test]
+    fn test_args_grid_does_not_panic() {
+        let values = [
+            "", " ", "@", "a@", "@1", "a@b@c", "é", "🦀", "../", "-", "*", "a,b", "=1.0"
+        ];
+        let flags = [
+            "--vers",
+            "--git",
+            "--path",
+            "--target",
+            "--features",
+            "--upgrade",
+            "--section-placement",
+        ];
+        for flag in &flags {
+            for value in &values {
+                for krate in &values {
+                    let argv = vec!["cargo-add", "add", krate, flag, value];
+                    let mut args = match Docopt::new(super::super::USAGE)
+                        .and_then(|d| d.argv(argv).deserialize::<Args>())
+                    {
+                        Ok(args) => args,
+                        Err(_) => continue,
+                    };
+                    args.flag_offline = true;
+                    let _ = args.get_section();
+                    let _ = args.get_section_placement();
+                    let _ = args.parse_dependencies(&mut Warnings::new());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("/ws/libs/foo"), Path::new("/ws/app")),
+            PathBuf::from("../libs/foo")
+        );
+        assert_eq!(
+            relative_path(Path::new("/ws/app/sub"), Path::new("/ws/app")),
+            PathBuf::from("sub")
+        );
+        assert_eq!(
+            relative_path(Path::new("/ws/app"), Path::new("/ws/app")),
+            PathBuf::from(".")
+        );
+    }
+
     #[test]
     fn test_path_as_arg_parsing() {
         let self_path = ".";
@@ -201,7 +1177,7 @@ mod tests {
             ..Args::default()
         };
         assert_eq!(
-            args_path.parse_dependencies().unwrap(),
+            args_path.parse_dependencies(&mut Warnings::new()).unwrap(),
             vec![Dependency::new("cargo-edit").set_path(self_path)]
         );
     }
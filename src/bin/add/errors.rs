@@ -0,0 +1,285 @@
+//! Errors of `cargo add`
+use std::error::Error as StdError;
+use std::{fmt, io, result};
+
+use cargo_edit;
+use semver;
+
+/// A `Result` with the `cargo add` error type
+pub type Result<T> = result::Result<T, Error>;
+
+/// Errors of `cargo add`
+#[derive(Debug)]
+pub enum Error {
+    /// An error from the `cargo_edit` library
+    CargoEditLib(cargo_edit::Error),
+    /// Output could not be written
+    Io(io::Error),
+    /// The version requirement passed with `--vers` could not be parsed
+    InvalidVersion(semver::ReqParseError),
+    /// `--target` was given an empty target
+    EmptyTarget,
+    /// Neither <crate>, <crates>, `--dev-crates` nor `--build-crates` was given
+    NoCrates,
+    /// `--optional` was combined with `--dev` or `--build`
+    OptionalDevOrBuild {
+        /// Whether it was `--dev`
+        dev: bool,
+    },
+    /// `--branch`, `--tag` or `--rev` was given for a dependency that does not come from git
+    GitReferenceWithoutGit,
+    /// More than one of `--branch`, `--tag` and `--rev` was given
+    ConflictingGitReferences,
+    /// The git repository has several packages and none was picked
+    AmbiguousGitPackage {
+        /// URL of the repository
+        url: String,
+        /// Names of the packages in the repository
+        packages: Vec<String>,
+    },
+    /// The package passed with `--crate` is not in the git repository
+    UnknownGitPackage {
+        /// URL of the repository
+        url: String,
+        /// Name passed with `--crate`
+        name: String,
+        /// Names of the packages in the repository
+        packages: Vec<String>,
+    },
+    /// `--crate` was given for a dependency that does not come from a git repository
+    CrateWithoutGitRepository,
+    /// No workspace member matches the pattern passed with `--members`
+    NoMatchingMembers(String),
+    /// `--include-excluded` was given without `--members`
+    IncludeExcludedWithoutMembers,
+    /// `--include-nested` was given without `--members`
+    IncludeNestedWithoutMembers,
+    /// Adding the dependency failed for some of the workspace members
+    MembersFailed {
+        /// Number of members that failed
+        failed: usize,
+        /// Number of members matched
+        total: usize,
+    },
+    /// The path of a path dependency could not be resolved
+    InvalidPath {
+        /// The path as given
+        path: String,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// Features passed together with `--no-default-features` enable the default features again
+    ContradictoryFeatures {
+        /// Name of the dependency
+        name: String,
+        /// The features that enable the default features
+        features: Vec<String>,
+    },
+    /// With `--offline`, a crate without a version is not in the lock file
+    NotInLockFile(String),
+    /// A crate without a version is not in the local source that replaces crates.io
+    NotInLocalSource {
+        /// Name of the crate
+        name: String,
+        /// Name of the source in the cargo configuration
+        source_name: String,
+        /// The directory of the source
+        path: String,
+    },
+    /// The file passed with `--path-to-crate` is not named like a `.crate` archive
+    InvalidCrateFile(String),
+    /// With `--locked`, no version in the lock file meets the requirement of a dependency
+    NotSatisfiedByLockFile {
+        /// Name of the dependency
+        name: String,
+        /// Version requirement of the dependency
+        version: String,
+        /// The versions of the crate in the lock file
+        locked: Vec<String>,
+    },
+    /// `--interactive-features` was combined with `--offline`
+    InteractiveFeaturesOffline,
+    /// `--max-age` was combined with `--offline`
+    MaxAgeOffline,
+    /// `--bump-msrv` was combined with `--offline`
+    BumpMsrvOffline,
+    /// `--explain` was combined with `--offline`
+    ExplainOffline,
+    /// `--compat-edition` was combined with `--offline`
+    CompatEditionOffline,
+    /// Some of the features passed with `--features` do not exist
+    UnknownFeatures {
+        /// Name of the dependency
+        name: String,
+        /// Version requirement of the dependency, or where a path dependency is (`at <path>`)
+        source: String,
+        /// The features that do not exist
+        unknown: Vec<String>,
+        /// The features the dependency offers
+        available: Vec<String>,
+    },
+}
+
+impl From<cargo_edit::Error> for Error {
+    fn from(e: cargo_edit::Error) -> Self {
+        Error::CargoEditLib(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            Error::CargoEditLib(ref e) => return e.fmt(f),
+            Error::Io(ref e) => return e.fmt(f),
+            Error::InvalidVersion(_) => message!("add-invalid-version"),
+            Error::EmptyTarget => message!("add-empty-target"),
+            Error::NoCrates => message!("add-no-crates"),
+            Error::OptionalDevOrBuild { dev } => {
+                let hint = if dev {
+                    message!("add-optional-dev-hint")
+                } else {
+                    message!("add-optional-build-hint")
+                };
+                format!("{}\n\n{}", message!("add-optional-dev-or-build"), hint)
+            }
+            Error::GitReferenceWithoutGit => message!("add-git-reference-without-git"),
+            Error::ConflictingGitReferences => message!("add-conflicting-git-references"),
+            Error::AmbiguousGitPackage {
+                ref url,
+                ref packages,
+            } => message!(
+                "add-ambiguous-git-package",
+                url = url,
+                packages = packages.join(", "),
+                first = packages[0]
+            ),
+            Error::UnknownGitPackage {
+                ref url,
+                ref name,
+                ref packages,
+            } => message!(
+                "add-unknown-git-package",
+                url = url,
+                name = name,
+                packages = packages.join(", ")
+            ),
+            Error::CrateWithoutGitRepository => message!("add-crate-without-git-repository"),
+            Error::NoMatchingMembers(ref pattern) => {
+                message!("add-no-matching-members", pattern = pattern)
+            }
+            Error::IncludeExcludedWithoutMembers => {
+                message!("add-without-members", flag = "--include-excluded")
+            }
+            Error::IncludeNestedWithoutMembers => {
+                message!("add-without-members", flag = "--include-nested")
+            }
+            Error::MembersFailed { failed, total } => {
+                message!("add-members-failed", failed = failed, total = total)
+            }
+            Error::InvalidPath { ref path, .. } => message!("add-invalid-path", path = path),
+            Error::ContradictoryFeatures {
+                ref name,
+                ref features,
+            } => {
+                let features: Vec<_> = features.iter().map(|f| format!("`{}`", f)).collect();
+                message!(
+                    "add-contradictory-features",
+                    name = name,
+                    features = features.join(", ")
+                )
+            }
+            Error::NotInLockFile(ref name) => message!("add-not-in-lock-file", name = name),
+            Error::NotInLocalSource {
+                ref name,
+                ref source_name,
+                ref path,
+            } => message!(
+                "add-not-in-local-source",
+                name = name,
+                source = source_name,
+                path = path
+            ),
+            Error::InvalidCrateFile(ref path) => message!("add-invalid-crate-file", path = path),
+            Error::NotSatisfiedByLockFile {
+                ref name,
+                ref version,
+                ref locked,
+            } => {
+                let locked = if locked.is_empty() {
+                    message!("add-locked-missing")
+                } else {
+                    message!("add-locked-versions", versions = locked.join(", "))
+                };
+                message!(
+                    "add-not-satisfied-by-lock-file",
+                    name = name,
+                    version = version,
+                    locked = locked
+                )
+            }
+            Error::InteractiveFeaturesOffline => {
+                message!("add-needs-registry-interactive-features")
+            }
+            Error::MaxAgeOffline => message!("add-needs-registry-max-age"),
+            Error::BumpMsrvOffline => message!("add-needs-registry-bump-msrv"),
+            Error::ExplainOffline => message!("add-needs-registry-explain"),
+            Error::CompatEditionOffline => message!("add-needs-registry-compat-edition"),
+            Error::UnknownFeatures {
+                ref name,
+                ref source,
+                ref unknown,
+                ref available,
+            } => {
+                let unknown: Vec<_> = unknown.iter().map(|f| format!("`{}`", f)).collect();
+                message!(
+                    "add-unknown-features",
+                    unknown = unknown.join(", "),
+                    name = name,
+                    source = source,
+                    available = available.join(", ")
+                )
+            }
+        };
+        f.write_str(&text)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::CargoEditLib(ref e) => e.source(),
+            Error::Io(ref e) => e.source(),
+            Error::InvalidVersion(ref e) => Some(e),
+            Error::InvalidPath { ref source, .. } => Some(source),
+            Error::EmptyTarget
+            | Error::NoCrates
+            | Error::OptionalDevOrBuild { .. }
+            | Error::GitReferenceWithoutGit
+            | Error::ConflictingGitReferences
+            | Error::AmbiguousGitPackage { .. }
+            | Error::UnknownGitPackage { .. }
+            | Error::CrateWithoutGitRepository
+            | Error::NoMatchingMembers(_)
+            | Error::IncludeExcludedWithoutMembers
+            | Error::IncludeNestedWithoutMembers
+            | Error::MembersFailed { .. }
+            | Error::ContradictoryFeatures { .. }
+            | Error::NotInLockFile(_)
+            | Error::NotInLocalSource { .. }
+            | Error::InvalidCrateFile(_)
+            | Error::NotSatisfiedByLockFile { .. }
+            | Error::InteractiveFeaturesOffline
+            | Error::MaxAgeOffline
+            | Error::BumpMsrvOffline
+            | Error::ExplainOffline
+            | Error::CompatEditionOffline
+            | Error::UnknownFeatures { .. } => None,
+        }
+    }
+}
@@ -5,71 +5,204 @@
 
 extern crate atty;
 extern crate docopt;
-#[macro_use]
-extern crate error_chain;
 extern crate semver;
-#[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 extern crate termcolor;
 
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
 use std::process;
 use std::io::Write;
+use std::path::PathBuf;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
+#[macro_use]
 extern crate cargo_edit;
-use cargo_edit::{Dependency, Manifest};
+use cargo_edit::{checklist, command_args, excluded_members, explain_latest_version, find,
+                 get_crate_features, get_crate_info, get_crate_links, get_crate_successor,
+                 get_latest_version_for_edition, get_provenance, nested_packages, record_changes,
+                 registry_cache_stats, workspace_members, ChecklistItem, CrateInfo, DepKind,
+                 DepTable, Dependency, FeatureInfo, Manifest, Prereleases, Provenance,
+                 RecordedChange, SelfDescription, VersionChoice, VersionVerdict, WarningKind,
+                 Warnings, WorkspaceMember};
+use serde_derive::Serialize;
 
 mod args;
-use args::Args;
+use self::args::{Args, OutputFormat};
 
-mod errors {
-    error_chain!{
-        links {
-            CargoEditLib(::cargo_edit::Error, ::cargo_edit::ErrorKind);
-        }
-        foreign_links {
-            Io(::std::io::Error);
-        }
-    }
-}
-use errors::*;
+mod errors;
+use self::errors::*;
 
 static USAGE: &'static str = r#"
 Usage:
-    cargo add <crate> [--dev|--build|--optional] [--vers=<ver>|--git=<uri>|--path=<uri>] [options]
-    cargo add <crates>... [--dev|--build|--optional] [options]
+    cargo add <crate> [--dev|--build] [--optional] [--vers=<ver>] [--git=<uri>|--path=<uri>] [options]
+    cargo add --git=<uri> --crate=<member> [--dev|--build] [--optional] [--vers=<ver>] [options]
+    cargo add --path-to-crate=<file> [--dev|--build] [--optional] [options]
+    cargo add <crates>... [--dev|--build] [--optional] [options]
+    cargo add (--dev-crates=<crates>|--build-crates=<crates>) [options]
     cargo add (-h|--help)
-    cargo add --version
+    cargo add --version [--json]
 
 Specify what crate to add:
     --vers <ver>            Specify the version to grab from the registry (crates.io).
                             You can also specify versions as part of the name, e.g
-                            `cargo add bitflags@0.3.2`.
-    --git <uri>             Specify a git repository to download the crate from.
+                            `cargo add bitflags@0.3.2`. Together with `--git` or `--path`, the
+                            version is added next to the source (as needed for publishing).
+    --git <uri>             Specify a git repository to download the crate from. `owner/repo`
+                            and `gh:owner/repo` are short for a repository on GitHub,
+                            `gl:group/project` for one on GitLab.
+    --branch <branch>       Use this branch of the git repository (from `--git` or a repository
+                            URL passed as <crate>).
+    --tag <tag>             Use this tag of the git repository.
+    --rev <rev>             Use this commit of the git repository.
+    --crate <member>        Add this package of the git repository, e.g. a member of its
+                            workspace. It must be in the repository at the given `--branch`,
+                            `--tag` or `--rev`.
     --path <uri>            Specify the path the crate should be loaded from.
+    --canonicalize          Resolve symlinks in the path of a path dependency and write it
+                            relative to the manifest. By default, the path is written as typed.
+    --path-to-crate <file>  Add the crate of a `.crate` archive, e.g. `foo-1.2.0.crate` from a
+                            local registry, as a dependency on its version. Cargo finds it in
+                            the local registry or directory source that replaces crates.io.
 
 Specify where to add the crate:
     -D --dev                Add crate as development dependency.
     -B --build              Add crate as build dependency.
-    --optional              Add as an optional dependency (for use in features). This does not work
-                            for `dev-dependencies` or `build-dependencies`.
-    --target <target>       Add as dependency to the given target platform. This does not work
-                            for `dev-dependencies` or `build-dependencies`.
+    --optional              Add as an optional dependency (for use in features). Cargo does not
+                            support optional `dev-dependencies` or `build-dependencies`, so this
+                            can not be combined with `--dev` or `--build`.
+    --emulate-optional-dev  Instead of an optional dev-dependency, which cargo does not support,
+                            add the crate as an optional dependency, enabled by a new feature
+                            `test-<crate>` to gate tests with. Implies `--optional`; can not be
+                            combined with `--build`.
+    --target <target>       Add as dependency to the given target platform. Together with
+                            `--dev` or `--build`, the crate is added to the
+                            `dev-dependencies` or `build-dependencies` of the target. Several
+                            targets can be given, separated by commas, e.g.
+                            `--target 'cfg(unix), cfg(windows)'`.
+    --dev-crates <crates>   Also add these crates (separated by commas or spaces) as development
+                            dependencies, e.g. `cargo add serde --dev-crates proptest,criterion`.
+                            `--optional`, `--target` and `--features` do not apply to them.
+    --build-crates <crates>
+                            Also add these crates as build dependencies, like `--dev-crates`.
 
 Options:
     --upgrade=<method>      Choose method of semantic version upgrade. Must be one of
                             "none" (exact version), "patch" (`~` modifier), "minor"
-                            (`^` modifier, default), or "all" (`>=`).
+                            (`^` modifier, default), or "all" (`>=`). A crate can have its own
+                            modifier after `@`, e.g. `cargo add serde@= log@~ rand`.
     --manifest-path=<path>  Path to the manifest to add a dependency to.
+    --members <glob>        Add the dependency to all members of the workspace (see
+                            `--manifest-path`) whose package name or path matches <glob>, e.g.
+                            'crates/*-client'. A summary of the members is printed at the end.
+    --include-excluded      With `--members`, also consider the packages in the directories the
+                            workspace excludes with `workspace.exclude` (e.g. `fuzz`).
+    --include-nested        With `--members`, also consider the packages below the workspace root
+                            that are not members of the workspace (e.g. the crates of test
+                            fixtures). Matching packages that are left out are noted.
+    --section-placement <where>
+                            Where to put the dependency section, if it does not exist yet. Must be
+                            one of "end" (default), "after-dependencies" (after the other
+                            dependency sections), or "before-features".
+    --group <label>         List new entries below a `# <label>` comment in their section (e.g.
+                            `--dev --group benches`), after the entries already there. The
+                            comment is added at the end of the section if it is missing.
     --allow-prerelease      Include prerelease versions when fetching from crates.io (e.g.
                             '0.6.0-alpha'). Defaults to false.
+    --prerelease <channel>  Include prerelease versions of <channel> and later channels when
+                            fetching from crates.io. Must be one of "alpha", "beta" or "rc", e.g.
+                            `--prerelease rc` picks '1.0.0-rc.2' but never '1.0.0-alpha.1'.
+                            Any prerelease is included for the crates listed in
+                            `package.metadata.cargo-edit.allow-prerelease` (or
+                            `workspace.metadata`) of the manifest or the root manifest of its
+                            workspace, e.g. `["bevy"]`.
+    -j --jobs <n>           Look up at most <n> crates at the same time, when adding several crates
+                            without a version. Defaults to the number of crates, up to 4.
+    --max-age <days>        Only pick versions published at least <days> days ago, as a cooldown
+                            against malicious releases that have not been noticed yet. Newer
+                            versions are passed over with a warning.
+    --offline               Do not query the registry: take the versions of crates from Cargo.lock
+                            (where they may be dependencies of other crates), and do not check
+                            features or print `--verbose` information. If a local registry or a
+                            directory source (e.g. from `cargo vendor`) replaces crates.io in
+                            `.cargo/config.toml`, versions always come from there.
+    --locked                Fail instead of adding a crates.io dependency whose version
+                            requirement no version in Cargo.lock meets, as cargo would have to
+                            change Cargo.lock on the next build.
+    --features <features>   Space or comma separated list of features to enable. Features can also
+                            follow the crate name, each with a leading `+`, e.g.
+                            `cargo add serde+derive`. For crates.io dependencies, the features
+                            are checked to exist. If the crate is already a dependency, the
+                            features it enables stay enabled; a feature with a leading `-` is
+                            removed from them (pass it with `=`, e.g. `--features=-std,+alloc`,
+                            where `+` marks a feature to add).
+    --no-default-features   Do not enable the default features of the crate.
+    --no-verify-features    Do not check that the features exist (in the registry, or in the
+                            manifest of a path dependency).
+    --interactive-features  Pick the features to enable from the ones the crate offers (crates.io
+                            dependencies only).
+    --output <format>       Print the result as "human" readable messages or as "json". With
+                            "json", the messages go to stderr; combine with `--quiet` to only get
+                            the JSON result, which lists the warnings, too. [default: human]
+    --provenance            Record where the versions of crates.io dependencies come from: the
+                            checksum of the version in the registry index, and the URL and ETag
+                            of the index file. Shown as notes, and in the JSON output. The
+                            checksum is cross-checked with the crates.io API, with a warning if
+                            they differ (e.g. because a proxy altered a response).
+    --ours                  If Cargo.toml has unresolved merge conflicts in dependency tables,
+                            resolve them entry by entry, preferring our side.
+    --theirs                Like `--ours`, but prefer their side.
+    --record <file>         Also record the changes in the change file <file> (created if it
+                            does not exist, appended to otherwise), to replay them on other
+                            manifests with `cargo dep apply <file>`.
+    --reason <why>          Keep why the crate is held at this version (e.g. "0.3 breaks the
+                            parser") in `package.metadata.cargo-edit.pins`. `cargo upgrade`
+                            leaves such crates alone unless they are named, and shows the reason.
     -q --quiet              Do not print any output in case of success.
+    -v --verbose            Also print the license and minimum supported Rust version (MSRV) of
+                            the crates.io dependencies being added, and how many registry
+                            lookups were answered from the cache of this invocation.
+    --bump-msrv             If a crates.io dependency needs a newer Rust than `rust-version` in
+                            `[package]`, raise `rust-version` to the MSRV of the dependency
+                            instead of warning about it.
+    --compat-edition        If a crates.io dependency uses a newer edition than the `edition` of
+                            the package, add the latest version of the crate that uses the
+                            edition of the package (or an older one) instead of warning about it.
+    --follow-renames        If a crates.io dependency says it was renamed, or deprecated in favour
+                            of another crate, add that crate instead (in its latest version, with
+                            the same features and options) instead of warning about it.
+    --explain               Do not add anything, but explain how the version of each crate is
+                            picked from crates.io: the published versions (newest first), the
+                            filters that pass over them (prereleases, yanked versions,
+                            `--max-age`), the picked version, and how its MSRV compares to the
+                            `rust-version` of the package. Works with `--output json`.
+    --dry-run               Print the changes without modifying the manifest.
     -h --help               Show this help page.
     -V --version            Show version.
+    --json                  With `--version`, print the version and what the command supports as
+                            JSON, for tools that drive it.
 
-This command allows you to add a dependency to a Cargo.toml manifest file. If <crate> is a github
-or gitlab repository URL, or a local path, `cargo add` will try to automatically get the crate name
-and set the appropriate `--git` or `--path` value.
+This command allows you to add a dependency to a Cargo.toml manifest file. If <crate> is the URL
+of a git repository (or a shorthand like `gh:owner/repo` or `gl:group/project`), or a local path,
+`cargo add` will try to automatically get the crate name and set the appropriate `--git` or
+`--path` value. For a repository, `git` fetches the manifests at the given `--branch`, `--tag` or
+`--rev` (without history or other files); if the repository is a workspace, you are asked which
+of its packages to add. If <crate> is another member of the workspace, it is added with its path
+and version instead of being looked up on crates.io. Unless the package sets `publish = false`, a
+warning is printed for git and path dependencies without a version, as they can not be published.
+For packages with several `[[bin]]` targets, a note lists the binaries and the features they need
+to be built with. If the package declares a `rust-version`, a warning is printed for crates.io
+dependencies that need a newer Rust (see `--bump-msrv`). For `-sys` crates, a note names the
+native library they link to, and a warning is printed if another `-sys` dependency of the package
+links to the same library, which cargo does not allow. Likewise, if the package declares an
+`edition`, a warning is printed for crates.io dependencies that use a newer edition, naming the
+latest version that does not (see `--compat-edition`). A warning is also printed for crates.io
+dependencies whose description says they were renamed or deprecated in favour of another crate
+(see `--follow-renames`).
+New entries are written in the style of the manifest: its spacing around `=`, after commas and
+inside brackets and braces, and its quotes (e.g. `serde={version='1.0'}` in a manifest that uses
+that style).
 
 Please note that Cargo treats versions like "1.2.3" as "^1.2.3" (and that "^1.2.3" is specified
 as ">=1.2.3 and <2.0.0"). By default, `cargo add` will use this format, as it is the one that the
@@ -77,13 +210,117 @@ crates.io registry suggests. One goal of `cargo add` is to prevent you from usin
 dependencies (version set to "*").
 "#;
 
-fn print_msg(dep: &Dependency, section: &[String], optional: bool) -> Result<()> {
-    let colorchoice = if atty::is(atty::Stream::Stdout) {
-        ColorChoice::Auto
-    } else {
-        ColorChoice::Never
+/// A dependency added by `cargo add`, as reported with `--output json`.
+#[derive(Debug, Serialize)]
+struct AddedDependency<'a> {
+    name: &'a str,
+    version: Option<&'a str>,
+    git: Option<&'a str>,
+    path: Option<&'a str>,
+    optional: bool,
+    features: Option<&'a [String]>,
+    section: Vec<String>,
+    /// The workspace member the dependency was added to, with `--members`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<&'a str>,
+    /// Where the version came from in the registry index, with `--provenance`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provenance: Option<&'a Provenance>,
+}
+
+/// The result of `cargo add`, as reported with `--output json`.
+#[derive(Debug, Serialize)]
+struct AddReport<'a> {
+    added: Vec<AddedDependency<'a>>,
+    warnings: &'a Warnings,
+}
+
+/// How the version of a crate is picked, as reported with `--explain --output json`.
+#[derive(Debug, Serialize)]
+struct ExplainedCrate {
+    name: String,
+    /// Whether the version is picked from the registry, rather than given or taken from a git
+    /// repository, a path or the workspace
+    looked_up: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prereleases: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_age_days: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    picked: Option<String>,
+    versions: Vec<VersionChoice>,
+}
+
+/// The result of `cargo add --explain`, as reported with `--output json`.
+#[derive(Debug, Serialize)]
+struct ExplainReport {
+    /// The `rust-version` of the package, if it declares one
+    rust_version: Option<String>,
+    crates: Vec<ExplainedCrate>,
+}
+
+/// Get the stream human readable messages are written to. With `--output json`, stdout is
+/// reserved for the JSON result.
+fn message_stream(format: OutputFormat) -> StandardStream {
+    let color_choice = |is_tty| {
+        if is_tty {
+            ColorChoice::Auto
+        } else {
+            ColorChoice::Never
+        }
     };
-    let mut output = StandardStream::stdout(colorchoice);
+    match format {
+        OutputFormat::Human => StandardStream::stdout(color_choice(atty::is(atty::Stream::Stdout))),
+        OutputFormat::Json => StandardStream::stderr(color_choice(atty::is(atty::Stream::Stderr))),
+    }
+}
+
+/// Describe the sections a dependency is added to, e.g. `dependencies`, or
+/// ``dependencies for targets `cfg(unix)`, `cfg(windows)` `` when it goes to several targets.
+fn describe_sections(sections: &[&DepTable]) -> String {
+    if sections.len() == 1 {
+        return sections[0].to_string();
+    }
+    let targets: Vec<_> = sections
+        .iter()
+        .filter_map(|section| section.target())
+        .map(|target| format!("`{}`", target))
+        .collect();
+    format!(
+        "{} for targets {}",
+        sections[0].kind().kind_table(),
+        targets.join(", ")
+    )
+}
+
+/// Batch the dependencies of `groups` by crate: a dependency added to several targets (see
+/// `--target`) is listed once, with all of its sections.
+fn batch_by_crate(groups: &[(DepTable, Vec<Dependency>)]) -> Vec<(&Dependency, Vec<&DepTable>)> {
+    let mut batches: Vec<(&Dependency, Vec<&DepTable>)> = Vec::new();
+    for &(ref section, ref deps) in groups {
+        for dep in deps {
+            let batch = batches.iter().position(|&(other, ref sections)| {
+                other == dep
+                    && sections[0].kind() == section.kind()
+                    && sections[0].target().is_some()
+                    && section.target().is_some()
+            });
+            match batch {
+                Some(i) => batches[i].1.push(section),
+                None => batches.push((dep, vec![section])),
+            }
+        }
+    }
+    batches
+}
+
+fn print_msg(
+    output: &mut StandardStream,
+    dep: &Dependency,
+    sections: &[&DepTable],
+    optional: bool,
+    info: Option<&CrateInfo>,
+) -> Result<()> {
     output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
     write!(output, "{:>12}", "Adding")?;
     output.reset()?;
@@ -97,62 +334,1238 @@ fn print_msg(dep: &Dependency, section: &[String], optional: bool) -> Result<()>
     if optional {
         write!(output, " optional")?;
     }
-    let section = if section.len() == 1 {
-        section[0].clone()
+    write!(output, " {}", describe_sections(sections))?;
+    if let Some(info) = info {
+        write!(
+            output,
+            " (license: {}, MSRV: {})",
+            info.license.as_ref().map_or("unknown", |l| l.as_str()),
+            info.rust_version.as_ref().map_or("unspecified", |v| v.as_str())
+        )?;
+    }
+    writeln!(output)?;
+    Ok(())
+}
+
+/// Point out which binary targets the new dependencies affect, if the package has several: all of
+/// them, but some only with features enabled.
+fn print_target_notes(
+    output: &mut StandardStream,
+    manifest: &Manifest,
+    groups: &[(DepTable, Vec<Dependency>)],
+) -> Result<()> {
+    let deps: Vec<_> = groups
+        .iter()
+        .filter(|&&(ref section, _)| section.kind() == DepKind::Normal)
+        .flat_map(|&(_, ref deps)| deps)
+        .collect();
+    let bins = manifest.bin_targets();
+    if deps.is_empty() || bins.len() < 2 {
+        return Ok(());
+    }
+
+    let names: Vec<_> = bins.iter().map(|bin| format!("`{}`", bin.name)).collect();
+    writeln!(
+        output,
+        "note: all binary targets ({}) can use the new dependencies",
+        names.join(", ")
+    )?;
+    for bin in bins.iter().filter(|bin| !bin.required_features.is_empty()) {
+        writeln!(
+            output,
+            "note: `{}` is only built with `--features {}`",
+            bin.name,
+            bin.required_features.join(",")
+        )?;
+    }
+    for dep in deps.iter().filter(|dep| dep.optional()) {
+        writeln!(
+            output,
+            "note: `{}` is optional, the binaries only get it with `--features {}`",
+            dep.name, dep.name
+        )?;
+    }
+    Ok(())
+}
+
+/// Look up the license and MSRV of the crates.io dependencies, for `--verbose`. A failed lookup
+/// only leaves out the information.
+fn get_crate_infos(
+    groups: &[(DepTable, Vec<Dependency>)],
+    warnings: &mut Warnings,
+) -> BTreeMap<String, CrateInfo> {
+    let mut infos = BTreeMap::new();
+    for dep in groups.iter().flat_map(|&(_, ref deps)| deps) {
+        let version = match dep.version() {
+            Some(version) if dep.is_registry() => version,
+            _ => continue,
+        };
+        match get_crate_info(&dep.name, version) {
+            Ok(info) => {
+                infos.insert(dep.name.clone(), info);
+            }
+            Err(err) => warnings.push(
+                WarningKind::Other,
+                format!("cannot get the license and MSRV of `{}`: {}", dep.name, err),
+            ),
+        }
+    }
+    infos
+}
+
+/// Look up where the versions of the crates.io dependencies come from, for `--provenance`. A
+/// failed lookup only leaves out the information.
+fn get_provenances(
+    groups: &[(DepTable, Vec<Dependency>)],
+    warnings: &mut Warnings,
+) -> BTreeMap<String, Provenance> {
+    let mut provenances = BTreeMap::new();
+    for dep in groups.iter().flat_map(|&(_, ref deps)| deps) {
+        let version = match dep.version() {
+            Some(version) if dep.is_registry() => version,
+            _ => continue,
+        };
+        match get_provenance(&dep.name, version, warnings) {
+            Ok(provenance) => {
+                provenances.insert(dep.name.clone(), provenance);
+            }
+            Err(err) => warnings.push(
+                WarningKind::Other,
+                format!("cannot get the provenance of `{}`: {}", dep.name, err),
+            ),
+        }
+    }
+    provenances
+}
+
+/// Print where the versions of the dependencies come from.
+fn print_provenances(
+    output: &mut StandardStream,
+    provenances: &BTreeMap<String, Provenance>,
+) -> Result<()> {
+    for (name, provenance) in provenances {
+        writeln!(
+            output,
+            "note: `{}` {} has checksum {} in {} (ETag {})",
+            name,
+            provenance.version,
+            provenance.checksum.as_ref().map(String::as_str).unwrap_or("(none)"),
+            provenance.index_url,
+            provenance.etag.as_ref().map(String::as_str).unwrap_or("(none)")
+        )?;
+    }
+    Ok(())
+}
+
+/// Features `-sys` crates commonly offer to build their native library from source instead of
+/// linking to the one installed on the system.
+const FROM_SOURCE_FEATURES: &[&str] = &["vendored", "bundled", "static", "vendor"];
+
+/// Look up the native libraries the new `-sys` crates link to (their `links` key), by crate name.
+/// A failed lookup only leaves out the information.
+fn get_native_libraries(
+    groups: &[(DepTable, Vec<Dependency>)],
+    warnings: &mut Warnings,
+) -> BTreeMap<String, String> {
+    let mut libraries = BTreeMap::new();
+    for dep in groups.iter().flat_map(|&(_, ref deps)| deps) {
+        let version = match dep.version() {
+            Some(version) if dep.is_registry() && dep.name.ends_with("-sys") => version,
+            _ => continue,
+        };
+        match get_crate_links(&dep.name, version, warnings) {
+            Ok(Some(library)) => {
+                libraries.insert(dep.name.clone(), library);
+            }
+            Ok(None) => {}
+            Err(err) => warnings.push(
+                WarningKind::Other,
+                format!("cannot get the native library of `{}`: {}", dep.name, err),
+            ),
+        }
+    }
+    libraries
+}
+
+/// Point out that the new `-sys` crates need their native library, and which of their features
+/// build it from source instead, if any.
+fn print_native_library_notes(
+    output: &mut StandardStream,
+    groups: &[(DepTable, Vec<Dependency>)],
+    libraries: &BTreeMap<String, String>,
+    warnings: &mut Warnings,
+) -> Result<()> {
+    for dep in groups.iter().flat_map(|&(_, ref deps)| deps) {
+        let library = match libraries.get(&dep.name) {
+            Some(library) => library,
+            None => continue,
+        };
+        let from_source = dep.version()
+            .and_then(|version| get_crate_features(&dep.name, version, warnings).ok())
+            .and_then(|features| {
+                features
+                    .into_iter()
+                    .find(|feature| FROM_SOURCE_FEATURES.contains(&feature.name.as_str()))
+            })
+            .map(|feature| feature.name);
+        let enabled = |feature: &str| {
+            dep.features()
+                .map_or(false, |features| features.iter().any(|f| f == feature))
+        };
+        match from_source {
+            Some(ref feature) if enabled(feature.as_str()) => writeln!(
+                output,
+                "note: `{}` links to the native library `{}`, which its `{}` feature builds \
+                 from source",
+                dep.name, library, feature
+            )?,
+            Some(ref feature) => writeln!(
+                output,
+                "note: `{}` links to the native library `{}`, which has to be installed (with \
+                 its development files) where the package is built, unless the `{}` feature \
+                 is enabled to build it from source",
+                dep.name, library, feature
+            )?,
+            None => writeln!(
+                output,
+                "note: `{}` links to the native library `{}`, which has to be installed (with \
+                 its development files) where the package is built",
+                dep.name, library
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// Warn about the `-sys` crates of the manifest that link to the same native library as a new
+/// one: cargo allows only one package per native library in the dependency graph.
+fn check_native_library_conflicts(
+    manifest: &Manifest,
+    libraries: &BTreeMap<String, String>,
+    warnings: &mut Warnings,
+) {
+    if libraries.is_empty() {
+        return;
+    }
+    let mut found = Vec::new();
+    for (_, table) in manifest.get_sections() {
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (key, entry) in table.iter() {
+            // Renamed dependencies name the crate with `package`.
+            let name = entry["package"].as_str().unwrap_or(key).to_owned();
+            let version = match Dependency::from_toml(key, entry) {
+                Some(ref dep) if dep.is_registry() => dep.version().map(String::from),
+                _ => None,
+            };
+            if let Some(version) = version {
+                if name.ends_with("-sys") && !libraries.contains_key(&name) {
+                    found.push((name, version));
+                }
+            }
+        }
+    }
+
+    found.sort();
+    found.dedup();
+    for (name, version) in found {
+        let library = match get_crate_links(&name, &version, warnings) {
+            Ok(Some(library)) => library,
+            _ => continue,
+        };
+        for (new, _) in libraries.iter().filter(|&(_, l)| *l == library) {
+            warnings.push(
+                WarningKind::NativeLibrary,
+                format!(
+                    "`{}` links to the native library `{}`, like `{}` already does. Cargo allows \
+                     only one package per native library, so the build will fail until one of \
+                     them is removed.",
+                    new, library, name
+                ),
+            );
+        }
+    }
+}
+
+/// Let the user pick the features to enable for a dependency from the ones it offers.
+fn pick_features(dep: Dependency, warnings: &mut Warnings) -> Result<Dependency> {
+    let version = if dep.is_registry() {
+        dep.version()
     } else {
-        format!("{} for target `{}`", &section[2], &section[1])
+        None
+    };
+    let features: Vec<_> = match version {
+        Some(version) => get_crate_features(&dep.name, version, warnings)?
+            .into_iter()
+            .filter(|feature| feature.name != "default")
+            .collect(),
+        None => {
+            warnings.push(
+                WarningKind::Other,
+                format!(
+                    "cannot list the features of `{}`, it is not a crates.io dependency",
+                    dep.name
+                ),
+            );
+            return Ok(dep);
+        }
+    };
+    if features.is_empty() {
+        return Ok(dep);
+    }
+
+    let items: Vec<_> = features
+        .iter()
+        .map(|feature| {
+            if feature.default {
+                ChecklistItem::new(&dep.name, &format!("{} (default)", feature.name))
+            } else {
+                ChecklistItem::new(&dep.name, &feature.name)
+            }
+        })
+        .collect();
+
+    let picked = checklist(&format!("Features of `{}` to enable", dep.name), &items)?;
+    if picked.is_empty() {
+        return Ok(dep);
+    }
+
+    let mut enabled = dep.features().map(|f| f.to_vec()).unwrap_or_default();
+    for i in picked {
+        if !enabled.contains(&features[i].name) {
+            enabled.push(features[i].name.clone());
+        }
+    }
+    Ok(dep.set_features(Some(enabled)))
+}
+
+/// The features a path dependency offers, from its manifest. The path is relative to the directory
+/// of the manifest that is edited, as cargo reads it. `None` if the manifest can not be read.
+fn path_features(args: &Args, path: &str) -> Option<Vec<FeatureInfo>> {
+    let manifest_path = find(&args.flag_manifest_path).ok()?;
+    let dir = manifest_path.parent()?;
+    let manifest = Manifest::open(&Some(dir.join(path).join("Cargo.toml"))).ok()?;
+    Some(manifest.features())
+}
+
+/// Check the features requested for a crates.io or path dependency against the ones it offers:
+/// they have to exist, and with `--no-default-features`, they must not just enable the default
+/// features again. Disabling the default features of a crate that has none only gets a warning.
+fn verify_features(args: &Args, dep: &Dependency, warnings: &mut Warnings) -> Result<()> {
+    let requested = dep.features().unwrap_or(&[]);
+    if requested.is_empty() && dep.default_features() {
+        return Ok(());
+    }
+
+    // The manifest of a path dependency is at hand, even offline.
+    let (source, offered) = match (dep.path(), dep.version()) {
+        (Some(path), _) if dep.git().is_none() => match path_features(args, path) {
+            Some(offered) => (format!("at `{}`", path), offered),
+            None => return Ok(()),
+        },
+        (_, Some(version)) if dep.is_registry() && !args.flag_offline => (
+            version.to_owned(),
+            get_crate_features(&dep.name, version, warnings)?,
+        ),
+        _ => return Ok(()),
     };
-    writeln!(output, " {}", section)?;
+    let available: Vec<_> = offered.iter().map(|feature| feature.name.clone()).collect();
+    let unknown: Vec<_> = requested
+        .iter()
+        // `dependency/feature` entries are left for cargo to check.
+        .filter(|feature| !feature.contains('/') && !available.contains(feature))
+        .cloned()
+        .collect();
+
+    if !unknown.is_empty() {
+        return Err(Error::UnknownFeatures {
+            name: dep.name.clone(),
+            source: source,
+            unknown: unknown,
+            available: available,
+        });
+    }
+
+    if dep.default_features() {
+        return Ok(());
+    }
+    // A feature that only enables `default` undoes `--no-default-features`.
+    let reenabling: Vec<_> = requested
+        .iter()
+        .filter(|name| {
+            *name == "default" || offered.iter().any(|feature| {
+                feature.name == **name && !feature.enables.is_empty()
+                    && feature.enables.iter().all(|f| f == "default")
+            })
+        })
+        .cloned()
+        .collect();
+    if !reenabling.is_empty() {
+        return Err(Error::ContradictoryFeatures {
+            name: dep.name.clone(),
+            features: reenabling,
+        });
+    }
+    let has_defaults = offered
+        .iter()
+        .any(|feature| feature.name == "default" && !feature.enables.is_empty());
+    if !has_defaults {
+        warnings.push(
+            WarningKind::Other,
+            format!(
+                "`{}` has no default features, so `--no-default-features` has no effect",
+                dep.name
+            ),
+        );
+    }
     Ok(())
 }
 
-fn handle_add(args: &Args) -> Result<()> {
-    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
-    let mut manifest = Manifest::open(&manifest_path)?;
-    let deps = &args.parse_dependencies()?;
+/// Print a summary of the workspace members a dependency was added to.
+fn print_summary(
+    output: &mut StandardStream,
+    results: &[(&WorkspaceMember, Result<()>)],
+) -> Result<()> {
+    for &(member, ref result) in results {
+        let (status, color) = match *result {
+            Ok(()) => ("Updated", Color::Green),
+            Err(_) => ("Failed", Color::Red),
+        };
+        output.set_color(ColorSpec::new().set_fg(Some(color)).set_bold(true))?;
+        write!(output, "{:>12}", status)?;
+        output.reset()?;
+        write!(output, " {} ({})", member.name, member.path)?;
+        match *result {
+            Ok(()) => writeln!(output)?,
+            Err(ref err) => writeln!(output, ": {}", err)?,
+        }
+    }
+    Ok(())
+}
 
-    deps.iter()
-        .map(|dep| {
-            if !args.flag_quiet {
-                print_msg(dep, &args.get_section(), args.flag_optional)?;
-            }
-            manifest
-                .insert_into_table(&args.get_section(), dep)
-                .map_err(Into::into)
+/// Parse a Rust version like `1.56` into a version that can be compared.
+fn parse_rust_version(version: &str) -> Option<semver::Version> {
+    let mut numbers: Vec<_> = version.trim().split('.').collect();
+    while numbers.len() < 3 {
+        numbers.push("0");
+    }
+    semver::Version::parse(&numbers.join(".")).ok()
+}
+
+/// Compare the MSRV of the new dependencies with the `rust-version` of the package, if it declares
+/// one: warn about the dependencies that need a newer Rust or, with `--bump-msrv`, raise
+/// `rust-version` to the highest MSRV among them.
+fn check_rust_version(
+    args: &Args,
+    manifest: &mut Manifest,
+    groups: &[(DepTable, Vec<Dependency>)],
+    infos: &BTreeMap<String, CrateInfo>,
+    output: &mut StandardStream,
+    warnings: &mut Warnings,
+) -> Result<()> {
+    let declared = match manifest.rust_version() {
+        Some(declared) => declared.to_owned(),
+        None => return Ok(()),
+    };
+    let declared_version = match parse_rust_version(&declared) {
+        Some(version) => version,
+        None => return Ok(()),
+    };
+
+    let mut highest: Option<(semver::Version, &str, &str)> = None;
+    for dep in groups.iter().flat_map(|&(_, ref deps)| deps) {
+        let required = match infos.get(&dep.name).and_then(|info| info.rust_version.as_ref()) {
+            Some(required) => required,
+            None => continue,
+        };
+        let version = match parse_rust_version(required) {
+            Some(version) => version,
+            None => continue,
+        };
+        if version <= declared_version {
+            continue;
+        }
+        if !args.flag_bump_msrv {
+            warnings.push(
+                WarningKind::RustVersion,
+                format!(
+                    "`{}` needs Rust {}, which is newer than the `rust-version` of the package \
+                     ({}). Pass `--bump-msrv` to raise it.",
+                    dep.name, required, declared
+                ),
+            );
+        }
+        if highest.as_ref().map_or(true, |&(ref h, _, _)| version > *h) {
+            highest = Some((version, required.as_str(), dep.name.as_str()));
+        }
+    }
+
+    if let (true, Some((_, required, name))) = (args.flag_bump_msrv, highest) {
+        if !args.flag_quiet {
+            output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+            write!(output, "{:>12}", "Updating")?;
+            output.reset()?;
+            writeln!(
+                output,
+                " rust-version from {} to {} (needed by {})",
+                declared, required, name
+            )?;
+        }
+        manifest.set_rust_version(required);
+    }
+    Ok(())
+}
+
+/// Parse an edition like `2018` into a year that can be compared.
+fn edition_year(edition: &str) -> Option<u32> {
+    edition.trim().parse().ok()
+}
+
+/// Get the oldest edition declared by the manifest or, with `--members`, by the members of the
+/// workspace the dependencies are added to, if any declares one.
+fn package_edition(manifest: Option<&Manifest>, members: &[WorkspaceMember]) -> Option<String> {
+    let editions: Vec<String> = match manifest {
+        Some(manifest) => manifest.edition().map(String::from).into_iter().collect(),
+        None => members
+            .iter()
+            .filter_map(|member| Manifest::open(&Some(member.manifest_path.clone())).ok())
+            .filter_map(|manifest| manifest.edition().map(String::from))
+            .collect(),
+    };
+    editions
+        .into_iter()
+        .filter_map(|edition| edition_year(&edition).map(|year| (year, edition)))
+        .min()
+        .map(|(_, edition)| edition)
+}
+
+/// Check whether a new crates.io dependency says it was renamed, or deprecated in favour of another
+/// crate: warn, naming the successor, or, with `--follow-renames`, switch to the successor. It is
+/// added in its latest version, keeping the version operator, features and options of the entry.
+/// A failed lookup only leaves out the check.
+fn check_successor(
+    args: &Args,
+    dep: Dependency,
+    output: &mut StandardStream,
+    warnings: &mut Warnings,
+) -> Result<Dependency> {
+    if !dep.is_registry() {
+        return Ok(dep);
+    }
+    let successor = match get_crate_successor(&dep.name) {
+        Ok(Some(successor)) => successor,
+        Ok(None) | Err(_) => return Ok(dep),
+    };
+    if !args.flag_follow_renames {
+        warnings.push(
+            WarningKind::Superseded,
+            format!(
+                "`{}` says it was renamed or deprecated in favour of `{}`; pass \
+                 `--follow-renames` to add that crate instead.",
+                dep.name, successor
+            ),
+        );
+        return Ok(dep);
+    }
+
+    let old = dep.version().unwrap_or_default();
+    let operator = &old[..old.find(|c: char| c.is_ascii_digit()).unwrap_or(0)];
+    let latest = args.latest_dependency(&successor, warnings)?;
+    let version = format!(
+        "{}{}",
+        operator,
+        latest.version().ok_or(cargo_edit::Error::NoVersionsAvailable)?
+    );
+    if !args.flag_quiet {
+        output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+        write!(output, "{:>12}", "Following")?;
+        output.reset()?;
+        writeln!(output, " the rename of {} to {} {}", dep.name, successor, version)?;
+    }
+    Ok(Dependency::new(&successor)
+        .set_version(&version)
+        .set_optional(dep.optional())
+        .set_default_features(dep.default_features())
+        .set_features(dep.features().map(|features| features.to_vec())))
+}
+
+/// Compare the edition of a new crates.io dependency with the `edition` of the package: warn if
+/// the dependency uses a newer edition, naming the latest version of the crate that does not, or,
+/// with `--compat-edition`, switch to that version.
+fn check_edition(
+    args: &Args,
+    edition: &str,
+    dep: Dependency,
+    infos: &mut BTreeMap<String, CrateInfo>,
+    output: &mut StandardStream,
+    warnings: &mut Warnings,
+) -> Result<Dependency> {
+    let used = match infos.get(&dep.name).and_then(|info| info.edition.clone()) {
+        Some(used) => used,
+        None => return Ok(dep),
+    };
+    let newer = match (edition_year(&used), edition_year(edition)) {
+        (Some(used), Some(declared)) => used > declared,
+        _ => false,
+    };
+    if !dep.is_registry() || !newer {
+        return Ok(dep);
+    }
+
+    let old = dep.version().unwrap_or_default().to_owned();
+    let prereleases = args.get_prereleases_of(&dep.name)?;
+    let compatible = match get_latest_version_for_edition(&dep.name, edition, prereleases)? {
+        Some(compatible) => compatible,
+        None => {
+            warnings.push(
+                WarningKind::Edition,
+                format!(
+                    "`{}` {} uses the {} edition, which is newer than the edition of the package \
+                     ({}), and no version of it is known to use the {} edition or an older one",
+                    dep.name, old, used, edition, edition
+                ),
+            );
+            return Ok(dep);
+        }
+    };
+    if !args.flag_compat_edition {
+        warnings.push(
+            WarningKind::Edition,
+            format!(
+                "`{}` {} uses the {} edition, which is newer than the edition of the package \
+                 ({}). Its latest version for the {} edition is {}; pass `--compat-edition` to \
+                 add that one.",
+                dep.name, old, used, edition, edition, compatible
+            ),
+        );
+        return Ok(dep);
+    }
+
+    // Keep the operator of the requirement, e.g. `=` for `--upgrade none`.
+    let operator = &old[..old.find(|c: char| c.is_ascii_digit()).unwrap_or(0)];
+    let version = format!("{}{}", operator, compatible);
+    if !args.flag_quiet {
+        output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+        write!(output, "{:>12}", "Downgrading")?;
+        output.reset()?;
+        writeln!(
+            output,
+            " {} from {} to {} (for the {} edition)",
+            dep.name, old, version, edition
+        )?;
+    }
+    match get_crate_info(&dep.name, &version) {
+        Ok(info) => {
+            infos.insert(dep.name.clone(), info);
+        }
+        Err(_) => {
+            infos.remove(&dep.name);
+        }
+    }
+    Ok(dep.set_version(&version))
+}
+
+/// Add the dependencies to their sections of a manifest and write it back to `manifest_path`.
+fn add_to_manifest(
+    args: &Args,
+    mut manifest: Manifest,
+    manifest_path: &Option<PathBuf>,
+    groups: &[(DepTable, Vec<Dependency>)],
+    infos: &BTreeMap<String, CrateInfo>,
+    libraries: &BTreeMap<String, String>,
+    output: &mut StandardStream,
+    warnings: &mut Warnings,
+) -> Result<()> {
+    let placement = args.get_section_placement()?;
+    manifest.lint(warnings);
+    check_native_library_conflicts(&manifest, libraries, warnings);
+
+    // `cargo publish` needs a version for every dependency; unpublished packages can do without.
+    if manifest.is_publishable() {
+        let deps = groups.iter().flat_map(|&(_, ref deps)| deps);
+        for dep in deps.filter(|dep| dep.version().is_none()) {
+            let source = if dep.git().is_some() { "git" } else { "path" };
+            warnings.push(
+                WarningKind::Unpublishable,
+                format!(
+                    "`{}` is a {} dependency without a version, so this package can not be \
+                     published. Pass `--vers`, or set `publish = false` in `[package]`.",
+                    dep.name, source
+                ),
+            );
+        }
+    }
+
+    let target_groups = args.expand_targets(groups)?;
+    if !args.flag_quiet {
+        for (dep, sections) in batch_by_crate(&target_groups) {
+            let info = infos.get(&dep.name).filter(|_| args.flag_verbose);
+            print_msg(output, dep, &sections, dep.optional(), info)?;
+        }
+    }
+    target_groups
+        .iter()
+        .flat_map(|&(ref section, ref deps)| deps.iter().map(move |dep| (section, dep)))
+        .map(|(section, dep)| {
+            match args.flag_group {
+                Some(ref label) => manifest.insert_into_group(section, dep, placement, label),
+                None => manifest.insert_into_table_at(section, dep, placement),
+            }.map_err(Into::into)
         })
         .collect::<Result<Vec<_>>>()
         .map_err(|err| {
             eprintln!("Could not edit `Cargo.toml`.\n\nERROR: {}", err);
             err
         })?;
+    if let Some(ref reason) = args.flag_reason {
+        for dep in groups.iter().flat_map(|&(_, ref deps)| deps) {
+            manifest.set_pin_reason(&dep.name, reason)?;
+        }
+    }
+    if args.flag_emulate_optional_dev {
+        for dep in groups.iter().flat_map(|&(_, ref deps)| deps) {
+            let feature = format!("test-{}", dep.name);
+            manifest.add_feature(&feature, &[format!("dep:{}", dep.name)])?;
+            if !args.flag_quiet {
+                writeln!(
+                    output,
+                    "note: `{}` is enabled by the feature `{}`: gate the tests that use it with \
+                     `#[cfg(feature = \"{}\")]` and run them with `cargo test --features {}`",
+                    dep.name, feature, feature, feature
+                )?;
+            }
+        }
+    }
+    check_rust_version(args, &mut manifest, groups, infos, output, warnings)?;
 
-    let mut file = Manifest::find_file(&manifest_path)?;
-    manifest.write_to_file(&mut file)?;
+    if !args.flag_quiet {
+        print_target_notes(output, &manifest, groups)?;
+    }
+
+    if !args.flag_dry_run {
+        manifest.write_to_path(manifest_path)?;
+    }
+    Ok(())
+}
 
+/// With `--locked`, check that a version in Cargo.lock meets the requirement of every crates.io
+/// dependency, so adding them does not change the locked versions.
+fn check_locked_versions(args: &Args, groups: &[(DepTable, Vec<Dependency>)]) -> Result<()> {
+    let locked = args.get_locked_versions()?;
+    let deps = groups.iter().flat_map(|&(_, ref deps)| deps);
+    for dep in deps.filter(|dep| dep.is_registry()) {
+        let version = match dep.version() {
+            Some(version) => version,
+            None => continue,
+        };
+        let req = semver::VersionReq::parse(version)
+            .map_err(cargo_edit::Error::InvalidVersionRequirement)?;
+        let versions = locked.get(&dep.name).map(Vec::as_slice).unwrap_or(&[]);
+        if !versions.iter().any(|locked| req.matches(locked)) {
+            return Err(Error::NotSatisfiedByLockFile {
+                name: dep.name.clone(),
+                version: version.to_owned(),
+                locked: versions.iter().map(|v| v.to_string()).collect(),
+            });
+        }
+    }
     Ok(())
 }
 
-fn main() {
+/// Describe which prerelease versions may be picked, e.g. `rc` for release candidates and
+/// releases.
+fn prerelease_setting(prereleases: Prereleases) -> String {
+    match prereleases {
+        Prereleases::None => "none".to_owned(),
+        Prereleases::Channel(channel) => format!("{:?}", channel).to_lowercase(),
+        Prereleases::All => "all".to_owned(),
+    }
+}
+
+/// Print how the version of a crate is picked, one published version per line. Versions older
+/// than the picked one are only counted.
+fn print_explanation(
+    output: &mut StandardStream,
+    krate: &ExplainedCrate,
+    rust_version: Option<&str>,
+) -> Result<()> {
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(output, "{:>12}", "Explaining")?;
+    output.reset()?;
+    let prereleases = match krate.prereleases {
+        Some(ref prereleases) => prereleases,
+        None => {
+            writeln!(
+                output,
+                " {}: not picked from the registry (the version, a git repository or a path was \
+                 given, or it is a member of the workspace)",
+                krate.name
+            )?;
+            return Ok(());
+        }
+    };
+    let max_age = match krate.max_age_days {
+        Some(days) => format!("{} days", days),
+        None => "none".to_owned(),
+    };
+    writeln!(
+        output,
+        " {} (prereleases: {}, yanked: skipped, max-age: {})",
+        krate.name, prereleases, max_age
+    )?;
+
+    let shown: Vec<_> = krate
+        .versions
+        .iter()
+        .filter(|v| v.verdict != VersionVerdict::Older)
+        .collect();
+    let width = shown.iter().map(|v| v.version.len()).max().unwrap_or(0);
+    for version in &shown {
+        write!(output, "{:>12} {:w$}  {}", "", version.version, version.verdict, w = width)?;
+        let details: Vec<_> = version
+            .published
+            .iter()
+            .map(|day| format!("published {}", day))
+            .chain(version.rust_version.iter().map(|msrv| format!("MSRV {}", msrv)))
+            .collect();
+        if !details.is_empty() {
+            write!(output, " ({})", details.join(", "))?;
+        }
+        writeln!(output)?;
+    }
+    let older = krate.versions.len() - shown.len();
+    if older > 0 {
+        writeln!(output, "{:>12} and {} older version(s)", "", older)?;
+    }
+
+    let picked = match krate.versions.iter().find(|v| v.verdict == VersionVerdict::Picked) {
+        Some(picked) => picked,
+        None => {
+            writeln!(output, "note: no version of `{}` is left to pick", krate.name)?;
+            return Ok(());
+        }
+    };
+    let msrvs = (
+        picked.rust_version.as_ref().and_then(|v| parse_rust_version(v)),
+        rust_version.and_then(parse_rust_version),
+    );
+    if let (Some(required), Some(declared)) = msrvs {
+        if required > declared {
+            writeln!(
+                output,
+                "note: `{}` {} needs Rust {}, newer than the `rust-version` of the package \
+                 ({}); it is picked anyway, with a warning (or `--bump-msrv`)",
+                krate.name,
+                picked.version,
+                picked.rust_version.as_ref().map_or("", String::as_str),
+                rust_version.unwrap_or("")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Explain how the version of each crate is picked, for `--explain`, without editing anything.
+fn explain(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let rust_version = Manifest::open(&manifest_path)
+        .ok()
+        .and_then(|manifest| manifest.rust_version().map(String::from));
+
+    let crates = args.get_registry_lookups()?
+        .into_iter()
+        .map(|(name, prereleases)| -> Result<ExplainedCrate> {
+            let prereleases = match prereleases {
+                Some(prereleases) => prereleases,
+                None => {
+                    return Ok(ExplainedCrate {
+                        name: name,
+                        looked_up: false,
+                        prereleases: None,
+                        max_age_days: None,
+                        picked: None,
+                        versions: vec![],
+                    });
+                }
+            };
+            let explanation = explain_latest_version(&name, prereleases, args.flag_max_age)?;
+            Ok(ExplainedCrate {
+                name: name,
+                looked_up: true,
+                prereleases: Some(prerelease_setting(prereleases)),
+                max_age_days: args.flag_max_age,
+                picked: explanation.picked().map(|v| v.version.clone()),
+                versions: explanation.versions,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if args.flag_output == OutputFormat::Json {
+        let report = ExplainReport {
+            rust_version: rust_version,
+            crates: crates,
+        };
+        println!(
+            "{}",
+            serde_json::to_string(&report).expect("report is correctly serialized")
+        );
+    } else {
+        let mut output = message_stream(args.flag_output);
+        for krate in &crates {
+            print_explanation(&mut output, krate, rust_version.as_ref().map(String::as_str))?;
+        }
+    }
+    Ok(())
+}
+
+/// Open a manifest and check that it can be edited, so a broken one fails before any lookup.
+fn open_manifest(args: &Args, manifest_path: &Option<PathBuf>) -> Result<Manifest> {
+    let manifest = Manifest::open_resolving(manifest_path, args.get_conflict_side())?;
+    manifest.validate()?;
+    Ok(manifest)
+}
+
+fn handle_add(args: &Args) -> Result<()> {
+    args.get_section()?;
+    // Fail on an invalid placement before anything is looked up.
+    args.get_section_placement()?;
+    if args.flag_max_age.is_some() && args.flag_offline {
+        return Err(Error::MaxAgeOffline);
+    }
+    if args.flag_bump_msrv && args.flag_offline {
+        return Err(Error::BumpMsrvOffline);
+    }
+    if args.flag_compat_edition && args.flag_offline {
+        return Err(Error::CompatEditionOffline);
+    }
+    if args.flag_explain {
+        if args.flag_offline {
+            return Err(Error::ExplainOffline);
+        }
+        return explain(args);
+    }
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+
+    // Either a single manifest, or the matching members of the workspace. Matching packages that
+    // are not members are only included on request, and noted otherwise.
+    let mut skipped = Vec::new();
+    let (manifest, members) = match args.flag_members {
+        Some(ref pattern) => {
+            let mut members = workspace_members(&manifest_path)?;
+            let others = [
+                (
+                    excluded_members(&manifest_path)?,
+                    args.flag_include_excluded,
+                    "which the workspace excludes",
+                    "--include-excluded",
+                ),
+                (
+                    nested_packages(&manifest_path)?,
+                    args.flag_include_nested,
+                    "which is not a member of the workspace",
+                    "--include-nested",
+                ),
+            ];
+            for &(ref packages, included, reason, flag) in &others {
+                for package in packages.iter().filter(|package| package.matches(pattern)) {
+                    if included {
+                        members.push(package.clone());
+                    } else {
+                        skipped.push((package.clone(), reason, flag));
+                    }
+                }
+            }
+            let members: Vec<_> = members
+                .into_iter()
+                .filter(|member| member.matches(pattern))
+                .collect();
+            if members.is_empty() {
+                return Err(Error::NoMatchingMembers(pattern.clone()));
+            }
+            (None, members)
+        }
+        None if args.flag_include_excluded => return Err(Error::IncludeExcludedWithoutMembers),
+        None if args.flag_include_nested => return Err(Error::IncludeNestedWithoutMembers),
+        None => {
+            let manifest = open_manifest(args, &manifest_path)?;
+            (Some(manifest), vec![])
+        }
+    };
+    // A broken manifest fails right away, not after the registry was queried. Members whose
+    // manifest is broken are reported with the others, as before.
+    let mut member_manifests: Vec<_> = members
+        .iter()
+        .map(|member| open_manifest(args, &Some(member.manifest_path.clone())))
+        .collect();
+    if !member_manifests.is_empty() && member_manifests.iter().all(|manifest| manifest.is_err()) {
+        return member_manifests.remove(0).map(|_| ());
+    }
+
+    let mut warnings = Warnings::new();
+    let mut groups = args.get_dependency_groups(&mut warnings)?;
+    let mut output = message_stream(args.flag_output);
+    if !args.flag_quiet {
+        for &(ref package, reason, flag) in &skipped {
+            writeln!(
+                output,
+                "note: skipping `{}` at {}, {}; pass `{}` to include it",
+                package.name, package.path, reason, flag
+            )?;
+        }
+    }
+
+    if !args.flag_offline {
+        groups = groups
+            .into_iter()
+            .map(|(section, deps)| {
+                let deps = deps.into_iter()
+                    .map(|dep| check_successor(args, dep, &mut output, &mut warnings))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((section, deps))
+            })
+            .collect::<Result<Vec<_>>>()?;
+    }
+
+    if args.flag_interactive_features {
+        if args.flag_offline {
+            return Err(Error::InteractiveFeaturesOffline);
+        }
+        groups = groups
+            .into_iter()
+            .map(|(section, deps)| {
+                let deps = deps.into_iter()
+                    .map(|dep| pick_features(dep, &mut warnings))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((section, deps))
+            })
+            .collect::<Result<Vec<_>>>()?;
+    }
+
+    if args.flag_locked {
+        check_locked_versions(args, &groups)?;
+    }
+
+    if !args.flag_no_verify_features {
+        for &(_, ref deps) in &groups {
+            for dep in deps {
+                verify_features(args, dep, &mut warnings)?;
+            }
+        }
+    }
+
+    // The MSRV of the crates is also needed to compare it with the `rust-version` of the package.
+    let compare_msrv = args.flag_bump_msrv
+        || manifest
+            .as_ref()
+            .map_or(true, |manifest| manifest.rust_version().is_some());
+    let edition = package_edition(manifest.as_ref(), &members);
+    let mut infos = if (args.flag_verbose && !args.flag_quiet || compare_msrv || edition.is_some())
+        && !args.flag_offline
+    {
+        get_crate_infos(&groups, &mut warnings)
+    } else {
+        BTreeMap::new()
+    };
+    if let Some(ref edition) = edition {
+        let mut checked = Vec::new();
+        for (section, deps) in groups {
+            let mut compatible = Vec::new();
+            for dep in deps {
+                compatible.push(check_edition(
+                    args,
+                    edition,
+                    dep,
+                    &mut infos,
+                    &mut output,
+                    &mut warnings,
+                )?);
+            }
+            checked.push((section, compatible));
+        }
+        groups = checked;
+    }
+    let libraries = if args.flag_offline {
+        BTreeMap::new()
+    } else {
+        get_native_libraries(&groups, &mut warnings)
+    };
+    let results = match manifest {
+        Some(manifest) => {
+            add_to_manifest(
+                args,
+                manifest,
+                &manifest_path,
+                &groups,
+                &infos,
+                &libraries,
+                &mut output,
+                &mut warnings,
+            )?;
+            vec![]
+        }
+        None => {
+            let results: Vec<_> = members
+                .iter()
+                .zip(member_manifests)
+                .map(|(member, manifest)| {
+                    let manifest_path = Some(member.manifest_path.clone());
+                    let result = manifest.and_then(|manifest| {
+                        add_to_manifest(
+                            args,
+                            manifest,
+                            &manifest_path,
+                            &groups,
+                            &infos,
+                            &libraries,
+                            &mut output,
+                            &mut warnings,
+                        )
+                    });
+                    (member, result)
+                })
+                .collect();
+            if !args.flag_quiet {
+                print_summary(&mut output, &results)?;
+            }
+            results
+        }
+    };
+
+    if let Some(change_file) = args.flag_record.as_ref().filter(|_| !args.flag_dry_run) {
+        let manifests = if results.is_empty() {
+            vec![find(&manifest_path)?]
+        } else {
+            results
+                .iter()
+                .filter(|&&(_, ref result)| result.is_ok())
+                .map(|&(member, _)| member.manifest_path.clone())
+                .collect()
+        };
+        let changes: Vec<_> = args
+            .expand_targets(&groups)?
+            .iter()
+            .flat_map(|&(ref section, ref deps)| {
+                deps.iter()
+                    .map(move |dep| RecordedChange::Add(section.clone(), dep.clone()))
+            })
+            .collect();
+        record_changes(change_file, &manifests, &changes)?;
+    }
+
+    if !args.flag_quiet {
+        print_native_library_notes(&mut output, &groups, &libraries, &mut warnings)?;
+    }
+    let provenances = if args.flag_provenance && !args.flag_offline {
+        get_provenances(&groups, &mut warnings)
+    } else {
+        BTreeMap::new()
+    };
+    if !args.flag_quiet {
+        print_provenances(&mut output, &provenances)?;
+    }
+    let cache_stats = registry_cache_stats();
+    if args.flag_verbose && !args.flag_quiet && cache_stats.lookups() > 0 {
+        writeln!(output, "note: {}", cache_stats)?;
+    }
+
+    warnings.render(&mut output)?;
+
+    if args.flag_output == OutputFormat::Json {
+        let packages: Vec<Option<&str>> = if results.is_empty() {
+            vec![None]
+        } else {
+            results
+                .iter()
+                .filter(|&&(_, ref result)| result.is_ok())
+                .map(|&(member, _)| Some(member.name.as_str()))
+                .collect()
+        };
+        let groups = &args.expand_targets(&groups)?;
+        let provenances = &provenances;
+        let report = AddReport {
+            added: packages
+                .iter()
+                .flat_map(|&package| {
+                    groups.iter().flat_map(move |&(ref section, ref deps)| {
+                        deps.iter().map(move |dep| AddedDependency {
+                            name: &dep.name,
+                            version: dep.version(),
+                            git: dep.git(),
+                            path: dep.path(),
+                            optional: dep.optional(),
+                            features: dep.features(),
+                            section: section.to_table(),
+                            package: package,
+                            provenance: provenances.get(&dep.name),
+                        })
+                    })
+                })
+                .collect(),
+            warnings: &warnings,
+        };
+        println!(
+            "{}",
+            serde_json::to_string(&report).expect("report is correctly serialized")
+        );
+    }
+
+    let failed = results.iter().filter(|&&(_, ref r)| r.is_err()).count();
+    if failed > 0 {
+        return Err(Error::MembersFailed {
+            failed: failed,
+            total: results.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// What the command supports, for `--version --json`.
+pub fn self_description() -> SelfDescription {
+    SelfDescription::new("cargo-add")
+        .set_features(&[
+            "sparse-index",
+            "offline",
+            "local-sources",
+            "workspace",
+            "provenance",
+            "record-changes",
+        ])
+        .set_output_formats(&[("add --output json", 1), ("change-file", 1)])
+}
+
+/// Run the command with the arguments of the process.
+pub fn main() {
+    let (argv, notes) = command_args().unwrap_or_else(|err| exit_with_error(&Error::from(err)));
+    for note in notes {
+        eprintln!("{}", note);
+    }
     let args = docopt::Docopt::new(USAGE)
-        .and_then(|d| d.deserialize::<Args>())
+        .and_then(|d| d.argv(argv).deserialize::<Args>())
         .unwrap_or_else(|err| err.exit());
 
     if args.flag_version {
-        println!("cargo-add version {}", env!("CARGO_PKG_VERSION"));
+        if args.flag_json {
+            println!("{}", self_description().to_json());
+        } else {
+            println!("cargo-add version {}", env!("CARGO_PKG_VERSION"));
+        }
         process::exit(0);
     }
 
     if let Err(err) = handle_add(&args) {
-        eprintln!("Command failed due to unhandled error: {}\n", err);
-
-        for e in err.iter().skip(1) {
-            eprintln!("Caused by: {}", e);
-        }
+        exit_with_error(&err);
+    }
+}
 
-        if let Some(backtrace) = err.backtrace() {
-            eprintln!("Backtrace: {:?}", backtrace);
-        }
+/// Print an error with its causes, and exit with a failure.
+fn exit_with_error(err: &Error) -> ! {
+    eprintln!("{}\n", message!("unhandled-error", error = err));
 
-        process::exit(1);
+    let mut cause = err.source();
+    while let Some(e) = cause {
+        eprintln!("{}", message!("caused-by", cause = e));
+        cause = e.source();
     }
+
+    process::exit(1);
 }
@@ -0,0 +1,481 @@
+//! `cargo dep apply`: apply the operations of a change file to several manifests at once
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use cargo_edit::{DepKind, DepTable, Dependency, GitReference, Manifest};
+use toml_edit;
+
+use super::errors::*;
+
+/// The keys an operation of a change file may have.
+const OPERATION_KEYS: &[&str] = &[
+    "op",
+    "crate",
+    "version",
+    "section",
+    "target",
+    "features",
+    "default-features",
+    "optional",
+    "git",
+    "branch",
+    "tag",
+    "rev",
+    "path",
+];
+
+/// What an operation of a change file does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Add the dependency, or update its entry (see `Manifest::insert_into_table`)
+    Add(Dependency),
+    /// Remove the entry of the crate
+    Remove(String),
+    /// Set the version requirement of the crates.io dependency on the crate
+    Upgrade {
+        /// Name of the crate
+        name: String,
+        /// The new version requirement
+        version: String,
+    },
+}
+
+/// An operation of a change file: an action on a dependency section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    /// The section the operation works on
+    pub section: DepTable,
+    /// What it does
+    pub action: Action,
+}
+
+/// A change file: the manifests to change, and the operations to apply to each of them, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeFile {
+    /// Paths of the manifests (or of the directories they are in)
+    pub manifests: Vec<PathBuf>,
+    /// The operations
+    pub operations: Vec<Operation>,
+}
+
+/// What an operation did to a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// The word describing the step, as shown in front of the message
+    pub status: &'static str,
+    /// What was done
+    pub message: String,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+/// Read a string of an operation.
+fn string(table: &toml_edit::Table, key: &str) -> Result<Option<String>> {
+    match table[key] {
+        toml_edit::Item::None => Ok(None),
+        ref item => item.as_str()
+            .map(|s| Some(s.to_owned()))
+            .ok_or_else(|| Error::InvalidChangeFile(format!("`{}` must be a string", key))),
+    }
+}
+
+/// Read a boolean of an operation.
+fn boolean(table: &toml_edit::Table, key: &str) -> Result<Option<bool>> {
+    match table[key] {
+        toml_edit::Item::None => Ok(None),
+        ref item => item.as_bool()
+            .map(Some)
+            .ok_or_else(|| Error::InvalidChangeFile(format!("`{}` must be a boolean", key))),
+    }
+}
+
+/// Read a list of strings (e.g. the manifests, or the features of an operation).
+fn strings(item: &toml_edit::Item, key: &str) -> Result<Option<Vec<String>>> {
+    if item.is_none() {
+        return Ok(None);
+    }
+    let invalid = || Error::InvalidChangeFile(format!("`{}` must be a list of strings", key));
+    item.as_array()
+        .ok_or_else(&invalid)?
+        .iter()
+        .map(|value| value.as_str().map(String::from).ok_or_else(&invalid))
+        .collect::<Result<Vec<_>>>()
+        .map(Some)
+}
+
+/// Read one `[[change]]` table.
+fn parse_operation(table: &toml_edit::Table) -> Result<Operation> {
+    if let Some((key, _)) = table.iter().find(|&(key, _)| !OPERATION_KEYS.contains(&key)) {
+        return Err(Error::InvalidChangeFile(format!("unknown key `{}`", key)));
+    }
+    let name = string(table, "crate")?
+        .ok_or_else(|| Error::InvalidChangeFile("every change needs a `crate`".into()))?;
+
+    let kind = match string(table, "section")? {
+        None => DepKind::Normal,
+        Some(section) => DepKind::from_kind_table(&section).ok_or_else(|| {
+            Error::InvalidChangeFile(format!(
+                "`{}` is not a dependency section, use `dependencies`, `dev-dependencies` or \
+                 `build-dependencies`",
+                section
+            ))
+        })?,
+    };
+    let section = match string(table, "target")? {
+        Some(target) => DepTable::new().set_kind(kind).set_target(target),
+        None => DepTable::new().set_kind(kind),
+    };
+    let version = string(table, "version")?;
+
+    let action = match string(table, "op")?.as_ref().map(String::as_str) {
+        Some("add") => {
+            let reference = match (
+                string(table, "branch")?,
+                string(table, "tag")?,
+                string(table, "rev")?,
+            ) {
+                (Some(branch), None, None) => Some(GitReference::Branch(branch)),
+                (None, Some(tag), None) => Some(GitReference::Tag(tag)),
+                (None, None, Some(rev)) => Some(GitReference::Rev(rev)),
+                (None, None, None) => None,
+                _ => {
+                    return Err(Error::InvalidChangeFile(format!(
+                        "`{}`: only one of `branch`, `tag` and `rev` may be given",
+                        name
+                    )))
+                }
+            };
+            let (git, path) = (string(table, "git")?, string(table, "path")?);
+            if reference.is_some() && git.is_none() {
+                return Err(Error::InvalidChangeFile(format!(
+                    "`{}`: `branch`, `tag` and `rev` need `git`",
+                    name
+                )));
+            }
+            let dep = match (git, path) {
+                (Some(git), None) => {
+                    let dep = Dependency::new(&name).set_git(&git);
+                    match reference {
+                        Some(reference) => dep.set_git_reference(reference),
+                        None => dep,
+                    }
+                }
+                (None, Some(path)) => Dependency::new(&name).set_path(&path),
+                (None, None) if version.is_some() => Dependency::new(&name),
+                (None, None) => {
+                    return Err(Error::InvalidChangeFile(format!(
+                        "`{}`: adding a crate needs a `version`, `git` or `path`",
+                        name
+                    )))
+                }
+                (Some(_), Some(_)) => {
+                    return Err(Error::InvalidChangeFile(format!(
+                        "`{}`: `git` and `path` can not be combined",
+                        name
+                    )))
+                }
+            };
+            let dep = match version {
+                Some(ref version) => dep.set_version(version),
+                None => dep,
+            };
+            Action::Add(
+                dep.set_optional(boolean(table, "optional")?.unwrap_or(false))
+                    .set_default_features(boolean(table, "default-features")?.unwrap_or(true))
+                    .set_features(strings(&table["features"], "features")?),
+            )
+        }
+        Some("rm") => Action::Remove(name),
+        Some("upgrade") => Action::Upgrade {
+            version: version.ok_or_else(|| {
+                Error::InvalidChangeFile(format!("`{}`: upgrading needs a `version`", name))
+            })?,
+            name: name,
+        },
+        Some(op) => {
+            return Err(Error::InvalidChangeFile(format!(
+                "unknown operation `{}`, use `add`, `rm` or `upgrade`",
+                op
+            )))
+        }
+        None => {
+            return Err(Error::InvalidChangeFile(format!(
+                "`{}`: every change needs an `op`",
+                name
+            )))
+        }
+    };
+
+    Ok(Operation {
+        section: section,
+        action: action,
+    })
+}
+
+/// Read a change file. Relative paths of manifests are taken relative to `base_dir`, the
+/// directory of the change file.
+///
+/// ```toml
+/// manifests = ["service-a", "../service-b/Cargo.toml"]
+///
+/// [[change]]
+/// op = "add"
+/// crate = "serde"
+/// version = "1.0"
+/// features = ["derive"]
+///
+/// [[change]]
+/// op = "rm"
+/// crate = "failure"
+/// section = "dev-dependencies"
+/// target = "cfg(unix)"
+///
+/// [[change]]
+/// op = "upgrade"
+/// crate = "tokio"
+/// version = "1.37"
+/// ```
+pub fn parse(contents: &str, base_dir: &Path) -> Result<ChangeFile> {
+    let document: toml_edit::Document = contents
+        .parse()
+        .map_err(|e| Error::InvalidChangeFile(format!("{}", e)))?;
+
+    let manifests = strings(&document["manifests"], "manifests")?
+        .ok_or_else(|| Error::InvalidChangeFile("`manifests` lists no manifest".into()))?;
+    let operations = match document["change"].as_array_of_tables() {
+        Some(changes) => changes
+            .iter()
+            .map(parse_operation)
+            .collect::<Result<Vec<_>>>()?,
+        None => vec![],
+    };
+    if manifests.is_empty() || operations.is_empty() {
+        return Err(Error::InvalidChangeFile(
+            "a change file needs `manifests` and at least one `[[change]]`".into(),
+        ));
+    }
+
+    Ok(ChangeFile {
+        manifests: manifests.iter().map(|path| base_dir.join(path)).collect(),
+        operations: operations,
+    })
+}
+
+/// Apply the operations to a manifest, in order. Removing or upgrading a crate that the section
+/// does not have is skipped, as are upgrades of dependencies that are not from crates.io (e.g.
+/// inherited from the workspace).
+pub fn apply(manifest: &mut Manifest, operations: &[Operation]) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    for operation in operations {
+        let section = &operation.section;
+        let table_path = section.to_table();
+        let entry = |manifest: &Manifest, name: &str| {
+            let item = table_path
+                .iter()
+                .fold(&manifest.data.root, |item, key| &item[key.as_str()]);
+            item[name].clone()
+        };
+
+        let step = match operation.action {
+            Action::Add(ref dep) => {
+                manifest.insert_into_table(section, dep)?;
+                let source = dep.version()
+                    .map(|version| format!("v{}", version))
+                    .or_else(|| dep.git().map(String::from))
+                    .or_else(|| dep.path().map(String::from))
+                    .unwrap_or_default();
+                Step {
+                    status: "Adding",
+                    message: format!("{} {} to {}", dep.name, source, section),
+                }
+            }
+            Action::Remove(ref name) => if entry(manifest, name).is_none() {
+                Step {
+                    status: "Skipping",
+                    message: format!("{}: not in {}", name, section),
+                }
+            } else {
+                manifest.remove_from_table(section, name)?;
+                Step {
+                    status: "Removing",
+                    message: format!("{} from {}", name, section),
+                }
+            },
+            Action::Upgrade {
+                ref name,
+                ref version,
+            } => {
+                let old = Dependency::from_toml(name, &entry(manifest, name));
+                match old {
+                    Some(ref old) if old.is_registry() => {
+                        let dep = Dependency::new(name).set_version(version);
+                        manifest.insert_into_table(section, &dep)?;
+                        Step {
+                            status: "Upgrading",
+                            message: format!(
+                                "{} v{} -> v{} in {}",
+                                name,
+                                old.version().unwrap_or_default(),
+                                version,
+                                section
+                            ),
+                        }
+                    }
+                    Some(_) => Step {
+                        status: "Skipping",
+                        message: format!("{}: not a crates.io dependency in {}", name, section),
+                    },
+                    None => Step {
+                        status: "Skipping",
+                        message: format!(
+                            "{}: not in {}, or inherited from the workspace",
+                            name, section
+                        ),
+                    },
+                }
+            }
+        };
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+/// Write a manifest that may be the root of a workspace, and a virtual manifest, over a copy of
+/// the manifest at `original` (so that it keeps its line endings) at `path`.
+fn write_manifest(manifest: &Manifest, original: &Path, path: &Path) -> Result<()> {
+    fs::copy(original, path)?;
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    if manifest.data["package"].is_none() {
+        manifest.write_workspace_to_file(&mut file)?;
+    } else {
+        manifest.write_to_file(&mut file)?;
+    }
+    Ok(())
+}
+
+/// Write the changed manifests: each is written to a file next to it first, and only once all of
+/// them are written, these files replace the manifests. If one can not be written, no manifest is
+/// changed.
+pub fn write_all(manifests: &[(PathBuf, Manifest)]) -> Result<()> {
+    let mut staged = Vec::new();
+    for &(ref path, ref manifest) in manifests {
+        let staging = path.with_file_name(".Cargo.toml.cargo-dep-apply");
+        staged.push((staging.clone(), path));
+        if let Err(e) = write_manifest(manifest, path, &staging) {
+            for &(ref staging, _) in &staged {
+                let _ = fs::remove_file(staging);
+            }
+            return Err(e);
+        }
+    }
+    for (staging, path) in staged {
+        fs::rename(&staging, path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_apply_changes() {
+        let changes = parse(
+            r#"manifests = ["a", "/b/Cargo.toml"]
+
+[[change]]
+op = "add"
+crate = "serde"
+version = "1.0"
+features = ["derive"]
+
+[[change]]
+op = "rm"
+crate = "failure"
+
+[[change]]
+op = "upgrade"
+crate = "log"
+version = "0.4"
+section = "dev-dependencies"
+target = "cfg(unix)"
+
+[[change]]
+op = "upgrade"
+crate = "missing"
+version = "1.0"
+"#,
+            Path::new("/repos"),
+        ).unwrap();
+        assert_eq!(
+            changes.manifests,
+            vec![PathBuf::from("/repos/a"), PathBuf::from("/b/Cargo.toml")]
+        );
+
+        let mut manifest: Manifest = r#"[package]
+name = "foo"
+
+[dependencies]
+failure = "0.1"
+
+[target.'cfg(unix)'.dev-dependencies]
+log = { version = "0.3", features = ["std"] }
+"#.parse()
+            .unwrap();
+        let steps = apply(&mut manifest, &changes.operations).unwrap();
+        let messages: Vec<_> = steps
+            .iter()
+            .map(|step| format!("{} {}", step.status, step))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Adding serde v1.0 to dependencies",
+                "Removing failure from dependencies",
+                "Upgrading log v0.3 -> v0.4 in dev-dependencies for target `cfg(unix)`",
+                "Skipping missing: not in dependencies, or inherited from the workspace",
+            ]
+        );
+
+        let dependencies = &manifest.data["dependencies"];
+        assert_eq!(dependencies["serde"]["version"].as_str(), Some("1.0"));
+        assert!(dependencies["failure"].is_none());
+        let log = &manifest.data["target"]["cfg(unix)"]["dev-dependencies"]["log"];
+        assert_eq!(log["version"].as_str(), Some("0.4"));
+        assert_eq!(log["features"].as_array().map(|a| a.len()), Some(1));
+    }
+
+    #[test]
+    fn reject_invalid_changes() {
+        let base = Path::new(".");
+        let invalid = |change: &str| {
+            let contents = format!("manifests = [\".\"]\n\n[[change]]\n{}", change);
+            match parse(&contents, base) {
+                Err(Error::InvalidChangeFile(message)) => message,
+                other => panic!("unexpected result {:?}", other),
+            }
+        };
+        assert_eq!(
+            invalid("op = \"add\"\ncrate = \"serde\"\n"),
+            "`serde`: adding a crate needs a `version`, `git` or `path`"
+        );
+        assert_eq!(
+            invalid("op = \"upgrade\"\ncrate = \"serde\"\n"),
+            "`serde`: upgrading needs a `version`"
+        );
+        assert_eq!(
+            invalid("op = \"replace\"\ncrate = \"serde\"\n"),
+            "unknown operation `replace`, use `add`, `rm` or `upgrade`"
+        );
+        assert_eq!(
+            invalid("op = \"rm\"\ncrate = \"serde\"\nsectoin = \"dev-dependencies\"\n"),
+            "unknown key `sectoin`"
+        );
+        assert!(parse("manifests = []\n", base).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! Handle `cargo dep` arguments
+
+use cargo_edit::GitReference;
+use serde_derive::Deserialize;
+
+/// How `cargo dep licenses` prints the licenses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum OutputFormat {
+    /// A table for humans
+    Table,
+    /// An SPDX-style JSON document
+    Json,
+}
+
+/// The format of the bill of materials of `cargo dep sbom`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SbomFormat {
+    /// CycloneDX JSON
+    Cyclonedx,
+}
+
+#[derive(Debug, Deserialize)]
+/// Docopts input args.
+pub struct Args {
+    /// Crate names
+    pub arg_crate: Vec<String>,
+    /// The change file of `apply`
+    pub arg_changes: String,
+    /// `dedupe`
+    pub cmd_dedupe: bool,
+    /// `hoist`
+    pub cmd_hoist: bool,
+    /// `unhoist`
+    pub cmd_unhoist: bool,
+    /// `patch`
+    pub cmd_patch: bool,
+    /// `patch add`
+    pub cmd_add: bool,
+    /// `patch rm`
+    pub cmd_rm: bool,
+    /// `migrate-replace`
+    pub cmd_migrate_replace: bool,
+    /// `publish-check`
+    pub cmd_publish_check: bool,
+    /// `fix-wildcards`
+    pub cmd_fix_wildcards: bool,
+    /// `features-report`
+    pub cmd_features_report: bool,
+    /// `fmt`
+    pub cmd_fmt: bool,
+    /// `verify-workspace`
+    pub cmd_verify_workspace: bool,
+    /// `licenses`
+    pub cmd_licenses: bool,
+    /// `sbom`
+    pub cmd_sbom: bool,
+    /// `graph`
+    pub cmd_graph: bool,
+    /// `bot-config`
+    pub cmd_bot_config: bool,
+    /// `apply`
+    pub cmd_apply: bool,
+    /// `Cargo.toml` path
+    pub flag_manifest_path: Option<String>,
+    /// `--dry-run`
+    pub flag_dry_run: bool,
+    /// `--git`
+    pub flag_git: Option<String>,
+    /// `--branch`
+    pub flag_branch: Option<String>,
+    /// `--tag`
+    pub flag_tag: Option<String>,
+    /// `--rev`
+    pub flag_rev: Option<String>,
+    /// `--path`
+    pub flag_path: Option<String>,
+    /// `--registry`
+    pub flag_registry: String,
+    /// `--exclude`
+    pub flag_exclude: Vec<String>,
+    /// `--min-members`
+    pub flag_min_members: usize,
+    /// `--sort`
+    pub flag_sort: bool,
+    /// `--align`
+    pub flag_align: bool,
+    /// `--fix`
+    pub flag_fix: bool,
+    /// `--output`
+    pub flag_output: OutputFormat,
+    /// `--format`
+    pub flag_format: SbomFormat,
+    /// `--mermaid`
+    pub flag_mermaid: bool,
+    /// `--renovate`
+    pub flag_renovate: bool,
+    /// `--dependabot`
+    pub flag_dependabot: bool,
+    /// `--quiet`
+    pub flag_quiet: bool,
+    /// `--version`
+    pub flag_version: bool,
+    /// `--json`
+    pub flag_json: bool,
+}
+
+impl Args {
+    /// Get the branch, tag or revision passed with `--branch`, `--tag` or `--rev`, if any
+    pub fn get_git_reference(&self) -> Option<GitReference> {
+        self.flag_branch
+            .clone()
+            .map(GitReference::Branch)
+            .or_else(|| self.flag_tag.clone().map(GitReference::Tag))
+            .or_else(|| self.flag_rev.clone().map(GitReference::Rev))
+    }
+}
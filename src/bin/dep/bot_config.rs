@@ -0,0 +1,128 @@
+//! `cargo dep bot-config`: a starter configuration for Dependabot or Renovate, covering every
+//! package of the workspace
+use serde_derive::Serialize;
+
+/// The configuration of Renovate (`renovate.json`).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Renovate<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    extends: Vec<&'static str>,
+    enabled_managers: Vec<&'static str>,
+    include_paths: &'a [String],
+}
+
+/// The packages a configuration covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotConfig {
+    /// The directories of the packages, relative to the workspace root (`""` for the root itself),
+    /// sorted and without duplicates. The root always comes first, as it holds `Cargo.lock`.
+    dirs: Vec<String>,
+}
+
+impl BotConfig {
+    /// The configuration for the packages in `dirs` (relative to the workspace root, with `/` as
+    /// separator) and the workspace root.
+    pub fn new<I: IntoIterator<Item = String>>(dirs: I) -> Self {
+        let mut dirs: Vec<String> = dirs.into_iter()
+            .map(|dir| dir.trim_matches('/').to_owned())
+            .chain(Some(String::new()))
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        BotConfig { dirs: dirs }
+    }
+
+    /// `.github/dependabot.yml`: weekly version updates of the `cargo` ecosystem, one entry per
+    /// directory.
+    pub fn dependabot(&self) -> String {
+        let mut config = String::from("version: 2\nupdates:\n");
+        for dir in &self.dirs {
+            let directory = format!("/{}", dir)
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"");
+            config.push_str("  - package-ecosystem: \"cargo\"\n");
+            config.push_str(&format!("    directory: \"{}\"\n", directory));
+            config.push_str("    schedule:\n");
+            config.push_str("      interval: \"weekly\"\n");
+        }
+        config
+    }
+
+    /// `renovate.json`: the recommended presets, with only the `cargo` manager, limited to the
+    /// manifests of the packages.
+    pub fn renovate(&self) -> String {
+        let manifests: Vec<String> = self.dirs
+            .iter()
+            .map(|dir| {
+                if dir.is_empty() {
+                    "Cargo.toml".to_owned()
+                } else {
+                    format!("{}/Cargo.toml", dir)
+                }
+            })
+            .collect();
+        let config = Renovate {
+            schema: "https://docs.renovatebot.com/renovate-schema.json",
+            extends: vec!["config:recommended"],
+            enabled_managers: vec!["cargo"],
+            include_paths: &manifests,
+        };
+        let mut config =
+            ::serde_json::to_string_pretty(&config).expect("configuration is correctly serialized");
+        config.push('\n');
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_bot_configs() {
+        let config = BotConfig::new(vec![
+            "two".to_owned(),
+            String::new(),
+            "fuzz".to_owned(),
+            "two/".to_owned(),
+        ]);
+        assert_eq!(
+            config.dependabot(),
+            r#"version: 2
+updates:
+  - package-ecosystem: "cargo"
+    directory: "/"
+    schedule:
+      interval: "weekly"
+  - package-ecosystem: "cargo"
+    directory: "/fuzz"
+    schedule:
+      interval: "weekly"
+  - package-ecosystem: "cargo"
+    directory: "/two"
+    schedule:
+      interval: "weekly"
+"#
+        );
+        assert_eq!(
+            config.renovate(),
+            r#"{
+  "$schema": "https://docs.renovatebot.com/renovate-schema.json",
+  "extends": [
+    "config:recommended"
+  ],
+  "enabledManagers": [
+    "cargo"
+  ],
+  "includePaths": [
+    "Cargo.toml",
+    "fuzz/Cargo.toml",
+    "two/Cargo.toml"
+  ]
+}
+"#
+        );
+    }
+}
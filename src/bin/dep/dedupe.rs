@@ -0,0 +1,390 @@
+//! `cargo dep dedupe`: remove dependency entries that are made redundant by another entry
+use std::collections::BTreeMap;
+use std::fmt;
+
+use cargo_edit::{DepKind, DepTable, Dependency, Manifest};
+use toml_edit;
+
+use super::errors::*;
+
+const KINDS: &[DepKind] = &[DepKind::Normal, DepKind::Development, DepKind::Build];
+
+/// A change that makes the manifest smaller without changing what it means.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Remove the entry `name` from `section`, it is the same as the one in `same_as`.
+    Remove {
+        /// The section with the entry
+        section: DepTable,
+        /// Name of the dependency
+        name: String,
+        /// The section with the same entry
+        same_as: DepTable,
+    },
+    /// Replace the same entries for two complementary targets by a plain entry.
+    Merge {
+        /// Kind of the dependency section
+        kind: DepKind,
+        /// Name of the dependency
+        name: String,
+        /// The complementary targets (`cfg(x)` and `cfg(not(x))`)
+        targets: (String, String),
+    },
+    /// Merge entries of a section whose names differ only in case, `-` and `_` into the first one.
+    MergeSpellings {
+        /// The section with the entries
+        section: DepTable,
+        /// Name of the entry that is kept
+        name: String,
+        /// Names of the entries merged into it
+        others: Vec<String>,
+    },
+}
+
+impl Change {
+    /// The word describing the change, as shown in front of the message
+    pub fn status(&self) -> &'static str {
+        match *self {
+            Change::Remove { .. } => "Removing",
+            Change::Merge { .. } | Change::MergeSpellings { .. } => "Merging",
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Change::Remove {
+                ref section,
+                ref name,
+                ref same_as,
+            } => write!(
+                f,
+                "{} from {} (same as in {})",
+                name, section, same_as
+            ),
+            Change::Merge {
+                ref kind,
+                ref name,
+                ref targets,
+            } => write!(
+                f,
+                "{} for targets `{}` and `{}` into {}",
+                name,
+                targets.0,
+                targets.1,
+                kind.kind_table()
+            ),
+            Change::MergeSpellings {
+                ref section,
+                ref name,
+                ref others,
+            } => write!(
+                f,
+                "{} into {} in {} (the same crate)",
+                others.join(", "),
+                name,
+                section
+            ),
+        }
+    }
+}
+
+/// Normalize a dependency entry, so entries written in different ways can be compared. Returns
+/// `None` for entries that are not understood.
+pub fn normalize(entry: &toml_edit::Item) -> Option<BTreeMap<String, String>> {
+    let mut normalized = BTreeMap::new();
+    if let Some(version) = entry.as_str() {
+        normalized.insert("version".to_owned(), format!("{:?}", version));
+        return Some(normalized);
+    }
+
+    for (key, value) in entry.as_table_like()?.iter() {
+        let value = value.as_value()?;
+        let value = match value.as_array() {
+            // The order of features does not matter.
+            Some(array) => {
+                let mut values: Vec<_> = array
+                    .iter()
+                    .map(|v| v.to_string().trim().to_owned())
+                    .collect();
+                values.sort();
+                values.dedup();
+                values.join(", ")
+            }
+            None => value.to_string().trim().to_owned(),
+        };
+        normalized.insert(key.to_owned(), value);
+    }
+    Some(normalized)
+}
+
+/// Whether two dependency entries mean the same.
+fn same_spec(a: &toml_edit::Item, b: &toml_edit::Item) -> bool {
+    match (normalize(a), normalize(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Get the complement of a `cfg(x)` target, i.e. `cfg(not(x))`.
+fn complement(target: &str) -> Option<String> {
+    let target: String = target.chars().filter(|c| !c.is_whitespace()).collect();
+    if target.starts_with("cfg(") && target.ends_with(')') && !target.starts_with("cfg(not(") {
+        Some(format!("cfg(not({}))", &target[4..target.len() - 1]))
+    } else {
+        None
+    }
+}
+
+/// Find the entries that can be removed or merged.
+pub fn find_changes(manifest: &Manifest) -> Vec<Change> {
+    let data = &manifest.data;
+    // Crates.io does not tell `Foo` and `foo` apart; these merges go first, as the other changes
+    // compare entries by name.
+    let mut changes: Vec<_> = manifest
+        .duplicate_crate_names()
+        .into_iter()
+        .map(|(section, mut names)| {
+            let name = names.remove(0);
+            Change::MergeSpellings {
+                section: section,
+                name: name,
+                others: names,
+            }
+        })
+        .collect();
+
+    // Dependencies are available to tests and examples, too.
+    if let Some(dev) = data["dev-dependencies"].as_table_like() {
+        for (name, dev_entry) in dev.iter() {
+            let entry = &data["dependencies"][name];
+            if entry["optional"].as_bool() != Some(true) && same_spec(entry, dev_entry) {
+                changes.push(Change::Remove {
+                    section: DepTable::new().set_kind(DepKind::Development),
+                    name: name.to_owned(),
+                    same_as: DepTable::new(),
+                });
+            }
+        }
+    }
+
+    let targets: Vec<_> = data.as_table()
+        .get("target")
+        .and_then(toml_edit::Item::as_table_like)
+        .map(|targets| targets.iter().collect())
+        .unwrap_or_default();
+
+    for &kind in KINDS {
+        let plain = &data[kind.kind_table()];
+        for &(target, table) in &targets {
+            let entries = match table[kind.kind_table()].as_table_like() {
+                Some(entries) => entries,
+                None => continue,
+            };
+            for (name, entry) in entries.iter() {
+                if same_spec(&plain[name], entry) {
+                    // A plain entry applies to all targets.
+                    changes.push(Change::Remove {
+                        section: DepTable::new().set_kind(kind).set_target(target),
+                        name: name.to_owned(),
+                        same_as: DepTable::new().set_kind(kind),
+                    });
+                    continue;
+                }
+
+                // A dependency for both `cfg(x)` and `cfg(not(x))` applies to all targets, too.
+                let other = complement(target).and_then(|complement| {
+                    targets.iter().find(|&&(other, _)| {
+                        other.chars().filter(|c| !c.is_whitespace()).collect::<String>()
+                            == complement
+                    })
+                });
+                if let Some(&(other, other_table)) = other {
+                    let other_entry = &other_table[kind.kind_table()][name];
+                    if plain[name].is_none() && same_spec(entry, other_entry) {
+                        changes.push(Change::Merge {
+                            kind: kind,
+                            name: name.to_owned(),
+                            targets: (target.to_owned(), other.to_owned()),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    changes
+}
+
+/// Get the item at `path`.
+fn item_mut<'a>(item: &'a mut toml_edit::Item, path: &[String]) -> &'a mut toml_edit::Item {
+    path.iter().fold(item, |item, segment| &mut item[segment.as_str()])
+}
+
+/// Remove the entry `name` from the table at `path`, and then any table left empty.
+pub fn remove_entry(manifest: &mut Manifest, path: &[String], name: &str) {
+    item_mut(&mut manifest.data.root, path)[name] = toml_edit::Item::None;
+
+    for depth in (1..path.len() + 1).rev() {
+        let table = item_mut(&mut manifest.data.root, &path[..depth]);
+        if table.as_table_like().map(|t| t.is_empty()).unwrap_or(false) {
+            *table = toml_edit::Item::None;
+        } else {
+            break;
+        }
+    }
+}
+
+/// Apply the changes found by `find_changes`.
+pub fn apply(manifest: &mut Manifest, changes: &[Change]) -> Result<()> {
+    for change in changes {
+        match *change {
+            Change::Remove {
+                ref section,
+                ref name,
+                ..
+            } => {
+                manifest.remove_from_table(section, name)?;
+            }
+            Change::Merge {
+                kind,
+                ref name,
+                ref targets,
+            } => {
+                let section = |target: &str| DepTable::new().set_kind(kind).set_target(target);
+                let entry = manifest.remove_from_table(&section(&targets.0), name)?;
+                manifest.remove_from_table(&section(&targets.1), name)?;
+                manifest.get_table(&DepTable::new().set_kind(kind).to_table())?[name] = entry;
+            }
+            Change::MergeSpellings {
+                ref section,
+                ref name,
+                ref others,
+            } => {
+                // The features the other entries enable are added to the kept one.
+                let mut features = Vec::new();
+                for other in others {
+                    let entry = manifest.remove_from_table(section, other)?;
+                    if let Some(dep) = Dependency::from_toml(other, &entry) {
+                        features.extend(dep.features().unwrap_or(&[]).iter().cloned());
+                    }
+                }
+                let table = manifest.get_table(&section.to_table())?;
+                let kept = match Dependency::from_toml(name, &table[name.as_str()]) {
+                    Some(kept) => kept,
+                    None => continue,
+                };
+                let mut merged = kept.features().unwrap_or(&[]).to_vec();
+                let len = merged.len();
+                for feature in features {
+                    if !merged.contains(&feature) {
+                        merged.push(feature);
+                    }
+                }
+                if merged.len() > len {
+                    table[name.as_str()] = kept.set_features(Some(merged)).to_toml().1;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complement_of_targets() {
+        assert_eq!(complement("cfg(unix)"), Some("cfg(not(unix))".to_owned()));
+        assert_eq!(
+            complement("cfg(target_os = \"linux\")"),
+            Some("cfg(not(target_os=\"linux\"))".to_owned())
+        );
+        assert_eq!(complement("cfg(not(unix))"), None);
+        assert_eq!(complement("x86_64-pc-windows-gnu"), None);
+    }
+
+    #[test]
+    fn dedupe_sections() {
+        let mut manifest: Manifest = r#"[package]
+name = "foo"
+
+[dependencies]
+docopt = "0.8"
+serde = { version = "1.0", features = ["derive", "std"] }
+pad = { version = "0.1", optional = true }
+
+[dev-dependencies]
+docopt = { version = "0.8" }
+serde = { version = "1.0", features = ["std", "derive"] }
+pad = "0.1"
+regex = "0.2"
+
+[target.'cfg(unix)'.dependencies]
+docopt = "0.8"
+libc = "0.2"
+
+[target.'cfg(not(unix))'.dependencies]
+libc = "0.2"
+"#.parse()
+            .unwrap();
+
+        let changes = find_changes(&manifest);
+        let messages: Vec<_> = changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "docopt from dev-dependencies (same as in dependencies)",
+                "serde from dev-dependencies (same as in dependencies)",
+                "docopt from dependencies for target `cfg(unix)` (same as in dependencies)",
+                "libc for targets `cfg(unix)` and `cfg(not(unix))` into dependencies",
+            ]
+        );
+
+        apply(&mut manifest, &changes).unwrap();
+        assert!(find_changes(&manifest).is_empty());
+        assert!(manifest.data["target"].is_none());
+        assert_eq!(manifest.data["dependencies"]["libc"].as_str(), Some("0.2"));
+        assert_eq!(manifest.data["dev-dependencies"]["pad"].as_str(), Some("0.1"));
+    }
+
+    #[test]
+    fn merge_spellings_of_a_crate() {
+        let mut manifest: Manifest = r#"[package]
+name = "foo"
+
+[dependencies]
+inflector = "0.11"
+Inflector = { version = "0.11", features = ["heavyweight"] }
+serde_json = "1.0"
+serde-json = "1.0"
+"#.parse()
+            .unwrap();
+
+        let changes = find_changes(&manifest);
+        let messages: Vec<_> = changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Inflector into inflector in dependencies (the same crate)",
+                "serde-json into serde_json in dependencies (the same crate)",
+            ]
+        );
+
+        apply(&mut manifest, &changes).unwrap();
+        assert!(find_changes(&manifest).is_empty());
+        let dependencies = &manifest.data["dependencies"];
+        assert!(dependencies["Inflector"].is_none());
+        assert_eq!(dependencies["inflector"]["version"].as_str(), Some("0.11"));
+        assert_eq!(
+            dependencies["inflector"]["features"]
+                .as_array()
+                .map(|features| features.len()),
+            Some(1)
+        );
+        assert_eq!(dependencies["serde_json"].as_str(), Some("1.0"));
+        assert!(dependencies["serde-json"].is_none());
+    }
+}
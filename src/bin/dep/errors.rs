@@ -0,0 +1,79 @@
+//! Errors of `cargo dep`
+use std::error::Error as StdError;
+use std::path::PathBuf;
+use std::{fmt, io, result};
+
+use cargo_edit;
+
+/// A `Result` with the `cargo dep` error type
+pub type Result<T> = result::Result<T, Error>;
+
+/// Errors of `cargo dep`
+#[derive(Debug)]
+pub enum Error {
+    /// An error from the `cargo_edit` library
+    CargoEditLib(cargo_edit::Error),
+    /// Output could not be written
+    Io(io::Error),
+    /// The dependency is not inherited from the workspace
+    NotInherited(String),
+    /// The entry of the dependency in the workspace root is not understood
+    UnsupportedEntry(String),
+    /// This many dependency entries keep the package from being published
+    PublishBlocked(usize),
+    /// This many dependencies are declared in different ways by the members of the workspace
+    WorkspaceInconsistent(usize),
+    /// There is no lock file at this path
+    MissingLockFile(PathBuf),
+    /// The change file of `cargo dep apply` is not understood, for this reason
+    InvalidChangeFile(String),
+}
+
+impl From<cargo_edit::Error> for Error {
+    fn from(e: cargo_edit::Error) -> Self {
+        Error::CargoEditLib(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            Error::CargoEditLib(ref e) => return e.fmt(f),
+            Error::Io(ref e) => return e.fmt(f),
+            Error::NotInherited(ref name) => message!("dep-not-inherited", name = name),
+            Error::UnsupportedEntry(ref name) => message!("dep-unsupported-entry", name = name),
+            Error::PublishBlocked(count) => message!("dep-publish-blocked", count = count),
+            Error::WorkspaceInconsistent(count) => {
+                message!("dep-workspace-inconsistent", count = count)
+            }
+            Error::MissingLockFile(ref path) => {
+                message!("dep-missing-lock-file", path = path.display())
+            }
+            Error::InvalidChangeFile(ref reason) => {
+                message!("dep-invalid-change-file", reason = reason)
+            }
+        };
+        f.write_str(&text)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::CargoEditLib(ref e) => e.source(),
+            Error::Io(ref e) => e.source(),
+            Error::NotInherited(_)
+            | Error::UnsupportedEntry(_)
+            | Error::PublishBlocked(_)
+            | Error::WorkspaceInconsistent(_)
+            | Error::MissingLockFile(_)
+            | Error::InvalidChangeFile(_) => None,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! `cargo dep features-report`: show what the default features of the dependencies pull in
+use std::collections::BTreeSet;
+use std::fmt;
+
+use cargo_edit::{self, DepTable, Dependency, FeatureInfo, Manifest};
+
+use super::errors::*;
+
+/// The default features a dependency entry enables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The section with the entry
+    pub section: DepTable,
+    /// Name of the dependency
+    pub name: String,
+    /// Version requirement of the dependency
+    pub version: String,
+    /// The features enabled by `default`, directly or through other features
+    pub defaults: Vec<String>,
+    /// The optional dependencies the default features pull in
+    pub pulled_in: Vec<String>,
+}
+
+impl Report {
+    /// A suggestion to trim the build, if the default features pull in optional dependencies.
+    pub fn suggestion(&self) -> Option<String> {
+        if self.pulled_in.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "disable the default features of {} (`default-features = false`) and enable only the \
+             ones you use of: {}",
+            self.name,
+            self.defaults.join(", ")
+        ))
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} in {}: default features {}",
+            self.name,
+            self.version,
+            self.section,
+            self.defaults.join(", ")
+        )?;
+        if !self.pulled_in.is_empty() {
+            write!(f, " (pulling in {})", self.pulled_in.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Report the default features of the registry dependencies that do not disable them.
+/// `features` looks up the features of a crate for a version requirement (e.g. with
+/// `get_crate_features`).
+pub fn find_reports<F>(manifest: &Manifest, mut features: F) -> Result<Vec<Report>>
+where
+    F: FnMut(&str, &str) -> cargo_edit::Result<Vec<FeatureInfo>>,
+{
+    let mut reports = Vec::new();
+    for (section, table) in manifest.get_sections() {
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (name, entry) in table.iter() {
+            let default_features = entry["default-features"]
+                .as_bool()
+                .or_else(|| entry["default_features"].as_bool())
+                .unwrap_or(true);
+            if !default_features {
+                continue;
+            }
+            let dep = match Dependency::from_toml(name, entry) {
+                Some(ref dep) if dep.is_registry() => dep.clone(),
+                _ => continue,
+            };
+            let version = match dep.version() {
+                Some(version) => version.to_owned(),
+                None => continue,
+            };
+            // Renamed dependencies name the crate with `package`.
+            let crate_name = entry["package"].as_str().unwrap_or(name);
+
+            let offered = features(crate_name, &version)?;
+            let defaults: Vec<_> = offered
+                .iter()
+                .filter(|feature| feature.default && feature.name != "default")
+                .collect();
+            if defaults.is_empty() {
+                continue;
+            }
+            let pulled_in: BTreeSet<_> = defaults
+                .iter()
+                .flat_map(|feature| feature.optional_deps.iter().cloned())
+                .collect();
+
+            reports.push(Report {
+                section: section.clone(),
+                name: name.to_owned(),
+                version: version,
+                defaults: defaults.iter().map(|feature| feature.name.clone()).collect(),
+                pulled_in: pulled_in.into_iter().collect(),
+            });
+        }
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feature(name: &str, default: bool, optional_deps: &[&str]) -> FeatureInfo {
+        FeatureInfo {
+            name: name.to_owned(),
+            default: default,
+            enables: vec![],
+            optional_deps: optional_deps.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn report_default_features() {
+        let manifest: Manifest = r#"[package]
+name = "foo"
+
+[dependencies]
+chrono = "0.4"
+lean = { version = "1.0", default-features = false }
+local = { path = "../local" }
+plain = "0.2"
+"#.parse()
+            .unwrap();
+
+        let reports = find_reports(&manifest, |name, _| {
+            Ok(match name {
+                "chrono" => vec![
+                    feature("clock", true, &["time"]),
+                    feature("default", true, &[]),
+                    feature("serde", false, &["serde"]),
+                    feature("std", true, &[]),
+                ],
+                "plain" => vec![feature("std", false, &[])],
+                _ => panic!("{} is not looked up", name),
+            })
+        }).unwrap();
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(
+            reports[0].to_string(),
+            "chrono 0.4 in dependencies: default features clock, std (pulling in time)"
+        );
+        assert_eq!(
+            reports[0].suggestion().unwrap(),
+            "disable the default features of chrono (`default-features = false`) and enable only \
+             the ones you use of: clock, std"
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! `cargo dep fmt`: normalize the formatting of the dependency sections
+//!
+//! The manifest is formatted line by line, so everything outside of the dependency sections, and
+//! the comments within them, stay as they are. Entries spanning several lines (and dependency
+//! tables like `[dependencies.foo]`) are left alone.
+
+const KINDS: &[&str] = &[
+    "dependencies",
+    "dev-dependencies",
+    "dev_dependencies",
+    "build-dependencies",
+    "build_dependencies",
+];
+
+/// How to format the dependency sections
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Sort the entries alphabetically, within each block of entries separated by empty lines
+    pub sort: bool,
+    /// Align the `=` of the entries within each block
+    pub align: bool,
+}
+
+/// A line of a dependency section
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line<'a> {
+    /// A single line entry, with its key and the formatted line (without alignment)
+    Entry {
+        key: String,
+        value: String,
+        comment: Option<&'a str>,
+    },
+    /// Anything else: comments, empty lines and entries that are not understood
+    Other(&'a str),
+}
+
+/// Format the dependency sections of a manifest. Returns the formatted manifest and the paths of
+/// the sections that changed.
+pub fn format(contents: &str, options: Options) -> (String, Vec<Vec<String>>) {
+    let mut formatted: Vec<String> = Vec::new();
+    let mut changed = Vec::new();
+    let mut section: Option<Vec<String>> = None;
+    let mut body: Vec<&str> = Vec::new();
+
+    let mut lines = contents.lines();
+    loop {
+        let line = lines.next();
+        let header = line.and_then(header_keys);
+        if let (Some(line), None) = (line, header.as_ref()) {
+            body.push(line);
+            continue;
+        }
+
+        // The section ends at the next header, or at the end of the manifest.
+        match section {
+            Some(ref section) if is_dependency_section(section) => {
+                let section_lines = format_section(&body, options);
+                if section_lines.iter().map(String::as_str).ne(body.iter().cloned()) {
+                    changed.push(section.clone());
+                }
+                formatted.extend(section_lines);
+            }
+            _ => formatted.extend(body.iter().map(|line| line.to_string())),
+        }
+        body.clear();
+
+        match line {
+            Some(line) => formatted.push(line.to_owned()),
+            None => break,
+        }
+        section = header;
+    }
+
+    let mut formatted = formatted.join("\n");
+    if contents.ends_with('\n') {
+        formatted.push('\n');
+    }
+    (formatted, changed)
+}
+
+/// The keys of a table header (e.g. `["target", "cfg(unix)", "dependencies"]` for
+/// `[target.'cfg(unix)'.dependencies]`), if `line` is one.
+fn header_keys(line: &str) -> Option<Vec<String>> {
+    let line = line.trim();
+    if !line.starts_with('[') {
+        return None;
+    }
+    let name = line.trim_start_matches('[');
+    let name = &name[..name.find(']')?];
+
+    let mut keys = vec![String::new()];
+    let mut quote = None;
+    for c in name.chars() {
+        match (c, quote) {
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (_, Some(q)) if c == q => quote = None,
+            ('.', None) => keys.push(String::new()),
+            _ => keys.last_mut().expect("at least one key").push(c),
+        }
+    }
+    Some(keys.iter().map(|key| key.trim().to_owned()).collect())
+}
+
+/// Whether a table holds dependency entries (and is not the table of a single dependency, like
+/// `[dependencies.foo]`).
+fn is_dependency_section(section: &[String]) -> bool {
+    let kind = match section.last() {
+        Some(kind) if KINDS.contains(&kind.as_str()) => kind,
+        _ => return false,
+    };
+    match section.len() {
+        1 => true,
+        2 => section[0] == "workspace" && kind == "dependencies",
+        3 => section[0] == "target",
+        _ => false,
+    }
+}
+
+/// Format the lines of a dependency section.
+fn format_section(body: &[&str], options: Options) -> Vec<String> {
+    let lines: Vec<Line> = body.iter().map(|line| parse_line(line)).collect();
+
+    // Blocks of lines, separated by empty lines
+    let mut formatted = Vec::new();
+    for block in lines.split(is_blank) {
+        let block = if options.sort {
+            sort_block(block)
+        } else {
+            block.to_vec()
+        };
+        let width = if options.align {
+            block
+                .iter()
+                .filter_map(|line| match *line {
+                    Line::Entry { ref key, .. } => Some(key.len()),
+                    Line::Other(_) => None,
+                })
+                .max()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        formatted.extend(block.iter().map(|line| render(line, width)));
+        formatted.push(String::new());
+    }
+    // `split` leaves out the empty lines: put them back as they were, in their places.
+    formatted.pop();
+    for (line, original) in formatted.iter_mut().zip(body) {
+        if original.trim().is_empty() {
+            *line = original.to_string();
+        }
+    }
+    formatted
+}
+
+fn is_blank(line: &Line) -> bool {
+    match *line {
+        Line::Other(line) => line.trim().is_empty(),
+        Line::Entry { .. } => false,
+    }
+}
+
+/// Sort the entries of a block by key. Comments right above an entry move with it; the comments
+/// at the top of the block (e.g. the label of a group of entries) stay there.
+fn sort_block<'a>(block: &[Line<'a>]) -> Vec<Line<'a>> {
+    let first_entry = block
+        .iter()
+        .position(|line| match *line {
+            Line::Entry { .. } => true,
+            Line::Other(_) => false,
+        })
+        .unwrap_or(block.len());
+    let (top, rest) = block.split_at(first_entry);
+
+    let mut entries: Vec<Vec<Line>> = Vec::new();
+    let mut pending = Vec::new();
+    for line in rest {
+        pending.push(line.clone());
+        if let Line::Entry { .. } = *line {
+            entries.push(pending.split_off(0));
+        }
+    }
+    entries.sort_by(|a, b| entry_key(a).cmp(entry_key(b)));
+
+    let mut sorted = top.to_vec();
+    sorted.extend(entries.into_iter().flatten());
+    sorted.extend(pending);
+    sorted
+}
+
+fn entry_key<'a>(lines: &'a [Line]) -> &'a str {
+    match lines.last() {
+        Some(&Line::Entry { ref key, .. }) => key.as_str(),
+        _ => "",
+    }
+}
+
+fn render(line: &Line, width: usize) -> String {
+    match *line {
+        Line::Entry {
+            ref key,
+            ref value,
+            comment,
+        } => {
+            let mut rendered = format!("{:width$} = {}", key, value, width = width);
+            if let Some(comment) = comment {
+                rendered.push(' ');
+                rendered.push_str(comment);
+            }
+            rendered
+        }
+        Line::Other(line) => line.to_owned(),
+    }
+}
+
+/// Parse a line of a dependency section. Entries that are not understood are kept as they are.
+fn parse_line(line: &str) -> Line {
+    let (code, comment) = split_comment(line);
+    let code = code.trim();
+    if code.is_empty() || line.starts_with(char::is_whitespace) {
+        return Line::Other(line);
+    }
+
+    let mut parts = split_top_level(code, '=');
+    let (key, value) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(key), Some(value), None) => (key.trim(), value.trim()),
+        _ => return Line::Other(line),
+    };
+    match (format_key(key), format_value(value)) {
+        (Some(key), Some(value)) => Line::Entry {
+            key: key,
+            value: value,
+            comment: comment,
+        },
+        _ => Line::Other(line),
+    }
+}
+
+/// Split a line into the code and the comment (including the `#`), if any.
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (_, Some(q)) if c == q => quote = None,
+            ('#', None) => return (&line[..i], Some(&line[i..])),
+            _ => {}
+        }
+    }
+    (line, None)
+}
+
+/// Split at the occurrences of `separator` outside of strings, arrays and inline tables.
+fn split_top_level(s: &str, separator: char) -> ::std::vec::IntoIter<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match (c, quote) {
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (_, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => {}
+            ('[', None) | ('{', None) => depth += 1,
+            (']', None) | ('}', None) => depth -= 1,
+            (c, None) if c == separator && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts.into_iter()
+}
+
+/// Write a key bare if possible, or as a basic string.
+fn format_key(key: &str) -> Option<String> {
+    let bare = |key: &str| {
+        !key.is_empty()
+            && key.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    if bare(key) {
+        return Some(key.to_owned());
+    }
+    let unquoted = format_string(key)?;
+    let inner = &unquoted[1..unquoted.len() - 1];
+    if bare(inner) {
+        Some(inner.to_owned())
+    } else {
+        Some(unquoted)
+    }
+}
+
+/// Write a string with double quotes, if that does not need escapes.
+fn format_string(s: &str) -> Option<String> {
+    if s.len() < 2 || s.starts_with("\"\"\"") || s.starts_with("'''") {
+        return None;
+    }
+    if s.starts_with('"') && s.ends_with('"') {
+        return Some(s.to_owned());
+    }
+    if s.starts_with('\'') && s.ends_with('\'') {
+        let inner = &s[1..s.len() - 1];
+        if inner.contains('"') || inner.contains('\\') {
+            return Some(s.to_owned());
+        }
+        return Some(format!("\"{}\"", inner));
+    }
+    None
+}
+
+/// Format a value: strings get double quotes, the keys of inline tables are sorted (`version`
+/// first, then `features`, then the others alphabetically), arrays and inline tables get the
+/// usual spacing. Returns `None` for values that are not understood.
+fn format_value(value: &str) -> Option<String> {
+    if value.starts_with('"') || value.starts_with('\'') {
+        return format_string(value);
+    }
+    if value.starts_with('[') {
+        if !value.ends_with(']') {
+            return None;
+        }
+        let items = split_top_level(&value[1..value.len() - 1], ',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(format_value)
+            .collect::<Option<Vec<_>>>()?;
+        return Some(format!("[{}]", items.join(", ")));
+    }
+    if value.starts_with('{') {
+        if !value.ends_with('}') {
+            return None;
+        }
+        let inner = value[1..value.len() - 1].trim();
+        if inner.is_empty() {
+            return Some("{}".to_owned());
+        }
+        let mut pairs = split_top_level(inner, ',')
+            .map(|pair| {
+                let mut parts = split_top_level(pair, '=');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(key), Some(value), None) => {
+                        Some((format_key(key.trim())?, format_value(value.trim())?))
+                    }
+                    _ => None,
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+        pairs.sort_by(|a, b| key_rank(&a.0).cmp(&key_rank(&b.0)));
+        let pairs: Vec<_> = pairs
+            .iter()
+            .map(|&(ref key, ref value)| format!("{} = {}", key, value))
+            .collect();
+        return Some(format!("{{ {} }}", pairs.join(", ")));
+    }
+    // Numbers, booleans and dates stay as they are.
+    if value.contains(char::is_whitespace) || value.contains(',') {
+        None
+    } else {
+        Some(value.to_owned())
+    }
+}
+
+/// The position of a key in an inline table
+fn key_rank(key: &str) -> (u8, &str) {
+    match key {
+        "version" => (0, key),
+        "features" => (1, key),
+        _ => (2, key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_dependency_sections() {
+        let input = r#"[package]
+name  =  'foo'
+
+[dependencies]
+serde={features=['derive'],version='1.0'}
+# parsing
+regex = { optional = true, version = "0.2" } # fast enough
+docopt    = "0.8"
+
+"log" = "0.4"
+multi = { version = "0.1", features = [
+    "a",
+] }
+
+[dependencies.chrono]
+version  =  '0.4'
+"#;
+
+        let (formatted, changed) = format(input, Options::default());
+        assert_eq!(
+            formatted,
+            r#"[package]
+name  =  'foo'
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+# parsing
+regex = { version = "0.2", optional = true } # fast enough
+docopt = "0.8"
+
+log = "0.4"
+multi = { version = "0.1", features = [
+    "a",
+] }
+
+[dependencies.chrono]
+version  =  '0.4'
+"#
+        );
+        assert_eq!(changed, vec![vec!["dependencies".to_owned()]]);
+
+        let options = Options {
+            sort: true,
+            align: true,
+        };
+        let (formatted, _) = format(&formatted, options);
+        assert!(formatted.contains(
+            r#"[dependencies]
+docopt = "0.8"
+# parsing
+regex  = { version = "0.2", optional = true } # fast enough
+serde  = { version = "1.0", features = ["derive"] }
+
+log = "0.4"
+"#
+        ));
+
+        // Formatted sections stay the same.
+        let (again, changed) = format(&formatted, options);
+        assert_eq!(again, formatted);
+        assert!(changed.is_empty());
+    }
+
+    #[test]
+    fn find_dependency_sections() {
+        let section = |header: &str| header_keys(header).map(|keys| is_dependency_section(&keys));
+        assert_eq!(section("[dev-dependencies]"), Some(true));
+        assert_eq!(section("[target.'cfg(unix)'.build-dependencies]"), Some(true));
+        assert_eq!(section("[workspace.dependencies]"), Some(true));
+        assert_eq!(section("[dependencies.foo]"), Some(false));
+        assert_eq!(section("[[bin]]"), Some(false));
+        assert_eq!(section("serde = \"1.0\""), None);
+    }
+}
@@ -0,0 +1,234 @@
+//! `cargo dep graph`: draw the members of a workspace and their direct dependencies
+use std::collections::BTreeSet;
+
+use cargo_edit::{DepKind, DepTable, Dependency};
+
+use super::hoist::Member;
+
+/// A direct dependency of a member.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    /// Name of the member
+    pub from: String,
+    /// Name of the crate (not the name of the entry, for a renamed dependency)
+    pub to: String,
+    /// The section of the entry
+    pub section: DepTable,
+    /// The version requirement, or the source of a dependency without one (`git`, `path` or
+    /// `workspace`)
+    pub requirement: String,
+}
+
+impl Edge {
+    /// The label of the edge, e.g. `0.3 (dev, cfg(unix))`.
+    fn label(&self) -> String {
+        let mut qualifiers = Vec::new();
+        match self.section.kind() {
+            DepKind::Normal => {}
+            DepKind::Development => qualifiers.push("dev"),
+            DepKind::Build => qualifiers.push("build"),
+        }
+        if let Some(target) = self.section.target() {
+            qualifiers.push(target);
+        }
+        if qualifiers.is_empty() {
+            self.requirement.clone()
+        } else {
+            format!("{} ({})", self.requirement, qualifiers.join(", "))
+        }
+    }
+}
+
+/// The graph of the members of a workspace and their direct dependencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Graph {
+    /// The names of the members
+    pub members: Vec<String>,
+    /// The crates the members depend on that are not members, sorted by name
+    pub crates: Vec<String>,
+    /// The dependencies, in the order of the members and of the sections `get_sections` lists
+    pub edges: Vec<Edge>,
+}
+
+/// Find the direct dependencies of the members, from their manifests alone.
+pub fn find_graph(members: &[Member]) -> Graph {
+    let names: Vec<String> = members.iter().map(|member| member.name.clone()).collect();
+    let mut crates = BTreeSet::new();
+    let mut edges = Vec::new();
+    for member in members {
+        for (section, table) in member.manifest.get_sections() {
+            let table = table.as_table_like().expect("Unexpected non-table");
+            for (name, entry) in table.iter() {
+                let requirement = if entry["workspace"].as_bool() == Some(true) {
+                    "workspace".to_owned()
+                } else {
+                    match Dependency::from_toml(name, entry) {
+                        Some(ref dep) => match dep.version() {
+                            Some(version) => version.to_owned(),
+                            None if dep.git().is_some() => "git".to_owned(),
+                            None => "path".to_owned(),
+                        },
+                        None => continue,
+                    }
+                };
+                // Renamed dependencies name the crate with `package`.
+                let to = entry["package"].as_str().unwrap_or(name).to_owned();
+                if !names.contains(&to) {
+                    crates.insert(to.clone());
+                }
+                edges.push(Edge {
+                    from: member.name.clone(),
+                    to: to,
+                    section: section.clone(),
+                    requirement: requirement,
+                });
+            }
+        }
+    }
+    Graph {
+        members: names,
+        crates: crates.into_iter().collect(),
+        edges: edges,
+    }
+}
+
+impl Graph {
+    /// The graph in the DOT language of Graphviz. Members are drawn as boxes.
+    pub fn dot(&self) -> String {
+        fn quoted(text: &str) -> String {
+            format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+
+        let mut dot = String::from("digraph dependencies {\n");
+        for member in &self.members {
+            dot.push_str(&format!("    {} [shape=box];\n", quoted(member)));
+        }
+        for krate in &self.crates {
+            dot.push_str(&format!("    {};\n", quoted(krate)));
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "    {} -> {} [label={}];\n",
+                quoted(&edge.from),
+                quoted(&edge.to),
+                quoted(&edge.label())
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The graph as a Mermaid flowchart. Members are drawn as boxes, other crates with rounded
+    /// corners.
+    pub fn mermaid(&self) -> String {
+        fn quoted(text: &str) -> String {
+            format!("\"{}\"", text.replace('"', "#quot;"))
+        }
+        // Node IDs can not contain every character of a crate name, so the nodes are numbered.
+        let id = |name: &str| {
+            let index = self.members
+                .iter()
+                .chain(&self.crates)
+                .position(|node| node == name)
+                .expect("every crate of an edge is a node");
+            format!("n{}", index)
+        };
+
+        let mut mermaid = String::from("flowchart LR\n");
+        for member in &self.members {
+            mermaid.push_str(&format!("    {}[{}]\n", id(member), quoted(member)));
+        }
+        for krate in &self.crates {
+            mermaid.push_str(&format!("    {}({})\n", id(krate), quoted(krate)));
+        }
+        for edge in &self.edges {
+            mermaid.push_str(&format!(
+                "    {} -->|{}| {}\n",
+                id(&edge.from),
+                quoted(&edge.label()),
+                id(&edge.to)
+            ));
+        }
+        mermaid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn member(name: &str, manifest: &str) -> Member {
+        Member {
+            name: name.to_owned(),
+            manifest_path: PathBuf::from(format!("{}/Cargo.toml", name)),
+            manifest: manifest.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn draw_graph() {
+        let members = vec![
+            member(
+                "one",
+                r#"[package]
+name = "one"
+
+[dependencies]
+log = "0.4"
+two = { path = "../two", version = "0.1" }
+json = { package = "serde_json", workspace = true }
+
+[target.'cfg(target_os = "linux")'.dev-dependencies]
+tempdir = { git = "https://github.com/rust-lang-nursery/tempdir" }
+"#,
+            ),
+            member(
+                "two",
+                r#"[package]
+name = "two"
+
+[build-dependencies]
+cc = "1.0"
+"#,
+            ),
+        ];
+        let graph = find_graph(&members);
+        assert_eq!(graph.members, vec!["one", "two"]);
+        assert_eq!(graph.crates, vec!["cc", "log", "serde_json", "tempdir"]);
+
+        assert_eq!(
+            graph.dot(),
+            r#"digraph dependencies {
+    "one" [shape=box];
+    "two" [shape=box];
+    "cc";
+    "log";
+    "serde_json";
+    "tempdir";
+    "one" -> "tempdir" [label="git (dev, cfg(target_os = \"linux\"))"];
+    "one" -> "log" [label="0.4"];
+    "one" -> "two" [label="0.1"];
+    "one" -> "serde_json" [label="workspace"];
+    "two" -> "cc" [label="1.0 (build)"];
+}
+"#
+        );
+        assert_eq!(
+            graph.mermaid(),
+            r#"flowchart LR
+    n0["one"]
+    n1["two"]
+    n2("cc")
+    n3("log")
+    n4("serde_json")
+    n5("tempdir")
+    n0 -->|"git (dev, cfg(target_os = #quot;linux#quot;))"| n5
+    n0 -->|"0.4"| n3
+    n0 -->|"0.1"| n1
+    n0 -->|"workspace"| n4
+    n1 -->|"1.0 (build)"| n2
+"#
+        );
+    }
+}
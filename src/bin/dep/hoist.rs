@@ -0,0 +1,251 @@
+//! `cargo dep hoist`: move dependencies that several workspace members declare identically to
+//! `[workspace.dependencies]`
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use cargo_edit::{DepTable, Manifest};
+use toml_edit;
+
+use super::dedupe::normalize;
+use super::errors::*;
+
+/// Keys that may differ between the members, as they are kept next to `workspace = true`.
+const MEMBER_KEYS: &[&str] = &["features", "optional"];
+
+/// A member of the workspace, with its manifest.
+#[derive(Debug)]
+pub struct Member {
+    /// Name of the package
+    pub name: String,
+    /// Path to the manifest
+    pub manifest_path: PathBuf,
+    /// The manifest
+    pub manifest: Manifest,
+}
+
+/// A dependency to move to the workspace root.
+#[derive(Debug, Clone)]
+pub struct Hoist {
+    /// Name of the dependency
+    pub name: String,
+    /// The entry for `[workspace.dependencies]`
+    pub spec: toml_edit::Item,
+    /// The members declaring the dependency, with the sections it is declared in
+    pub members: Vec<(String, PathBuf, Vec<DepTable>)>,
+}
+
+impl fmt::Display for Hoist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<_> = self.members.iter().map(|m| m.0.as_str()).collect();
+        write!(
+            f,
+            "{} from {} members ({})",
+            self.name,
+            names.len(),
+            names.join(", ")
+        )
+    }
+}
+
+/// The entry for the workspace root: the member's entry without the keys that stay with the member.
+/// Returns `None` for entries that can not be hoisted: path dependencies (their path is relative to
+/// the member) and entries that are inherited already.
+fn root_spec(entry: &toml_edit::Item) -> Option<toml_edit::Item> {
+    if entry.is_str() {
+        return Some(entry.clone());
+    }
+
+    let table = entry.as_table_like()?;
+    if table.iter().any(|(key, _)| key == "path" || key == "workspace") {
+        return None;
+    }
+
+    let mut spec = toml_edit::InlineTable::default();
+    for (key, value) in table.iter() {
+        if !MEMBER_KEYS.contains(&key) {
+            spec.get_or_insert(key, value.as_value()?.clone());
+        }
+    }
+    if spec.len() == 1 {
+        if let Some(version) = spec.get("version").and_then(|v| v.as_str()) {
+            return Some(toml_edit::value(version));
+        }
+    }
+    spec.fmt();
+    Some(toml_edit::value(toml_edit::Value::InlineTable(spec)))
+}
+
+/// Find the dependencies declared identically (apart from their features and whether they are
+/// optional) by at least `min_members` members. Dependencies that the root already declares are
+/// skipped.
+pub fn find_changes(root: &Manifest, members: &[Member], min_members: usize) -> Vec<Hoist> {
+    // Candidates by name, and then by normalized spec.
+    let mut candidates: BTreeMap<String, BTreeMap<BTreeMap<String, String>, Hoist>> =
+        BTreeMap::new();
+
+    for member in members {
+        for (section, table) in member.manifest.get_sections() {
+            let table = table.as_table_like().expect("Unexpected non-table");
+            for (name, entry) in table.iter() {
+                let spec = match root_spec(entry) {
+                    Some(spec) => spec,
+                    None => continue,
+                };
+                let key = match normalize(&spec) {
+                    Some(key) => key,
+                    None => continue,
+                };
+
+                let hoist = candidates
+                    .entry(name.to_owned())
+                    .or_insert_with(BTreeMap::new)
+                    .entry(key)
+                    .or_insert_with(|| Hoist {
+                        name: name.to_owned(),
+                        spec: spec,
+                        members: vec![],
+                    });
+                let member_index = match hoist.members.iter().position(|m| m.0 == member.name) {
+                    Some(i) => i,
+                    None => {
+                        hoist.members.push((
+                            member.name.clone(),
+                            member.manifest_path.clone(),
+                            vec![],
+                        ));
+                        hoist.members.len() - 1
+                    }
+                };
+                hoist.members[member_index].2.push(section.clone());
+            }
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|&(ref name, _)| root.data["workspace"]["dependencies"][name].is_none())
+        .filter_map(|(_, specs)| {
+            // Only one spec per name can go to the root; prefer the most common one.
+            specs
+                .into_iter()
+                .map(|(_, hoist)| hoist)
+                .filter(|hoist| hoist.members.len() >= min_members)
+                .fold(None, |best: Option<Hoist>, hoist| match best {
+                    Some(best) if best.members.len() >= hoist.members.len() => Some(best),
+                    _ => Some(hoist),
+                })
+        })
+        .collect()
+}
+
+/// Apply the changes found by `find_changes`: rewrite the members' entries, then add the specs to
+/// the root manifest at `root_path`.
+pub fn apply(root_path: &PathBuf, hoists: &[Hoist]) -> Result<()> {
+    let mut by_member: BTreeMap<PathBuf, Vec<(&str, &DepTable)>> = BTreeMap::new();
+    for hoist in hoists {
+        for &(_, ref path, ref sections) in &hoist.members {
+            let entries = by_member.entry(path.clone()).or_insert_with(Vec::new);
+            entries.extend(sections.iter().map(|s| (hoist.name.as_str(), s)));
+        }
+    }
+
+    for (path, entries) in by_member {
+        let path = Some(path);
+        let mut manifest = Manifest::open(&path)?;
+        for (name, section) in entries {
+            manifest.inherit_dependency(section, name)?;
+        }
+        manifest.write_to_path(&path)?;
+    }
+
+    // The root is only opened now, as it may be one of the members, too.
+    let root_path = Some(root_path.clone());
+    let mut root = Manifest::open(&root_path)?;
+    {
+        let table = root.get_table(&["workspace".to_owned(), "dependencies".to_owned()])?;
+        for hoist in hoists {
+            table[&hoist.name] = hoist.spec.clone();
+        }
+    }
+    root.write_workspace_to_path(&root_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cargo_edit::DepKind;
+
+    fn member(name: &str, manifest: &str) -> Member {
+        Member {
+            name: name.to_owned(),
+            manifest_path: PathBuf::from(format!("{}/Cargo.toml", name)),
+            manifest: manifest.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn hoist_shared_dependencies() {
+        let root: Manifest = "[workspace]\nmembers = [\"one\", \"two\", \"three\"]\n\n\
+                              [workspace.dependencies]\nlog = \"0.4\"\n"
+            .parse()
+            .unwrap();
+        let members = vec![
+            member(
+                "one",
+                r#"[package]
+name = "one"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+libc = "0.2"
+log = "0.4"
+local = { path = "../local" }
+"#,
+            ),
+            member(
+                "two",
+                r#"[package]
+name = "two"
+
+[dependencies]
+serde = "1.0"
+libc = "0.1"
+log = "0.4"
+local = { path = "../local" }
+
+[dev-dependencies]
+regex = "0.2"
+"#,
+            ),
+            member(
+                "three",
+                r#"[package]
+name = "three"
+
+[dev-dependencies]
+libc = "0.2"
+"#,
+            ),
+        ];
+
+        let hoists = find_changes(&root, &members, 2);
+        let messages: Vec<_> = hoists.iter().map(|h| h.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "libc from 2 members (one, three)",
+                "serde from 2 members (one, two)",
+            ]
+        );
+        assert_eq!(hoists[0].spec.as_str(), Some("0.2"));
+        assert_eq!(
+            hoists[0].members[1].2,
+            vec![DepTable::new().set_kind(DepKind::Development)]
+        );
+        assert_eq!(hoists[1].spec.as_str(), Some("1.0"));
+
+        assert!(find_changes(&root, &members, 3).is_empty());
+    }
+}
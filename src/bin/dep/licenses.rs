@@ -0,0 +1,303 @@
+//! `cargo dep licenses`: list the licenses of the direct dependencies
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::Path;
+
+use cargo_edit::{self, CrateInfo, Dependency, Manifest};
+use serde_derive::Serialize;
+
+use super::errors::*;
+
+/// SPDX identifiers of licenses that put no conditions on how derived works are licensed.
+const PERMISSIVE: &[&str] = &[
+    "0BSD",
+    "Apache-2.0",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "BSL-1.0",
+    "CC0-1.0",
+    "ISC",
+    "MIT",
+    "MIT-0",
+    "NCSA",
+    "Unicode-3.0",
+    "Unicode-DFS-2016",
+    "Unlicense",
+    "WTFPL",
+    "X11",
+    "Zlib",
+];
+
+/// Prefixes of the SPDX identifiers of copyleft licenses, e.g. `GPL-3.0-only` or `MPL-2.0`.
+const COPYLEFT: &[&str] = &[
+    "AGPL-",
+    "CC-BY-SA-",
+    "CDDL-",
+    "EPL-",
+    "EUPL-",
+    "GPL-",
+    "LGPL-",
+    "MPL-",
+    "OSL-",
+];
+
+/// Why the license of a dependency needs a closer look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Concern {
+    /// No license is declared, or the license expression names a license that is not known
+    Unknown,
+    /// Every choice the license expression offers includes a copyleft license
+    Copyleft,
+}
+
+impl fmt::Display for Concern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Concern::Unknown => write!(f, "unknown license"),
+            Concern::Copyleft => write!(f, "copyleft license"),
+        }
+    }
+}
+
+/// Where the license of a dependency was looked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// The registry metadata of the latest version matching the requirement
+    Registry,
+    /// The manifest at the path of the dependency
+    Path,
+    /// Nowhere: the licenses of git dependencies are not looked up
+    Git,
+}
+
+/// The license of a direct dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct License {
+    /// Name of the crate
+    pub name: String,
+    /// Version requirement of the dependency, if any
+    pub version: Option<String>,
+    /// Where the license was looked up
+    pub source: Source,
+    /// The SPDX license expression, if one was found
+    pub license: Option<String>,
+    /// Why the license needs a closer look, if it does
+    pub concern: Option<Concern>,
+}
+
+/// Find out why a license expression needs a closer look, if it does. `OR` (and the older `/`)
+/// separate choices, `AND` and `WITH` are not distinguished from each other, and parentheses are
+/// not taken into account.
+pub fn concern(license: Option<&str>) -> Option<Concern> {
+    let license = match license {
+        Some(license) => license,
+        None => return Some(Concern::Unknown),
+    };
+    let license = license.replace('/', " OR ").replace('(', " ").replace(')', " ");
+
+    let mut choices = vec![vec![]];
+    let mut tokens = license.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "OR" => choices.push(vec![]),
+            "AND" => {}
+            "WITH" => {
+                // The exception to the license, e.g. `LLVM-exception`
+                tokens.next();
+            }
+            id => choices
+                .last_mut()
+                .expect("there is always a choice")
+                .push(id.trim_end_matches('+')),
+        }
+    }
+
+    let ids: Vec<&str> = choices.iter().flat_map(|choice| choice.iter().cloned()).collect();
+    if ids.is_empty() || ids.iter().any(|&id| !is_permissive(id) && !is_copyleft(id)) {
+        Some(Concern::Unknown)
+    } else if choices.iter().all(|choice| choice.iter().any(|&id| is_copyleft(id))) {
+        Some(Concern::Copyleft)
+    } else {
+        None
+    }
+}
+
+/// Whether `id` names a license of `PERMISSIVE`
+fn is_permissive(id: &str) -> bool {
+    PERMISSIVE.iter().any(|known| known.eq_ignore_ascii_case(id))
+}
+
+/// Whether `id` names a license of `COPYLEFT`
+fn is_copyleft(id: &str) -> bool {
+    let id = id.to_uppercase();
+    COPYLEFT.iter().any(|prefix| id.starts_with(prefix))
+}
+
+/// The license declared in the manifest at `path` (relative to `manifest_dir`), if any.
+fn local_license(manifest_dir: &Path, path: &str) -> Option<String> {
+    let manifest = Manifest::open(&Some(manifest_dir.join(path).join("Cargo.toml"))).ok()?;
+    manifest.data["package"]["license"].as_str().map(String::from)
+}
+
+/// Look up the licenses of the dependencies of a manifest in `manifest_dir`: from the manifest at
+/// the path of path dependencies, and with `registry_license` (e.g. `get_crate_info`) for the
+/// others. Each crate is listed once, with the first requirement it is declared with. Dependencies
+/// inherited from the workspace are left out.
+pub fn find_licenses<F>(
+    manifest: &Manifest,
+    manifest_dir: &Path,
+    mut registry_license: F,
+) -> Result<Vec<License>>
+where
+    F: FnMut(&str, &str) -> cargo_edit::Result<CrateInfo>,
+{
+    let mut seen = BTreeSet::new();
+    let mut licenses = Vec::new();
+    for (_, table) in manifest.get_sections() {
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (key, entry) in table.iter() {
+            let dep = match Dependency::from_toml(key, entry) {
+                Some(dep) => dep,
+                None => continue,
+            };
+            // Renamed dependencies name the crate with `package`.
+            let name = entry["package"].as_str().unwrap_or(key).to_owned();
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+
+            let (source, license) = if let Some(path) = dep.path() {
+                (Source::Path, local_license(manifest_dir, path))
+            } else if dep.git().is_some() {
+                (Source::Git, None)
+            } else {
+                let version = dep.version().unwrap_or("*");
+                (Source::Registry, registry_license(&name, version)?.license)
+            };
+            licenses.push(License {
+                concern: concern(license.as_ref().map(String::as_str)),
+                name: name,
+                version: dep.version().map(String::from),
+                source: source,
+                license: license,
+            });
+        }
+    }
+    Ok(licenses)
+}
+
+/// Lay out the licenses as a table, one dependency per line.
+pub fn table(licenses: &[License]) -> String {
+    let rows: Vec<[String; 4]> = licenses
+        .iter()
+        .map(|l| {
+            [
+                l.name.clone(),
+                l.version.clone().unwrap_or_else(|| "-".to_owned()),
+                l.license.clone().unwrap_or_else(|| "-".to_owned()),
+                l.concern.map(|c| c.to_string()).unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let header = [
+        "Crate".to_owned(),
+        "Version".to_owned(),
+        "License".to_owned(),
+        "Note".to_owned(),
+    ];
+
+    let mut widths = [0; 4];
+    for row in Some(&header).into_iter().chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut table = String::new();
+    for row in Some(&header).into_iter().chain(&rows) {
+        let line = format!(
+            "{:w0$}  {:w1$}  {:w2$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        );
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// An SPDX-style document listing the dependencies of a package.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxDocument {
+    spdx_version: &'static str,
+    data_license: &'static str,
+    #[serde(rename = "SPDXID")]
+    spdx_id: &'static str,
+    name: String,
+    packages: Vec<SpdxPackage>,
+}
+
+/// A dependency in an `SpdxDocument`. What is not known is `NOASSERTION`, as in SPDX.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    name: String,
+    version_info: String,
+    download_location: String,
+    license_declared: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+/// Describe the licenses of the dependencies of the package `name` as an SPDX-style document.
+pub fn spdx_document(name: &str, licenses: &[License]) -> SpdxDocument {
+    let no_assertion = || "NOASSERTION".to_owned();
+    SpdxDocument {
+        spdx_version: "SPDX-2.3",
+        data_license: "CC0-1.0",
+        spdx_id: "SPDXRef-DOCUMENT",
+        name: name.to_owned(),
+        packages: licenses
+            .iter()
+            .map(|l| SpdxPackage {
+                spdx_id: format!("SPDXRef-Package-{}", l.name),
+                name: l.name.clone(),
+                version_info: l.version.clone().unwrap_or_else(&no_assertion),
+                download_location: match l.source {
+                    Source::Registry => format!("https://crates.io/crates/{}", l.name),
+                    Source::Path | Source::Git => no_assertion(),
+                },
+                license_declared: l.license.clone().unwrap_or_else(&no_assertion),
+                comment: l.concern.map(|c| c.to_string()),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concerns() {
+        assert_eq!(concern(Some("MIT OR Apache-2.0")), None);
+        assert_eq!(concern(Some("MIT/Apache-2.0")), None);
+        assert_eq!(concern(Some("Apache-2.0 WITH LLVM-exception")), None);
+        assert_eq!(concern(Some("MIT OR GPL-3.0-only")), None);
+        assert_eq!(concern(Some("GPL-2.0+")), Some(Concern::Copyleft));
+        assert_eq!(
+            concern(Some("LGPL-2.1-or-later OR MPL-2.0")),
+            Some(Concern::Copyleft)
+        );
+        assert_eq!(concern(Some("MIT AND Proprietary")), Some(Concern::Unknown));
+        assert_eq!(concern(Some("")), Some(Concern::Unknown));
+        assert_eq!(concern(None), Some(Concern::Unknown));
+    }
+}
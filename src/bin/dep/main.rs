@@ -0,0 +1,656 @@
+//! `cargo dep`
+#![warn(missing_docs, missing_debug_implementations, missing_copy_implementations, trivial_casts,
+        trivial_numeric_casts, unsafe_code, unstable_features, unused_import_braces,
+        unused_qualifications)]
+
+extern crate atty;
+extern crate docopt;
+extern crate semver;
+extern crate serde_derive;
+extern crate serde_json;
+extern crate termcolor;
+extern crate toml_edit;
+
+use std::error::Error as StdError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::io::{self, Write};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+#[macro_use]
+extern crate cargo_edit;
+use cargo_edit::{command_args, excluded_members, find, find_lock_file, find_workspace_root,
+                 get_crate_features, get_crate_info, get_latest_dependency, read_lock_file,
+                 read_locked_packages, workspace_members, DepTable, Manifest, Prereleases,
+                 SelfDescription, Warnings};
+
+mod args;
+use self::args::{Args, OutputFormat, SbomFormat};
+
+mod apply;
+mod bot_config;
+mod dedupe;
+mod features_report;
+mod format;
+mod graph;
+mod hoist;
+mod licenses;
+mod patch;
+mod publish_check;
+mod replace;
+mod sbom;
+mod unhoist;
+mod verify;
+mod wildcards;
+
+mod errors;
+use self::errors::*;
+
+static USAGE: &'static str = r#"
+Usage:
+    cargo dep dedupe [options]
+    cargo dep hoist [--min-members=<n>] [options]
+    cargo dep unhoist [<crate>...] [options]
+    cargo dep patch add <crate> (--git=<uri> [--branch=<branch>|--tag=<tag>|--rev=<rev>]|--path=<path>) [options]
+    cargo dep patch rm <crate> [options]
+    cargo dep migrate-replace [options]
+    cargo dep publish-check [options]
+    cargo dep fix-wildcards [--exclude=<crate>...] [options]
+    cargo dep features-report [options]
+    cargo dep fmt [--sort] [--align] [options]
+    cargo dep verify-workspace [--fix] [options]
+    cargo dep licenses [--output=<format>] [options]
+    cargo dep sbom [--format=<format>] [options]
+    cargo dep graph [--mermaid] [options]
+    cargo dep bot-config (--renovate|--dependabot) [options]
+    cargo dep apply <changes> [options]
+    cargo dep (-h|--help)
+    cargo dep --version [--json]
+
+Options:
+    --manifest-path=<path>  Path to the manifest to work on.
+    --dry-run               Print the changes without modifying the manifest.
+    --min-members=<n>       Only hoist dependencies declared by at least <n> members. [default: 2]
+    --git=<uri>             Patch the crate with the one from this git repository.
+    --branch=<branch>       Use this branch of the git repository.
+    --tag=<tag>             Use this tag of the git repository.
+    --rev=<rev>             Use this commit of the git repository.
+    --path=<path>           Patch the crate with the one at this path.
+    --exclude=<crate>       Leave the requirement of this crate as it is.
+    --sort                  Sort the entries alphabetically, within each block of entries
+                            separated by empty lines.
+    --align                 Align the `=` of the entries within each block.
+    --fix                   Harmonize the declarations: the newest version requirement and all
+                            the features any member enables.
+    --registry=<registry>   The registry whose crate is patched: `crates-io` or the URL of a
+                            registry. [default: crates-io]
+    --output=<format>       Print the licenses as a "table" or as an SPDX-style "json" document.
+                            [default: table]
+    --format=<format>       The format of the bill of materials. The only format is "cyclonedx"
+                            (CycloneDX JSON). [default: cyclonedx]
+    --mermaid               Draw the graph as a Mermaid flowchart instead of in the DOT language.
+    --renovate              Print a configuration for Renovate (`renovate.json`).
+    --dependabot            Print a configuration for Dependabot (`.github/dependabot.yml`).
+    -q --quiet              Do not print any output in case of success.
+    -h --help               Show this help page.
+    -V --version            Show version.
+    --json                  With `--version`, print the version and what the command supports as
+                            JSON, for tools that drive it.
+
+Maintain the dependencies of a Cargo.toml manifest file.
+
+Subcommands:
+    dedupe    Remove dependency entries that are made redundant by another entry: dev-dependencies
+              that are the same as a normal dependency, target-specific entries that are the same
+              as the plain entry, and the same entry for both `cfg(x)` and `cfg(not(x))`. Entries
+              whose names differ only in case, `-` and `_` (e.g. `Foo` and `foo`, the same crate
+              on crates.io) are merged into the first one, with the features of all of them.
+    hoist     Move dependencies that several members of the workspace (see `--manifest-path`)
+              declare the same way to `[workspace.dependencies]` of the root manifest, and let the
+              members inherit them with `workspace = true`. The features a member enables and
+              whether the dependency is optional stay with the member. Path dependencies are not
+              hoisted.
+    unhoist   Replace the dependencies the manifest inherits from the workspace with
+              `workspace = true` by the entries of `[workspace.dependencies]` (e.g. to move the
+              package out of the workspace). Without <crate>, all inherited dependencies are
+              replaced.
+    patch     Add or remove entries of `[patch.crates-io]` (or `[patch.<registry>]`), which
+              replace a crate everywhere in the dependency graph, e.g. with a local fork. An
+              existing patch of the crate is replaced. Patches only take effect in the root
+              manifest of a workspace.
+    migrate-replace
+              Move the entries of the deprecated `[replace]` table to `[patch]` tables. Entries
+              that can not be converted (e.g. because they do not have a `git` or `path` source)
+              are listed with the reason and left in `[replace]`.
+    publish-check
+              List the dependency entries that keep the package from being published: git and
+              path dependencies without a version (except dev-dependencies) and wildcard version
+              requirements. Fails if there are any, unless the package sets `publish = false`.
+    fix-wildcards
+              Replace wildcard requirements (e.g. `*` or `1.*`) and requirements without an upper
+              bound (e.g. `>= 1.0`) by caret requirements of the version recorded in `Cargo.lock`
+              or, for dependencies that are not locked, the latest version on crates.io.
+    features-report
+              List the crates.io dependencies that enable their default features, which features
+              those are and which optional dependencies they pull in (from the registry index),
+              with a suggestion to trim the ones that pull in dependencies. Nothing is changed.
+    fmt       Format the dependency sections: double quotes for strings, `version` first and
+              `features` second in inline tables, and the usual spacing. Comments, entries that
+              span several lines and the rest of the manifest are left as they are.
+    verify-workspace
+              List the dependencies that the members of the workspace declare with different
+              version requirements, different features, or from git in some members and from a
+              registry in others. Fails if there are any, e.g. to enforce consistency in CI. Path
+              dependencies and dependencies inherited from the workspace are not checked.
+    licenses  List the licenses of the direct dependencies: from crates.io for registry
+              dependencies, from the manifest at the path of path dependencies. Licenses that are
+              unknown (not declared, git dependencies, or not a well-known SPDX identifier) and
+              copyleft licenses (e.g. GPL or MPL, unless the expression offers another choice) are
+              flagged. Dependencies inherited from the workspace are not listed.
+    sbom      Print a software bill of materials: every package in `Cargo.lock` with its
+              version, checksum and source, what it depends on, and whether the manifest needs it
+              at run time, optionally, or only for tests and build scripts.
+    graph     Draw the members of the workspace and their direct dependencies, in the DOT
+              language of Graphviz (or as a Mermaid flowchart with `--mermaid`), e.g. for
+              documentation. The edges are labeled with the version requirement and the kind of
+              the dependency. Only the manifests are read, nothing is looked up.
+    bot-config
+              Print a starter configuration for Renovate or Dependabot that covers the root of the
+              workspace, every member and the packages the workspace excludes (e.g. fuzz targets),
+              to adjust and commit. Packages below the root that are not part of the workspace,
+              e.g. test fixtures, are left out.
+    apply     Apply the changes listed in the TOML file <changes> to several manifests at once:
+              `manifests` lists the manifests (relative to the file), and each `[[change]]` adds
+              (`op = "add"`), removes (`"rm"`) or upgrades (`"upgrade"`) a `crate`, in the
+              `section` (`dependencies` by default) and `target` given. The change file is
+              described in the README. If any change fails, no manifest is modified.
+"#;
+
+fn print_msg(status: &str, message: &str) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(output, "{:>12}", status)?;
+    output.reset()?;
+    writeln!(output, " {}", message)?;
+    Ok(())
+}
+
+fn handle_dedupe(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let mut manifest = Manifest::open(&manifest_path)?;
+
+    let changes = dedupe::find_changes(&manifest);
+    if !args.flag_quiet {
+        for change in &changes {
+            print_msg(change.status(), &change.to_string())?;
+        }
+    }
+
+    if changes.is_empty() || args.flag_dry_run {
+        return Ok(());
+    }
+
+    dedupe::apply(&mut manifest, &changes)?;
+
+    manifest.write_to_path(&manifest_path)?;
+
+    Ok(())
+}
+
+/// Open the manifests of the members of the workspace.
+fn open_members(manifest_path: &Option<PathBuf>) -> Result<Vec<hoist::Member>> {
+    workspace_members(manifest_path)?
+        .into_iter()
+        .map(|member| {
+            Ok(hoist::Member {
+                manifest: Manifest::open(&Some(member.manifest_path.clone()))?,
+                name: member.name,
+                manifest_path: member.manifest_path,
+            })
+        })
+        .collect()
+}
+
+fn handle_hoist(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let root_path = find(&manifest_path)?;
+    let root = Manifest::open(&Some(root_path.clone()))?;
+    if root.data["workspace"].is_none() {
+        return Err(cargo_edit::Error::NotWorkspaceRoot.into());
+    }
+
+    let members = open_members(&manifest_path)?;
+
+    let hoists = hoist::find_changes(&root, &members, args.flag_min_members);
+    if !args.flag_quiet {
+        for hoist in &hoists {
+            print_msg("Hoisting", &hoist.to_string())?;
+        }
+    }
+
+    if hoists.is_empty() || args.flag_dry_run {
+        return Ok(());
+    }
+
+    hoist::apply(&root_path, &hoists)
+}
+
+fn handle_unhoist(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let member_path = find(&manifest_path)?;
+    let root_path = find_workspace_root(&member_path)?;
+
+    let member_dir = match member_path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    }.canonicalize()?;
+    let root_dir = root_path.parent().unwrap_or_else(|| Path::new(""));
+    let member_dir = member_dir.strip_prefix(root_dir).unwrap_or(&member_dir);
+
+    let root = Manifest::open(&Some(root_path.clone()))?;
+    let member_path = Some(member_path);
+    let mut member = Manifest::open(&member_path)?;
+
+    let changes = unhoist::find_changes(&root, &member, member_dir, &args.arg_crate)?;
+    if !args.flag_quiet {
+        for change in &changes {
+            print_msg("Inlining", &change.to_string())?;
+        }
+    }
+
+    if changes.is_empty() || args.flag_dry_run {
+        return Ok(());
+    }
+
+    unhoist::apply(&mut member, &changes)?;
+    member.write_to_path(&member_path)?;
+    Ok(())
+}
+
+fn handle_patch(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let mut manifest = Manifest::open(&manifest_path)?;
+    let name = &args.arg_crate[0];
+    let section = format!("patch.{}", args.flag_registry);
+
+    if args.cmd_add {
+        let dep = patch::patch_dependency(
+            name,
+            args.flag_git.as_ref().map(String::as_str),
+            args.get_git_reference(),
+            args.flag_path.as_ref().map(String::as_str),
+        );
+        if !args.flag_quiet {
+            let source = dep.git().or_else(|| dep.path()).unwrap_or_default();
+            print_msg("Patching", &format!("{} with {} in {}", name, source, section))?;
+        }
+        patch::add(&mut manifest, &args.flag_registry, &dep)?;
+    } else {
+        if !args.flag_quiet {
+            print_msg("Removing", &format!("{} from {}", name, section))?;
+        }
+        patch::remove(&mut manifest, &args.flag_registry, name)?;
+    }
+
+    if args.flag_dry_run {
+        return Ok(());
+    }
+    write_root_manifest(&manifest, &manifest_path)
+}
+
+/// Write a manifest that may be the root of a workspace, and a virtual manifest.
+fn write_root_manifest(manifest: &Manifest, manifest_path: &Option<PathBuf>) -> Result<()> {
+    if manifest.data["package"].is_none() {
+        manifest.write_workspace_to_path(manifest_path)?;
+    } else {
+        manifest.write_to_path(manifest_path)?;
+    }
+    Ok(())
+}
+
+fn handle_migrate_replace(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let mut manifest = Manifest::open(&manifest_path)?;
+
+    let migrations = replace::find_changes(&manifest);
+    if !args.flag_quiet {
+        for migration in &migrations {
+            print_msg(migration.status(), &migration.to_string())?;
+        }
+    }
+
+    if migrations.is_empty() || args.flag_dry_run {
+        return Ok(());
+    }
+
+    replace::apply(&mut manifest, &migrations)?;
+    write_root_manifest(&manifest, &manifest_path)
+}
+
+fn handle_publish_check(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let manifest = Manifest::open(&manifest_path)?;
+
+    if !manifest.is_publishable() {
+        if !args.flag_quiet {
+            print_msg("Skipping", "the package is not published (`publish = false`)")?;
+        }
+        return Ok(());
+    }
+
+    let findings = publish_check::find_problems(&manifest);
+    for finding in &findings {
+        print_msg("Blocking", &finding.to_string())?;
+    }
+    if !findings.is_empty() {
+        return Err(Error::PublishBlocked(findings.len()));
+    }
+    Ok(())
+}
+
+fn handle_fix_wildcards(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let path = find(&manifest_path)?;
+    let locked = read_lock_file(&find_lock_file(&path))?;
+
+    let manifest_path = Some(path);
+    let mut manifest = Manifest::open(&manifest_path)?;
+
+    let fixes = wildcards::find_fixes(&manifest, &locked, &args.flag_exclude, |name| {
+        let dep = get_latest_dependency(name, Prereleases::None)?;
+        Ok(dep.version()
+            .expect("Latest dependency has a version")
+            .to_owned())
+    })?;
+    if !args.flag_quiet {
+        for fix in &fixes {
+            print_msg("Pinning", &fix.to_string())?;
+        }
+    }
+
+    if fixes.is_empty() || args.flag_dry_run {
+        return Ok(());
+    }
+
+    wildcards::apply(&mut manifest, &fixes)?;
+    manifest.write_to_path(&manifest_path)?;
+    Ok(())
+}
+
+fn handle_features_report(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let manifest = Manifest::open(&manifest_path)?;
+
+    let mut warnings = Warnings::new();
+    let reports = features_report::find_reports(&manifest, |name, version_req| {
+        get_crate_features(name, version_req, &mut warnings)
+    })?;
+    if !args.flag_quiet {
+        for report in &reports {
+            print_msg("Defaults", &report.to_string())?;
+            if let Some(suggestion) = report.suggestion() {
+                print_msg("Suggestion", &suggestion)?;
+            }
+        }
+        warnings.render(&mut io::stdout())?;
+    }
+    Ok(())
+}
+
+fn handle_fmt(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let manifest = Manifest::open(&manifest_path)?;
+
+    let options = format::Options {
+        sort: args.flag_sort,
+        align: args.flag_align,
+    };
+    let (formatted, changed) = format::format(&manifest.to_string(), options);
+    if !args.flag_quiet {
+        for section in &changed {
+            let described = DepTable::from_table(section)
+                .map(|table| table.to_string())
+                .unwrap_or_else(|| section.join("."));
+            print_msg("Formatting", &described)?;
+        }
+    }
+
+    if changed.is_empty() || args.flag_dry_run {
+        return Ok(());
+    }
+
+    let manifest: Manifest = formatted.parse()?;
+    write_root_manifest(&manifest, &manifest_path)
+}
+
+fn handle_verify_workspace(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let mut members = open_members(&manifest_path)?;
+
+    let inconsistencies = verify::find_inconsistencies(&members);
+    let (fixed, left): (Vec<_>, Vec<_>) = inconsistencies
+        .into_iter()
+        .partition(|inconsistency| args.flag_fix && inconsistency.harmonized.is_some());
+
+    if !args.flag_quiet {
+        for inconsistency in &fixed {
+            let harmonized = inconsistency.harmonized.as_ref().expect("only harmonized");
+            print_msg("Harmonizing", &format!("{} to {}", inconsistency, harmonized))?;
+        }
+    }
+    for inconsistency in &left {
+        print_msg("Mismatch", &inconsistency.to_string())?;
+    }
+
+    if !fixed.is_empty() && !args.flag_dry_run {
+        for index in verify::apply(&mut members, &fixed)? {
+            let member = &members[index];
+            let path = Some(member.manifest_path.clone());
+            member.manifest.write_to_path(&path)?;
+        }
+    }
+
+    if !left.is_empty() {
+        return Err(Error::WorkspaceInconsistent(left.len()));
+    }
+    Ok(())
+}
+
+fn handle_licenses(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let path = find(&manifest_path)?;
+    let manifest = Manifest::open(&Some(path.clone()))?;
+    let manifest_dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+
+    let licenses = licenses::find_licenses(&manifest, manifest_dir, get_crate_info)?;
+    match args.flag_output {
+        OutputFormat::Table => if !args.flag_quiet {
+            print!("{}", licenses::table(&licenses));
+        },
+        OutputFormat::Json => {
+            let name = manifest.data["package"]["name"].as_str().unwrap_or_default();
+            let document = licenses::spdx_document(name, &licenses);
+            println!(
+                "{}",
+                serde_json::to_string(&document).expect("document is correctly serialized")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn handle_sbom(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let path = find(&manifest_path)?;
+    let manifest = Manifest::open(&Some(path.clone()))?;
+    let lock_file = find_lock_file(&path);
+    let locked = read_locked_packages(&lock_file)?.ok_or(Error::MissingLockFile(lock_file))?;
+
+    match args.flag_format {
+        SbomFormat::Cyclonedx => {
+            let bom = sbom::cyclonedx(&manifest, &locked);
+            println!(
+                "{}",
+                serde_json::to_string(&bom).expect("bill of materials is correctly serialized")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn handle_graph(args: &Args) -> Result<()> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let members = open_members(&manifest_path)?;
+
+    let graph = graph::find_graph(&members);
+    if args.flag_mermaid {
+        print!("{}", graph.mermaid());
+    } else {
+        print!("{}", graph.dot());
+    }
+    Ok(())
+}
+
+fn handle_bot_config(args: &Args) -> Result<()> {
+    let manifest_path = find(&args.flag_manifest_path.as_ref().map(From::from))?;
+    let root_manifest = match find_workspace_root(&manifest_path) {
+        Ok(root_manifest) => root_manifest,
+        Err(cargo_edit::Error::WorkspaceRootNotFound) => manifest_path,
+        Err(e) => return Err(e.into()),
+    };
+    let root_manifest = Some(root_manifest);
+    let dirs = workspace_members(&root_manifest)?
+        .into_iter()
+        .chain(excluded_members(&root_manifest)?)
+        .map(|package| package.path);
+
+    let config = bot_config::BotConfig::new(dirs);
+    if args.flag_renovate {
+        print!("{}", config.renovate());
+    } else {
+        print!("{}", config.dependabot());
+    }
+    Ok(())
+}
+
+fn handle_apply(args: &Args) -> Result<()> {
+    let changes_path = Path::new(&args.arg_changes);
+    let contents = fs::read_to_string(changes_path)?;
+    let base_dir = match changes_path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let changes = apply::parse(&contents, base_dir)?;
+
+    let mut manifests = Vec::new();
+    let mut steps = Vec::new();
+    for path in &changes.manifests {
+        let path = find(&Some(path.clone()))?;
+        let mut manifest = Manifest::open(&Some(path.clone()))?;
+        for step in apply::apply(&mut manifest, &changes.operations)? {
+            steps.push((step, path.clone()));
+        }
+        manifests.push((path, manifest));
+    }
+    if !args.flag_quiet {
+        for &(ref step, ref path) in &steps {
+            print_msg(step.status, &format!("{}: {}", path.display(), step))?;
+        }
+    }
+
+    if args.flag_dry_run {
+        return Ok(());
+    }
+    apply::write_all(&manifests)
+}
+
+/// What the command supports, for `--version --json`.
+pub fn self_description() -> SelfDescription {
+    SelfDescription::new("cargo-dep")
+        .set_features(&["sparse-index", "workspace", "dry-run", "apply-changes"])
+        .set_output_formats(&[
+            ("dep licenses --output json", 1),
+            ("dep sbom --format cyclonedx", 1),
+            ("change-file", 1),
+        ])
+}
+
+/// Run the command with the arguments of the process.
+pub fn main() {
+    let (argv, notes) = command_args().unwrap_or_else(|err| exit_with_error(&Error::from(err)));
+    for note in notes {
+        eprintln!("{}", note);
+    }
+    let args = docopt::Docopt::new(USAGE)
+        .and_then(|d| d.argv(argv).deserialize::<Args>())
+        .unwrap_or_else(|err| err.exit());
+
+    if args.flag_version {
+        if args.flag_json {
+            println!("{}", self_description().to_json());
+        } else {
+            println!("cargo-dep version {}", env!("CARGO_PKG_VERSION"));
+        }
+        process::exit(0);
+    }
+
+    let result = if args.cmd_dedupe {
+        handle_dedupe(&args)
+    } else if args.cmd_hoist {
+        handle_hoist(&args)
+    } else if args.cmd_unhoist {
+        handle_unhoist(&args)
+    } else if args.cmd_patch {
+        handle_patch(&args)
+    } else if args.cmd_migrate_replace {
+        handle_migrate_replace(&args)
+    } else if args.cmd_publish_check {
+        handle_publish_check(&args)
+    } else if args.cmd_fix_wildcards {
+        handle_fix_wildcards(&args)
+    } else if args.cmd_features_report {
+        handle_features_report(&args)
+    } else if args.cmd_fmt {
+        handle_fmt(&args)
+    } else if args.cmd_verify_workspace {
+        handle_verify_workspace(&args)
+    } else if args.cmd_licenses {
+        handle_licenses(&args)
+    } else if args.cmd_sbom {
+        handle_sbom(&args)
+    } else if args.cmd_graph {
+        handle_graph(&args)
+    } else if args.cmd_bot_config {
+        handle_bot_config(&args)
+    } else if args.cmd_apply {
+        handle_apply(&args)
+    } else {
+        Ok(())
+    };
+
+    if let Err(err) = result {
+        exit_with_error(&err);
+    }
+}
+
+/// Print an error with its causes, and exit with a failure.
+fn exit_with_error(err: &Error) -> ! {
+    eprintln!("{}\n", message!("unhandled-error", error = err));
+
+    let mut cause = err.source();
+    while let Some(e) = cause {
+        eprintln!("{}", message!("caused-by", cause = e));
+        cause = e.source();
+    }
+
+    process::exit(1);
+}
@@ -0,0 +1,95 @@
+//! `cargo dep patch`: manage the `[patch.<registry>]` tables
+use cargo_edit::{self, Dependency, GitReference, Manifest};
+
+use super::dedupe::remove_entry;
+use super::errors::*;
+
+/// Path of the patch table for `registry` (e.g. `crates-io`, or the URL of a registry).
+fn patch_section(registry: &str) -> Vec<String> {
+    vec!["patch".to_owned(), registry.to_owned()]
+}
+
+/// Build the patch entry for the crate `name` from a git repository or a path.
+pub fn patch_dependency(
+    name: &str,
+    git: Option<&str>,
+    reference: Option<GitReference>,
+    path: Option<&str>,
+) -> Dependency {
+    let dep = Dependency::new(name);
+    match (git, path) {
+        (Some(repo), _) => {
+            let dep = dep.set_git(repo);
+            match reference {
+                Some(reference) => dep.set_git_reference(reference),
+                None => dep,
+            }
+        }
+        (None, Some(path)) => dep.set_path(path),
+        // The usage requires `--git` or `--path`.
+        (None, None) => dep,
+    }
+}
+
+/// Add (or replace) the patch for `dep` in the table of `registry`.
+pub fn add(manifest: &mut Manifest, registry: &str, dep: &Dependency) -> Result<()> {
+    let section = patch_section(registry);
+    // A patch is replaced as a whole, there is nothing to merge.
+    if !manifest.data["patch"][registry][&dep.name].is_none() {
+        remove_entry(manifest, &section, &dep.name);
+    }
+    manifest.get_table(&section)?[&dep.name] = dep.to_toml().1;
+    Ok(())
+}
+
+/// Remove the patch of the crate `name` from the table of `registry`, and the table if it is left
+/// empty.
+pub fn remove(manifest: &mut Manifest, registry: &str, name: &str) -> Result<()> {
+    if manifest.data["patch"][registry][name].is_none() {
+        return Err(cargo_edit::Error::DependencyMissing(
+            name.into(),
+            patch_section(registry).join("."),
+        ).into());
+    }
+    remove_entry(manifest, &patch_section(registry), name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_remove_patches() {
+        let mut manifest: Manifest = "[package]\nname = \"foo\"\n\n[dependencies]\nserde = \"1.0\"\n"
+            .parse()
+            .unwrap();
+
+        let dep = patch_dependency(
+            "serde",
+            Some("https://github.com/me/serde"),
+            Some(GitReference::Branch("fix".into())),
+            None,
+        );
+        add(&mut manifest, "crates-io", &dep).unwrap();
+        {
+            let patch = &manifest.data["patch"]["crates-io"]["serde"];
+            assert_eq!(patch["git"].as_str(), Some("https://github.com/me/serde"));
+            assert_eq!(patch["branch"].as_str(), Some("fix"));
+        }
+
+        // Replacing a patch does not keep any of the old keys.
+        let dep = patch_dependency("serde", None, None, Some("../serde"));
+        add(&mut manifest, "crates-io", &dep).unwrap();
+        {
+            let patch = &manifest.data["patch"]["crates-io"]["serde"];
+            assert_eq!(patch["path"].as_str(), Some("../serde"));
+            assert!(patch["git"].is_none());
+            assert!(patch["branch"].is_none());
+        }
+
+        remove(&mut manifest, "crates-io", "serde").unwrap();
+        assert!(manifest.data["patch"].is_none());
+        assert!(remove(&mut manifest, "crates-io", "serde").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! `cargo dep publish-check`: find dependency entries that keep a package from being published
+use std::fmt;
+
+use cargo_edit::{DepKind, DepTable, Dependency, Manifest};
+
+/// Why a dependency entry keeps the package from being published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// A path dependency without a version
+    PathWithoutVersion,
+    /// A git dependency without a version
+    GitWithoutVersion,
+    /// A version requirement with a wildcard (e.g. `*` or `1.*`)
+    Wildcard,
+}
+
+/// A dependency entry that keeps the package from being published.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// The section with the entry
+    pub section: DepTable,
+    /// Name of the dependency
+    pub name: String,
+    /// What is wrong with the entry
+    pub problem: Problem,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let problem = match self.problem {
+            Problem::PathWithoutVersion => "path dependency without a version",
+            Problem::GitWithoutVersion => "git dependency without a version",
+            Problem::Wildcard => "wildcard version requirement, which crates.io does not accept",
+        };
+        write!(f, "{} in {}: {}", self.name, self.section, problem)
+    }
+}
+
+/// Whether a version requirement contains a wildcard.
+pub fn is_wildcard(req: &str) -> bool {
+    req.contains('*')
+}
+
+/// Check a dependency. Dev-dependencies do not need a version, `cargo publish` leaves out the ones
+/// that have none.
+fn check(dep: &Dependency, is_dev: bool) -> Option<Problem> {
+    match dep.version() {
+        Some(version) if is_wildcard(version) => Some(Problem::Wildcard),
+        Some(_) => None,
+        None if is_dev => None,
+        None if dep.git().is_some() => Some(Problem::GitWithoutVersion),
+        None => Some(Problem::PathWithoutVersion),
+    }
+}
+
+/// Find the dependency entries that keep the package from being published.
+pub fn find_problems(manifest: &Manifest) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (section, table) in manifest.get_sections() {
+        let is_dev = section.kind() == DepKind::Development;
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (name, entry) in table.iter() {
+            let problem = Dependency::from_toml(name, entry).and_then(|dep| check(&dep, is_dev));
+            if let Some(problem) = problem {
+                findings.push(Finding {
+                    section: section.clone(),
+                    name: name.to_owned(),
+                    problem: problem,
+                });
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_publish_problems() {
+        let manifest: Manifest = r#"[package]
+name = "foo"
+
+[dependencies]
+a = "1.0"
+b = "*"
+c = { path = "../c" }
+d = { path = "../d", version = "0.1" }
+e = { git = "https://github.com/me/e" }
+f = { version = "0.2.*", optional = true }
+
+[dev-dependencies]
+g = { path = "../g" }
+h = "*"
+"#.parse()
+            .unwrap();
+
+        let findings: Vec<_> = find_problems(&manifest)
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                "h in dev-dependencies: wildcard version requirement, which crates.io does not \
+                 accept",
+                "b in dependencies: wildcard version requirement, which crates.io does not accept",
+                "c in dependencies: path dependency without a version",
+                "e in dependencies: git dependency without a version",
+                "f in dependencies: wildcard version requirement, which crates.io does not accept",
+            ]
+        );
+    }
+}
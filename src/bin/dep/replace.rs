@@ -0,0 +1,205 @@
+//! `cargo dep migrate-replace`: convert the deprecated `[replace]` table into `[patch]` tables
+use std::fmt;
+
+use cargo_edit::Manifest;
+use toml_edit;
+
+use super::dedupe::remove_entry;
+use super::errors::*;
+
+/// The URL of the crates.io index, as used in package ids.
+const CRATES_IO_INDEX: &str = "https://github.com/rust-lang/crates.io-index";
+
+/// What happens to an entry of `[replace]`.
+#[derive(Debug, Clone)]
+pub enum Migration {
+    /// The entry is moved to `[patch.<registry>]`.
+    Convert {
+        /// Key of the entry in `[replace]` (e.g. `foo:0.1.0`)
+        key: String,
+        /// Name of the crate
+        name: String,
+        /// Key of the patch table (`crates-io` or the URL of the registry)
+        registry: String,
+        /// The entry
+        spec: toml_edit::Item,
+    },
+    /// The entry has to be converted by hand.
+    Skip {
+        /// Key of the entry in `[replace]`
+        key: String,
+        /// Why it can not be converted
+        reason: String,
+    },
+}
+
+impl Migration {
+    /// The word describing the migration, as shown in front of the message
+    pub fn status(&self) -> &'static str {
+        match *self {
+            Migration::Convert { .. } => "Migrating",
+            Migration::Skip { .. } => "Skipping",
+        }
+    }
+}
+
+impl fmt::Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Migration::Convert {
+                ref key,
+                ref registry,
+                ..
+            } => write!(f, "`{}` to patch.{}", key, registry),
+            Migration::Skip {
+                ref key,
+                ref reason,
+            } => write!(f, "`{}`: {}", key, reason),
+        }
+    }
+}
+
+/// Split a package id spec like `foo:0.1.0` or `https://example.com/index#foo:0.1.0` into the
+/// registry and the crate name.
+fn parse_key(key: &str) -> Option<(String, String)> {
+    let (source, package) = match key.rfind('#') {
+        Some(i) => (Some(&key[..i]), &key[i + 1..]),
+        None => (None, key),
+    };
+    let name = package.splitn(2, ':').next()?.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let registry = match source {
+        None => "crates-io".to_owned(),
+        Some(url) if url.trim_end_matches('/') == CRATES_IO_INDEX => "crates-io".to_owned(),
+        Some(url) => url.trim_start_matches("registry+").to_owned(),
+    };
+    Some((registry, name.to_owned()))
+}
+
+/// Work out how to migrate every entry of `[replace]`.
+pub fn find_changes(manifest: &Manifest) -> Vec<Migration> {
+    let entries = match manifest.data["replace"].as_table_like() {
+        Some(entries) => entries,
+        None => return vec![],
+    };
+
+    let mut migrations: Vec<Migration> = Vec::new();
+    for (key, entry) in entries.iter() {
+        let skip = |reason: &str| Migration::Skip {
+            key: key.to_owned(),
+            reason: reason.to_owned(),
+        };
+
+        let (registry, name) = match parse_key(key) {
+            Some(parsed) => parsed,
+            None => {
+                migrations.push(skip("the package id is not understood"));
+                continue;
+            }
+        };
+        if entry["git"].is_none() && entry["path"].is_none() {
+            migrations.push(skip("patches need a `git` or `path` source"));
+            continue;
+        }
+        let patched_already = !manifest.data["patch"][&registry][&name].is_none()
+            || migrations.iter().any(|m| match *m {
+                Migration::Convert {
+                    name: ref n,
+                    registry: ref r,
+                    ..
+                } => *n == name && *r == registry,
+                Migration::Skip { .. } => false,
+            });
+        if patched_already {
+            migrations.push(skip(&format!(
+                "`{}` is patched already, and only one version of a crate can be patched",
+                name
+            )));
+            continue;
+        }
+
+        migrations.push(Migration::Convert {
+            key: key.to_owned(),
+            name: name,
+            registry: registry,
+            spec: entry.clone(),
+        });
+    }
+    migrations
+}
+
+/// Apply the migrations found by `find_changes`. Entries that are skipped stay in `[replace]`.
+pub fn apply(manifest: &mut Manifest, migrations: &[Migration]) -> Result<()> {
+    for migration in migrations {
+        if let Migration::Convert {
+            ref key,
+            ref name,
+            ref registry,
+            ref spec,
+        } = *migration
+        {
+            manifest.get_table(&["patch".to_owned(), registry.clone()])?[name] = spec.clone();
+            remove_entry(manifest, &["replace".to_owned()], key);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_package_ids() {
+        assert_eq!(
+            parse_key("foo:0.1.0"),
+            Some(("crates-io".to_owned(), "foo".to_owned()))
+        );
+        assert_eq!(
+            parse_key("https://github.com/rust-lang/crates.io-index#foo:0.1.0"),
+            Some(("crates-io".to_owned(), "foo".to_owned()))
+        );
+        assert_eq!(
+            parse_key("https://example.com/index#bar"),
+            Some(("https://example.com/index".to_owned(), "bar".to_owned()))
+        );
+        assert_eq!(parse_key(":0.1.0"), None);
+    }
+
+    #[test]
+    fn migrate_replace_entries() {
+        let mut manifest: Manifest = r#"[package]
+name = "foo"
+
+[replace]
+"bar:0.1.0" = { git = "https://github.com/me/bar" }
+"bar:0.2.0" = { git = "https://github.com/me/bar", branch = "v2" }
+"baz:1.0.0" = { path = "../baz" }
+"qux:1.0.0" = "1.0.1"
+"#.parse()
+            .unwrap();
+
+        let migrations = find_changes(&manifest);
+        let messages: Vec<_> = migrations.iter().map(|m| m.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "`bar:0.1.0` to patch.crates-io",
+                "`bar:0.2.0`: `bar` is patched already, and only one version of a crate can be \
+                 patched",
+                "`baz:1.0.0` to patch.crates-io",
+                "`qux:1.0.0`: patches need a `git` or `path` source",
+            ]
+        );
+
+        apply(&mut manifest, &migrations).unwrap();
+        let patches = &manifest.data["patch"]["crates-io"];
+        assert_eq!(patches["bar"]["git"].as_str(), Some("https://github.com/me/bar"));
+        assert_eq!(patches["baz"]["path"].as_str(), Some("../baz"));
+        assert!(manifest.data["replace"]["bar:0.1.0"].is_none());
+        assert!(!manifest.data["replace"]["bar:0.2.0"].is_none());
+    }
+}
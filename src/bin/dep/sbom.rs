@@ -0,0 +1,267 @@
+//! `cargo dep sbom`: a software bill of materials, from the manifest and `Cargo.lock`
+use std::collections::HashMap;
+
+use cargo_edit::{DepKind, Dependency, LockedPackage, Manifest};
+use serde_derive::Serialize;
+
+/// How a package is needed by the package the bill of materials is for, in CycloneDX terms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Only for tests or build scripts (dev- and build-dependencies)
+    Excluded,
+    /// An optional dependency
+    Optional,
+    /// A dependency
+    Required,
+}
+
+/// A CycloneDX bill of materials (JSON, specification 1.5).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bom {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    version: u32,
+    metadata: Metadata,
+    components: Vec<Component>,
+    dependencies: Vec<DependencyGraph>,
+}
+
+/// The package the bill of materials is for.
+#[derive(Debug, Serialize)]
+struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component: Option<Component>,
+}
+
+/// A package, e.g. a crate from crates.io.
+#[derive(Debug, Serialize)]
+struct Component {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    name: String,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<Scope>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purl: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<Hash>,
+    #[serde(rename = "externalReferences", skip_serializing_if = "Vec::is_empty")]
+    external_references: Vec<ExternalReference>,
+}
+
+/// The checksum of a package.
+#[derive(Debug, Serialize)]
+struct Hash {
+    alg: &'static str,
+    content: String,
+}
+
+/// Where a package comes from, for packages that are not from a registry.
+#[derive(Debug, Serialize)]
+struct ExternalReference {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    url: String,
+}
+
+/// The packages a package depends on.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DependencyGraph {
+    #[serde(rename = "ref")]
+    reference: String,
+    depends_on: Vec<String>,
+}
+
+/// How the manifest needs each crate it depends on directly, by crate name. A crate declared in
+/// several sections gets the widest scope, e.g. `Required` for a dependency that is also a
+/// dev-dependency.
+fn direct_scopes(manifest: &Manifest) -> HashMap<String, Scope> {
+    let mut scopes = HashMap::new();
+    for (section, table) in manifest.get_sections() {
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (key, entry) in table.iter() {
+            if Dependency::from_toml(key, entry).is_none() {
+                continue;
+            }
+            let scope = match section.kind() {
+                DepKind::Normal if entry["optional"].as_bool() == Some(true) => Scope::Optional,
+                DepKind::Normal => Scope::Required,
+                DepKind::Development | DepKind::Build => Scope::Excluded,
+            };
+            // Renamed dependencies name the crate with `package`.
+            let name = entry["package"].as_str().unwrap_or(key).to_owned();
+            let widest = scopes.entry(name).or_insert(scope);
+            *widest = (*widest).max(scope);
+        }
+    }
+    scopes
+}
+
+/// The reference of a package within the bill of materials
+fn bom_ref(package: &LockedPackage) -> String {
+    format!("{}@{}", package.name, package.version)
+}
+
+/// Describe a locked package as a component.
+fn component(package: &LockedPackage, kind: &'static str, scope: Option<Scope>) -> Component {
+    let source = package.source.as_ref().map(String::as_str).unwrap_or("");
+    let from_registry = source.starts_with("registry+") || source.starts_with("sparse+");
+    let git = if source.starts_with("git+") {
+        // The commit follows the `#`, e.g. `git+https://github.com/me/fork#0123abcd`.
+        vec![ExternalReference {
+            kind: "vcs",
+            url: source["git+".len()..].to_owned(),
+        }]
+    } else {
+        vec![]
+    };
+    Component {
+        kind: kind,
+        bom_ref: bom_ref(package),
+        name: package.name.clone(),
+        version: package.version.to_string(),
+        scope: scope,
+        purl: if from_registry {
+            Some(format!("pkg:cargo/{}@{}", package.name, package.version))
+        } else {
+            None
+        },
+        hashes: package
+            .checksum
+            .iter()
+            .map(|checksum| Hash {
+                alg: "SHA-256",
+                content: checksum.clone(),
+            })
+            .collect(),
+        external_references: git,
+    }
+}
+
+/// Combine the manifest with the packages of its lock file into a CycloneDX bill of materials.
+/// Every locked package but the one of the manifest is a component; the ones the manifest depends
+/// on directly get a scope. Without a `[package]` (e.g. for a virtual manifest), the bill of
+/// materials is for the whole workspace.
+pub fn cyclonedx(manifest: &Manifest, locked: &[LockedPackage]) -> Bom {
+    let package = &manifest.data["package"];
+    let root = locked.iter().position(|p| {
+        p.source.is_none()
+            && Some(p.name.as_str()) == package["name"].as_str()
+            && Some(p.version.to_string().as_str()) == package["version"].as_str()
+    });
+
+    let scopes = direct_scopes(manifest);
+    let direct: Vec<&LockedPackage> = match root {
+        Some(root) => locked
+            .iter()
+            .filter(|p| locked[root].dependencies.iter().any(|d| p.is_named_by(d)))
+            .collect(),
+        None => vec![],
+    };
+    let components = locked
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| Some(index) != root)
+        .map(|(_, p)| {
+            let scope = if direct.contains(&p) {
+                scopes.get(&p.name).cloned()
+            } else {
+                None
+            };
+            component(p, "library", scope)
+        })
+        .collect();
+
+    let dependencies = locked
+        .iter()
+        .map(|p| DependencyGraph {
+            reference: bom_ref(p),
+            depends_on: p.dependencies
+                .iter()
+                .filter_map(|d| locked.iter().find(|other| other.is_named_by(d)))
+                .map(bom_ref)
+                .collect(),
+        })
+        .collect();
+
+    Bom {
+        bom_format: "CycloneDX",
+        spec_version: "1.5",
+        version: 1,
+        metadata: Metadata {
+            component: root.map(|root| component(&locked[root], "application", None)),
+        },
+        components: components,
+        dependencies: dependencies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cargo_edit::parse_locked_packages;
+
+    #[test]
+    fn scopes_and_graph() {
+        let manifest = r#"[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+a = "0.2"
+b = { git = "https://github.com/me/b", optional = true }
+
+[dev-dependencies]
+a = "0.2"
+"#;
+        let locked = parse_locked_packages(
+            r#"[[package]]
+name = "a"
+version = "0.2.7"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "0123abcd"
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "a",
+ "b",
+]
+
+[[package]]
+name = "b"
+version = "1.0.0"
+source = "git+https://github.com/me/b#0123abcd"
+dependencies = [
+ "a",
+]
+"#,
+        ).unwrap();
+
+        let bom = cyclonedx(&manifest.parse().unwrap(), &locked);
+        let root = bom.metadata.component.as_ref().unwrap();
+        assert_eq!(root.bom_ref, "app@0.1.0");
+        assert_eq!(bom.components.len(), 2);
+        assert_eq!(bom.components[0].scope, Some(Scope::Required));
+        assert_eq!(
+            bom.components[0].purl,
+            Some("pkg:cargo/a@0.2.7".to_owned())
+        );
+        assert_eq!(bom.components[0].hashes[0].content, "0123abcd");
+        assert_eq!(bom.components[1].scope, Some(Scope::Optional));
+        assert_eq!(bom.components[1].purl, None);
+        assert_eq!(
+            bom.components[1].external_references[0].url,
+            "https://github.com/me/b#0123abcd"
+        );
+        assert_eq!(bom.dependencies[1].depends_on, vec!["a@0.2.7", "b@1.0.0"]);
+        assert_eq!(bom.dependencies[2].depends_on, vec!["a@0.2.7"]);
+    }
+}
@@ -0,0 +1,223 @@
+//! `cargo dep unhoist`: replace dependencies inherited from the workspace by the entries they
+//! inherit
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use cargo_edit::{DepTable, Manifest};
+use toml_edit;
+
+use super::errors::*;
+
+/// An inherited entry to replace.
+#[derive(Debug, Clone)]
+pub struct Unhoist {
+    /// The section with the entry
+    pub section: DepTable,
+    /// Name of the dependency
+    pub name: String,
+    /// The entry to write instead of the inherited one
+    pub spec: toml_edit::Item,
+}
+
+impl fmt::Display for Unhoist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} from the workspace into {}", self.name, self.section)
+    }
+}
+
+/// Express `path`, relative to the workspace root, relative to the member directory `member_dir`
+/// (itself relative to the workspace root).
+fn rebase_path(path: &str, member_dir: &Path) -> String {
+    if Path::new(path).is_absolute() {
+        return path.to_owned();
+    }
+    let mut rebased: PathBuf = member_dir
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .map(|_| Component::ParentDir)
+        .collect();
+    rebased.push(path);
+    rebased
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Merge the entry of the workspace root with the member's inherited entry, which may add
+/// features and make the dependency optional.
+fn concrete_spec(
+    root_entry: &toml_edit::Item,
+    member_entry: &toml_edit::Item,
+    member_dir: &Path,
+) -> Option<toml_edit::Item> {
+    let mut spec = toml_edit::InlineTable::default();
+    let mut features = Vec::new();
+
+    if let Some(version) = root_entry.as_str() {
+        spec.get_or_insert("version", version);
+    } else {
+        for (key, value) in root_entry.as_table_like()?.iter() {
+            let value = value.as_value()?;
+            match (key, value.as_str()) {
+                ("path", Some(path)) => {
+                    spec.get_or_insert("path", rebase_path(path, member_dir));
+                }
+                ("features", _) => features.extend(strings(value)),
+                _ => {
+                    spec.get_or_insert(key, value.clone());
+                }
+            }
+        }
+    }
+
+    for feature in member_entry["features"].as_value().map(strings).unwrap_or_default() {
+        if !features.contains(&feature) {
+            features.push(feature);
+        }
+    }
+    if !features.is_empty() {
+        let mut array = toml_edit::Array::default();
+        for feature in &features {
+            array.push(feature.as_str());
+        }
+        spec.get_or_insert("features", toml_edit::Value::Array(array));
+    }
+    if let Some(optional) = member_entry["optional"].as_bool() {
+        spec.get_or_insert("optional", optional);
+    }
+
+    if spec.len() == 1 {
+        if let Some(version) = spec.get("version").and_then(|v| v.as_str()) {
+            return Some(toml_edit::value(version));
+        }
+    }
+    spec.fmt();
+    Some(toml_edit::value(toml_edit::Value::InlineTable(spec)))
+}
+
+/// The strings in an array value.
+fn strings(value: &toml_edit::Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Find the inherited entries of `member` to replace. If `names` is empty, all inherited entries
+/// are replaced. `member_dir` is the member's directory, relative to the workspace root.
+pub fn find_changes(
+    root: &Manifest,
+    member: &Manifest,
+    member_dir: &Path,
+    names: &[String],
+) -> Result<Vec<Unhoist>> {
+    let workspace_deps = &root.data["workspace"]["dependencies"];
+    let mut changes = Vec::new();
+
+    for (section, table) in member.get_sections() {
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (name, entry) in table.iter() {
+            if entry["workspace"].as_bool() != Some(true)
+                || !(names.is_empty() || names.iter().any(|n| n == name))
+            {
+                continue;
+            }
+            let root_entry = &workspace_deps[name];
+            if root_entry.is_none() {
+                return Err(cargo_edit::Error::DependencyMissing(
+                    name.into(),
+                    "workspace.dependencies".into(),
+                ).into());
+            }
+            let spec = concrete_spec(root_entry, entry, member_dir)
+                .ok_or_else(|| Error::UnsupportedEntry(name.into()))?;
+            changes.push(Unhoist {
+                section: section.clone(),
+                name: name.to_owned(),
+                spec: spec,
+            });
+        }
+    }
+
+    for name in names {
+        if !changes.iter().any(|c| c.name == *name) {
+            return Err(Error::NotInherited(name.clone()));
+        }
+    }
+    Ok(changes)
+}
+
+/// Apply the changes found by `find_changes`.
+pub fn apply(member: &mut Manifest, changes: &[Unhoist]) -> Result<()> {
+    for change in changes {
+        member.get_table(&change.section.to_table())?[&change.name] = change.spec.clone();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebase_paths() {
+        assert_eq!(rebase_path("libs/foo", Path::new("crates/bar")), "../../libs/foo");
+        assert_eq!(rebase_path("libs/foo", Path::new("")), "libs/foo");
+    }
+
+    #[test]
+    fn unhoist_inherited_entries() {
+        let root: Manifest = r#"[workspace]
+members = ["crates/one"]
+
+[workspace.dependencies]
+log = "0.4"
+serde = { version = "1.0", features = ["std"] }
+local = { path = "libs/local" }
+"#.parse()
+            .unwrap();
+        let mut member: Manifest = r#"[package]
+name = "one"
+
+[dependencies]
+log = { workspace = true }
+serde = { workspace = true, features = ["derive"], optional = true }
+regex = "0.2"
+
+[dev-dependencies]
+local = { workspace = true }
+"#.parse()
+            .unwrap();
+        let member_dir = Path::new("crates/one");
+
+        let changes = find_changes(&root, &member, member_dir, &[]).unwrap();
+        let messages: Vec<_> = changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "local from the workspace into dev-dependencies",
+                "log from the workspace into dependencies",
+                "serde from the workspace into dependencies",
+            ]
+        );
+
+        apply(&mut member, &changes).unwrap();
+        assert_eq!(member.data["dependencies"]["log"].as_str(), Some("0.4"));
+        let serde = &member.data["dependencies"]["serde"];
+        assert_eq!(serde["version"].as_str(), Some("1.0"));
+        assert_eq!(serde["features"].as_array().map(|a| a.len()), Some(2));
+        assert_eq!(serde["optional"].as_bool(), Some(true));
+        assert_eq!(
+            member.data["dev-dependencies"]["local"]["path"].as_str(),
+            Some("../../libs/local")
+        );
+
+        assert!(find_changes(&root, &member, member_dir, &["regex".to_owned()]).is_err());
+    }
+}
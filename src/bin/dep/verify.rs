@@ -0,0 +1,355 @@
+//! `cargo dep verify-workspace`: find dependencies that the members of a workspace declare in
+//! different ways
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use cargo_edit::Dependency;
+use semver;
+use toml_edit;
+
+use super::errors::*;
+use super::hoist::Member;
+
+/// How the declarations of a dependency differ between the members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difference {
+    /// Different version requirements
+    Versions,
+    /// Different sets of features
+    Features,
+    /// From a git repository in some members, from a registry in others
+    Sources,
+}
+
+/// A dependency that the members of a workspace declare in different ways.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inconsistency {
+    /// Name of the crate
+    pub name: String,
+    /// What differs
+    pub difference: Difference,
+    /// What each member declares, e.g. `("one", "0.3")`
+    pub declared: Vec<(String, String)>,
+    /// What `--fix` makes all members declare, if the declarations can be harmonized
+    pub harmonized: Option<Harmonized>,
+}
+
+/// What the members of a workspace are made to declare by `--fix`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Harmonized {
+    /// The newest of the version requirements
+    Version(String),
+    /// All the features any member enables
+    Features(Vec<String>),
+}
+
+impl fmt::Display for Harmonized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Harmonized::Version(ref version) => write!(f, "{}", version),
+            Harmonized::Features(ref features) => write!(f, "{}", describe_features(features)),
+        }
+    }
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let difference = match self.difference {
+            Difference::Versions => "versions",
+            Difference::Features => "features",
+            Difference::Sources => "sources",
+        };
+        let declared: Vec<_> = self.declared
+            .iter()
+            .map(|&(ref member, ref declared)| format!("{}: {}", member, declared))
+            .collect();
+        write!(
+            f,
+            "{}: different {} ({})",
+            self.name,
+            difference,
+            declared.join(", ")
+        )
+    }
+}
+
+/// One entry of a dependency in the manifest of a member.
+#[derive(Debug)]
+struct Declaration<'a> {
+    /// Name of the member
+    member: &'a str,
+    /// Version requirement, if any
+    version: Option<String>,
+    /// Features, sorted
+    features: Vec<String>,
+    /// Whether the dependency comes from a git repository
+    git: bool,
+}
+
+/// The name of the crate an entry depends on, unless the entry is inherited from the workspace or
+/// a path dependency: these can not differ in a way that matters.
+fn crate_name<'a>(key: &'a str, entry: &'a toml_edit::Item) -> Option<(&'a str, Dependency)> {
+    if entry["workspace"].as_bool() == Some(true) {
+        return None;
+    }
+    let dep = Dependency::from_toml(key, entry)?;
+    if dep.path().is_some() {
+        return None;
+    }
+    // Renamed dependencies name the crate with `package`.
+    Some((entry["package"].as_str().unwrap_or(key), dep))
+}
+
+/// The lowest version a requirement like `^1.2` allows, to find the newest requirement. Wildcards
+/// (e.g. `0.2.*`) are not understood.
+fn lower_bound(req: &str) -> Option<semver::Version> {
+    let first = req.split(',').next().unwrap_or(req);
+    let first = first.trim_start_matches(|c: char| "=^~<>".contains(c)).trim();
+    let mut numbers: Vec<_> = first.split('.').collect();
+    while numbers.len() < 3 {
+        numbers.push("0");
+    }
+    semver::Version::parse(&numbers.join(".")).ok()
+}
+
+/// Describe a set of features, e.g. `[derive, std]`.
+fn describe_features(features: &[String]) -> String {
+    format!("[{}]", features.join(", "))
+}
+
+/// Add what a member declares, unless it is listed already (e.g. for a dev-dependency, too).
+fn push_declared(declared: &mut Vec<(String, String)>, member: &str, value: String) {
+    let entry = (member.to_owned(), value);
+    if !declared.contains(&entry) {
+        declared.push(entry);
+    }
+}
+
+/// Find the dependencies that the members declare with different version requirements, features
+/// or sources.
+pub fn find_inconsistencies(members: &[Member]) -> Vec<Inconsistency> {
+    let mut declarations: BTreeMap<String, Vec<Declaration>> = BTreeMap::new();
+    for member in members {
+        for (_, table) in member.manifest.get_sections() {
+            let table = table.as_table_like().expect("Unexpected non-table");
+            for (key, entry) in table.iter() {
+                let (name, dep) = match crate_name(key, entry) {
+                    Some(found) => found,
+                    None => continue,
+                };
+                let mut features = dep.features().map(|f| f.to_vec()).unwrap_or_default();
+                features.sort();
+                features.dedup();
+                declarations
+                    .entry(name.to_owned())
+                    .or_insert_with(Vec::new)
+                    .push(Declaration {
+                        member: &member.name,
+                        version: dep.version().map(String::from),
+                        features: features,
+                        git: dep.git().is_some(),
+                    });
+            }
+        }
+    }
+
+    let mut inconsistencies = Vec::new();
+    for (name, declarations) in declarations {
+        let registry: Vec<_> = declarations.iter().filter(|d| !d.git).collect();
+
+        let versions: BTreeSet<_> = registry.iter().filter_map(|d| d.version.as_ref()).collect();
+        if versions.len() > 1 {
+            let mut declared = Vec::new();
+            for declaration in &registry {
+                if let Some(ref version) = declaration.version {
+                    push_declared(&mut declared, declaration.member, version.clone());
+                }
+            }
+            let newest = versions
+                .iter()
+                .filter_map(|req| lower_bound(req).map(|version| (version, req.to_string())))
+                .max()
+                .map(|(_, req)| Harmonized::Version(req));
+            inconsistencies.push(Inconsistency {
+                name: name.clone(),
+                difference: Difference::Versions,
+                declared: declared,
+                harmonized: newest,
+            });
+        }
+
+        let feature_sets: BTreeSet<_> = declarations.iter().map(|d| &d.features).collect();
+        if feature_sets.len() > 1 {
+            let mut declared = Vec::new();
+            for declaration in &declarations {
+                let features = describe_features(&declaration.features);
+                push_declared(&mut declared, declaration.member, features);
+            }
+            let all: BTreeSet<_> = declarations
+                .iter()
+                .flat_map(|d| d.features.iter().cloned())
+                .collect();
+            inconsistencies.push(Inconsistency {
+                name: name.clone(),
+                difference: Difference::Features,
+                declared: declared,
+                harmonized: Some(Harmonized::Features(all.into_iter().collect())),
+            });
+        }
+
+        if !registry.is_empty() && registry.len() < declarations.len() {
+            let mut declared = Vec::new();
+            for declaration in &declarations {
+                let source = if declaration.git { "git" } else { "registry" };
+                push_declared(&mut declared, declaration.member, source.to_owned());
+            }
+            inconsistencies.push(Inconsistency {
+                name: name,
+                difference: Difference::Sources,
+                declared: declared,
+                harmonized: None,
+            });
+        }
+    }
+    inconsistencies
+}
+
+/// Rewrite the entries of the members to the harmonized version requirement or features. Returns
+/// the indices of the members that changed.
+pub fn apply(members: &mut [Member], inconsistencies: &[Inconsistency]) -> Result<Vec<usize>> {
+    let mut changed = Vec::new();
+    for (index, member) in members.iter_mut().enumerate() {
+        let mut entries = Vec::new();
+        for (section, table) in member.manifest.get_sections() {
+            let table = table.as_table_like().expect("Unexpected non-table");
+            for (key, entry) in table.iter() {
+                if let Some((name, dep)) = crate_name(key, entry) {
+                    entries.push((section.clone(), key.to_owned(), name.to_owned(), dep));
+                }
+            }
+        }
+
+        for (section, key, name, dep) in entries {
+            for inconsistency in inconsistencies.iter().filter(|i| i.name == name) {
+                let table = member.manifest.get_table(&section.to_table())?;
+                let entry = &mut table[&key];
+                match inconsistency.harmonized {
+                    Some(Harmonized::Version(ref version)) => {
+                        if dep.git().is_some() || dep.version() == Some(version.as_str()) {
+                            continue;
+                        }
+                        if entry.is_str() {
+                            *entry = toml_edit::value(version.as_str());
+                        } else {
+                            entry["version"] = toml_edit::value(version.as_str());
+                        }
+                    }
+                    Some(Harmonized::Features(ref all)) => {
+                        let mut features = dep.features().map(|f| f.to_vec()).unwrap_or_default();
+                        features.sort();
+                        features.dedup();
+                        if features == *all {
+                            continue;
+                        }
+                        let mut array = toml_edit::Array::default();
+                        for feature in all {
+                            array.push(feature.as_str());
+                        }
+                        if let Some(version) = entry.as_str().map(String::from) {
+                            let mut table = toml_edit::InlineTable::default();
+                            table.get_or_insert("version", version.as_str());
+                            *entry = toml_edit::value(toml_edit::Value::InlineTable(table));
+                        }
+                        entry["features"] = toml_edit::value(toml_edit::Value::Array(array));
+                    }
+                    None => continue,
+                }
+                entry.as_inline_table_mut().map(|t| t.fmt());
+                if !changed.contains(&index) {
+                    changed.push(index);
+                }
+            }
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn member(name: &str, manifest: &str) -> Member {
+        Member {
+            name: name.to_owned(),
+            manifest_path: PathBuf::from(format!("{}/Cargo.toml", name)),
+            manifest: manifest.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn find_and_fix_inconsistencies() {
+        let mut members = vec![
+            member(
+                "one",
+                r#"[package]
+name = "one"
+
+[dependencies]
+log = "0.3"
+serde = { version = "1.0", features = ["derive"] }
+regex = "0.2"
+local = { path = "../local" }
+"#,
+            ),
+            member(
+                "two",
+                r#"[package]
+name = "two"
+
+[dependencies]
+log = "0.4"
+serde = "1.0"
+regex = { git = "https://github.com/rust-lang/regex" }
+local = { path = "../../local" }
+
+[dev-dependencies]
+log = "0.4"
+"#,
+            ),
+        ];
+
+        let inconsistencies = find_inconsistencies(&members);
+        let messages: Vec<_> = inconsistencies.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "log: different versions (one: 0.3, two: 0.4)",
+                "regex: different sources (one: registry, two: git)",
+                "serde: different features (one: [derive], two: [])",
+            ]
+        );
+        assert_eq!(
+            inconsistencies[0].harmonized,
+            Some(Harmonized::Version("0.4".to_owned()))
+        );
+        assert_eq!(inconsistencies[1].harmonized, None);
+
+        assert_eq!(apply(&mut members, &inconsistencies).unwrap(), vec![0, 1]);
+        assert_eq!(
+            members[0].manifest.data["dependencies"]["log"].as_str(),
+            Some("0.4")
+        );
+        let serde = &members[1].manifest.data["dependencies"]["serde"];
+        assert_eq!(serde["version"].as_str(), Some("1.0"));
+        assert_eq!(serde["features"].as_array().map(|a| a.len()), Some(1));
+        let messages: Vec<_> = find_inconsistencies(&members)
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["regex: different sources (one: registry, two: git)"]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! `cargo dep fix-wildcards`: pin wildcard and unbounded version requirements
+use std::fmt;
+
+use cargo_edit::{DepTable, Dependency, LockedVersions, Manifest};
+use semver::VersionReq;
+use toml_edit;
+
+use super::errors::*;
+use super::publish_check::is_wildcard;
+
+/// Replace a too broad version requirement by a caret requirement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    /// The section with the entry
+    pub section: DepTable,
+    /// Name of the dependency
+    pub name: String,
+    /// The requirement being replaced
+    pub old: String,
+    /// The new requirement
+    pub new: String,
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in {}: {} -> {}", self.name, self.section, self.old, self.new)
+    }
+}
+
+/// Whether a version requirement accepts (nearly) any version: it contains a wildcard or only
+/// has a lower bound (e.g. `>= 0.3`).
+pub fn is_too_broad(req: &str) -> bool {
+    is_wildcard(req) || (req.trim_left().starts_with('>') && !req.contains('<'))
+}
+
+/// The highest locked version of `name` that meets `req`.
+fn locked_version(locked: &LockedVersions, name: &str, req: &str) -> Option<String> {
+    let req = VersionReq::parse(req).ok()?;
+    locked
+        .get(name)?
+        .iter()
+        .filter(|v| req.matches(v))
+        .max()
+        .map(|v| v.to_string())
+}
+
+/// Find the too broad requirements and what to replace them with: the locked version, or, if the
+/// dependency is not locked, the version returned by `latest`.
+pub fn find_fixes<F>(
+    manifest: &Manifest,
+    locked: &LockedVersions,
+    exclude: &[String],
+    mut latest: F,
+) -> Result<Vec<Fix>>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let mut fixes = Vec::new();
+    for (section, table) in manifest.get_sections() {
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (name, entry) in table.iter() {
+            if exclude.iter().any(|e| e == name) {
+                continue;
+            }
+            let dep = match Dependency::from_toml(name, entry) {
+                Some(dep) => dep,
+                None => continue,
+            };
+            let old = match dep.version() {
+                Some(version) if is_too_broad(version) => version.to_owned(),
+                _ => continue,
+            };
+            let new = match locked_version(locked, name, &old) {
+                Some(version) => version,
+                None => latest(name)?,
+            };
+            fixes.push(Fix {
+                section: section.clone(),
+                name: name.to_owned(),
+                old: old,
+                new: new,
+            });
+        }
+    }
+    Ok(fixes)
+}
+
+/// Replace the requirements in the manifest.
+pub fn apply(manifest: &mut Manifest, fixes: &[Fix]) -> Result<()> {
+    for fix in fixes {
+        let table = manifest.get_table(&fix.section.to_table())?;
+        let entry = &mut table[&fix.name];
+        if entry.is_str() {
+            *entry = toml_edit::value(fix.new.as_str());
+        } else {
+            entry["version"] = toml_edit::value(fix.new.as_str());
+            entry.as_inline_table_mut().map(|t| t.fmt());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cargo_edit::parse_lock_file;
+
+    #[test]
+    fn pin_broad_requirements() {
+        let mut manifest: Manifest = r#"[package]
+name = "foo"
+
+[dependencies]
+a = "*"
+b = { version = "0.2.*", features = ["std"] }
+c = ">= 1.0"
+d = ">= 1.0, < 3"
+e = "*"
+f = "0.3"
+"#.parse()
+            .unwrap();
+        let locked = parse_lock_file(
+            r#"[[package]]
+name = "a"
+version = "1.2.3"
+
+[[package]]
+name = "b"
+version = "0.2.7"
+
+[[package]]
+name = "b"
+version = "0.3.1"
+"#,
+        ).unwrap();
+
+        let exclude = vec!["e".to_owned()];
+        let fixes = find_fixes(&manifest, &locked, &exclude, |name| {
+            Ok(format!("{}--LATEST", name))
+        }).unwrap();
+        let descriptions: Vec<_> = fixes.iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                "a in dependencies: * -> 1.2.3",
+                "b in dependencies: 0.2.* -> 0.2.7",
+                "c in dependencies: >= 1.0 -> c--LATEST",
+            ]
+        );
+
+        apply(&mut manifest, &fixes).unwrap();
+        assert_eq!(manifest.data["dependencies"]["a"].as_str(), Some("1.2.3"));
+        assert_eq!(
+            manifest.data["dependencies"]["b"]["version"].as_str(),
+            Some("0.2.7")
+        );
+        assert_eq!(
+            manifest.data["dependencies"]["b"]["features"]
+                .as_array()
+                .map(|f| f.len()),
+            Some(1)
+        );
+        assert_eq!(manifest.data["dependencies"]["e"].as_str(), Some("*"));
+    }
+}
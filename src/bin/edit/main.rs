@@ -0,0 +1,124 @@
+//! `cargo edit`
+#![warn(missing_docs, missing_debug_implementations, missing_copy_implementations, trivial_casts,
+        trivial_numeric_casts, unsafe_code, unstable_features, unused_import_braces,
+        unused_qualifications)]
+
+extern crate atty;
+#[macro_use]
+extern crate cargo_edit;
+extern crate cargo_metadata;
+extern crate docopt;
+extern crate semver;
+extern crate serde_derive;
+extern crate serde_json;
+extern crate termcolor;
+extern crate toml_edit;
+
+use std::process;
+
+use cargo_edit::{find_plugin, find_plugins, untranslated_command_args, SelfDescription};
+
+#[path = "../add/main.rs"]
+mod add;
+#[path = "../dep/main.rs"]
+mod dep;
+#[path = "../rm/main.rs"]
+mod rm;
+#[path = "../upgrade/main.rs"]
+mod upgrade;
+
+static USAGE: &'static str = r"
+Run one of the cargo-edit commands.
+
+Usage:
+    cargo edit <command> [<args>...]
+    cargo edit --list
+    cargo edit (-h | --help)
+    cargo edit (-V | --version) [--json]
+
+Commands:
+    add        Add a dependency to a Cargo.toml manifest file.
+    rm         Remove a dependency from a Cargo.toml manifest file.
+    upgrade    Upgrade the dependencies of a Cargo.toml manifest file.
+    dep        Maintain the dependencies of a workspace.
+
+Any `cargo-edit-<name>` binary in the `PATH` runs as `cargo edit <name>`. Use `--list` to show
+all available commands and their versions. With `--version --json`, the version and what the
+built-in commands support are printed as JSON.
+
+The binary can also be linked as `cargo-add`, `cargo-rm`, `cargo-upgrade` and `cargo-dep`, so that
+`cargo add ...` runs `cargo edit add ...`.
+";
+
+/// The commands built into the binary.
+static COMMANDS: &'static [&'static str] = &["add", "rm", "upgrade", "dep"];
+
+/// Print the built-in commands and the plugins with their versions.
+fn list_commands() {
+    let version = env!("CARGO_PKG_VERSION");
+    println!("Installed commands:");
+    for command in COMMANDS {
+        println!("    {:<10} {}", command, version);
+    }
+    for plugin in find_plugins() {
+        let version = plugin.version().unwrap_or_else(|| "(unknown version)".to_string());
+        println!(
+            "    {:<10} {} ({})",
+            plugin.name,
+            version,
+            plugin.path.display()
+        );
+    }
+}
+
+/// Run a `cargo-edit-<name>` binary, and exit with its status.
+fn run_plugin(name: &str, args: &[String]) {
+    let plugin = match find_plugin(name) {
+        Some(plugin) => plugin,
+        None => {
+            eprintln!("No such command: `cargo edit {}`\n", name);
+            eprintln!("{}", USAGE.trim());
+            process::exit(1);
+        }
+    };
+
+    match plugin.command().args(args).status() {
+        Ok(status) => process::exit(status.code().unwrap_or(1)),
+        Err(err) => {
+            eprintln!("{}", message!("unhandled-error", error = err));
+            process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let args = untranslated_command_args();
+
+    match args.get(1).map(|s| s.as_str()) {
+        Some("add") => add::main(),
+        Some("rm") => rm::main(),
+        Some("upgrade") => upgrade::main(),
+        Some("dep") => dep::main(),
+        Some("-V") | Some("--version") => {
+            if args.get(2).map(|s| s.as_str()) == Some("--json") {
+                let description = SelfDescription::new("cargo-edit")
+                    .merge(&add::self_description())
+                    .merge(&rm::self_description())
+                    .merge(&upgrade::self_description())
+                    .merge(&dep::self_description());
+                println!("{}", description.to_json());
+            } else {
+                println!("cargo-edit version {}", env!("CARGO_PKG_VERSION"));
+            }
+        }
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE.trim());
+        }
+        Some("--list") => list_commands(),
+        Some(name) if !name.starts_with('-') => run_plugin(name, &args[2..]),
+        _ => {
+            eprintln!("{}", USAGE.trim());
+            process::exit(1);
+        }
+    }
+}
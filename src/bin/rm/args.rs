@@ -1,31 +1,94 @@
 //! Handle `cargo rm` arguments
 
+use cargo_edit::{ConflictSide, DepKind, DepTable};
+use serde_derive::Deserialize;
+
+/// How to report the result of `cargo rm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum OutputFormat {
+    /// Human readable messages on stdout
+    Human,
+    /// A JSON document on stdout, messages on stderr
+    Json,
+}
+
 #[derive(Debug, Deserialize)]
 /// Docopts input args.
 pub struct Args {
-    /// Crate name
-    pub arg_crate: String,
+    /// Crate name (pick interactively if missing)
+    pub arg_crate: Option<String>,
     /// dev-dependency
     pub flag_dev: bool,
     /// build-dependency
     pub flag_build: bool,
+    /// '--target'
+    pub flag_target: Option<String>,
+    /// '--all-in-target'
+    pub flag_all_in_target: bool,
     /// `Cargo.toml` path
     pub flag_manifest_path: Option<String>,
     /// `--version`
     pub flag_version: bool,
+    /// `--json`
+    pub flag_json: bool,
     /// '--quiet'
     pub flag_quiet: bool,
+    /// '--ours'
+    pub flag_ours: bool,
+    /// '--theirs'
+    pub flag_theirs: bool,
+    /// '--gc-workspace'
+    pub flag_gc_workspace: bool,
+    /// '--dry-run'
+    pub flag_dry_run: bool,
+    /// '--output'
+    pub flag_output: OutputFormat,
+    /// '--record'
+    pub flag_record: Option<String>,
 }
 
 impl Args {
     /// Get depenency section
-    pub fn get_section(&self) -> &'static str {
-        if self.flag_dev {
-            "dev-dependencies"
+    pub fn get_section(&self) -> DepTable {
+        let section = if self.flag_dev {
+            DepTable::new().set_kind(DepKind::Development)
         } else if self.flag_build {
-            "build-dependencies"
+            DepTable::new().set_kind(DepKind::Build)
+        } else {
+            DepTable::new()
+        };
+        match self.flag_target {
+            Some(ref target) => section.set_target(target.as_str()),
+            None => section,
+        }
+    }
+
+    /// Get the sections to pick dependencies from: the one selected by `--dev` or `--build`, or
+    /// else all three kinds; of the target given with `--target`, if any.
+    pub fn get_sections(&self) -> Vec<DepTable> {
+        if self.flag_dev || self.flag_build {
+            return vec![self.get_section()];
+        }
+        [DepKind::Normal, DepKind::Development, DepKind::Build]
+            .iter()
+            .map(|&kind| {
+                let section = DepTable::new().set_kind(kind);
+                match self.flag_target {
+                    Some(ref target) => section.set_target(target.as_str()),
+                    None => section,
+                }
+            })
+            .collect()
+    }
+
+    /// Get the side to prefer when resolving merge conflicts in the manifest
+    pub fn get_conflict_side(&self) -> Option<ConflictSide> {
+        if self.flag_ours {
+            Some(ConflictSide::Ours)
+        } else if self.flag_theirs {
+            Some(ConflictSide::Theirs)
         } else {
-            "dependencies"
+            None
         }
     }
 }
@@ -33,12 +96,21 @@ impl Args {
 impl Default for Args {
     fn default() -> Args {
         Args {
-            arg_crate: "demo".to_owned(),
+            arg_crate: Some("demo".to_owned()),
             flag_dev: false,
             flag_build: false,
+            flag_target: None,
+            flag_all_in_target: false,
             flag_manifest_path: None,
             flag_version: false,
+            flag_json: false,
             flag_quiet: false,
+            flag_ours: false,
+            flag_theirs: false,
+            flag_gc_workspace: false,
+            flag_dry_run: false,
+            flag_output: OutputFormat::Human,
+            flag_record: None,
         }
     }
 }
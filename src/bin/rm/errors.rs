@@ -0,0 +1,60 @@
+//! Errors of `cargo rm`
+use std::error::Error as StdError;
+use std::{fmt, io, result};
+
+use cargo_edit;
+
+/// A `Result` with the `cargo rm` error type
+pub type Result<T> = result::Result<T, Error>;
+
+/// Errors of `cargo rm`
+#[derive(Debug)]
+pub enum Error {
+    /// An error from the `cargo_edit` library
+    CargoEditLib(cargo_edit::Error),
+    /// Output could not be written
+    Io(io::Error),
+    /// The manifest does not have any dependencies to pick from
+    NoDependencies,
+    /// No crate was given, and there is no terminal to pick one from
+    NoCrateSpecified,
+    /// With `--all-in-target`, the target has no dependencies
+    NoDependenciesInTarget(String),
+}
+
+impl From<cargo_edit::Error> for Error {
+    fn from(e: cargo_edit::Error) -> Self {
+        Error::CargoEditLib(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            Error::CargoEditLib(ref e) => return e.fmt(f),
+            Error::Io(ref e) => return e.fmt(f),
+            Error::NoDependencies => message!("rm-no-dependencies"),
+            Error::NoCrateSpecified => message!("rm-no-crate-specified"),
+            Error::NoDependenciesInTarget(ref target) => {
+                message!("rm-no-dependencies-in-target", target = target)
+            }
+        };
+        f.write_str(&text)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::CargoEditLib(ref e) => e.source(),
+            Error::Io(ref e) => e.source(),
+            _ => None,
+        }
+    }
+}
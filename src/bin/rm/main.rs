@@ -5,105 +5,394 @@
 
 extern crate atty;
 extern crate docopt;
-#[macro_use]
-extern crate error_chain;
-#[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 extern crate termcolor;
+extern crate toml_edit;
 
+use std::error::Error as StdError;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::io::Write;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
+#[macro_use]
 extern crate cargo_edit;
-use cargo_edit::Manifest;
+use cargo_edit::{checklist, command_args, find, record_changes, ChecklistItem, DepTable, Dependency,
+                 Manifest, RecordedChange, SelfDescription, Warnings};
+use serde_derive::Serialize;
 
 mod args;
-use args::Args;
+use self::args::{Args, OutputFormat};
 
-mod errors {
-    error_chain!{
-        links {
-            CargoEditLib(::cargo_edit::Error, ::cargo_edit::ErrorKind);
-        }
-        foreign_links {
-            Io(::std::io::Error);
-        }
-    }
-}
-use errors::*;
+mod errors;
+use self::errors::*;
+
+mod workspace;
 
-static USAGE: &'static str = r"
+static USAGE: &'static str = r#"
 Usage:
-    cargo rm <crate> [--dev|--build] [options]
+    cargo rm [<crate>] [--dev|--build] [--target=<target>] [options]
+    cargo rm --target=<target> --all-in-target [options]
     cargo rm (-h|--help)
-    cargo rm --version
+    cargo rm --version [--json]
 
 Options:
     -D --dev                Remove crate as development dependency.
     -B --build              Remove crate as build dependency.
+    --target <target>       Remove the crate from the dependencies of the given target platform,
+                            e.g. 'cfg(windows)'.
+    --all-in-target         Remove every dependency of the target given with `--target` (normal,
+                            development and build dependencies), and with them its table, e.g.
+                            when support for the platform is dropped.
     --manifest-path=<path>  Path to the manifest to remove a dependency from.
+    --ours                  If Cargo.toml has unresolved merge conflicts in dependency tables,
+                            resolve them entry by entry, preferring our side.
+    --theirs                Like `--ours`, but prefer their side.
+    --gc-workspace          When the removed path dependency was the last one on a workspace member
+                            that is listed in `members` of the workspace root, remove it from the
+                            list as well.
+    --record <file>         Also record the changes in the change file <file> (created if it
+                            does not exist, appended to otherwise), to replay them on other
+                            manifests with `cargo dep apply <file>`.
+    --output <format>       Print the result as "human" readable messages or as "json". With
+                            "json", the messages go to stderr. [default: human]
+    --dry-run               Print the changes without modifying the manifest.
     -q --quiet              Do not print any output in case of success.
     -h --help               Show this help page.
     -V --version            Show version.
+    --json                  With `--version`, print the version and what the command supports as
+                            JSON, for tools that drive it.
 
 Remove a dependency from a Cargo.toml manifest file.
-";
 
-fn print_msg(name: &str, section: &str) -> Result<()> {
-    let colorchoice = if atty::is(atty::Stream::Stdout) {
-        ColorChoice::Auto
-    } else {
-        ColorChoice::Never
+Each removed entry is printed as it was written in the manifest (e.g. `serde = { version = "1.0",
+features = ["derive"] }`), so it can be pasted back to undo the removal.
+
+If no <crate> is given, `cargo rm` lists the current dependencies (limited to the section selected
+by `--dev` or `--build`, if any) and lets you pick which ones to remove.
+
+With `--all-in-target`, every dependency of the target platform given with `--target` is removed,
+and the emptied `[target.<target>]` tables with them.
+
+When a path dependency within a workspace is removed, a note tells which other members still depend
+on it.
+"#;
+
+/// A dependency removed by `cargo rm`, as reported with `--output json`.
+#[derive(Debug, Serialize)]
+struct RemovedDependency {
+    name: String,
+    section: Vec<String>,
+    /// The removed entry as a line of TOML, e.g. `serde = "1.0"`
+    spec: String,
+    version: Option<String>,
+    git: Option<String>,
+    path: Option<String>,
+    optional: bool,
+    features: Option<Vec<String>>,
+}
+
+impl RemovedDependency {
+    fn new(name: &str, section: &DepTable, entry: &toml_edit::Item) -> Self {
+        let dep = Dependency::from_toml(name, entry);
+        let dep = dep.as_ref();
+        RemovedDependency {
+            name: name.to_owned(),
+            section: section.to_table(),
+            spec: entry_spec(name, entry),
+            version: dep.and_then(|d| d.version()).map(String::from),
+            git: dep.and_then(|d| d.git()).map(String::from),
+            path: dep.and_then(|d| d.path()).map(String::from),
+            optional: dep.map_or(false, |d| d.optional()),
+            features: dep.and_then(|d| d.features()).map(|f| f.to_vec()),
+        }
+    }
+}
+
+/// The result of `cargo rm`, as reported with `--output json`.
+#[derive(Debug, Serialize)]
+struct RmReport {
+    removed: Vec<RemovedDependency>,
+}
+
+/// Write an entry as a line of TOML (`name = ...`) that can be pasted back into its section.
+/// Entries written as a table of their own (`[dependencies.name]`) become an inline table.
+fn entry_spec(name: &str, entry: &toml_edit::Item) -> String {
+    let value = match *entry {
+        toml_edit::Item::Value(ref value) => value.clone(),
+        toml_edit::Item::Table(ref table) => {
+            let mut inline = toml_edit::InlineTable::default();
+            for (key, item) in table.iter() {
+                if let Some(value) = item.as_value() {
+                    inline.get_or_insert(key, value.clone());
+                }
+            }
+            inline.fmt();
+            toml_edit::Value::InlineTable(inline)
+        }
+        _ => return name.to_owned(),
+    };
+    format!("{} = {}", name, value.to_string().trim())
+}
+
+/// Get the stream human readable messages are written to. With `--output json`, stdout is
+/// reserved for the JSON result.
+fn message_stream(format: OutputFormat) -> StandardStream {
+    let color_choice = |is_tty| {
+        if is_tty {
+            ColorChoice::Auto
+        } else {
+            ColorChoice::Never
+        }
     };
-    let mut output = StandardStream::stdout(colorchoice);
+    match format {
+        OutputFormat::Human => StandardStream::stdout(color_choice(atty::is(atty::Stream::Stdout))),
+        OutputFormat::Json => StandardStream::stderr(color_choice(atty::is(atty::Stream::Stderr))),
+    }
+}
+
+fn print_msg(output: &mut StandardStream, removed: &str, section: &str) -> Result<()> {
     output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
     write!(output, "{:>12}", "Removing")?;
     output.reset()?;
-    writeln!(output, " {} from {}", name, section)?;
+    writeln!(output, " {} from {}", removed, section)?;
     Ok(())
 }
 
+/// The entries of the dependency sections that exist, as `(section, name)` pairs.
+fn section_entries(manifest: &Manifest, sections: Vec<DepTable>) -> Vec<(DepTable, String)> {
+    sections
+        .into_iter()
+        .flat_map(|section| {
+            let table = section
+                .to_table()
+                .iter()
+                .fold(&manifest.data.root, |item, key| &item[key.as_str()]);
+            let names: Vec<String> = table
+                .as_table_like()
+                .map(|table| table.iter().map(|(name, _)| name.to_owned()).collect())
+                .unwrap_or_default();
+            names.into_iter().map(move |name| (section.clone(), name))
+        })
+        .collect()
+}
+
+/// Let the user pick the dependencies to remove from a checklist. Returns `(section, name)` pairs.
+fn pick_dependencies(manifest: &Manifest, args: &Args) -> Result<Vec<(DepTable, String)>> {
+    let entries = section_entries(manifest, args.get_sections());
+    let items: Vec<_> = entries
+        .iter()
+        .map(|&(ref section, ref name)| ChecklistItem::new(&section.to_string(), name))
+        .collect();
+
+    if items.is_empty() {
+        return Err(Error::NoDependencies);
+    }
+
+    Ok(checklist("Dependencies to remove", &items)?
+        .into_iter()
+        .map(|i| entries[i].clone())
+        .collect())
+}
+
 fn handle_rm(args: &Args) -> Result<()> {
+    if args.arg_crate.is_none() && !args.flag_all_in_target && !atty::is(atty::Stream::Stdin) {
+        return Err(Error::NoCrateSpecified);
+    }
+
     let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
-    let mut manifest = Manifest::open(&manifest_path)?;
+    let mut manifest = Manifest::open_resolving(&manifest_path, args.get_conflict_side())?;
+
+    let to_remove = match args.arg_crate {
+        _ if args.flag_all_in_target => {
+            let entries = section_entries(&manifest, args.get_sections());
+            if entries.is_empty() {
+                let target = args.flag_target.clone().unwrap_or_default();
+                return Err(Error::NoDependenciesInTarget(target));
+            }
+            entries
+        }
+        Some(ref name) => vec![(args.get_section(), name.clone())],
+        None => pick_dependencies(&manifest, args)?,
+    };
+
+    if to_remove.is_empty() {
+        return Ok(());
+    }
+
+    let mut output = message_stream(args.flag_output);
+    let mut removed = Vec::new();
+    let mut path_deps = Vec::new();
+    for &(ref section, ref name) in &to_remove {
+        if let Some(path) = path_dependency(&manifest, &manifest_path, section, name)? {
+            path_deps.push((name, path));
+        }
+        let entry = manifest.remove_from_table(section, name)?;
+        let dep = RemovedDependency::new(name, section, &entry);
+        if !args.flag_quiet {
+            print_msg(&mut output, &dep.spec, &section.to_string())?;
+        }
+        removed.push(dep);
+    }
+    // The reason a crate was pinned goes with its last entry.
+    for &(_, ref name) in &to_remove {
+        let listed = manifest
+            .get_sections()
+            .iter()
+            .any(|&(_, ref table)| !table[name.as_str()].is_none());
+        if !listed {
+            manifest.remove_pin_reason(name);
+        }
+    }
+
+    if !args.flag_dry_run {
+        manifest.write_to_path(&manifest_path)?;
+    }
+
+    if let Some(change_file) = args.flag_record.as_ref().filter(|_| !args.flag_dry_run) {
+        let changes: Vec<_> = to_remove
+            .iter()
+            .map(|&(ref section, ref name)| RecordedChange::Remove(section.clone(), name.clone()))
+            .collect();
+        record_changes(Path::new(change_file), &[find(&manifest_path)?], &changes)?;
+    }
+
+    for (name, path) in path_deps {
+        report_path_usage(args, &mut output, &manifest_path, name, &path)?;
+    }
 
     if !args.flag_quiet {
-        print_msg(&args.arg_crate, args.get_section())?;
+        let mut warnings = Warnings::new();
+        manifest.lint(&mut warnings);
+        warnings.render(&mut output)?;
     }
 
-    manifest
-        .remove_from_table(args.get_section(), args.arg_crate.as_ref())
-        .map_err(From::from)
-        .and_then(|_| {
-            let mut file = Manifest::find_file(&manifest_path)?;
-            manifest.write_to_file(&mut file)?;
+    if args.flag_output == OutputFormat::Json {
+        let report = RmReport { removed: removed };
+        println!(
+            "{}",
+            serde_json::to_string(&report).expect("report is correctly serialized")
+        );
+    }
 
-            Ok(())
-        })
+    Ok(())
+}
+
+/// Get the canonical directory of the dependency `name` in `section`, if it is a path dependency.
+fn path_dependency(
+    manifest: &Manifest,
+    manifest_path: &Option<PathBuf>,
+    section: &DepTable,
+    name: &str,
+) -> Result<Option<PathBuf>> {
+    let entry = section
+        .to_table()
+        .iter()
+        .fold(&manifest.data.root, |item, key| &item[key.as_str()]);
+    let path = match Dependency::from_toml(name, &entry[name]) {
+        Some(ref dep) => dep.path().map(PathBuf::from),
+        None => None,
+    };
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    let manifest_path = find(manifest_path)?;
+    let manifest_dir = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+    Ok(fs::canonicalize(manifest_dir.join(path)).ok())
 }
 
-fn main() {
+/// Tell which other workspace members still depend on the target of a removed path dependency.
+/// If none does, and it is a listed member, offer to (or, with `--gc-workspace`, do) remove it
+/// from the workspace.
+fn report_path_usage(
+    args: &Args,
+    output: &mut StandardStream,
+    manifest_path: &Option<PathBuf>,
+    name: &str,
+    dep_dir: &Path,
+) -> Result<()> {
+    let usage = match workspace::path_usage(&find(manifest_path)?, dep_dir)? {
+        Some(usage) => usage,
+        None => return Ok(()),
+    };
+
+    if !usage.users.is_empty() {
+        if !args.flag_quiet {
+            writeln!(
+                output,
+                "note: `{}` is still a path dependency of {}",
+                name,
+                usage.users.join(", ")
+            )?;
+        }
+        return Ok(());
+    }
+
+    let member = match usage.member {
+        Some(ref member) if usage.listed => member,
+        _ => return Ok(()),
+    };
+    if args.flag_gc_workspace {
+        let removed =
+            args.flag_dry_run || workspace::remove_listed_member(&usage.root, &member.path)?;
+        if removed && !args.flag_quiet {
+            print_msg(output, &member.path, "workspace members")?;
+        }
+    } else if !args.flag_quiet {
+        writeln!(
+            output,
+            "note: no other workspace member depends on `{}` any more; pass `--gc-workspace` to \
+             also remove `{}` from the workspace members",
+            name, member.path
+        )?;
+    }
+    Ok(())
+}
+
+/// What the command supports, for `--version --json`.
+pub fn self_description() -> SelfDescription {
+    SelfDescription::new("cargo-rm")
+        .set_features(&["workspace", "record-changes"])
+        .set_output_formats(&[("rm --output json", 1), ("change-file", 1)])
+}
+
+/// Run the command with the arguments of the process.
+pub fn main() {
+    let (argv, notes) = command_args().unwrap_or_else(|err| exit_with_error(&Error::from(err)));
+    for note in notes {
+        eprintln!("{}", note);
+    }
     let args = docopt::Docopt::new(USAGE)
-        .and_then(|d| d.deserialize::<Args>())
+        .and_then(|d| d.argv(argv).deserialize::<Args>())
         .unwrap_or_else(|err| err.exit());
 
     if args.flag_version {
-        println!("cargo-rm version {}", env!("CARGO_PKG_VERSION"));
+        if args.flag_json {
+            println!("{}", self_description().to_json());
+        } else {
+            println!("cargo-rm version {}", env!("CARGO_PKG_VERSION"));
+        }
         process::exit(0);
     }
 
     if let Err(err) = handle_rm(&args) {
-        eprintln!("Command failed due to unhandled error: {}\n", err);
-
-        for e in err.iter().skip(1) {
-            eprintln!("Caused by: {}", e);
-        }
+        exit_with_error(&err);
+    }
+}
 
-        if let Some(backtrace) = err.backtrace() {
-            eprintln!("Backtrace: {:?}", backtrace);
-        }
+/// Print an error with its causes, and exit with a failure.
+fn exit_with_error(err: &Error) -> ! {
+    eprintln!("{}\n", message!("unhandled-error", error = err));
 
-        process::exit(1);
+    let mut cause = err.source();
+    while let Some(e) = cause {
+        eprintln!("{}", message!("caused-by", cause = e));
+        cause = e.source();
     }
+
+    process::exit(1);
 }
@@ -0,0 +1,134 @@
+//! Cross-references of path dependencies between the members of a workspace
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cargo_edit::{find_workspace_root, workspace_members, Dependency, Manifest, WorkspaceMember};
+use toml_edit;
+
+use super::errors::*;
+
+/// Who else in the workspace depends on the target of a removed path dependency.
+#[derive(Debug)]
+pub struct PathUsage {
+    /// The workspace member the path leads to, if any
+    pub member: Option<WorkspaceMember>,
+    /// Names of the other members that still have a path dependency on it
+    pub users: Vec<String>,
+    /// Whether the member is listed by its path in `members` of the workspace root (and not only
+    /// matched by a glob or pulled in as a path dependency)
+    pub listed: bool,
+    /// Path to the root manifest of the workspace
+    pub root: PathBuf,
+}
+
+/// Find the other members of the workspace of `manifest_path` that depend on `dep_dir` (the
+/// canonical directory of a path dependency). Returns `None` outside of a workspace.
+pub fn path_usage(manifest_path: &Path, dep_dir: &Path) -> Result<Option<PathUsage>> {
+    let root = match find_workspace_root(manifest_path) {
+        Ok(root) => root,
+        Err(_) => return Ok(None),
+    };
+    let manifest_path = fs::canonicalize(manifest_path)?;
+
+    let mut member = None;
+    let mut users = Vec::new();
+    for candidate in workspace_members(&Some(root.clone()))? {
+        let candidate_path = fs::canonicalize(&candidate.manifest_path)?;
+        if candidate_path == manifest_path {
+            continue;
+        }
+        if candidate_path.parent() == Some(dep_dir) {
+            member = Some(candidate);
+            continue;
+        }
+        if depends_on(&candidate_path, dep_dir)? {
+            users.push(candidate.name);
+        }
+    }
+
+    let listed = match member {
+        Some(ref member) => listed_members(&Manifest::open(&Some(root.clone()))?)
+            .iter()
+            .any(|path| normalize(path) == member.path),
+        None => false,
+    };
+
+    Ok(Some(PathUsage {
+        member: member,
+        users: users,
+        listed: listed,
+        root: root,
+    }))
+}
+
+/// Remove the entry for `member_path` (relative to the workspace root) from `members` of the
+/// workspace root. Returns whether it was listed.
+pub fn remove_listed_member(root: &Path, member_path: &str) -> Result<bool> {
+    let root_path = Some(root.to_owned());
+    let mut manifest = Manifest::open(&root_path)?;
+
+    let listed = listed_members(&manifest);
+    let kept: Vec<_> = listed
+        .iter()
+        .filter(|path| normalize(path) != member_path)
+        .collect();
+    if kept.len() == listed.len() {
+        return Ok(false);
+    }
+
+    let mut members = toml_edit::Array::default();
+    for path in kept {
+        members.push(path.as_str());
+    }
+    manifest.data["workspace"]["members"] =
+        toml_edit::Item::Value(toml_edit::Value::Array(members));
+
+    manifest.write_workspace_to_path(&root_path)?;
+    Ok(true)
+}
+
+/// Whether the package of `manifest_path` has a path dependency on `dep_dir`, in any section.
+fn depends_on(manifest_path: &Path, dep_dir: &Path) -> Result<bool> {
+    let manifest = Manifest::open(&Some(manifest_path.to_owned()))?;
+    let manifest_dir = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+
+    for (_, section) in manifest.get_sections() {
+        let table = match section.as_table_like() {
+            Some(table) => table,
+            None => continue,
+        };
+        for (name, item) in table.iter() {
+            let path = match Dependency::from_toml(name, item) {
+                Some(ref dep) => dep.path().map(|path| manifest_dir.join(path)),
+                None => None,
+            };
+            if let Some(path) = path {
+                if fs::canonicalize(&path).ok().as_ref().map(|p| p.as_path()) == Some(dep_dir) {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// The paths listed in `members` of a workspace root
+fn listed_members(root: &Manifest) -> Vec<String> {
+    root.data["workspace"]["members"]
+        .as_array()
+        .map(|members| {
+            members
+                .iter()
+                .filter_map(|m| m.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Write a member path like `WorkspaceMember::path` does (e.g. `crates/foo` for `./crates/foo/`).
+fn normalize(path: &str) -> String {
+    path.split(|c: char| c == '/' || c == '\\')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
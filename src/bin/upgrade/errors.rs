@@ -0,0 +1,96 @@
+//! Errors of `cargo upgrade`
+use std::error::Error as StdError;
+use std::{fmt, io, result};
+
+use cargo_edit;
+use cargo_metadata;
+
+/// A `Result` with the `cargo upgrade` error type
+pub type Result<T> = result::Result<T, Error>;
+
+/// Errors of `cargo upgrade`
+#[derive(Debug)]
+pub enum Error {
+    /// An error from the `cargo_edit` library
+    CargoEditLib(cargo_edit::Error),
+    /// The workspace metadata could not be read
+    CargoMetadata {
+        /// What was being attempted
+        action: &'static str,
+        /// The underlying error
+        source: cargo_metadata::Error,
+    },
+    /// The manifest is a virtual manifest, and `--all` was not given
+    VirtualManifest,
+    /// `--hoist` was given without `--consolidate`
+    HoistWithoutConsolidate,
+    /// `--include-excluded` or `--include-nested` was given without `--all`
+    IncludeWithoutAll(&'static str),
+    /// Two flags that contradict each other were given
+    ConflictingFlags(&'static str, &'static str),
+    /// The value of `--to-rev` is not `head`, `branch:<name>` or `tag:<name>`
+    InvalidGitTarget(String),
+    /// The new version of a dependency could not be determined
+    FetchNewVersion(cargo_edit::Error),
+    /// Output could not be written
+    Io {
+        /// What was being attempted
+        action: &'static str,
+        /// The underlying error
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// Wrap a `cargo_metadata` error, describing what was being attempted.
+    pub fn metadata(action: &'static str) -> impl FnOnce(cargo_metadata::Error) -> Error {
+        move |source| Error::CargoMetadata { action, source }
+    }
+
+    /// Wrap an I/O error, describing what was being attempted.
+    pub fn io(action: &'static str) -> impl FnOnce(io::Error) -> Error {
+        move |source| Error::Io { action, source }
+    }
+}
+
+impl From<cargo_edit::Error> for Error {
+    fn from(e: cargo_edit::Error) -> Self {
+        Error::CargoEditLib(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            Error::CargoEditLib(ref e) => return e.fmt(f),
+            Error::CargoMetadata { action, .. } | Error::Io { action, .. } => action.to_owned(),
+            Error::VirtualManifest => message!("upgrade-virtual-manifest"),
+            Error::HoistWithoutConsolidate => message!("upgrade-hoist-without-consolidate"),
+            Error::IncludeWithoutAll(flag) => message!("upgrade-without-all", flag = flag),
+            Error::ConflictingFlags(flag, other) => {
+                message!("upgrade-conflicting-flags", flag = flag, other = other)
+            }
+            Error::InvalidGitTarget(ref target) => {
+                message!("upgrade-invalid-git-target", target = target)
+            }
+            Error::FetchNewVersion(_) => message!("upgrade-fetch-new-version"),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::CargoEditLib(ref e) => e.source(),
+            Error::CargoMetadata { ref source, .. } => Some(source),
+            Error::VirtualManifest
+            | Error::HoistWithoutConsolidate
+            | Error::IncludeWithoutAll(_)
+            | Error::ConflictingFlags(..)
+            | Error::InvalidGitTarget(_) => None,
+            Error::FetchNewVersion(ref e) => Some(e),
+            Error::Io { ref source, .. } => Some(source),
+        }
+    }
+}
@@ -5,62 +5,131 @@
 
 extern crate cargo_metadata;
 extern crate docopt;
-#[macro_use]
-extern crate error_chain;
-#[macro_use]
+extern crate semver;
 extern crate serde_derive;
 extern crate toml_edit;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error as StdError;
 use std::path::{Path, PathBuf};
-use std::io::Write;
+use std::io::{self, Write};
 use std::process;
 
+#[macro_use]
 extern crate cargo_edit;
-use cargo_edit::{find, get_latest_dependency, CrateName, Dependency, LocalManifest};
+use cargo_edit::{checklist, command_args, excluded_members, find, get_compatible_dependencies,
+                 get_latest_dependencies, git_remote_commit, ignored_upgrades, nested_packages,
+                 prerelease_crates, record_changes, registry_cache_stats, ChecklistItem,
+                 CrateName, Dependency, LocalManifest, Manifest, Prereleases, RecordedChange,
+                 SelfDescription, Warnings, WorkspaceMember};
+use serde_derive::Deserialize;
 
 extern crate termcolor;
 use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
 
-mod errors {
-    error_chain!{
-        links {
-            CargoEditLib(::cargo_edit::Error, ::cargo_edit::ErrorKind);
-            CargoMetadata(::cargo_metadata::Error, ::cargo_metadata::ErrorKind);
-        }
-    }
-}
-use errors::*;
+mod errors;
+use self::errors::*;
 
-static USAGE: &'static str = r"
+static USAGE: &'static str = r#"
 Upgrade dependencies as specified in the local manifest file (i.e. Cargo.toml).
 
 Usage:
     cargo upgrade [options] [<dependency>]...
     cargo upgrade (-h | --help)
-    cargo upgrade (-V | --version)
+    cargo upgrade (-V | --version) [--json]
 
 Options:
     --all                   Upgrade all packages in the workspace.
+    --include-excluded      With `--all`, also upgrade the packages in the directories the
+                            workspace excludes with `workspace.exclude` (e.g. `fuzz`).
+    --include-nested        With `--all`, also upgrade the packages below the workspace root that
+                            are not members of the workspace (e.g. the crates of test fixtures).
     --manifest-path PATH    Path to the manifest to upgrade.
     --allow-prerelease      Include prerelease versions when fetching from crates.io (e.g.
                             '0.6.0-alpha'). Defaults to false.
+    --prerelease <channel>  Include prerelease versions of <channel> and later channels when
+                            fetching from crates.io. Must be one of "alpha", "beta" or "rc", e.g.
+                            `--prerelease rc` picks '1.0.0-rc.2' but never '1.0.0-alpha.1'.
+                            Any prerelease is included for the crates listed in
+                            `package.metadata.cargo-edit.allow-prerelease` (or
+                            `workspace.metadata`) of the manifests, e.g. `["bevy"]`.
+    -j --jobs <n>           Look up at most <n> crates at the same time. Defaults to the number of
+                            dependencies to upgrade, up to 4.
     --dry-run               Print changes to be made without making them. Defaults to false.
+    --save-exact            Pin the upgraded dependencies to the exact new version (e.g.
+                            `=1.2.3`). Defaults to false.
+    --compatible            Upgrade to the latest versions the current requirements allow (e.g.
+                            1.2 to 1.4.1, but not to 2.0), instead of the latest versions.
+    --skip-git              Leave git dependencies alone, even if they also have a version.
+    --skip-path             Leave path dependencies alone, even if they also have a version.
+    --update-revs           Also move the pinned `rev` of git dependencies to `--to-rev`.
+    --git-only              Only move the pinned `rev` of git dependencies to `--to-rev`, without
+                            looking up the latest versions on crates.io.
+    --to-rev <rev>          The commit to pin git dependencies to with `--update-revs` or
+                            `--git-only`: "head" for the head of the default branch of the
+                            repository, "branch:<name>" for the tip of a branch, or "tag:<name>"
+                            for a tag. [default: head]
+    --report <format>       After upgrading, print a summary of the changes. The only format is
+                            "markdown", a table suitable for a pull request description.
+    --consolidate           Instead of looking up the latest versions, upgrade every dependency
+                            that is required with different versions to the highest of them.
+    --hoist                 With `--consolidate`, move the agreed requirements to
+                            `[workspace.dependencies]` of the workspace root and let the packages
+                            inherit them with `workspace = true`.
+    --record <file>         Also record the changes in the change file <file> (created if it
+                            does not exist, appended to otherwise), to replay them on other
+                            manifests with `cargo dep apply <file>`.
+    -i --interactive        List the upgrades, grouped by their impact (patch, minor or breaking),
+                            and apply only the ones picked.
+    -v --verbose            Also print how many registry lookups were answered from the cache of
+                            this invocation.
     -h --help               Show this help page.
     -V --version            Show version.
+    --json                  With `--version`, print the version and what the command supports as
+                            JSON, for tools that drive it.
 
 This command differs from `cargo update`, which updates the dependency versions recorded in the
 local lock file (Cargo.lock).
 
+With `--compatible`, the requirements are only raised to the latest version that `cargo update`
+could pick anyway, e.g. `log = "0.4"` to `log = "0.4.20"`. A crate that is required with several
+requirements is upgraded to the latest version all of them allow, or skipped if there is none.
+
 If `<dependency>`(s) are provided, only the specified dependencies will be upgraded. The version to
 upgrade to for each can be specified with e.g. `docopt@0.8.0` or `serde@>=0.9,<2.0`.
 
 Dev, build, and all target dependencies will also be upgraded. Only dependencies from crates.io are
-supported. Git/path dependencies will be ignored.
+supported. Git/path dependencies will be ignored, unless they also have a version and are named, or
+also required from crates.io elsewhere; `--skip-git` and `--skip-path` leave them alone in any case.
+With `--update-revs`, the git dependencies pinned to a `rev` are upgraded, too, to the commit
+`--to-rev` points to in their repository (abbreviated revisions stay abbreviated); `--git-only`
+upgrades only them.
 
 All packages in the workspace will be upgraded if the `--all` flag is supplied. The `--all` flag may
-be supplied in the presence of a virtual manifest.
-";
+be supplied in the presence of a virtual manifest. Requirements in `[workspace.dependencies]` of the
+workspace root are upgraded, too.
+Packages that the workspace excludes, like fuzz targets or example projects with workspaces of
+their own, are upgraded along with the members if `--include-excluded` is supplied, too. Likewise,
+`--include-nested` upgrades the other packages below the workspace root, which are neither members
+nor excluded. The packages left out are noted.
+
+With `--consolidate --all`, the packages of a workspace are brought to consistent versions without
+contacting crates.io: e.g. if one package requires `log` 0.3 and another `log` 0.4, both end up
+with 0.4.
+
+With `--interactive`, the upgrades are listed with numbers first, and only the ones you pick are
+applied. An upgrade is breaking if the old requirement does not allow the new version, following
+the caret rules of cargo (e.g. 0.8 to 0.9, or 1.2 to 2.0).
+
+Crates that a manifest holds back with a reason in `package.metadata.cargo-edit.pins` (see
+`cargo add --reason`) are skipped, and the reason is printed. A crate named as <dependency> is
+upgraded anyway, after printing the reason.
+
+Crates listed in `package.metadata.cargo-edit.ignore-upgrades` of a manifest, or
+`workspace.metadata.cargo-edit.ignore-upgrades` of the root manifest of its workspace (e.g.
+`ignore-upgrades = ["openssl"]`), are held back as well, unless named as <dependency>. They are
+printed as held, and listed below the table of `--report markdown`.
+"#;
 
 /// Docopts input args.
 #[derive(Debug, Deserialize)]
@@ -71,12 +140,239 @@ struct Args {
     flag_manifest_path: Option<String>,
     /// `--all`
     flag_all: bool,
+    /// `--include-excluded`
+    flag_include_excluded: bool,
+    /// `--include-nested`
+    flag_include_nested: bool,
     /// `--allow-prerelease`
     flag_allow_prerelease: bool,
+    /// `--prerelease <channel>`
+    flag_prerelease: Option<String>,
+    /// `--jobs <n>`
+    flag_jobs: Option<usize>,
     /// `--dry-run`
     flag_dry_run: bool,
+    /// `--save-exact`
+    flag_save_exact: bool,
+    /// `--compatible`
+    flag_compatible: bool,
+    /// `--skip-git`
+    flag_skip_git: bool,
+    /// `--skip-path`
+    flag_skip_path: bool,
+    /// `--update-revs`
+    flag_update_revs: bool,
+    /// `--git-only`
+    flag_git_only: bool,
+    /// `--to-rev <rev>`
+    flag_to_rev: String,
+    /// `--report <format>`
+    flag_report: Option<ReportFormat>,
+    /// `--consolidate`
+    flag_consolidate: bool,
+    /// `--hoist`
+    flag_hoist: bool,
+    /// `--interactive`
+    flag_interactive: bool,
+    /// `--verbose`
+    flag_verbose: bool,
+    /// `--record <file>`
+    flag_record: Option<String>,
     /// `--version`
     flag_version: bool,
+    /// `--json`
+    flag_json: bool,
+}
+
+/// Format of the summary printed with `--report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+enum ReportFormat {
+    /// A Markdown table
+    Markdown,
+}
+
+/// The commit to pin git dependencies to with `--update-revs` or `--git-only`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GitTarget {
+    /// The head of the default branch
+    Head,
+    /// The tip of a branch
+    Branch(String),
+    /// A tag
+    Tag(String),
+}
+
+impl GitTarget {
+    /// Parse the value of `--to-rev`: `head`, `branch:<name>` or `tag:<name>`.
+    fn parse(target: &str) -> Result<GitTarget> {
+        if target == "head" {
+            return Ok(GitTarget::Head);
+        }
+        match target.find(':').map(|i| (&target[..i], &target[i + 1..])) {
+            Some(("branch", name)) if !name.is_empty() => Ok(GitTarget::Branch(name.to_owned())),
+            Some(("tag", name)) if !name.is_empty() => Ok(GitTarget::Tag(name.to_owned())),
+            _ => Err(Error::InvalidGitTarget(target.to_owned())),
+        }
+    }
+
+    /// The name of the reference on the remote, e.g. `refs/heads/main`
+    fn reference(&self) -> String {
+        match *self {
+            GitTarget::Head => "HEAD".to_owned(),
+            GitTarget::Branch(ref name) => format!("refs/heads/{}", name),
+            GitTarget::Tag(ref name) => format!("refs/tags/{}", name),
+        }
+    }
+}
+
+/// Moves the `rev` of pinned git dependencies, for `--update-revs` and `--git-only`.
+#[derive(Debug)]
+struct RevUpgrades {
+    /// The commit to move to
+    target: GitTarget,
+    /// The dependencies to upgrade; all if empty
+    only_update: Vec<String>,
+    /// The commit `target` points to, by repository URL, so each repository is asked once
+    commits: HashMap<String, String>,
+}
+
+impl RevUpgrades {
+    /// Move the `rev` of the git dependencies of `manifest` that have one. Returns the revisions
+    /// that changed.
+    fn apply(
+        &mut self,
+        manifest: &mut LocalManifest,
+        package: &str,
+        dry_run: bool,
+    ) -> Result<Vec<Change>> {
+        let mut changes = Vec::new();
+        for (section, table) in manifest.get_sections() {
+            let table = table.as_table_like().expect("Unexpected non-table");
+            for (name, entry) in table.iter() {
+                if !self.only_update.is_empty() && !self.only_update.iter().any(|n| n == name) {
+                    continue;
+                }
+                let (url, old) = match (entry["git"].as_str(), entry["rev"].as_str()) {
+                    (Some(url), Some(old)) => (url, old),
+                    _ => continue,
+                };
+                if !self.commits.contains_key(url) {
+                    let commit = git_remote_commit(url, &self.target.reference())?;
+                    self.commits.insert(url.to_owned(), commit);
+                }
+                let commit = &self.commits[url];
+                let abbreviated = old.len() >= 7
+                    && old.len() < commit.len()
+                    && old.chars().all(|c| c.is_ascii_hexdigit());
+                let new = if abbreviated {
+                    &commit[..old.len()]
+                } else {
+                    commit.as_str()
+                };
+                if old == new {
+                    continue;
+                }
+
+                print_status(
+                    "Upgrading",
+                    &format!("{} {} -> {}", name, short_hash(old), short_hash(new)),
+                )?;
+                manifest.set_git_rev(&section, name, new, dry_run)?;
+                changes.push(Change {
+                    package: package.to_owned(),
+                    name: name.to_owned(),
+                    old: old.to_owned(),
+                    new: new.to_owned(),
+                    rev: true,
+                });
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Abbreviate a commit hash as git does by default, to 7 digits.
+fn short_hash(rev: &str) -> &str {
+    if rev.len() > 7 && rev.chars().all(|c| c.is_ascii_hexdigit()) {
+        &rev[..7]
+    } else {
+        rev
+    }
+}
+
+/// Which dependency entries are upgraded, following `--skip-git` and `--skip-path`.
+#[derive(Debug, Clone, Copy)]
+struct Sources {
+    /// Whether git dependencies are upgraded
+    git: bool,
+    /// Whether path dependencies are upgraded
+    path: bool,
+}
+
+impl Sources {
+    /// Whether the dependency `entry` is upgraded.
+    fn include(self, entry: &toml_edit::Item) -> bool {
+        (self.git || entry["git"].is_none()) && (self.path || entry["path"].is_none())
+    }
+}
+
+/// A requirement changed (or, with `--dry-run`, to be changed) by the upgrade.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Change {
+    /// Package whose manifest is changed
+    package: String,
+    /// Name of the dependency
+    name: String,
+    /// Version requirement (or, for a git dependency, revision) before the upgrade
+    old: String,
+    /// Version requirement (or, for a git dependency, revision) after the upgrade
+    new: String,
+    /// Whether `old` and `new` are git revisions
+    rev: bool,
+}
+
+/// How much an upgrade changes a requirement, from the least to the most disruptive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Impact {
+    /// Only the patch version changes (e.g. 1.2.3 to 1.2.5)
+    Patch,
+    /// A compatible minor version (e.g. 1.2 to 1.4)
+    Minor,
+    /// A version the old requirement does not allow (e.g. 0.8 to 0.9, or 1.2 to 2.0)
+    Breaking,
+    /// One of the requirements is not a plain version (e.g. a wildcard)
+    Unknown,
+}
+
+impl Impact {
+    /// Get the impact of upgrading the requirement `old` to `new`.
+    fn of(old: &str, new: &str) -> Impact {
+        let (old, new) = match (lower_bound(old), lower_bound(new)) {
+            (Some(old), Some(new)) => (old, new),
+            _ => return Impact::Unknown,
+        };
+        // The leftmost non-zero number decides the compatibility.
+        let compatible = old.major == new.major
+            && (old.major != 0 || old.minor == new.minor)
+            && (old.major != 0 || old.minor != 0 || old.patch == new.patch);
+        if !compatible {
+            Impact::Breaking
+        } else if old.minor != new.minor {
+            Impact::Minor
+        } else {
+            Impact::Patch
+        }
+    }
+
+    /// The heading the upgrades are listed under
+    fn heading(self) -> &'static str {
+        match self {
+            Impact::Patch => "Patch upgrades",
+            Impact::Minor => "Minor upgrades",
+            Impact::Breaking => "Breaking upgrades",
+            Impact::Unknown => "Other upgrades",
+        }
+    }
 }
 
 /// A collection of manifests.
@@ -88,7 +384,7 @@ impl Manifests {
         let manifest_path = manifest_path.clone().map(PathBuf::from);
 
         cargo_metadata::metadata(manifest_path.as_ref().map(Path::new))
-            .chain_err(|| "Failed to get workspace metadata")?
+            .map_err(Error::metadata("Failed to get workspace metadata"))?
             .packages
             .into_iter()
             .map(|package| {
@@ -101,6 +397,31 @@ impl Manifests {
             .map(Manifests)
     }
 
+    /// Add the manifests of the `others` packages below the workspace root that are not members
+    /// (see `excluded_members` and `nested_packages`).
+    fn add_others(&mut self, others: &[WorkspaceMember]) -> Result<()> {
+        for other in others {
+            // A package outside of the workspace has metadata of its own.
+            let metadata = cargo_metadata::metadata(Some(other.manifest_path.as_path())).map_err(
+                Error::metadata("Failed to get metadata of a package outside the workspace"),
+            )?;
+            let resolved = other.manifest_path.canonicalize().map_err(Error::io(
+                "Failed to resolve the manifest of a package outside the workspace",
+            ))?;
+            let packages = metadata.packages.into_iter().filter(|package| {
+                Path::new(&package.manifest_path)
+                    .canonicalize()
+                    .map(|path| path == resolved)
+                    .unwrap_or(false)
+            });
+            for package in packages {
+                let manifest = LocalManifest::try_new(Path::new(&package.manifest_path))?;
+                self.0.push((manifest, package));
+            }
+        }
+        Ok(())
+    }
+
     /// Get the manifest specified by the manifest path. Try to make an educated guess if no path is
     /// provided.
     fn get_local_one(manifest_path: &Option<String>) -> Result<Self> {
@@ -110,15 +431,14 @@ impl Manifests {
         let manifest = LocalManifest::find(&manifest_path)?;
 
         let packages = cargo_metadata::metadata(manifest_path.as_ref().map(Path::new))
-            .chain_err(|| "Invalid manifest")?
+            .map_err(Error::metadata("Invalid manifest"))?
             .packages;
         let package = packages
             .iter()
             .find(|p| p.manifest_path == resolved_manifest_path)
             // If we have successfully got metadata, but our manifest path does not correspond to a
             // package, we must have been called against a virtual manifest.
-            .chain_err(|| "Found virtual manifest, but this command requires running against an \
-                           actual package in this workspace. Try adding `--all`.")?;
+            .ok_or(Error::VirtualManifest)?;
 
         Ok(Manifests(vec![(manifest, package.to_owned())]))
     }
@@ -162,36 +482,521 @@ impl Manifests {
         }))
     }
 
-    /// Upgrade the manifests on disk following the previously-determined upgrade schema.
-    fn upgrade(self, upgraded_deps: &ActualUpgrades, dry_run: bool) -> Result<()> {
+    /// Get the dependencies that are required with different versions across the manifests,
+    /// together with the highest of these requirements. If `only_update` is not empty, only the
+    /// named dependencies are considered.
+    fn get_consolidated(&self, only_update: &[String]) -> Result<ActualUpgrades> {
+        let only_update = only_update
+            .iter()
+            .map(|name| match CrateName::new(name).parse_as_version()? {
+                Some(dependency) => Ok(dependency.name),
+                None => Ok(name.clone()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut requirements: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for &(ref manifest, _) in &self.0 {
+            for (_, table) in manifest.get_sections() {
+                let table = table.as_table_like().expect("Unexpected non-table");
+                for (name, entry) in table.iter() {
+                    if !only_update.is_empty() && !only_update.iter().any(|n| n == name) {
+                        continue;
+                    }
+                    // Git and path dependencies are left alone.
+                    let version = match entry.as_str() {
+                        Some(version) => version,
+                        None if entry["git"].is_none() && entry["path"].is_none() => {
+                            match entry["version"].as_str() {
+                                Some(version) => version,
+                                None => continue,
+                            }
+                        }
+                        None => continue,
+                    };
+                    let versions = requirements.entry(name.to_owned()).or_insert_with(Vec::new);
+                    if !versions.iter().any(|v| v == version) {
+                        versions.push(version.to_owned());
+                    }
+                }
+            }
+        }
+
+        Ok(ActualUpgrades(
+            requirements
+                .into_iter()
+                .filter(|&(_, ref versions)| versions.len() > 1)
+                .filter_map(|(name, versions)| {
+                    versions
+                        .into_iter()
+                        .filter_map(|req| lower_bound(&req).map(|version| (version, req)))
+                        .max()
+                        .map(|(_, req)| (name, req))
+                })
+                .collect(),
+        ))
+    }
+
+    /// List the upgrades that change a requirement, grouped by their impact, and keep only the
+    /// ones the user picks.
+    fn pick_upgrades(
+        &self,
+        mut upgraded_deps: ActualUpgrades,
+        sources: Sources,
+    ) -> Result<ActualUpgrades> {
+        let mut candidates = Vec::new();
+        for (name, version) in &upgraded_deps.0 {
+            let mut olds: Vec<String> = Vec::new();
+            for &(ref manifest, _) in &self.0 {
+                for old in old_versions(manifest, name, sources) {
+                    if old != *version && !olds.contains(&old) {
+                        olds.push(old);
+                    }
+                }
+            }
+            if let Some(impact) = olds.iter().map(|old| Impact::of(old, version)).max() {
+                let label = format!("{} {} -> {}", name, olds.join(", "), version);
+                candidates.push((impact, label, name.clone()));
+            }
+        }
+        if candidates.is_empty() {
+            return Ok(ActualUpgrades(HashMap::new()));
+        }
+        candidates.sort();
+
+        let items: Vec<_> = candidates
+            .iter()
+            .map(|&(impact, ref label, _)| ChecklistItem::new(impact.heading(), label))
+            .collect();
+        Ok(ActualUpgrades(
+            checklist("Upgrades to apply", &items)?
+                .into_iter()
+                .filter_map(|i| {
+                    let name = &candidates[i].2;
+                    upgraded_deps.0.remove(name).map(|version| (name.clone(), version))
+                })
+                .collect(),
+        ))
+    }
+
+    /// Leave out the upgrades of crates that a manifest pins with a reason (see `cargo add
+    /// --reason`), unless they are among the `named` ones. The reasons are printed for the pinned
+    /// crates whose requirements would change.
+    fn hold_back_pinned(
+        &self,
+        mut upgraded_deps: ActualUpgrades,
+        named: &[String],
+        sources: Sources,
+    ) -> Result<ActualUpgrades> {
+        let mut names: Vec<String> = upgraded_deps.0.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            let version = upgraded_deps.0[&name].clone();
+            for &(ref manifest, ref package) in &self.0 {
+                let reason = match manifest.pin_reason(&name) {
+                    Some(reason) => reason,
+                    None => continue,
+                };
+                if old_versions(manifest, &name, sources)
+                    .iter()
+                    .all(|old| *old == version)
+                {
+                    continue;
+                }
+                if named.contains(&name) {
+                    print_status(
+                        "Pinned",
+                        &format!(
+                            "{} in {}: {} (upgrading it, as it is named)",
+                            name, package.name, reason
+                        ),
+                    )?;
+                } else {
+                    print_status(
+                        "Skipping",
+                        &format!("{}, pinned in {}: {}", name, package.name, reason),
+                    )?;
+                    upgraded_deps.0.remove(&name);
+                    break;
+                }
+            }
+        }
+        Ok(upgraded_deps)
+    }
+
+    /// Leave out the upgrades of crates that a manifest, or the root manifest of its workspace,
+    /// lists in `package.metadata.cargo-edit.ignore-upgrades` (or `workspace.metadata`), unless
+    /// they are among the `named` ones. Like pinned crates, they are held back in all manifests.
+    /// The held upgrades are returned and printed, for each manifest whose requirement they would
+    /// change.
+    fn hold_back_ignored(
+        &self,
+        mut upgraded_deps: ActualUpgrades,
+        named: &[String],
+        sources: Sources,
+    ) -> Result<(ActualUpgrades, Vec<Change>)> {
+        let mut held = Vec::new();
+        for &(ref manifest, ref package) in &self.0 {
+            let ignored = ignored_upgrades(Path::new(&package.manifest_path))?;
+            for name in ignored.iter().filter(|name| !named.contains(name)) {
+                let version = match upgraded_deps.0.get(name) {
+                    Some(version) => version.clone(),
+                    None => continue,
+                };
+                for old in old_versions(manifest, name, sources) {
+                    if old != version {
+                        held.push(Change {
+                            package: package.name.clone(),
+                            name: name.clone(),
+                            old: old,
+                            new: version.clone(),
+                            rev: false,
+                        });
+                    }
+                }
+            }
+        }
+        held.sort();
+        held.dedup();
+
+        for change in &held {
+            print_status(
+                "Holding",
+                &format!(
+                    "{} {} in {}, listed in `ignore-upgrades` ({} is available)",
+                    change.name, change.old, change.package, change.new
+                ),
+            )?;
+            upgraded_deps.0.remove(&change.name);
+        }
+        Ok((upgraded_deps, held))
+    }
+
+    /// Describe the upgrades as changes to record in a change file: one per dependency section
+    /// that requires another version of a crate. Git revisions are not recorded.
+    fn recorded_changes(
+        &self,
+        upgraded_deps: &ActualUpgrades,
+        sources: Sources,
+    ) -> Vec<RecordedChange> {
+        let mut changes = Vec::new();
+        for &(ref manifest, _) in &self.0 {
+            for (section, table) in manifest.get_sections() {
+                for (name, version) in &upgraded_deps.0 {
+                    let dep = &table[name.as_str()];
+                    if !sources.include(dep) {
+                        continue;
+                    }
+                    let old = dep.as_str().or_else(|| dep["version"].as_str());
+                    let change =
+                        RecordedChange::Upgrade(section.clone(), name.clone(), version.clone());
+                    if old.map_or(false, |old| old != version) && !changes.contains(&change) {
+                        changes.push(change);
+                    }
+                }
+            }
+        }
+        changes
+    }
+
+    /// Get the paths of the manifests.
+    fn paths(&self) -> Vec<PathBuf> {
+        self.0
+            .iter()
+            .map(|&(_, ref package)| PathBuf::from(&package.manifest_path))
+            .collect()
+    }
+
+    /// Get the requirement to upgrade each of the `names` within with `--compatible`: all of its
+    /// requirements across the manifests (and the `[workspace.dependencies]` of `root`), combined
+    /// like `1.2, 1.4`. Dependencies without a requirement are left out.
+    fn requirements(
+        &self,
+        names: &[String],
+        root: Option<&Manifest>,
+        sources: Sources,
+    ) -> HashMap<String, String> {
+        let mut requirements = HashMap::new();
+        for name in names {
+            let mut reqs: Vec<String> = Vec::new();
+            for &(ref manifest, _) in &self.0 {
+                reqs.extend(old_versions(manifest, name, sources));
+            }
+            if let Some(root) = root {
+                let dep = &root.data["workspace"]["dependencies"][name.as_str()];
+                if sources.include(dep) {
+                    reqs.extend(dep.as_str().or_else(|| dep["version"].as_str()).map(String::from));
+                }
+            }
+            reqs.sort();
+            reqs.dedup();
+            if !reqs.is_empty() {
+                requirements.insert(name.clone(), reqs.join(", "));
+            }
+        }
+        requirements
+    }
+
+    /// Get the crates for which any prerelease may be picked, as listed by any of the manifests
+    /// or the root manifest of their workspace.
+    fn prerelease_crates(&self) -> Result<Vec<String>> {
+        let mut crates = Vec::new();
+        for path in self.paths() {
+            crates.extend(prerelease_crates(&path)?);
+        }
+        Ok(crates)
+    }
+
+    /// Upgrade the manifests on disk following the previously-determined upgrade schema, and move
+    /// the `rev` of git dependencies with `revs`. Returns the requirements that changed.
+    fn upgrade(
+        self,
+        upgraded_deps: &ActualUpgrades,
+        sources: Sources,
+        mut revs: Option<RevUpgrades>,
+        dry_run: bool,
+    ) -> Result<Vec<Change>> {
         if dry_run {
-            let bufwtr = BufferWriter::stdout(ColorChoice::Always);
-            let mut buffer = bufwtr.buffer();
-            buffer
-                .set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))
-                .chain_err(|| "Failed to set output colour")?;
-            write!(&mut buffer, "Starting dry run. ")
-                .chain_err(|| "Failed to write dry run message")?;
-            buffer
-                .set_color(&ColorSpec::new())
-                .chain_err(|| "Failed to clear output colour")?;
-            writeln!(&mut buffer, "Changes will not be saved.")
-                .chain_err(|| "Failed to write dry run message")?;
-            bufwtr
-                .print(&buffer)
-                .chain_err(|| "Failed to print dry run message")?;
+            print_dry_run_message()?;
         }
 
+        let mut changes = Vec::new();
         for (mut manifest, package) in self.0 {
             println!("{}:", package.name);
 
+            let mut warnings = Warnings::new();
+            manifest.lint(&mut warnings);
+            warnings
+                .render(&mut io::stdout())
+                .map_err(Error::io("Failed to print warnings"))?;
+
             for (name, version) in &upgraded_deps.0 {
-                manifest.upgrade(&Dependency::new(name).set_version(version), dry_run)?;
+                for old in old_versions(&manifest, name, sources) {
+                    if old != *version {
+                        changes.push(Change {
+                            package: package.name.clone(),
+                            name: name.clone(),
+                            old: old,
+                            new: version.clone(),
+                            rev: false,
+                        });
+                    }
+                }
+                manifest.upgrade_where(
+                    &Dependency::new(name).set_version(version),
+                    dry_run,
+                    |entry| sources.include(entry),
+                )?;
+            }
+            if let Some(ref mut revs) = revs {
+                changes.extend(revs.apply(&mut manifest, &package.name, dry_run)?);
             }
         }
 
-        Ok(())
+        changes.sort();
+        changes.dedup();
+        Ok(changes)
+    }
+}
+
+/// Print the note that a dry run does not save the changes.
+fn print_dry_run_message() -> Result<()> {
+    let bufwtr = BufferWriter::stdout(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    buffer
+        .set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))
+        .map_err(Error::io("Failed to set output colour"))?;
+    write!(&mut buffer, "Starting dry run. ")
+        .map_err(Error::io("Failed to write dry run message"))?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .map_err(Error::io("Failed to clear output colour"))?;
+    writeln!(&mut buffer, "Changes will not be saved.")
+        .map_err(Error::io("Failed to write dry run message"))?;
+    bufwtr
+        .print(&buffer)
+        .map_err(Error::io("Failed to print dry run message"))
+}
+
+/// Get the distinct version requirements of a dependency across all dependency sections, leaving
+/// out the entries `sources` excludes.
+fn old_versions(manifest: &LocalManifest, name: &str, sources: Sources) -> Vec<String> {
+    let mut versions = Vec::new();
+    for (_, table) in manifest.get_sections() {
+        let dep = &table[name];
+        if !sources.include(dep) {
+            continue;
+        }
+        if let Some(version) = dep.as_str().or_else(|| dep["version"].as_str()) {
+            if !versions.iter().any(|v| v == version) {
+                versions.push(version.to_string());
+            }
+        }
+    }
+    versions
+}
+
+/// The lowest version a requirement like `^1.2` or `>=0.9, <2.0` allows, used to compare
+/// requirements. Wildcards (e.g. `0.2.*`) are not understood.
+fn lower_bound(req: &str) -> Option<semver::Version> {
+    let first = bare_version(req.split(',').next().unwrap_or(req));
+    let (numbers, pre) = match first.find('-') {
+        Some(i) => (&first[..i], &first[i..]),
+        None => (first, ""),
+    };
+    let mut numbers: Vec<_> = numbers.split('.').collect();
+    while numbers.len() < 3 {
+        numbers.push("0");
+    }
+    semver::Version::parse(&format!("{}{}", numbers.join("."), pre)).ok()
+}
+
+/// Print a status message, in the style of cargo.
+fn print_status(status: &str, message: &str) -> Result<()> {
+    let bufwtr = BufferWriter::stdout(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    buffer
+        .set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))
+        .map_err(Error::io("Failed to set output colour"))?;
+    write!(&mut buffer, "{:>12}", status).map_err(Error::io("Failed to write status"))?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .map_err(Error::io("Failed to clear output colour"))?;
+    writeln!(&mut buffer, " {}", message).map_err(Error::io("Failed to write status"))?;
+    bufwtr
+        .print(&buffer)
+        .map_err(Error::io("Failed to print status"))
+}
+
+/// Get the path of the workspace root manifest.
+fn workspace_root(manifest_path: &Option<String>) -> Result<PathBuf> {
+    Ok(find(&manifest_path.clone().map(PathBuf::from))?)
+}
+
+/// Upgrade the requirements in `[workspace.dependencies]` of the workspace root, if there are any.
+fn upgrade_workspace_dependencies(
+    manifest_path: &Option<String>,
+    upgraded_deps: &ActualUpgrades,
+    sources: Sources,
+    dry_run: bool,
+) -> Result<()> {
+    let root_path = Some(workspace_root(manifest_path)?);
+    let mut root = Manifest::open(&root_path)?;
+    if !root.data["workspace"]["dependencies"].is_table_like() {
+        return Ok(());
+    }
+
+    for (name, version) in &upgraded_deps.0 {
+        if !sources.include(&root.data["workspace"]["dependencies"][name.as_str()]) {
+            continue;
+        }
+        root.update_workspace_entry(&Dependency::new(name).set_version(version), dry_run)?;
+    }
+    root.write_workspace_to_path(&root_path)?;
+    Ok(())
+}
+
+/// Move the requirements to `[workspace.dependencies]` of the workspace root, and let the
+/// packages in `members` inherit them.
+fn hoist(
+    manifest_path: &Option<String>,
+    members: &[PathBuf],
+    upgraded_deps: &ActualUpgrades,
+    dry_run: bool,
+) -> Result<()> {
+    let root_path = Some(workspace_root(manifest_path)?);
+    // Fail before any member is touched.
+    if Manifest::open(&root_path)?.data["workspace"].is_none() {
+        return Err(cargo_edit::Error::NotWorkspaceRoot.into());
+    }
+
+    for member in members {
+        let member = Some(member.clone());
+        let mut manifest = Manifest::open(&member)?;
+        for (section, table) in manifest.get_sections() {
+            for name in upgraded_deps.0.keys() {
+                if !table[name.as_str()].is_none() {
+                    manifest.inherit_dependency(&section, name)?;
+                }
+            }
+        }
+        if !dry_run {
+            manifest.write_to_path(&member)?;
+        }
+    }
+
+    // The root is only opened now, as it may be one of the members, too.
+    let mut root = Manifest::open(&root_path)?;
+    let mut names: Vec<_> = upgraded_deps.0.keys().collect();
+    names.sort();
+    for name in names {
+        let version = &upgraded_deps.0[name];
+        print_status("Hoisting", &format!("{} v{} to the workspace root", name, version))?;
+        root.insert_into_workspace(&Dependency::new(name).set_version(version))?;
+    }
+    if !dry_run {
+        root.write_workspace_to_path(&root_path)?;
+    }
+    Ok(())
+}
+
+/// Strip the operator off a requirement like `^1.2` to get something that can be used in a URL.
+fn bare_version(req: &str) -> &str {
+    req.trim_start_matches(|c: char| "=^~<>".contains(c)).trim()
+}
+
+/// Render the changes as a Markdown table, linking to the diffs and the new documentation. The
+/// `held` upgrades (see `hold_back_ignored`) are listed below the table.
+fn markdown_report(changes: &[Change], held: &[Change]) -> String {
+    // Only name the packages when a workspace is upgraded.
+    let mut packages = changes.iter().chain(held).map(|change| &change.package);
+    let first = packages.next();
+    let with_package = packages.any(|package| Some(package) != first);
+
+    let mut report = String::new();
+    if changes.is_empty() {
+        report.push_str("No dependencies were upgraded.\n");
+    } else if with_package {
+        report.push_str("| Package | Crate | From | To | Links |\n|---|---|---|---|---|\n");
+    } else {
+        report.push_str("| Crate | From | To | Links |\n|---|---|---|---|\n");
+    }
+    for change in changes {
+        if with_package {
+            report.push_str(&format!("| {} ", change.package));
+        }
+        if change.rev {
+            report.push_str(&format!(
+                "| `{}` | `{}` | `{}` | - |\n",
+                change.name, change.old, change.new
+            ));
+            continue;
+        }
+        report.push_str(&format!(
+            "| `{name}` | `{old}` | `{new}` | \
+             [diff](https://diff.rs/{name}/{old_version}/{new_version}) · \
+             [docs](https://docs.rs/{name}/{new_version}) |\n",
+            name = change.name,
+            old = change.old,
+            new = change.new,
+            old_version = bare_version(&change.old),
+            new_version = bare_version(&change.new),
+        ));
     }
+
+    if !held.is_empty() {
+        report.push_str("\nHeld back, as listed in `ignore-upgrades`:\n\n");
+        for change in held {
+            report.push_str("- ");
+            if with_package {
+                report.push_str(&format!("{}: ", change.package));
+            }
+            report.push_str(&format!(
+                "`{}` `{}` (`{}` is available)\n",
+                change.name, change.old, change.new
+            ));
+        }
+    }
+    report
 }
 
 /// The set of dependencies to be upgraded, alongside desired versions, if specified by the user.
@@ -203,29 +1008,96 @@ struct ActualUpgrades(HashMap<String, String>);
 
 impl DesiredUpgrades {
     /// Transform the dependencies into their upgraded forms. If a version is specified, all
-    /// dependencies will get that version.
-    fn get_upgraded(self, allow_prerelease: bool) -> Result<ActualUpgrades> {
-        self.0
-            .into_iter()
-            .map(|(name, version)| {
-                if let Some(v) = version {
-                    Ok((name, v))
-                } else {
-                    get_latest_dependency(&name, allow_prerelease)
-                        .map(|new_dep| {
-                            (
-                                name,
-                                new_dep
-                                    .version()
-                                    .expect("Invalid dependency type")
-                                    .to_string(),
-                            )
-                        })
-                        .chain_err(|| "Failed to get new version")
+    /// dependencies will get that version. With `compatible`, the others get the latest version
+    /// their requirement in it allows, and are left out if there is none. With `save_exact`, the
+    /// new versions are pinned with `=`. Up to `jobs` crates are looked up at the same time (see
+    /// `get_latest_dependencies`). Any prerelease may be picked for the crates in
+    /// `prerelease_crates`.
+    fn get_upgraded(
+        self,
+        prereleases: Prereleases,
+        prerelease_crates: &[String],
+        save_exact: bool,
+        compatible: Option<HashMap<String, String>>,
+        jobs: Option<usize>,
+    ) -> Result<ActualUpgrades> {
+        let (specified, latest): (Vec<_>, Vec<_>) =
+            self.0.into_iter().partition(|&(_, ref version)| version.is_some());
+
+        // The latest versions are looked up all at once, to save on requests to the registry.
+        let names: Vec<_> = latest.into_iter().map(|(name, _)| name).collect();
+        let new_versions: Vec<(String, String)> = match compatible {
+            Some(known) => {
+                let requirements: Vec<(String, String)> = names
+                    .into_iter()
+                    .filter_map(|name| {
+                        let req = known.get(&name).cloned();
+                        req.map(|req| (name, req))
+                    })
+                    .collect();
+                let mut warnings = Warnings::new();
+                let new_deps = get_compatible_dependencies(
+                    &requirements,
+                    prereleases,
+                    prerelease_crates,
+                    jobs,
+                    &mut warnings,
+                ).map_err(Error::FetchNewVersion)?;
+                warnings
+                    .render(&mut io::stdout())
+                    .map_err(Error::io("Failed to print warnings"))?;
+                let mut new_versions = Vec::new();
+                for ((name, req), new_dep) in requirements.into_iter().zip(new_deps) {
+                    match new_dep {
+                        Some(new_dep) => {
+                            let version = new_dep.version().expect("Invalid dependency type");
+                            new_versions.push((name, version.to_string()));
+                        }
+                        None => print_status(
+                            "Skipping",
+                            &format!("{}, no version is compatible with `{}`", name, req),
+                        )?,
+                    }
                 }
-            })
-            .collect::<Result<_>>()
-            .map(ActualUpgrades)
+                new_versions
+            }
+            None => {
+                let new_deps =
+                    get_latest_dependencies(&names, prereleases, prerelease_crates, jobs)
+                        .map_err(Error::FetchNewVersion)?;
+                names
+                    .into_iter()
+                    .zip(new_deps)
+                    .map(|(name, new_dep)| {
+                        let version = new_dep.version().expect("Invalid dependency type");
+                        (name, version.to_string())
+                    })
+                    .collect()
+            }
+        };
+        let fetched = new_versions.into_iter().map(|(name, version)| {
+            if save_exact {
+                (name, format!("={}", version))
+            } else {
+                (name, version)
+            }
+        });
+
+        Ok(ActualUpgrades(
+            specified
+                .into_iter()
+                .map(|(name, version)| {
+                    let version = version.expect("only specified versions");
+                    // Only plain versions can be pinned, requirements like `>=0.9, <2.0` are kept.
+                    if save_exact && semver::Version::parse(&version).is_ok() {
+                        (name, format!("={}", version))
+                    } else {
+                        (name, version)
+                    }
+                })
+                .chain(fetched)
+                .collect(),
+        ))
     }
 }
 
@@ -236,45 +1108,236 @@ fn process(args: Args) -> Result<()> {
         arg_dependency,
         flag_manifest_path,
         flag_all,
+        flag_include_excluded,
+        flag_include_nested,
         flag_allow_prerelease,
+        flag_prerelease,
         flag_dry_run,
+        flag_save_exact,
+        flag_compatible,
+        flag_skip_git,
+        flag_skip_path,
+        flag_update_revs,
+        flag_git_only,
+        flag_to_rev,
+        flag_report,
+        flag_consolidate,
+        flag_hoist,
+        flag_interactive,
+        flag_verbose,
+        flag_jobs,
+        flag_record,
         ..
     } = args;
 
+    if flag_hoist && !flag_consolidate {
+        return Err(Error::HoistWithoutConsolidate);
+    }
+    if flag_include_excluded && !flag_all {
+        return Err(Error::IncludeWithoutAll("--include-excluded"));
+    }
+    if flag_include_nested && !flag_all {
+        return Err(Error::IncludeWithoutAll("--include-nested"));
+    }
+    let conflicting = [
+        ("--git-only", flag_git_only, "--consolidate", flag_consolidate),
+        ("--git-only", flag_git_only, "--compatible", flag_compatible),
+        ("--compatible", flag_compatible, "--consolidate", flag_consolidate),
+        ("--git-only", flag_git_only, "--interactive", flag_interactive),
+        ("--git-only", flag_git_only, "--skip-git", flag_skip_git),
+        ("--update-revs", flag_update_revs, "--skip-git", flag_skip_git),
+    ];
+    for &(flag, given, other, other_given) in &conflicting {
+        if given && other_given {
+            return Err(Error::ConflictingFlags(flag, other));
+        }
+    }
+    let sources = Sources {
+        git: !flag_skip_git,
+        path: !flag_skip_path,
+    };
+
+    let prereleases = match flag_prerelease {
+        Some(channel) => Prereleases::Channel(channel.parse()?),
+        None => Prereleases::from(flag_allow_prerelease),
+    };
+
     let manifests = if flag_all {
-        Manifests::get_all(&flag_manifest_path)
+        // The workspace comes first, so a broken root manifest fails to give its metadata.
+        let mut manifests = Manifests::get_all(&flag_manifest_path)?;
+        let manifest_path = flag_manifest_path.clone().map(PathBuf::from);
+        let candidates = [
+            (
+                excluded_members(&manifest_path)?,
+                flag_include_excluded,
+                "which the workspace excludes",
+                "--include-excluded",
+            ),
+            (
+                nested_packages(&manifest_path)?,
+                flag_include_nested,
+                "which is not a member of the workspace",
+                "--include-nested",
+            ),
+        ];
+        let mut others = Vec::new();
+        for &(ref packages, included, reason, flag) in &candidates {
+            if included {
+                others.extend(packages.iter().cloned());
+                continue;
+            }
+            for package in packages {
+                println!(
+                    "note: skipping `{}` at {}, {}; pass `{}` to include it",
+                    package.name, package.path, reason, flag
+                );
+            }
+        }
+        manifests.add_others(&others)?;
+        manifests
+    } else {
+        Manifests::get_local_one(&flag_manifest_path)?
+    };
+
+    // The names of the crates to upgrade, without the versions to upgrade them to.
+    let named: Vec<String> = arg_dependency
+        .iter()
+        .map(|name| match CrateName::new(name).parse_as_version()? {
+            Some(dependency) => Ok(dependency.name),
+            None => Ok(name.clone()),
+        })
+        .collect::<Result<_>>()?;
+
+    let revs = if flag_update_revs || flag_git_only {
+        Some(RevUpgrades {
+            target: GitTarget::parse(&flag_to_rev)?,
+            only_update: named.clone(),
+            commits: HashMap::new(),
+        })
+    } else {
+        None
+    };
+
+    let upgraded_dependencies = if flag_git_only {
+        ActualUpgrades(HashMap::new())
+    } else if flag_consolidate {
+        manifests.get_consolidated(&arg_dependency)?
     } else {
-        Manifests::get_local_one(&flag_manifest_path)
-    }?;
+        let desired = manifests.get_dependencies(arg_dependency)?;
+        let requirements = if flag_compatible {
+            let names: Vec<String> = desired.0.keys().cloned().collect();
+            let root = if flag_all {
+                Some(Manifest::open(&Some(workspace_root(&flag_manifest_path)?))?)
+            } else {
+                None
+            };
+            Some(manifests.requirements(&names, root.as_ref(), sources))
+        } else {
+            None
+        };
+        desired.get_upgraded(
+            prereleases,
+            &manifests.prerelease_crates()?,
+            flag_save_exact,
+            requirements,
+            flag_jobs,
+        )?
+    };
+
+    let upgraded_dependencies =
+        manifests.hold_back_pinned(upgraded_dependencies, &named, sources)?;
+    let (upgraded_dependencies, held) =
+        manifests.hold_back_ignored(upgraded_dependencies, &named, sources)?;
+    let upgraded_dependencies = if flag_interactive {
+        manifests.pick_upgrades(upgraded_dependencies, sources)?
+    } else {
+        upgraded_dependencies
+    };
+
+    let members = manifests.paths();
+    let recorded = manifests.recorded_changes(&upgraded_dependencies, sources);
+    let changes = manifests.upgrade(&upgraded_dependencies, sources, revs, flag_dry_run)?;
+
+    if flag_hoist {
+        hoist(
+            &flag_manifest_path,
+            &members,
+            &upgraded_dependencies,
+            flag_dry_run,
+        )?;
+    } else if flag_all && !flag_git_only {
+        upgrade_workspace_dependencies(
+            &flag_manifest_path,
+            &upgraded_dependencies,
+            sources,
+            flag_dry_run,
+        )?;
+    }
+
+    if let Some(change_file) = flag_record {
+        record_changes(Path::new(&change_file), &members, &recorded)?;
+    }
 
-    let existing_dependencies = manifests.get_dependencies(arg_dependency)?;
+    if let Some(ReportFormat::Markdown) = flag_report {
+        println!();
+        print!("{}", markdown_report(&changes, &held));
+    }
 
-    let upgraded_dependencies = existing_dependencies.get_upgraded(flag_allow_prerelease)?;
+    let cache_stats = registry_cache_stats();
+    if flag_verbose && cache_stats.lookups() > 0 {
+        println!("note: {}", cache_stats);
+    }
 
-    manifests.upgrade(&upgraded_dependencies, flag_dry_run)
+    Ok(())
 }
 
-fn main() {
+/// What the command supports, for `--version --json`.
+pub fn self_description() -> SelfDescription {
+    SelfDescription::new("cargo-upgrade")
+        .set_features(&[
+            "sparse-index",
+            "workspace",
+            "dry-run",
+            "git-revs",
+            "record-changes",
+            "compatible-upgrades",
+        ])
+        .set_output_formats(&[("upgrade --report markdown", 1), ("change-file", 1)])
+}
+
+/// Run the command with the arguments of the process.
+pub fn main() {
+    let (argv, notes) = command_args().unwrap_or_else(|err| exit_with_error(&Error::from(err)));
+    for note in notes {
+        eprintln!("{}", note);
+    }
     let args = docopt::Docopt::new(USAGE)
-        .and_then(|d| d.deserialize::<Args>())
+        .and_then(|d| d.argv(argv).deserialize::<Args>())
         .unwrap_or_else(|err| err.exit());
 
     if args.flag_version {
-        println!("cargo-upgrade version {}", env!("CARGO_PKG_VERSION"));
+        if args.flag_json {
+            println!("{}", self_description().to_json());
+        } else {
+            println!("cargo-upgrade version {}", env!("CARGO_PKG_VERSION"));
+        }
         process::exit(0);
     }
 
     if let Err(err) = process(args) {
-        eprintln!("Command failed due to unhandled error: {}\n", err);
-
-        for e in err.iter().skip(1) {
-            eprintln!("Caused by: {}", e);
-        }
+        exit_with_error(&err);
+    }
+}
 
-        if let Some(backtrace) = err.backtrace() {
-            eprintln!("Backtrace: {:?}", backtrace);
-        }
+/// Print an error with its causes, and exit with a failure.
+fn exit_with_error(err: &Error) -> ! {
+    eprintln!("{}\n", message!("unhandled-error", error = err));
 
-        process::exit(1);
+    let mut cause = err.source();
+    while let Some(e) = cause {
+        eprintln!("{}", message!("caused-by", cause = e));
+        cause = e.source();
     }
+
+    process::exit(1);
 }
@@ -0,0 +1,148 @@
+//! Responses from the registry, kept for the rest of the invocation.
+//!
+//! A crate is often looked up more than once in one invocation, e.g. when several members of a
+//! workspace depend on it, or when its features are checked right after its latest version was
+//! looked up. The responses are kept per thread; lookups running on worker threads are cached by
+//! the thread that started them.
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use errors::*;
+
+thread_local! {
+    /// How often a response was taken from a cache, and how often it had to be fetched.
+    static STATS: Cell<CacheStats> = Cell::new(CacheStats::default());
+}
+
+/// How often registry responses were reused instead of fetched again, in this invocation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups answered from the cache
+    pub hits: usize,
+    /// Lookups that had to be sent to the registry
+    pub misses: usize,
+}
+
+impl CacheStats {
+    /// The number of lookups
+    pub fn lookups(&self) -> usize {
+        self.hits + self.misses
+    }
+
+    /// The share of lookups answered from the cache, in percent, if there were any lookups.
+    pub fn hit_rate(&self) -> Option<f64> {
+        match self.lookups() {
+            0 => None,
+            lookups => Some(self.hits as f64 * 100.0 / lookups as f64),
+        }
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} registry lookups answered from cache ({:.0}%)",
+            self.hits,
+            self.lookups(),
+            self.hit_rate().unwrap_or(0.0)
+        )
+    }
+}
+
+/// How often registry responses were reused instead of fetched again, so far.
+pub fn registry_cache_stats() -> CacheStats {
+    STATS.with(Cell::get)
+}
+
+fn record(hit: bool) {
+    STATS.with(|stats| {
+        let mut current = stats.get();
+        if hit {
+            current.hits += 1;
+        } else {
+            current.misses += 1;
+        }
+        stats.set(current);
+    });
+}
+
+/// Responses by key (e.g. the lowercase name of a crate). Failed lookups are not kept, so they
+/// are tried again.
+#[derive(Debug)]
+pub(crate) struct Cache<T> {
+    entries: RefCell<HashMap<String, Rc<T>>>,
+}
+
+impl<T> Cache<T> {
+    pub fn new() -> Self {
+        Cache {
+            entries: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The response for `key`, if it was fetched before.
+    pub fn get(&self, key: &str) -> Option<Rc<T>> {
+        let cached = self.entries.borrow().get(key).cloned();
+        if cached.is_some() {
+            record(true);
+        }
+        cached
+    }
+
+    /// Keep a response that was just fetched.
+    pub fn insert(&self, key: &str, value: T) -> Rc<T> {
+        record(false);
+        let value = Rc::new(value);
+        self.entries
+            .borrow_mut()
+            .insert(key.to_owned(), Rc::clone(&value));
+        value
+    }
+
+    /// The response for `key`, fetched with `fetch` unless it was fetched before.
+    pub fn get_or_fetch<F>(&self, key: &str, fetch: F) -> Result<Rc<T>>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        match self.get(key) {
+            Some(cached) => Ok(cached),
+            None => Ok(self.insert(key, fetch()?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fetches_once_and_counts() {
+        let cache = Cache::new();
+        let before = registry_cache_stats();
+
+        let mut fetched = 0;
+        for _ in 0..3 {
+            let value = cache
+                .get_or_fetch("serde", || {
+                    fetched += 1;
+                    Ok(1)
+                })
+                .unwrap();
+            assert_eq!(*value, 1);
+        }
+        assert!(cache.get_or_fetch("log", || Err(Error::NoVersionsAvailable)).is_err());
+        assert!(cache.get("log").is_none());
+        assert_eq!(fetched, 1);
+
+        let after = registry_cache_stats();
+        assert_eq!(after.hits - before.hits, 2);
+        assert_eq!(after.misses - before.misses, 1);
+        assert_eq!(
+            CacheStats { hits: 3, misses: 1 }.to_string(),
+            "3 of 4 registry lookups answered from cache (75%)"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Recording changes to the dependencies of manifests in a change file, which `cargo dep apply`
+//! replays on other manifests.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use toml_edit;
+
+use dep_table::{DepKind, DepTable};
+use dependency::Dependency;
+use errors::*;
+
+/// A change to a dependency section, as recorded in a `[[change]]` table of a change file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordedChange {
+    /// The dependency was added to the section (`op = "add"`)
+    Add(DepTable, Dependency),
+    /// The crate was removed from the section (`op = "rm"`)
+    Remove(DepTable, String),
+    /// The requirement of the crate was upgraded to a version (`op = "upgrade"`)
+    Upgrade(DepTable, String, String),
+}
+
+impl RecordedChange {
+    /// The keys of the `[[change]]` table, as a document of their own
+    fn to_document(&self) -> toml_edit::Document {
+        let mut document = toml_edit::Document::new();
+        let (op, section, name) = match *self {
+            RecordedChange::Add(ref section, ref dep) => ("add", section, dep.name.as_str()),
+            RecordedChange::Remove(ref section, ref name) => ("rm", section, name.as_str()),
+            RecordedChange::Upgrade(ref section, ref name, _) => {
+                ("upgrade", section, name.as_str())
+            }
+        };
+        document["op"] = toml_edit::value(op);
+        document["crate"] = toml_edit::value(name);
+        if section.kind() != DepKind::Normal {
+            document["section"] = toml_edit::value(section.kind().kind_table());
+        }
+        if let Some(target) = section.target() {
+            document["target"] = toml_edit::value(target);
+        }
+
+        match *self {
+            RecordedChange::Add(_, ref dep) => {
+                if let Some(version) = dep.version() {
+                    document["version"] = toml_edit::value(version);
+                }
+                if let Some(git) = dep.git() {
+                    document["git"] = toml_edit::value(git);
+                }
+                if let Some(reference) = dep.git_reference() {
+                    document[reference.key()] = toml_edit::value(reference.value());
+                }
+                if let Some(path) = dep.path() {
+                    document["path"] = toml_edit::value(path);
+                }
+                if let Some(features) = dep.features() {
+                    let mut array = toml_edit::Array::default();
+                    for feature in features {
+                        array.push(feature.as_str());
+                    }
+                    document["features"] = toml_edit::value(toml_edit::Value::Array(array));
+                }
+                if !dep.default_features() {
+                    document["default-features"] = toml_edit::value(false);
+                }
+                if dep.optional() {
+                    document["optional"] = toml_edit::value(true);
+                }
+            }
+            RecordedChange::Upgrade(_, _, ref version) => {
+                document["version"] = toml_edit::value(version.as_str());
+            }
+            RecordedChange::Remove(..) => {}
+        }
+        document
+    }
+}
+
+/// The path of a manifest as listed in a change file in `dir`: relative to `dir` if the manifest
+/// is in it, absolute otherwise.
+fn listed_path(dir: &Path, manifest: &Path) -> String {
+    let manifest = fs::canonicalize(manifest).unwrap_or_else(|_| manifest.to_owned());
+    let relative = fs::canonicalize(dir)
+        .ok()
+        .and_then(|dir| manifest.strip_prefix(dir).ok().map(PathBuf::from));
+    relative
+        .unwrap_or(manifest)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Add manifests and changes to the contents of a change file. The manifests are added to
+/// `manifests` unless they are listed already, and each change is appended as a `[[change]]`
+/// table.
+fn append_changes(
+    contents: &str,
+    manifests: &[String],
+    changes: &[RecordedChange],
+) -> Result<String> {
+    let mut document: toml_edit::Document = contents.parse().map_err(Error::InvalidToml)?;
+    let mut listed: Vec<String> = document["manifests"]
+        .as_array()
+        .map(|listed| listed.iter().filter_map(|m| m.as_str()).map(String::from).collect())
+        .unwrap_or_default();
+    for manifest in manifests {
+        if !listed.contains(manifest) {
+            listed.push(manifest.clone());
+        }
+    }
+    let mut array = toml_edit::Array::default();
+    for manifest in &listed {
+        array.push(manifest.as_str());
+    }
+    document["manifests"] = toml_edit::value(toml_edit::Value::Array(array));
+
+    let mut contents = document.to_string();
+    for change in changes {
+        contents.push_str("\n[[change]]\n");
+        contents.push_str(&change.to_document().to_string());
+    }
+    Ok(contents)
+}
+
+/// Record changes made to `manifests` in the change file at `path`, which is created if it does
+/// not exist. Recording the same manifest again, e.g. for the next command of an interactive
+/// session, only appends the changes, so `cargo dep apply` replays them in the order they were
+/// made.
+pub fn record_changes(
+    path: &Path,
+    manifests: &[PathBuf],
+    changes: &[RecordedChange],
+) -> Result<()> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Error::io("Failed to read the change file")(e)),
+    };
+    let dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let manifests: Vec<_> = manifests.iter().map(|m| listed_path(dir, m)).collect();
+
+    let contents = append_changes(&contents, &manifests, changes)?;
+    fs::write(path, contents).map_err(Error::io("Failed to write the change file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_changes_to_change_file() {
+        let serde = Dependency::new("serde")
+            .set_version("1.0")
+            .set_features(Some(vec!["derive".to_owned()]));
+        let contents = append_changes(
+            "",
+            &["one/Cargo.toml".to_owned()],
+            &[RecordedChange::Add(DepTable::new(), serde)],
+        ).unwrap();
+        assert_eq!(
+            contents,
+            r#"manifests = ["one/Cargo.toml"]
+
+[[change]]
+op = "add"
+crate = "serde"
+version = "1.0"
+features = ["derive"]
+"#
+        );
+
+        let unix = DepTable::new()
+            .set_kind(DepKind::Development)
+            .set_target("cfg(unix)");
+        let contents = append_changes(
+            &contents,
+            &["one/Cargo.toml".to_owned(), "two/Cargo.toml".to_owned()],
+            &[
+                RecordedChange::Remove(unix, "log".to_owned()),
+                RecordedChange::Upgrade(DepTable::new(), "libc".to_owned(), "0.2.30".to_owned()),
+            ],
+        ).unwrap();
+        assert_eq!(
+            contents,
+            r#"manifests = ["one/Cargo.toml", "two/Cargo.toml"]
+
+[[change]]
+op = "add"
+crate = "serde"
+version = "1.0"
+features = ["derive"]
+
+[[change]]
+op = "rm"
+crate = "log"
+section = "dev-dependencies"
+target = "cfg(unix)"
+
+[[change]]
+op = "upgrade"
+crate = "libc"
+version = "0.2.30"
+"#
+        );
+    }
+}
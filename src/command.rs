@@ -0,0 +1,459 @@
+//! The command line of the `cargo-*` binaries.
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use errors::*;
+use workspace::workspace_members;
+
+/// The prefix of the file name of external `cargo edit` commands.
+const PLUGIN_PREFIX: &'static str = "cargo-edit-";
+
+/// What a flag of upstream cargo-edit becomes here
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Upstream {
+    /// The flag is spelled like this here
+    Flag(&'static str),
+    /// The value of the flag is an argument here (e.g. the dependency of `cargo upgrade --package`)
+    Argument,
+    /// The value of the flag is a member of the workspace, whose manifest is passed as
+    /// `--manifest-path`
+    Member,
+    /// The flag has no counterpart here and is dropped, with a note giving this reason
+    Ignored(&'static str),
+}
+
+/// The flags of upstream cargo-edit (`--dry-run`, `--offline`, `--pinned` and `--package`) that
+/// a command does not take as they are, as (command, upstream spelling, what it becomes). The
+/// flags not listed for a command are its own, e.g. `--offline` of `cargo add`.
+const UPSTREAM_ALIASES: &'static [(&'static str, &'static str, Upstream)] = &[
+    ("add", "--package", Upstream::Flag("--members")),
+    ("add", "-p", Upstream::Flag("--members")),
+    ("add", "--pinned", Upstream::Ignored("only `cargo upgrade` upgrades pinned requirements")),
+    ("rm", "--package", Upstream::Member),
+    ("rm", "-p", Upstream::Member),
+    ("rm", "--offline", Upstream::Ignored("`cargo rm` does not use the registry")),
+    ("rm", "--pinned", Upstream::Ignored("only `cargo upgrade` upgrades pinned requirements")),
+    ("upgrade", "--package", Upstream::Argument),
+    ("upgrade", "-p", Upstream::Argument),
+    (
+        "upgrade",
+        "--offline",
+        Upstream::Ignored("`cargo upgrade` needs the registry to find the latest versions"),
+    ),
+    (
+        "upgrade",
+        "--pinned",
+        Upstream::Ignored("`cargo upgrade` upgrades requirements pinned with `=` in any case"),
+    ),
+    ("dep", "--package", Upstream::Member),
+    ("dep", "-p", Upstream::Member),
+    (
+        "dep",
+        "--offline",
+        Upstream::Ignored("`cargo dep` looks up crates in the registry where it needs to"),
+    ),
+    ("dep", "--pinned", Upstream::Ignored("only `cargo upgrade` upgrades pinned requirements")),
+];
+
+/// Whether the upstream flag takes a value.
+fn takes_value(upstream: &str) -> bool {
+    upstream == "--package" || upstream == "-p"
+}
+
+/// Get the arguments of the command, as the `cargo <command>` usage strings expect them: the name
+/// of the command (e.g. `add`) right after the binary.
+///
+/// Cargo runs `cargo add ...` as `cargo-add add ...`. For the multicall `cargo-edit` binary, it
+/// runs `cargo edit add ...` as `cargo-edit edit add ...`, so the leading `edit` is dropped.
+///
+/// The flags of upstream cargo-edit that are spelled differently here (e.g. `cargo add --package`
+/// for `--members`) are translated, so commands written for either work with both. The notes on
+/// the flags that are dropped come with the arguments, for the command to print. Fails if the
+/// package of `--package` is not a member of the workspace.
+pub fn command_args() -> Result<(Vec<String>, Vec<String>)> {
+    let args = untranslated_command_args();
+    let workspace = manifest_path_arg(&args).map(PathBuf::from);
+    translate_upstream_flags(args, |name| member_manifest(&workspace, name))
+}
+
+/// Get the arguments of the command like `command_args`, but without translating the flags of
+/// upstream cargo-edit, e.g. to pass them on to the command that translates them.
+pub fn untranslated_command_args() -> Vec<String> {
+    strip_edit(env::args().collect())
+}
+
+fn strip_edit(mut args: Vec<String>) -> Vec<String> {
+    if args.len() > 1 && args[1] == "edit" {
+        args.remove(1);
+    }
+    args
+}
+
+/// The manifest of the workspace member `name`.
+fn member_manifest(workspace: &Option<PathBuf>, name: &str) -> Result<PathBuf> {
+    workspace_members(workspace)?
+        .into_iter()
+        .find(|member| member.name == name)
+        .map(|member| member.manifest_path)
+        .ok_or_else(|| Error::UnknownWorkspaceMember(name.to_owned()))
+}
+
+/// Translate the flags of upstream cargo-edit in `args` to ours (see `UPSTREAM_ALIASES`), and
+/// get the notes on the dropped ones. `member_manifest` finds the manifest of a workspace member,
+/// which replaces the `--manifest-path` given, if any. The arguments after `--` are left alone.
+fn translate_upstream_flags<F>(
+    args: Vec<String>,
+    member_manifest: F,
+) -> Result<(Vec<String>, Vec<String>)>
+where
+    F: Fn(&str) -> Result<PathBuf>,
+{
+    let command = match args.get(1) {
+        Some(command) => command.clone(),
+        None => return Ok((args, Vec::new())),
+    };
+    let aliases: Vec<_> = UPSTREAM_ALIASES
+        .iter()
+        .filter(|&&(alias_command, _, _)| alias_command == command)
+        .collect();
+
+    let mut translated = Vec::with_capacity(args.len());
+    let mut notes = Vec::new();
+    let mut member = None;
+    let mut args = args.into_iter();
+    translated.extend(args.by_ref().take(2));
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            translated.push(arg);
+            translated.extend(args.by_ref());
+            break;
+        }
+        let alias = aliases
+            .iter()
+            .filter_map(|&&(_, upstream, ours)| {
+                attached_value(&arg, upstream)
+                    .map(|value| (upstream, ours, value.map(String::from)))
+            })
+            .next();
+        let (upstream, ours, value) = match alias {
+            Some(alias) => alias,
+            None => {
+                translated.push(arg);
+                continue;
+            }
+        };
+        let value = match value {
+            Some(value) => Some(value),
+            None if takes_value(upstream) => args.next(),
+            None => None,
+        };
+        match (ours, value) {
+            (Upstream::Flag(ours), None) => translated.push(ours.to_owned()),
+            (Upstream::Flag(ours), Some(value)) => translated.push(format!("{}={}", ours, value)),
+            (Upstream::Argument, Some(value)) => translated.push(value),
+            (Upstream::Member, Some(value)) => member = Some(member_manifest(&value)?),
+            // Without its value, the flag is left for the usage to reject.
+            (Upstream::Argument, None) | (Upstream::Member, None) => translated.push(arg),
+            (Upstream::Ignored(reason), _) => {
+                notes.push(format!("note: ignoring `{}`: {}", upstream, reason))
+            }
+        }
+    }
+
+    if let Some(member) = member {
+        translated = strip_manifest_path(translated);
+        translated.insert(2, format!("--manifest-path={}", member.display()));
+    }
+    Ok((translated, notes))
+}
+
+/// The value of `--manifest-path` in `args`, if given.
+fn manifest_path_arg(args: &[String]) -> Option<&str> {
+    let mut args = args.iter().take_while(|arg| *arg != "--");
+    while let Some(arg) = args.next() {
+        match attached_value(arg, "--manifest-path") {
+            Some(Some(value)) => return Some(value),
+            Some(None) => return args.next().map(|value| value.as_str()),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Drop `--manifest-path` and its value from `args`.
+fn strip_manifest_path(args: Vec<String>) -> Vec<String> {
+    let mut stripped = Vec::with_capacity(args.len());
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            stripped.push(arg);
+            stripped.extend(args.by_ref());
+            break;
+        }
+        match attached_value(&arg, "--manifest-path") {
+            Some(Some(_)) => {}
+            Some(None) => {
+                args.next();
+            }
+            None => stripped.push(arg),
+        }
+    }
+    stripped
+}
+
+/// Whether `arg` is the flag `upstream`, and the value attached to it, if any: after `=` or, for a
+/// short flag, right after it (e.g. `-pfoo`).
+fn attached_value<'a>(arg: &'a str, upstream: &str) -> Option<Option<&'a str>> {
+    if arg == upstream {
+        return Some(None);
+    }
+    if !arg.starts_with(upstream) {
+        return None;
+    }
+    let rest = &arg[upstream.len()..];
+    if rest.starts_with('=') {
+        Some(Some(&rest[1..]))
+    } else if !upstream.starts_with("--") {
+        Some(Some(rest))
+    } else {
+        None
+    }
+}
+
+/// An external `cargo-edit-<name>` binary, which runs as `cargo edit <name>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plugin {
+    /// Name of the command, i.e. the file name without the `cargo-edit-` prefix
+    pub name: String,
+    /// Path of the binary
+    pub path: PathBuf,
+}
+
+impl Plugin {
+    /// Ask the plugin for its version, by running it with `--version`.
+    ///
+    /// The version is the last word of the first line of the output (e.g. `1.0.0` for
+    /// `cargo-edit-foo version 1.0.0`).
+    pub fn version(&self) -> Option<String> {
+        let output = Command::new(&self.path).arg("--version").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().last())
+            .map(|version| version.to_string())
+    }
+
+    /// Prepare running the plugin as `cargo edit <name>`.
+    ///
+    /// Like cargo does for its subcommands, the name of the command is passed as the first
+    /// argument, i.e. `cargo-edit-<name> <name> <args>...`.
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.path);
+        command.arg(&self.name);
+        command
+    }
+}
+
+/// Find the `cargo-edit-<name>` binaries in the directories of the `PATH`, sorted by name.
+///
+/// When a command is in several directories, the first one wins, as it does for the shell.
+pub fn find_plugins() -> Vec<Plugin> {
+    let mut plugins: Vec<Plugin> = Vec::new();
+    let dirs = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect())
+        .unwrap_or_else(Vec::new);
+
+    for dir in dirs {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let name = match entry.file_name().to_str().and_then(plugin_name) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            if !path.is_file() || plugins.iter().any(|plugin| plugin.name == name) {
+                continue;
+            }
+            plugins.push(Plugin { name, path });
+        }
+    }
+
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins
+}
+
+/// Find the `cargo-edit-<name>` binary for a command.
+pub fn find_plugin(name: &str) -> Option<Plugin> {
+    find_plugins().into_iter().find(|plugin| plugin.name == name)
+}
+
+/// Get the name of the command of a `cargo-edit-<name>` binary.
+fn plugin_name(file_name: &str) -> Option<&str> {
+    if !file_name.starts_with(PLUGIN_PREFIX) {
+        return None;
+    }
+    let name = &file_name[PLUGIN_PREFIX.len()..];
+    let name = if env::consts::EXE_SUFFIX.is_empty() {
+        name
+    } else if name.ends_with(env::consts::EXE_SUFFIX) {
+        &name[..name.len() - env::consts::EXE_SUFFIX.len()]
+    } else {
+        return None;
+    };
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn strip_leading_edit() {
+        assert_eq!(
+            strip_edit(args(&["cargo-edit", "edit", "add", "serde"])),
+            args(&["cargo-edit", "add", "serde"])
+        );
+        assert_eq!(
+            strip_edit(args(&["cargo-add", "add", "edit"])),
+            args(&["cargo-add", "add", "edit"])
+        );
+        assert_eq!(strip_edit(args(&["cargo-edit"])), args(&["cargo-edit"]));
+    }
+
+    /// Translate `arguments`, in a workspace with the members `one` and `two`.
+    fn translate(arguments: &[&str]) -> (Vec<String>, Vec<String>) {
+        translate_upstream_flags(args(arguments), |name| match name {
+            "one" | "two" => Ok(PathBuf::from(format!("ws/{}/Cargo.toml", name))),
+            _ => Err(Error::UnknownWorkspaceMember(name.to_owned())),
+        }).expect("arguments are translated")
+    }
+
+    #[test]
+    fn translate_upstream_package() {
+        assert_eq!(
+            translate(&["cargo-add", "add", "serde", "--package", "one"]).0,
+            args(&["cargo-add", "add", "serde", "--members=one"])
+        );
+        assert_eq!(
+            translate(&["cargo-add", "add", "serde", "-ptwo", "--package=t*"]).0,
+            args(&["cargo-add", "add", "serde", "--members=two", "--members=t*"])
+        );
+        // The manifest of the member replaces the one given.
+        assert_eq!(
+            translate(&["cargo-rm", "rm", "-p", "one", "serde", "--manifest-path", "Cargo.toml"]).0,
+            args(&["cargo-rm", "rm", "--manifest-path=ws/one/Cargo.toml", "serde"])
+        );
+        assert_eq!(
+            translate(&["cargo-upgrade", "upgrade", "--package", "serde", "-plog"]).0,
+            args(&["cargo-upgrade", "upgrade", "serde", "log"])
+        );
+        assert_eq!(
+            translate(&["cargo-dep", "dep", "fmt", "--package=two"]).0,
+            args(&["cargo-dep", "dep", "--manifest-path=ws/two/Cargo.toml", "fmt"])
+        );
+
+        // Without a value, the usage rejects the flag.
+        assert_eq!(
+            translate(&["cargo-upgrade", "upgrade", "-p"]).0,
+            args(&["cargo-upgrade", "upgrade", "-p"])
+        );
+        let unknown = translate_upstream_flags(args(&["cargo-rm", "rm", "-pthree"]), |name| {
+            Err(Error::UnknownWorkspaceMember(name.to_owned()))
+        });
+        assert_eq!(
+            unknown.unwrap_err().to_string(),
+            "No member of the workspace is named `three`"
+        );
+    }
+
+    #[test]
+    fn accept_upstream_dry_run() {
+        for &(bin, command) in &[
+            ("cargo-add", "add"),
+            ("cargo-rm", "rm"),
+            ("cargo-upgrade", "upgrade"),
+            ("cargo-dep", "dep"),
+        ] {
+            let arguments = [bin, command, "--dry-run"];
+            assert_eq!(translate(&arguments), (args(&arguments), vec![]));
+        }
+    }
+
+    #[test]
+    fn accept_upstream_offline() {
+        let arguments = ["cargo-add", "add", "serde", "--offline"];
+        assert_eq!(translate(&arguments), (args(&arguments), vec![]));
+
+        for &(bin, command) in &[
+            ("cargo-rm", "rm"),
+            ("cargo-upgrade", "upgrade"),
+            ("cargo-dep", "dep"),
+        ] {
+            let (translated, notes) = translate(&[bin, command, "--offline", "--quiet"]);
+            assert_eq!(translated, args(&[bin, command, "--quiet"]));
+            assert_eq!(notes.len(), 1);
+            assert!(notes[0].starts_with("note: ignoring `--offline`: "), "{}", notes[0]);
+        }
+    }
+
+    #[test]
+    fn accept_upstream_pinned() {
+        for &(bin, command) in &[
+            ("cargo-add", "add"),
+            ("cargo-rm", "rm"),
+            ("cargo-upgrade", "upgrade"),
+            ("cargo-dep", "dep"),
+        ] {
+            let (translated, notes) = translate(&[bin, command, "--pinned", "--dry-run"]);
+            assert_eq!(translated, args(&[bin, command, "--dry-run"]));
+            assert_eq!(notes.len(), 1);
+            assert!(notes[0].starts_with("note: ignoring `--pinned`: "), "{}", notes[0]);
+        }
+        assert_eq!(
+            translate(&["cargo-upgrade", "upgrade", "--pinned"]).1,
+            args(&[
+                "note: ignoring `--pinned`: `cargo upgrade` upgrades requirements pinned with `=` \
+                 in any case",
+            ])
+        );
+    }
+
+    #[test]
+    fn leave_other_arguments_alone() {
+        let arguments = ["cargo-rm", "rm", "--packages", "--manifest-path=Cargo.toml"];
+        assert_eq!(translate(&arguments), (args(&arguments), vec![]));
+        let arguments = ["cargo-add", "add", "--", "-p", "--pinned"];
+        assert_eq!(translate(&arguments), (args(&arguments), vec![]));
+        assert_eq!(
+            manifest_path_arg(&args(&["cargo-rm", "rm", "--manifest-path", "ws/Cargo.toml"])),
+            Some("ws/Cargo.toml")
+        );
+        assert_eq!(manifest_path_arg(&args(&["cargo-rm", "rm", "--", "--manifest-path=x"])), None);
+    }
+
+    #[test]
+    fn plugin_names() {
+        let exe = |name: &str| format!("{}{}", name, env::consts::EXE_SUFFIX);
+        assert_eq!(plugin_name(&exe("cargo-edit-foo")), Some("foo"));
+        assert_eq!(plugin_name(&exe("cargo-edit-foo-bar")), Some("foo-bar"));
+        assert_eq!(plugin_name(&exe("cargo-edit-")), None);
+        assert_eq!(plugin_name(&exe("cargo-add")), None);
+        assert_eq!(plugin_name(&exe("cargo-edit")), None);
+    }
+}
@@ -0,0 +1,317 @@
+//! Detection and resolution of unresolved git merge conflicts in manifests.
+use std::mem;
+
+use errors::*;
+
+/// Keys of the tables that hold dependencies.
+const DEPENDENCY_KINDS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Which side of a merge conflict to prefer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictSide {
+    /// The branch being merged into (between `<<<<<<<` and `=======`)
+    Ours,
+    /// The branch being merged (between `=======` and `>>>>>>>`)
+    Theirs,
+}
+
+/// A region between conflict markers.
+#[derive(Debug)]
+struct Conflict<'a> {
+    /// Line of the `<<<<<<<` marker (1-based)
+    start: usize,
+    /// Line of the `>>>>>>>` marker, or the last line if it is missing
+    end: usize,
+    /// Header of the table the conflict is in
+    section: Option<String>,
+    ours: Vec<&'a str>,
+    theirs: Vec<&'a str>,
+}
+
+#[derive(Debug)]
+enum Chunk<'a> {
+    Line(&'a str),
+    Conflict(Conflict<'a>),
+}
+
+/// Which part of a conflict is being read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Ours,
+    Base,
+    Theirs,
+}
+
+/// The name of a table header (e.g. `target.'cfg(unix)'.dependencies`), if `line` is one.
+pub(crate) fn header(line: &str) -> Option<String> {
+    let line = line.trim();
+    if !line.starts_with('[') {
+        return None;
+    }
+    let name = line.trim_start_matches('[');
+    name.find(']').map(|end| name[..end].trim().to_owned())
+}
+
+fn is_dependency_section(header: &str) -> bool {
+    header
+        .split('.')
+        .map(|segment| segment.trim().trim_matches('"').trim_matches('\''))
+        .any(|segment| DEPENDENCY_KINDS.contains(&segment))
+}
+
+/// Split a manifest into plain lines and conflicts.
+fn split_conflicts(contents: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut section = None;
+    let mut current: Option<Conflict> = None;
+    let mut part = Part::Ours;
+
+    for (i, line) in contents.lines().enumerate() {
+        let finished = match current {
+            None => {
+                if line.starts_with("<<<<<<<") {
+                    current = Some(Conflict {
+                        start: i + 1,
+                        end: i + 1,
+                        section: section.clone(),
+                        ours: vec![],
+                        theirs: vec![],
+                    });
+                    part = Part::Ours;
+                } else {
+                    if let Some(name) = header(line) {
+                        section = Some(name);
+                    }
+                    chunks.push(Chunk::Line(line));
+                }
+                false
+            }
+            Some(ref mut conflict) => {
+                conflict.end = i + 1;
+                if line.starts_with(">>>>>>>") {
+                    true
+                } else {
+                    if line.starts_with("|||||||") {
+                        part = Part::Base;
+                    } else if line.trim_end() == "=======" {
+                        part = Part::Theirs;
+                    } else {
+                        match part {
+                            Part::Ours => conflict.ours.push(line),
+                            Part::Base => {}
+                            Part::Theirs => conflict.theirs.push(line),
+                        }
+                    }
+                    false
+                }
+            }
+        };
+        if finished {
+            chunks.extend(current.take().map(Chunk::Conflict));
+        }
+    }
+    // A conflict without an end marker still is a conflict.
+    chunks.extend(current.map(Chunk::Conflict));
+
+    chunks
+}
+
+/// Change in bracket nesting over a line, ignoring strings and comments.
+fn nesting(line: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote = None;
+    for c in line.chars() {
+        match (c, quote) {
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (_, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => {}
+            ('#', None) => break,
+            ('[', None) | ('{', None) => depth += 1,
+            (']', None) | ('}', None) => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Split one side of a conflict into entries, by key. Comments and empty lines belong to the entry
+/// that follows them; trailing ones form an entry with an empty key. Returns `None` for lines that
+/// are not key/value pairs.
+fn entries<'a>(lines: &[&'a str]) -> Option<Vec<(String, Vec<&'a str>)>> {
+    let mut entries: Vec<(String, Vec<&'a str>)> = Vec::new();
+    let mut pending = Vec::new();
+    let mut depth = 0;
+
+    for &line in lines {
+        pending.push(line);
+        let trimmed = line.trim();
+        if depth > 0 {
+            // continuation of a multi-line value
+            entries.last_mut()?.1.append(&mut pending);
+        } else if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            let key = trimmed.splitn(2, '=').next()?;
+            if key.len() == trimmed.len() {
+                return None;
+            }
+            let key = key.trim().trim_matches('"').trim_matches('\'').to_owned();
+            entries.push((key, mem::replace(&mut pending, vec![])));
+        }
+        depth += nesting(line);
+    }
+    if !pending.is_empty() {
+        entries.push((String::new(), pending));
+    }
+
+    Some(entries)
+}
+
+/// Resolve a conflict entry by entry: entries from `side` win, entries only present on the other
+/// side are kept as well.
+fn resolve<'a>(conflict: &Conflict<'a>, side: ConflictSide) -> Result<Vec<&'a str>> {
+    let unresolvable = || Error::UnresolvableConflict(conflict.start);
+
+    let in_dependencies = conflict
+        .section
+        .as_ref()
+        .map(|section| is_dependency_section(section))
+        .unwrap_or(false);
+    let has_headers = conflict
+        .ours
+        .iter()
+        .chain(conflict.theirs.iter())
+        .any(|line| header(line).is_some());
+    if !in_dependencies || has_headers {
+        return Err(unresolvable());
+    }
+
+    let (preferred, other) = match side {
+        ConflictSide::Ours => (&conflict.ours, &conflict.theirs),
+        ConflictSide::Theirs => (&conflict.theirs, &conflict.ours),
+    };
+    let preferred = entries(preferred).ok_or_else(unresolvable)?;
+    let other = entries(other).ok_or_else(unresolvable)?;
+
+    let mut lines: Vec<&str> = preferred
+        .iter()
+        .flat_map(|&(_, ref lines)| lines.iter().cloned())
+        .collect();
+    for (key, entry) in other {
+        if !preferred.iter().any(|&(ref k, _)| *k == key) {
+            lines.extend(entry);
+        }
+    }
+    Ok(lines)
+}
+
+/// Check a manifest for unresolved merge conflicts, returning an error with their line numbers.
+pub fn check_conflicts(contents: &str) -> Result<()> {
+    let conflicts: Vec<_> = split_conflicts(contents)
+        .into_iter()
+        .filter_map(|chunk| match chunk {
+            Chunk::Conflict(conflict) => Some((conflict.start, conflict.end)),
+            Chunk::Line(_) => None,
+        })
+        .collect();
+
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::MergeConflicts(conflicts))
+    }
+}
+
+/// Resolve the unresolved merge conflicts of a manifest.
+///
+/// Only conflicts inside dependency tables can be resolved. They are resolved entry by entry: if
+/// both sides have an entry for a dependency, the one from `side` is used; entries only present on
+/// one side are kept.
+pub fn resolve_conflicts(contents: &str, side: ConflictSide) -> Result<String> {
+    let mut lines = Vec::new();
+    for chunk in split_conflicts(contents) {
+        match chunk {
+            Chunk::Line(line) => lines.push(line),
+            Chunk::Conflict(conflict) => lines.extend(resolve(&conflict, side)?),
+        }
+    }
+
+    let mut resolved = lines.join("\n");
+    if contents.ends_with('\n') {
+        resolved.push('\n');
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFLICTED: &str = r#"[package]
+name = "foo"
+
+[dependencies]
+docopt = "0.8"
+<<<<<<< HEAD
+regex = "0.2"
+# for the CLI
+pad = "0.1"
+=======
+regex = "1.0"
+serde = { version = "1.0", features = [
+    "derive",
+] }
+>>>>>>> feature
+"#;
+
+    #[test]
+    fn report_conflict_lines() {
+        match check_conflicts(CONFLICTED) {
+            Err(Error::MergeConflicts(conflicts)) => assert_eq!(conflicts, vec![(6, 15)]),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(check_conflicts("[package]\nname = \"foo\"\n").is_ok());
+    }
+
+    #[test]
+    fn resolve_per_entry() {
+        assert_eq!(
+            resolve_conflicts(CONFLICTED, ConflictSide::Ours).unwrap(),
+            r#"[package]
+name = "foo"
+
+[dependencies]
+docopt = "0.8"
+regex = "0.2"
+# for the CLI
+pad = "0.1"
+serde = { version = "1.0", features = [
+    "derive",
+] }
+"#
+        );
+        assert_eq!(
+            resolve_conflicts(CONFLICTED, ConflictSide::Theirs).unwrap(),
+            r#"[package]
+name = "foo"
+
+[dependencies]
+docopt = "0.8"
+regex = "1.0"
+serde = { version = "1.0", features = [
+    "derive",
+] }
+# for the CLI
+pad = "0.1"
+"#
+        );
+    }
+
+    #[test]
+    fn refuse_conflicts_outside_dependencies() {
+        let conflicted = "[package]\n<<<<<<< HEAD\nversion = \"0.1.0\"\n=======\n\
+                          version = \"0.2.0\"\n>>>>>>> feature\n";
+        match resolve_conflicts(conflicted, ConflictSide::Ours) {
+            Err(Error::UnresolvableConflict(2)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
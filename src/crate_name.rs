@@ -5,8 +5,97 @@ use {get_crate_name_from_github, get_crate_name_from_gitlab, get_crate_name_from
 use Dependency;
 use errors::*;
 
+/// Maximum length of a crate name on crates.io
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Names crates.io does not accept: the ones Windows reserves for devices, and the ones of the
+/// crates that come with Rust.
+const RESERVED_NAMES: &[&str] = &[
+    "alloc", "aux", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9", "con",
+    "core", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9", "nul",
+    "proc-macro", "proc_macro", "prn", "std", "test",
+];
+
+/// The name crates.io knows a crate by, for comparing names: it does not tell case, `-` and `_`
+/// apart, e.g. `Foo_bar` and `foo-bar` are the same crate.
+pub fn canonical_crate_name(name: &str) -> String {
+    name.to_lowercase().replace('_', "-")
+}
+
+/// Check that `name` follows the naming rules of crates.io: at most 64 ASCII letters, digits, `-`
+/// and `_`, starting with a letter, and not a reserved name (e.g. `nul` or `std`).
+///
+/// This is checked before looking up a crate, so a typo fails right away instead of with a
+/// "not found" from the registry.
+pub fn validate_crate_name(name: &str) -> Result<()> {
+    let invalid = |reason| {
+        Err(Error::InvalidCrateName {
+            name: name.into(),
+            reason: reason,
+        })
+    };
+
+    if name.is_empty() {
+        return invalid("crate-name-empty");
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return invalid("crate-name-too-long");
+    }
+    if let Some(c) = name.chars()
+        .find(|&c| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+    {
+        return invalid(if c.is_ascii() {
+            "crate-name-invalid-character"
+        } else {
+            "crate-name-not-ascii"
+        });
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return invalid("crate-name-leading-character");
+    }
+    if RESERVED_NAMES.contains(&name.to_lowercase().as_str()) {
+        return invalid("crate-name-reserved");
+    }
+    Ok(())
+}
+
+/// Expand a shorthand for a repository on GitHub or GitLab to its HTTPS URL: `gh:owner/repo` and
+/// `owner/repo` (e.g. `rust-lang/regex`) for GitHub, `gl:group/project` for GitLab. Returns `None`
+/// if `repo` is not a shorthand (e.g. a URL).
+pub fn expand_git_shorthand(repo: &str) -> Option<String> {
+    let (host, path) = if repo.starts_with("gh:") {
+        ("https://github.com", &repo[3..])
+    } else if repo.starts_with("gl:") {
+        ("https://gitlab.com", &repo[3..])
+    } else {
+        ("https://github.com", repo)
+    };
+    let is_name = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    let parts: Vec<_> = path.split('/').collect();
+    if parts.len() == 2 && parts.iter().all(|part| is_name(part)) {
+        Some(format!("{}/{}", host, path))
+    } else {
+        None
+    }
+}
+
+/// Operators that may follow the `@` on their own, to look up the latest version and require it
+/// with this operator (e.g. `docopt@=` for `=0.8.3`).
+const VERSION_OPERATORS: &[&str] = &["=", "~", "^", ">="];
+
+/// Schemes of the git repository URLs that are not on GitHub or GitLab.
+const GIT_URL_SCHEMES: &[&str] = &["https://", "http://", "ssh://", "git://", "file://"];
+
 /// A crate specifier. This can be a plain name (e.g. `docopt`), a name and a versionreq (e.g.
-/// `docopt@^0.8`), a URL, or a path.
+/// `docopt@^0.8`), a name and an operator for the latest version (e.g. `docopt@=`), the URL of a
+/// git repository, a shorthand for a repository (`gh:owner/repo` or `gl:group/project`), or a
+/// path. Names may be followed by features to enable, each with a leading `+` (e.g.
+/// `serde+derive+rc@1.0`).
 #[derive(Debug)]
 pub struct CrateName<'a>(&'a str);
 
@@ -16,9 +105,61 @@ impl<'a> CrateName<'a> {
         CrateName(name)
     }
 
+    /// The name of the crate, without features and version (e.g. `serde` for `serde+derive@1.0`).
+    pub fn name(&self) -> &'a str {
+        let spec = self.spec();
+        if is_path_or_url(spec) {
+            return spec;
+        }
+        spec.split('+').next().unwrap_or(spec)
+    }
+
+    /// The features given with `+` after the name (e.g. `derive` and `rc` for `serde+derive+rc`).
+    pub fn features(&self) -> Vec<String> {
+        if is_path_or_url(self.spec()) {
+            return vec![];
+        }
+        self.spec()
+            .split('+')
+            .skip(1)
+            .filter(|feature| !feature.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// The specifier without the versionreq
+    fn spec(&self) -> &'a str {
+        if self.has_scheme() {
+            // An `@` separates the user, e.g. `ssh://git@example.com/repo.git`.
+            return self.0;
+        }
+        self.0.splitn(2, '@').next().unwrap_or(self.0)
+    }
+
+    /// The part after the `@`, if any
+    fn version_req(&self) -> Option<&'a str> {
+        if self.has_scheme() {
+            return None;
+        }
+        self.0.splitn(2, '@').nth(1)
+    }
+
+    /// Does this start with the scheme of a git repository URL?
+    fn has_scheme(&self) -> bool {
+        GIT_URL_SCHEMES
+            .iter()
+            .any(|scheme| self.0.starts_with(scheme))
+    }
+
     /// Does this specify a versionreq?
     pub fn has_version(&self) -> bool {
-        self.0.contains('@')
+        self.version_req().is_some() && self.version_operator().is_none()
+    }
+
+    /// The operator to require the latest version with, if only an operator follows the `@`
+    /// (e.g. `=` for `docopt@=`).
+    pub fn version_operator(&self) -> Option<&'a str> {
+        self.version_req().filter(|req| VERSION_OPERATORS.contains(req))
     }
 
     /// Is this a URI?
@@ -27,11 +168,31 @@ impl<'a> CrateName<'a> {
     }
 
     fn is_github_url(&self) -> bool {
-        self.0.contains("https://github.com")
+        self.0.contains("https://github.com") || self.0.starts_with("gh:")
     }
 
     fn is_gitlab_url(&self) -> bool {
-        self.0.contains("https://gitlab.com")
+        self.0.contains("https://gitlab.com") || self.0.starts_with("gl:")
+    }
+
+    /// The URL of the git repository, if this is one (e.g. `https://github.com/owner/repo`,
+    /// `ssh://git@example.com/repo.git` or `gh:owner/repo`), with a shorthand expanded.
+    pub fn git_url(&self) -> Option<String> {
+        if self.is_github_url() || self.is_gitlab_url() || self.has_scheme() {
+            Some(self.repository_url())
+        } else {
+            None
+        }
+    }
+
+    /// The URL of the repository, with a shorthand (e.g. `gh:owner/repo`) expanded.
+    fn repository_url(&self) -> String {
+        if self.0.starts_with("gh:") || self.0.starts_with("gl:") {
+            if let Some(url) = expand_git_shorthand(self.0) {
+                return url;
+            }
+        }
+        self.0.to_owned()
     }
 
     fn is_path(&self) -> bool {
@@ -43,11 +204,20 @@ impl<'a> CrateName<'a> {
     /// version.
     pub fn parse_as_version(&self) -> Result<Option<Dependency>> {
         if self.has_version() {
-            let xs: Vec<_> = self.0.splitn(2, '@').collect();
-            let (name, version) = (xs[0], xs[1]);
-            semver::VersionReq::parse(version).chain_err(|| "Invalid crate version requirement")?;
+            let version = self.version_req().expect("a versionreq follows the `@`");
+            validate_crate_name(self.name())?;
+            semver::VersionReq::parse(version).map_err(Error::InvalidVersionRequirement)?;
 
-            Ok(Some(Dependency::new(name).set_version(version)))
+            let features = self.features();
+            Ok(Some(
+                Dependency::new(self.name())
+                    .set_version(version)
+                    .set_features(if features.is_empty() {
+                        None
+                    } else {
+                        Some(features)
+                    }),
+            ))
         } else {
             Ok(None)
         }
@@ -56,12 +226,14 @@ impl<'a> CrateName<'a> {
     /// Will parse this crate name on the assumption that it is a URI.
     pub fn parse_crate_name_from_uri(&self) -> Result<Dependency> {
         if self.is_github_url() {
-            if let Ok(ref crate_name) = get_crate_name_from_github(self.0) {
-                return Ok(Dependency::new(crate_name).set_git(self.0));
+            let url = self.repository_url();
+            if let Ok(ref crate_name) = get_crate_name_from_github(&url) {
+                return Ok(Dependency::new(crate_name).set_git(&url));
             }
         } else if self.is_gitlab_url() {
-            if let Ok(ref crate_name) = get_crate_name_from_gitlab(self.0) {
-                return Ok(Dependency::new(crate_name).set_git(self.0));
+            let url = self.repository_url();
+            if let Ok(ref crate_name) = get_crate_name_from_gitlab(&url) {
+                return Ok(Dependency::new(crate_name).set_git(&url));
             }
         } else if self.is_path() {
             if let Ok(ref crate_name) = get_crate_name_from_path(self.0) {
@@ -69,6 +241,121 @@ impl<'a> CrateName<'a> {
             }
         }
 
-        bail!("Unable to obtain crate informations from `{}`.\n", self.0)
+        Err(Error::UnresolvableCrate(self.0.into()))
+    }
+}
+
+/// Whether the part of a specifier before the versionreq is a path or URL, which may contain `+`.
+fn is_path_or_url(spec: &str) -> bool {
+    spec.contains(|c: char| c == '.' || c == '/' || c == '\\')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_crate_names() {
+        for name in &["docopt", "serde_json", "cargo-edit", "a", "x86"] {
+            assert!(validate_crate_name(name).is_ok(), "{} is valid", name);
+        }
+        assert!(validate_crate_name(&"a".repeat(64)).is_ok());
+    }
+
+    #[test]
+    fn invalid_crate_names() {
+        let reason = |name: &str| match validate_crate_name(name) {
+            Err(Error::InvalidCrateName { reason, .. }) => reason,
+            other => panic!("{} is invalid, but got {:?}", name, other),
+        };
+        assert_eq!(reason(""), "crate-name-empty");
+        assert_eq!(reason(&"a".repeat(65)), "crate-name-too-long");
+        assert_eq!(reason("foo bar"), "crate-name-invalid-character");
+        assert_eq!(reason("fõo"), "crate-name-not-ascii");
+        assert_eq!(reason("3d"), "crate-name-leading-character");
+        assert_eq!(reason("_foo"), "crate-name-leading-character");
+        assert_eq!(reason("nul"), "crate-name-reserved");
+        assert_eq!(reason("NUL"), "crate-name-reserved");
+        assert_eq!(reason("std"), "crate-name-reserved");
+    }
+
+    #[test]
+    fn canonical_crate_names() {
+        assert_eq!(canonical_crate_name("Foo_bar"), "foo-bar");
+        assert_eq!(canonical_crate_name("foo-bar"), canonical_crate_name("FOO_BAR"));
+        assert_ne!(canonical_crate_name("foobar"), canonical_crate_name("foo-bar"));
+    }
+
+    #[test]
+    fn parse_features() {
+        let krate = CrateName::new("serde+derive+rc@1.0");
+        assert_eq!(krate.name(), "serde");
+        assert_eq!(krate.features(), vec!["derive", "rc"]);
+        let dep = krate.parse_as_version().unwrap().unwrap();
+        assert_eq!(dep.name, "serde");
+        assert_eq!(dep.version(), Some("1.0"));
+        assert_eq!(dep.features(), Some(&["derive".to_owned(), "rc".to_owned()][..]));
+
+        let krate = CrateName::new("serde+derive");
+        assert_eq!(krate.name(), "serde");
+        assert_eq!(krate.features(), vec!["derive"]);
+        assert!(krate.parse_as_version().unwrap().is_none());
+
+        let krate = CrateName::new("docopt+std@>=");
+        assert_eq!(krate.name(), "docopt");
+        assert_eq!(krate.features(), vec!["std"]);
+        assert_eq!(krate.version_operator(), Some(">="));
+        assert!(krate.parse_as_version().unwrap().is_none());
+        assert_eq!(CrateName::new("docopt@>=0.8").version_operator(), None);
+
+        let krate = CrateName::new("docopt@1.0.0+build");
+        assert_eq!(krate.name(), "docopt");
+        assert!(krate.features().is_empty());
+
+        let krate = CrateName::new("lib/c++");
+        assert_eq!(krate.name(), "lib/c++");
+        assert!(krate.features().is_empty());
+    }
+
+    #[test]
+    fn validate_name_with_version() {
+        assert!(CrateName::new("3d@1.0").parse_as_version().is_err());
+        assert!(CrateName::new("docopt@1.0").parse_as_version().is_ok());
+    }
+
+    #[test]
+    fn git_shorthands() {
+        assert_eq!(
+            expand_git_shorthand("rust-lang/regex"),
+            Some("https://github.com/rust-lang/regex".to_owned())
+        );
+        assert_eq!(
+            expand_git_shorthand("gh:rust-lang/regex"),
+            Some("https://github.com/rust-lang/regex".to_owned())
+        );
+        assert_eq!(
+            expand_git_shorthand("gl:Polly-lang/Polly"),
+            Some("https://gitlab.com/Polly-lang/Polly".to_owned())
+        );
+        assert_eq!(expand_git_shorthand("https://github.com/rust-lang/regex"), None);
+        assert_eq!(expand_git_shorthand("../regex"), None);
+        assert_eq!(expand_git_shorthand("gh:rust-lang"), None);
+        assert!(CrateName::new("gh:rust-lang/regex").is_url_or_path());
+    }
+
+    #[test]
+    fn git_urls() {
+        assert_eq!(
+            CrateName::new("gh:rust-lang/regex").git_url(),
+            Some("https://github.com/rust-lang/regex".to_owned())
+        );
+        let krate = CrateName::new("ssh://git@example.com/regex.git");
+        assert_eq!(
+            krate.git_url(),
+            Some("ssh://git@example.com/regex.git".to_owned())
+        );
+        assert!(!krate.has_version());
+        assert_eq!(CrateName::new("../regex").git_url(), None);
+        assert_eq!(CrateName::new("regex@1.0").git_url(), None);
     }
 }
@@ -0,0 +1,139 @@
+//! The dependency sections of a manifest.
+use std::fmt;
+
+/// The kind of dependencies a section holds
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DepKind {
+    /// `[dependencies]`
+    Normal,
+    /// `[dev-dependencies]`, for tests, examples and benchmarks
+    Development,
+    /// `[build-dependencies]`, for build scripts
+    Build,
+}
+
+impl DepKind {
+    /// All kinds, in the order their sections are listed by `Manifest::get_sections`
+    pub const ALL: &'static [DepKind] = &[DepKind::Development, DepKind::Build, DepKind::Normal];
+
+    /// The key of the section (e.g. `dev-dependencies`)
+    pub fn kind_table(self) -> &'static str {
+        match self {
+            DepKind::Normal => "dependencies",
+            DepKind::Development => "dev-dependencies",
+            DepKind::Build => "build-dependencies",
+        }
+    }
+
+    /// The kind of a section key. The deprecated spellings (e.g. `dev_dependencies`) are not
+    /// accepted.
+    pub fn from_kind_table(key: &str) -> Option<DepKind> {
+        DepKind::ALL
+            .iter()
+            .cloned()
+            .find(|kind| kind.kind_table() == key)
+    }
+}
+
+/// A dependency section: the kind of dependencies, and the target they are restricted to, if any
+/// (e.g. `[target.'cfg(unix)'.dev-dependencies]`).
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DepTable {
+    kind: DepKind,
+    target: Option<String>,
+}
+
+impl Default for DepTable {
+    fn default() -> DepTable {
+        DepTable::new()
+    }
+}
+
+impl DepTable {
+    /// `[dependencies]`
+    pub fn new() -> DepTable {
+        DepTable {
+            kind: DepKind::Normal,
+            target: None,
+        }
+    }
+
+    /// Set the kind of dependencies
+    pub fn set_kind(mut self, kind: DepKind) -> DepTable {
+        self.kind = kind;
+        self
+    }
+
+    /// Restrict the section to a target (e.g. `cfg(unix)` or `x86_64-pc-windows-gnu`)
+    pub fn set_target<T: Into<String>>(mut self, target: T) -> DepTable {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// The kind of dependencies
+    pub fn kind(&self) -> DepKind {
+        self.kind
+    }
+
+    /// The target the section is restricted to, if any
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_ref().map(String::as_str)
+    }
+
+    /// The path of the section in the manifest (e.g. `["target", "cfg(unix)", "dependencies"]`)
+    pub fn to_table(&self) -> Vec<String> {
+        match self.target {
+            Some(ref target) => vec![
+                "target".to_owned(),
+                target.clone(),
+                self.kind.kind_table().to_owned(),
+            ],
+            None => vec![self.kind.kind_table().to_owned()],
+        }
+    }
+
+    /// The section at a path in the manifest, if it is a dependency section
+    pub fn from_table(path: &[String]) -> Option<DepTable> {
+        match path.len() {
+            1 => DepKind::from_kind_table(&path[0]).map(|kind| DepTable::new().set_kind(kind)),
+            3 if path[0] == "target" => DepKind::from_kind_table(&path[2])
+                .map(|kind| DepTable::new().set_kind(kind).set_target(path[1].clone())),
+            _ => None,
+        }
+    }
+}
+
+/// Describe the section, e.g. "dev-dependencies for target `cfg(unix)`".
+impl fmt::Display for DepTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.target {
+            Some(ref target) => write!(f, "{} for target `{}`", self.kind.kind_table(), target),
+            None => write!(f, "{}", self.kind.kind_table()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn section_paths() {
+        let unix = DepTable::new()
+            .set_kind(DepKind::Development)
+            .set_target("cfg(unix)");
+        assert_eq!(
+            unix.to_table(),
+            vec!["target", "cfg(unix)", "dev-dependencies"]
+        );
+        assert_eq!(DepTable::from_table(&unix.to_table()), Some(unix.clone()));
+        assert_eq!(unix.to_string(), "dev-dependencies for target `cfg(unix)`");
+
+        assert_eq!(DepTable::new().to_string(), "dependencies");
+        assert_eq!(DepTable::from_table(&["dev_dependencies".to_owned()]), None);
+        assert_eq!(
+            DepTable::from_table(&["patch".to_owned(), "crates-io".to_owned()]),
+            None
+        );
+    }
+}
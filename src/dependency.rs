@@ -3,8 +3,45 @@ use toml_edit;
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 enum DependencySource {
     Version(String),
-    Git(String),
-    Path(String),
+    // Git and path dependencies may also carry a version, which is used when publishing.
+    Git {
+        repo: String,
+        version: Option<String>,
+        reference: Option<GitReference>,
+    },
+    Path {
+        path: String,
+        version: Option<String>,
+    },
+}
+
+/// The branch, tag or revision of a git dependency
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub enum GitReference {
+    /// A branch (`branch = "..."`)
+    Branch(String),
+    /// A tag (`tag = "..."`)
+    Tag(String),
+    /// A commit (`rev = "..."`)
+    Rev(String),
+}
+
+impl GitReference {
+    /// The key of the reference in a dependency table
+    pub fn key(&self) -> &'static str {
+        match *self {
+            GitReference::Branch(_) => "branch",
+            GitReference::Tag(_) => "tag",
+            GitReference::Rev(_) => "rev",
+        }
+    }
+
+    /// The name of the branch or tag, or the commit
+    pub fn value(&self) -> &str {
+        match *self {
+            GitReference::Branch(ref v) | GitReference::Tag(ref v) | GitReference::Rev(ref v) => v,
+        }
+    }
 }
 
 /// A dependency handled by Cargo
@@ -13,6 +50,9 @@ pub struct Dependency {
     /// The name of the dependency (as it is set in its `Cargo.toml` and known to crates.io)
     pub name: String,
     optional: bool,
+    default_features: bool,
+    features: Option<Vec<String>>,
+    removed_features: Vec<String>,
     source: DependencySource,
 }
 
@@ -21,6 +61,9 @@ impl Default for Dependency {
         Dependency {
             name: "".into(),
             optional: false,
+            default_features: true,
+            features: None,
+            removed_features: vec![],
             source: DependencySource::Version("0.1.0".into()),
         }
     }
@@ -36,20 +79,61 @@ impl Dependency {
     }
 
     /// Set dependency to a given version
+    ///
+    /// For a git or path dependency, the version is written next to the `git`/`path` field.
     pub fn set_version(mut self, version: &str) -> Dependency {
-        self.source = DependencySource::Version(version.into());
+        let version = version.to_owned();
+        self.source = match self.source {
+            DependencySource::Version(_) => DependencySource::Version(version),
+            DependencySource::Git {
+                repo, reference, ..
+            } => DependencySource::Git {
+                repo: repo,
+                version: Some(version),
+                reference: reference,
+            },
+            DependencySource::Path { path, .. } => DependencySource::Path {
+                path: path,
+                version: Some(version),
+            },
+        };
         self
     }
 
     /// Set dependency to a given repository
+    ///
+    /// This replaces any version set before; call `set_version` afterwards to keep one.
     pub fn set_git(mut self, repo: &str) -> Dependency {
-        self.source = DependencySource::Git(repo.into());
+        self.source = DependencySource::Git {
+            repo: repo.into(),
+            version: None,
+            reference: None,
+        };
+        self
+    }
+
+    /// Set the branch, tag or revision of a git dependency
+    ///
+    /// Dependencies that are not git dependencies are returned unchanged.
+    pub fn set_git_reference(mut self, reference: GitReference) -> Dependency {
+        if let DependencySource::Git {
+            reference: ref mut r,
+            ..
+        } = self.source
+        {
+            *r = Some(reference);
+        }
         self
     }
 
     /// Set dependency to a given path
+    ///
+    /// This replaces any version set before; call `set_version` afterwards to keep one.
     pub fn set_path(mut self, path: &str) -> Dependency {
-        self.source = DependencySource::Path(path.into());
+        self.source = DependencySource::Path {
+            path: path.into(),
+            version: None,
+        };
         self
     }
 
@@ -59,41 +143,189 @@ impl Dependency {
         self
     }
 
+    /// Set whether the default features of the dependency are enabled
+    pub fn set_default_features(mut self, default_features: bool) -> Dependency {
+        self.default_features = default_features;
+        self
+    }
+
+    /// Set the features to enable for the dependency
+    pub fn set_features(mut self, features: Option<Vec<String>>) -> Dependency {
+        self.features = features;
+        self
+    }
+
+    /// Set the features to remove from an existing entry of the dependency (e.g. with
+    /// `cargo add --features=-std`). A new entry does not enable them in the first place.
+    pub fn set_removed_features(mut self, features: Vec<String>) -> Dependency {
+        self.removed_features = features;
+        self
+    }
+
     /// Get version of dependency
     pub fn version(&self) -> Option<&str> {
-        if let DependencySource::Version(ref version) = self.source {
-            Some(version)
+        match self.source {
+            DependencySource::Version(ref version) => Some(version),
+            DependencySource::Git { ref version, .. }
+            | DependencySource::Path { ref version, .. } => version.as_ref().map(|v| v.as_str()),
+        }
+    }
+
+    /// Get the git repository of the dependency
+    pub fn git(&self) -> Option<&str> {
+        if let DependencySource::Git { ref repo, .. } = self.source {
+            Some(repo)
+        } else {
+            None
+        }
+    }
+
+    /// Get the branch, tag or revision of a git dependency
+    pub fn git_reference(&self) -> Option<&GitReference> {
+        if let DependencySource::Git { ref reference, .. } = self.source {
+            reference.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Get the path of the dependency
+    pub fn path(&self) -> Option<&str> {
+        if let DependencySource::Path { ref path, .. } = self.source {
+            Some(path)
         } else {
             None
         }
     }
 
+    /// Whether the dependency comes from the registry (i.e. is neither a git nor a path
+    /// dependency)
+    pub fn is_registry(&self) -> bool {
+        match self.source {
+            DependencySource::Version(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Get whether the dependency is optional
+    pub fn optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Get whether the default features of the dependency are enabled
+    pub fn default_features(&self) -> bool {
+        self.default_features
+    }
+
+    /// Get the features enabled for the dependency
+    pub fn features(&self) -> Option<&[String]> {
+        self.features.as_ref().map(|f| f.as_slice())
+    }
+
+    /// Get the features to remove from an existing entry of the dependency
+    pub fn removed_features(&self) -> &[String] {
+        &self.removed_features
+    }
+
+    /// Read a dependency from its entry in a dependency table
+    ///
+    /// Returns `None` for entries that do not name a version, git repository or path (e.g. ones
+    /// inherited from the workspace with `workspace = true`).
+    pub fn from_toml(name: &str, item: &toml_edit::Item) -> Option<Dependency> {
+        if let Some(version) = item.as_str() {
+            return Some(Dependency::new(name).set_version(version));
+        }
+        item.as_table_like()?;
+
+        let dep = if let Some(repo) = item["git"].as_str() {
+            let dep = Dependency::new(name).set_git(repo);
+            let reference = item["branch"]
+                .as_str()
+                .map(|b| GitReference::Branch(b.into()))
+                .or_else(|| item["tag"].as_str().map(|t| GitReference::Tag(t.into())))
+                .or_else(|| item["rev"].as_str().map(|r| GitReference::Rev(r.into())));
+            match reference {
+                Some(reference) => dep.set_git_reference(reference),
+                None => dep,
+            }
+        } else if let Some(path) = item["path"].as_str() {
+            Dependency::new(name).set_path(path)
+        } else if item["version"].as_str().is_some() {
+            Dependency::new(name)
+        } else {
+            return None;
+        };
+
+        let dep = match item["version"].as_str() {
+            Some(version) => dep.set_version(version),
+            None => dep,
+        };
+        let features = item["features"].as_array().map(|features| {
+            features
+                .iter()
+                .filter_map(|f| f.as_str().map(String::from))
+                .collect()
+        });
+        Some(
+            dep.set_optional(item["optional"].as_bool().unwrap_or(false))
+                .set_default_features(item["default-features"].as_bool().unwrap_or(true))
+                .set_features(features),
+        )
+    }
+
     /// Convert dependency to TOML
     ///
     /// Returns a tuple with the dependency's name and either the version as a `String`
     /// or the path/git repository as an `InlineTable`.
-    /// (If the dependency is set as `optional`, an `InlineTable` is returned in any case.)
+    /// (If the dependency is set as `optional`, disables its default features or has features set,
+    /// an `InlineTable` is returned in any case.)
     pub fn to_toml(&self) -> (String, toml_edit::Item) {
-        let data: toml_edit::Item = match (self.optional, self.source.clone()) {
+        let data: toml_edit::Item = match (
+            self.optional || !self.default_features || self.features.is_some(),
+            self.source.clone(),
+        ) {
             // Extra short when version flag only
             (false, DependencySource::Version(v)) => toml_edit::value(v),
             // Other cases are represented as an inline table
-            (optional, source) => {
+            (_, source) => {
                 let mut data = toml_edit::InlineTable::default();
 
                 match source {
                     DependencySource::Version(v) => {
                         data.get_or_insert("version", v);
                     }
-                    DependencySource::Git(v) => {
-                        data.get_or_insert("git", v);
+                    DependencySource::Git {
+                        repo,
+                        version,
+                        reference,
+                    } => {
+                        if let Some(v) = version {
+                            data.get_or_insert("version", v);
+                        }
+                        data.get_or_insert("git", repo);
+                        if let Some(reference) = reference {
+                            data.get_or_insert(reference.key(), reference.value());
+                        }
+                    }
+                    DependencySource::Path { path, version } => {
+                        if let Some(v) = version {
+                            data.get_or_insert("version", v);
+                        }
+                        data.get_or_insert("path", path);
                     }
-                    DependencySource::Path(v) => {
-                        data.get_or_insert("path", v);
+                }
+                if !self.default_features {
+                    data.get_or_insert("default-features", false);
+                }
+                if let Some(ref features) = self.features {
+                    let mut array = toml_edit::Array::default();
+                    for feature in features {
+                        array.push(feature.as_str());
                     }
+                    data.get_or_insert("features", toml_edit::Value::Array(array));
                 }
                 if self.optional {
-                    data.get_or_insert("optional", optional);
+                    data.get_or_insert("optional", self.optional);
                 }
 
                 data.fmt();
@@ -104,3 +336,35 @@ impl Dependency {
         (self.name.clone(), data)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_through_toml() {
+        let deps = vec![
+            Dependency::new("a").set_version("0.1"),
+            Dependency::new("b").set_version("1.0").set_optional(true),
+            Dependency::new("c")
+                .set_git("https://github.com/me/c")
+                .set_git_reference(GitReference::Tag("v1".into()))
+                .set_features(Some(vec!["std".into()])),
+            Dependency::new("d").set_path("../d").set_version("0.2"),
+            Dependency::new("e")
+                .set_version("0.4")
+                .set_default_features(false)
+                .set_features(Some(vec!["clock".into()])),
+        ];
+        for dep in deps {
+            let (name, item) = dep.to_toml();
+            assert_eq!(Dependency::from_toml(&name, &item), Some(dep));
+        }
+    }
+
+    #[test]
+    fn inherited_dependencies_are_not_understood() {
+        let manifest: toml_edit::Document = "a = { workspace = true }\n".parse().unwrap();
+        assert_eq!(Dependency::from_toml("a", &manifest["a"]), None);
+    }
+}
@@ -1,46 +1,226 @@
-error_chain!{
-    errors {
-        /// Failed to fetch crate from crates.io
-        FetchVersionFailure {
-            description("Failed to fetch crate version from crates.io")
-        }
-        /// Invalid JSON from crates.io response
-        InvalidCratesIoJson {
-            description("Invalid JSON (the crate may not exist)")
-        }
-        /// No versions available
-        NoVersionsAvailable {
-            description("No available versions exist. Either all were yanked \
-                         or only prerelease versions exist. Trying with the \
-                         --allow-prerelease flag might solve the issue."
-            )
-        }
-        /// Unable to parse external Cargo.toml
-        ParseCargoToml {
-            description("Unable to parse external Cargo.toml")
-        }
-        /// Cargo.toml could not be found.
-        MissingManifest {
-            description("Unable to find Cargo.toml")
-        }
-        /// Cargo.toml is valid toml, but doesn't contain the expected fields
-        InvalidManifest {
-            description("Cargo.toml missing expected `package` or `project` fields")
-        }
-        /// Found a workspace manifest when expecting a normal manifest
-        UnexpectedRootManifest {
-            description("Found virtual manifest, but this command requires running against an \
-                         actual package in this workspace.")
-        }
-        /// The TOML table could not be found.
-        NonExistentTable(table: String) {
-            description("non existent table")
-            display("The table `{}` could not be found.", table)
-        }
-        /// The dependency could not be found.
-        NonExistentDependency(name: String, table: String) {
-            description("non existent dependency")
-            display("The dependency `{}` could not be found in `{}`.", name, table)
+//! Errors returned by `cargo_edit`.
+use std::error::Error as StdError;
+use std::{fmt, io, result};
+
+use cargo_metadata;
+use reqwest;
+use semver;
+use serde_json;
+use toml_edit;
+
+/// A `Result` with `cargo_edit::Error` as error type
+pub type Result<T> = result::Result<T, Error>;
+
+/// Errors returned by `cargo_edit`
+#[derive(Debug)]
+pub enum Error {
+    /// An I/O operation failed
+    Io {
+        /// What was being attempted
+        action: &'static str,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// A request to crates.io or a git host failed
+    Network {
+        /// What was being attempted
+        action: &'static str,
+        /// The underlying error
+        source: reqwest::Error,
+    },
+    /// Invalid JSON from crates.io response
+    InvalidCratesIoJson(serde_json::Error),
+    /// No versions available
+    NoVersionsAvailable,
+    /// No version of a crate matches the requested version requirement
+    NoMatchingVersion {
+        /// Name of the crate
+        name: String,
+        /// The version requirement
+        req: String,
+    },
+    /// No version of a crate was published long enough ago (see `--max-age`)
+    NoVersionsOldEnough {
+        /// Name of the crate
+        name: String,
+        /// The minimum age of a version, in days
+        days: u64,
+    },
+    /// The crate could not be found in the registry
+    CrateNotFound(String),
+    /// The registry answered the lookup of a crate with an error status other than "not found",
+    /// e.g. a server error or a "429 Too Many Requests" that persisted after the retries
+    RegistryStatus {
+        /// Name of the crate
+        name: String,
+        /// The HTTP status code
+        status: u16,
+    },
+    /// The lookup of a crate was not sent because another lookup of the same batch failed
+    LookupCancelled(String),
+    /// The crate name does not follow the naming rules of crates.io
+    InvalidCrateName {
+        /// The name
+        name: String,
+        /// Which rule it breaks, as the ID of its message in the catalog (e.g.
+        /// `crate-name-reserved`)
+        reason: &'static str,
+    },
+    /// The version requirement could not be parsed
+    InvalidVersionRequirement(semver::ReqParseError),
+    /// Neither a registry crate nor a usable git repository or path could be found for a crate
+    /// specifier
+    UnresolvableCrate(String),
+    /// The git repository URL could not be parsed
+    InvalidGitUrl(String),
+    /// The branch, tag or revision can not be passed to `git` (e.g. it starts with `-`)
+    InvalidGitReference(String),
+    /// A `git` command failed
+    GitCommand {
+        /// The arguments of `git`
+        command: String,
+        /// What `git` printed to stderr
+        message: String,
+    },
+    /// Unable to parse external Cargo.toml
+    ParseCargoToml,
+    /// Cargo.toml could not be found.
+    ManifestNotFound,
+    /// Cargo.toml could not be parsed
+    ManifestParse(Box<Error>),
+    /// The manifest is not valid TOML
+    InvalidToml(toml_edit::TomlError),
+    /// Cargo.toml is valid toml, but doesn't contain the expected fields
+    InvalidManifest,
+    /// A dependency section (e.g. `target.'cfg(unix)'.dependencies`) is not a table
+    InvalidSection(String),
+    /// Found a workspace manifest when expecting a normal manifest
+    UnexpectedRootManifest,
+    /// The manifest is not the root of a workspace (it has no `[workspace]` table)
+    NotWorkspaceRoot,
+    /// No manifest with a `[workspace]` table was found above a package
+    WorkspaceRootNotFound,
+    /// The TOML table could not be found.
+    SectionMissing(String),
+    /// The dependency (first field) could not be found in the table (second field).
+    DependencyMissing(String, String),
+    /// A dependency table does not have a `version` field
+    MissingVersionField,
+    /// The placement for new sections is not known
+    InvalidSectionPlacement(String),
+    /// The prerelease channel is not known
+    InvalidPrereleaseChannel(String),
+    /// The manifest has unresolved merge conflicts, between these (1-based) lines
+    MergeConflicts(Vec<(usize, usize)>),
+    /// The merge conflict starting at this line can not be resolved automatically
+    UnresolvableConflict(usize),
+    /// The members of the workspace could not be determined
+    WorkspaceMetadata(cargo_metadata::Error),
+    /// No member of the workspace has this name
+    UnknownWorkspaceMember(String),
+    /// The answer to an interactive prompt could not be understood
+    InvalidSelection {
+        /// The invalid part of the answer
+        input: String,
+        /// Number of choices offered
+        len: usize,
+    },
+}
+
+impl Error {
+    /// Wrap an I/O error, describing what was being attempted.
+    pub(crate) fn io(action: &'static str) -> impl FnOnce(io::Error) -> Error {
+        move |source| Error::Io { action, source }
+    }
+
+    /// Wrap a network error, describing what was being attempted.
+    pub(crate) fn network(action: &'static str) -> impl FnOnce(reqwest::Error) -> Error {
+        move |source| Error::Network { action, source }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            Error::Io { action, .. } | Error::Network { action, .. } => action.to_owned(),
+            Error::InvalidCratesIoJson(_) => message!("invalid-crates-io-json"),
+            Error::NoVersionsAvailable => message!("no-versions-available"),
+            Error::NoMatchingVersion { ref name, ref req } => {
+                message!("no-matching-version", name = name, req = req)
+            }
+            Error::NoVersionsOldEnough { ref name, days } => {
+                message!("no-versions-old-enough", name = name, days = days)
+            }
+            Error::CrateNotFound(ref name) => message!("crate-not-found", name = name),
+            Error::RegistryStatus { ref name, status } => {
+                message!("registry-status", name = name, status = status)
+            }
+            Error::LookupCancelled(ref name) => message!("lookup-cancelled", name = name),
+            Error::InvalidCrateName {
+                ref name,
+                ref reason,
+            } => message!("invalid-crate-name", name = name, reason = message!(reason)),
+            Error::InvalidVersionRequirement(_) => message!("invalid-version-requirement"),
+            Error::UnresolvableCrate(ref spec) => message!("unresolvable-crate", spec = spec),
+            Error::InvalidGitUrl(ref url) => message!("invalid-git-url", url = url),
+            Error::InvalidGitReference(ref reference) => {
+                message!("invalid-git-reference", reference = reference)
+            }
+            Error::GitCommand {
+                ref command,
+                ref message,
+            } => message!("git-command", command = command, message = message),
+            Error::ParseCargoToml => message!("parse-cargo-toml"),
+            Error::ManifestNotFound => message!("manifest-not-found"),
+            Error::ManifestParse(_) => message!("manifest-parse"),
+            Error::InvalidToml(_) => message!("invalid-toml"),
+            Error::InvalidManifest => message!("invalid-manifest"),
+            Error::InvalidSection(ref table) => message!("invalid-section", table = table),
+            Error::UnexpectedRootManifest => message!("unexpected-root-manifest"),
+            Error::NotWorkspaceRoot => message!("not-workspace-root"),
+            Error::WorkspaceRootNotFound => message!("workspace-root-not-found"),
+            Error::SectionMissing(ref table) => message!("section-missing", table = table),
+            Error::DependencyMissing(ref name, ref table) => {
+                message!("dependency-missing", name = name, table = table)
+            }
+            Error::MissingVersionField => message!("missing-version-field"),
+            Error::InvalidSectionPlacement(ref placement) => {
+                message!("invalid-section-placement", placement = placement)
+            }
+            Error::InvalidPrereleaseChannel(ref channel) => {
+                message!("invalid-prerelease-channel", channel = channel)
+            }
+            Error::MergeConflicts(ref conflicts) => {
+                let lines: Vec<_> = conflicts
+                    .iter()
+                    .map(|&(start, end)| format!("{}-{}", start, end))
+                    .collect();
+                message!("merge-conflicts", lines = lines.join(", "))
+            }
+            Error::UnresolvableConflict(line) => message!("unresolvable-conflict", line = line),
+            Error::WorkspaceMetadata(_) => message!("workspace-metadata"),
+            Error::UnknownWorkspaceMember(ref name) => {
+                message!("unknown-workspace-member", name = name)
+            }
+            Error::InvalidSelection { ref input, len } => {
+                message!("invalid-selection", input = input, len = len)
+            }
+        };
+        f.write_str(&text)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::Io { ref source, .. } => Some(source),
+            Error::Network { ref source, .. } => Some(source),
+            Error::InvalidCratesIoJson(ref e) => Some(e),
+            Error::InvalidVersionRequirement(ref e) => Some(e),
+            Error::ManifestParse(ref e) => Some(&**e),
+            Error::InvalidToml(ref e) => Some(e),
+            Error::WorkspaceMetadata(ref e) => Some(e),
+            _ => None,
         }
     }
 }
@@ -3,40 +3,92 @@ use regex::Regex;
 use reqwest;
 use semver;
 use serde_json as json;
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::io::Read;
 use std::path::Path;
-use std::time::Duration;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use env_proxy;
 
+use cache::Cache;
+use crate_name::validate_crate_name;
 use errors::*;
+use index::get_latest_dependency_from_index;
+use prerelease::Prereleases;
+use warnings::{WarningKind, Warnings};
 
 const REGISTRY_HOST: &str = "https://crates.io";
 
 #[derive(Deserialize)]
 struct Versions {
+    #[serde(rename = "crate", default)] krate: Option<CrateMetadata>,
     versions: Vec<CrateVersion>,
 }
 
+/// What crates.io says about a crate as a whole
+#[derive(Deserialize)]
+struct CrateMetadata {
+    #[serde(default)] description: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct CrateVersion {
     #[serde(rename = "crate")] name: String,
     #[serde(rename = "num")] version: semver::Version,
     yanked: bool,
+    #[serde(default)] license: Option<String>,
+    #[serde(default)] rust_version: Option<String>,
+    #[serde(default)] edition: Option<String>,
+    #[serde(default)] created_at: Option<String>,
+    #[serde(default)] checksum: Option<String>,
+}
+
+/// Metadata of a published version of a crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateInfo {
+    /// The SPDX license expression, if the crate declares one
+    pub license: Option<String>,
+    /// The minimum supported Rust version (`rust-version`), if the crate declares one
+    pub rust_version: Option<String>,
+    /// The Rust edition of the crate (e.g. `2021`), if the registry knows it
+    pub edition: Option<String>,
 }
 
 /// Query latest version from crates.io
 ///
-/// The latest version will be returned as a `Dependency`. This will fail, when
+/// The latest version will be returned as a `Dependency`. Warnings (e.g. about a differently named
+/// crate) are printed to stdout. This will fail, when
 ///
 /// - there is no Internet connection,
 /// - the response from crates.io is an error or in an incorrect format,
 /// - or when a crate with the given name does not exist on crates.io.
-pub fn get_latest_dependency(crate_name: &str, flag_allow_prerelease: bool) -> Result<Dependency> {
+pub fn get_latest_dependency(crate_name: &str, prereleases: Prereleases) -> Result<Dependency> {
+    let mut warnings = Warnings::new();
+    let dep = get_latest_dependency_with_warnings(crate_name, prereleases, &mut warnings);
+    warnings.print();
+    dep
+}
+
+/// Query latest version from crates.io, like `get_latest_dependency`, but collect the warnings
+/// instead of printing them.
+///
+/// The versions are listed by the index file of the crate, which takes a single request however
+/// many versions were published. Crates that are not found in the index (e.g. because of a
+/// `-`/`_` mix-up) are looked up with the crates.io API.
+pub fn get_latest_dependency_with_warnings(
+    crate_name: &str,
+    prereleases: Prereleases,
+    warnings: &mut Warnings,
+) -> Result<Dependency> {
+    validate_crate_name(crate_name)?;
+
     if env::var("CARGO_IS_TEST").is_ok() {
         // We are in a simulated reality. Nothing is real here.
         // FIXME: Use actual test handling code.
-        let new_version = if flag_allow_prerelease {
+        let new_version = if prereleases != Prereleases::None {
             format!("{}--PRERELEASE_VERSION_TEST", crate_name)
         } else {
             format!("{}--CURRENT_VERSION_TEST", crate_name)
@@ -45,39 +97,536 @@ pub fn get_latest_dependency(crate_name: &str, flag_allow_prerelease: bool) -> R
         return Ok(Dependency::new(crate_name).set_version(&new_version));
     }
 
+    match get_latest_dependency_from_index(crate_name, prereleases, warnings) {
+        Err(Error::CrateNotFound(_)) => {
+            get_latest_dependency_from_api(crate_name, prereleases, warnings)
+        }
+        result => result,
+    }
+}
+
+/// Query the latest version with the crates.io API, which also finds crates named with `-` and
+/// `_` mixed up.
+pub(crate) fn get_latest_dependency_from_api(
+    crate_name: &str,
+    prereleases: Prereleases,
+    warnings: &mut Warnings,
+) -> Result<Dependency> {
     let crate_versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
 
-    let dep = read_latest_version(&crate_versions, flag_allow_prerelease)?;
+    let dep = read_latest_version(&crate_versions, prereleases, warnings)?;
 
     if dep.name != crate_name {
-        println!("WARN: Added `{}` instead of `{}`", dep.name, crate_name);
+        warnings.push(
+            WarningKind::RenamedCrate,
+            format!("Added `{}` instead of `{}`", dep.name, crate_name),
+        );
     }
 
     Ok(dep)
 }
 
-// Checks whether a version object is a stable release
-fn version_is_stable(version: &CrateVersion) -> bool {
-    !version.version.is_prerelease()
+/// Query the latest version matching `req` with the crates.io API, like
+/// `get_latest_dependency_from_api`. The dependency is `None` if no published version matches.
+pub(crate) fn get_compatible_dependency_from_api(
+    crate_name: &str,
+    req: &semver::VersionReq,
+    prereleases: Prereleases,
+    warnings: &mut Warnings,
+) -> Result<Option<Dependency>> {
+    let crate_versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
+
+    let dep = read_compatible_version(&crate_versions, req, prereleases);
+
+    if let Some(ref dep) = dep {
+        if dep.name != crate_name {
+            warnings.push(
+                WarningKind::RenamedCrate,
+                format!("Added `{}` instead of `{}`", dep.name, crate_name),
+            );
+        }
+    }
+
+    Ok(dep)
+}
+
+/// Query the latest version from crates.io that was published at least `max_age_days` days ago
+///
+/// Like `get_latest_dependency_with_warnings`, but newer versions are passed over, as a cooldown
+/// against malicious releases that have not been noticed yet. The passed over versions are
+/// reported as a warning. Versions without a publication date are passed over, too.
+pub fn get_latest_dependency_with_max_age(
+    crate_name: &str,
+    prereleases: Prereleases,
+    max_age_days: u64,
+    warnings: &mut Warnings,
+) -> Result<Dependency> {
+    validate_crate_name(crate_name)?;
+
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality. Every version is old enough.
+        return get_latest_dependency_with_warnings(crate_name, prereleases, warnings);
+    }
+
+    let crate_versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
+    let today = days_since_epoch(SystemTime::now());
+    let dep = read_latest_version_before(
+        &crate_versions,
+        prereleases,
+        today.saturating_sub(max_age_days),
+        max_age_days,
+        warnings,
+    )?;
+
+    if dep.name != crate_name {
+        warnings.push(
+            WarningKind::RenamedCrate,
+            format!("Added `{}` instead of `{}`", dep.name, crate_name),
+        );
+    }
+
+    Ok(dep)
+}
+
+/// What became of a published version when the latest version of a crate was picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionVerdict {
+    /// The version that is picked
+    Picked,
+    /// A prerelease the prerelease settings exclude
+    Prerelease,
+    /// A yanked version
+    Yanked,
+    /// Published within the last `--max-age` days, or without a publication date
+    TooRecent,
+    /// Older than the picked version, so not considered
+    Older,
+}
+
+impl fmt::Display for VersionVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VersionVerdict::Picked => write!(f, "picked"),
+            VersionVerdict::Prerelease => write!(f, "skipped: prerelease"),
+            VersionVerdict::Yanked => write!(f, "skipped: yanked"),
+            VersionVerdict::TooRecent => write!(f, "skipped: too recent"),
+            VersionVerdict::Older => write!(f, "older"),
+        }
+    }
+}
+
+/// A published version of a crate and its verdict
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VersionChoice {
+    /// The version
+    pub version: String,
+    /// What became of it
+    pub verdict: VersionVerdict,
+    /// The day it was published (`YYYY-MM-DD`), if known
+    pub published: Option<String>,
+    /// The minimum supported Rust version (`rust-version`) it declares, if any
+    pub rust_version: Option<String>,
+}
+
+/// How the latest version of a crate is picked: every published version, newest first, with the
+/// filter that passed over it, if any.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VersionExplanation {
+    /// Name of the crate, as published
+    pub name: String,
+    /// The published versions, newest first
+    pub versions: Vec<VersionChoice>,
+}
+
+impl VersionExplanation {
+    /// The version that is picked, if any
+    pub fn picked(&self) -> Option<&VersionChoice> {
+        self.versions
+            .iter()
+            .find(|v| v.verdict == VersionVerdict::Picked)
+    }
+}
+
+/// Explain how the latest version of a crate is picked, like `get_latest_dependency_with_warnings`
+/// (or `get_latest_dependency_with_max_age` with `max_age_days`) would pick it: prereleases are
+/// filtered out first, then yanked versions, then the ones published too recently. The newest of
+/// the remaining versions is picked.
+pub fn explain_latest_version(
+    crate_name: &str,
+    prereleases: Prereleases,
+    max_age_days: Option<u64>,
+) -> Result<VersionExplanation> {
+    validate_crate_name(crate_name)?;
+
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality, with a few versions of every crate. Every version is old
+        // enough.
+        let versions = ["1.0.0-rc.1", "0.9.1", "0.9.0", "0.8.0"]
+            .iter()
+            .map(|version| CrateVersion {
+                name: crate_name.into(),
+                version: semver::Version::parse(version).expect("valid test version"),
+                yanked: *version == "0.9.1",
+                license: None,
+                rust_version: None,
+                edition: None,
+                created_at: None,
+                checksum: None,
+            })
+            .collect();
+        return Ok(explain_versions(
+            &Versions {
+                krate: None,
+                versions: versions,
+            },
+            prereleases,
+            None,
+        ));
+    }
+
+    let crate_versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
+    let today = days_since_epoch(SystemTime::now());
+    let cutoff = max_age_days.map(|days| today.saturating_sub(days));
+    Ok(explain_versions(&crate_versions, prereleases, cutoff))
+}
+
+/// Give every version its verdict, like `read_latest_version` (or `read_latest_version_before`
+/// with a `cutoff`) picks the latest version.
+fn explain_versions(
+    versions: &Versions,
+    prereleases: Prereleases,
+    cutoff: Option<u64>,
+) -> VersionExplanation {
+    let mut picked = false;
+    let choices = versions
+        .versions
+        .iter()
+        .map(|v| {
+            let published = v.created_at.as_ref().and_then(|d| parse_date(d));
+            let verdict = if !prereleases.accepts(&v.version) {
+                VersionVerdict::Prerelease
+            } else if v.yanked {
+                VersionVerdict::Yanked
+            } else if cutoff.map_or(false, |cutoff| published.map_or(true, |p| p > cutoff)) {
+                VersionVerdict::TooRecent
+            } else if picked {
+                VersionVerdict::Older
+            } else {
+                picked = true;
+                VersionVerdict::Picked
+            };
+            VersionChoice {
+                version: v.version.to_string(),
+                verdict: verdict,
+                // `parse_date` only accepts timestamps starting with a `YYYY-MM-DD` day.
+                published: match (published, v.created_at.as_ref()) {
+                    (Some(_), Some(date)) => Some(date[..10].to_owned()),
+                    _ => None,
+                },
+                rust_version: v.rust_version.clone(),
+            }
+        })
+        .collect();
+
+    VersionExplanation {
+        name: versions
+            .versions
+            .first()
+            .map(|v| v.name.clone())
+            .unwrap_or_default(),
+        versions: choices,
+    }
+}
+
+/// Query the license and minimum supported Rust version of a crate from crates.io
+///
+/// The metadata of the latest non-yanked version matching `version_req` is returned. This will
+/// fail, when
+///
+/// - there is no Internet connection,
+/// - the response from crates.io is an error or in an incorrect format,
+/// - or when no published version of the crate matches the requirement.
+pub fn get_crate_info(crate_name: &str, version_req: &str) -> Result<CrateInfo> {
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality. Every crate has the same metadata.
+        return Ok(CrateInfo {
+            license: Some("MIT OR Apache-2.0".into()),
+            rust_version: Some("1.31".into()),
+            edition: Some("2018".into()),
+        });
+    }
+
+    validate_crate_name(crate_name)?;
+    let req = semver::VersionReq::parse(version_req).map_err(Error::InvalidVersionRequirement)?;
+    let versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
+    read_crate_info(&versions, &req).ok_or_else(|| Error::NoMatchingVersion {
+        name: crate_name.into(),
+        req: version_req.into(),
+    })
+}
+
+/// Read the metadata of the latest non-yanked version matching `req`
+fn read_crate_info(versions: &Versions, req: &semver::VersionReq) -> Option<CrateInfo> {
+    versions
+        .versions
+        .iter()
+        .find(|v| !v.yanked && req.matches(&v.version))
+        .map(|v| CrateInfo {
+            license: v.license.clone(),
+            rust_version: v.rust_version.clone(),
+            edition: v.edition.clone(),
+        })
+}
+
+/// Query the SHA-256 checksum of the `.crate` file of a version with the crates.io API, which is
+/// `None` if crates.io does not list one. Fails like `get_crate_info`, and with
+/// `Error::NoMatchingVersion` if the version was not published.
+pub(crate) fn get_api_checksum(
+    crate_name: &str,
+    version: &semver::Version,
+) -> Result<Option<String>> {
+    let versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
+    read_checksum(&versions, version).ok_or_else(|| Error::NoMatchingVersion {
+        name: crate_name.into(),
+        req: format!("={}", version),
+    })
+}
+
+/// Read the checksum of a version, if it was published
+fn read_checksum(versions: &Versions, version: &semver::Version) -> Option<Option<String>> {
+    versions
+        .versions
+        .iter()
+        .find(|v| v.version == *version)
+        .map(|v| v.checksum.clone())
+}
+
+/// Query the latest version of a crate from crates.io whose edition is `edition` or an older one,
+/// e.g. for a package of that edition. Versions without a known edition are passed over, so the
+/// result is `None` if no version is known to fit. This will fail, when
+///
+/// - there is no Internet connection,
+/// - or the response from crates.io is an error or in an incorrect format.
+pub fn get_latest_version_for_edition(
+    crate_name: &str,
+    edition: &str,
+    prereleases: Prereleases,
+) -> Result<Option<String>> {
+    validate_crate_name(crate_name)?;
+
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality, where every crate has a version for every edition.
+        return Ok(Some(format!("{}--EDITION_{}_TEST", crate_name, edition)));
+    }
+
+    let versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
+    Ok(read_latest_version_for_edition(&versions, edition, prereleases))
+}
+
+/// Read the latest non-yanked version of `edition` or an older edition
+fn read_latest_version_for_edition(
+    versions: &Versions,
+    edition: &str,
+    prereleases: Prereleases,
+) -> Option<String> {
+    let year = |edition: &str| edition.trim().parse::<u32>().ok();
+    let max = year(edition)?;
+    versions
+        .versions
+        .iter()
+        .filter(|v| !v.yanked && prereleases.accepts(&v.version))
+        .find(|v| {
+            v.edition
+                .as_ref()
+                .and_then(|e| year(e))
+                .map_or(false, |year| year <= max)
+        })
+        .map(|v| v.version.to_string())
+}
+
+/// Query the crate that replaces a crate on crates.io, if its description says that it was renamed
+/// or deprecated in favour of another crate (e.g. "Renamed to `foo`", "Deprecated, use `foo`
+/// instead"). This will fail, when
+///
+/// - there is no Internet connection,
+/// - or the response from crates.io is an error or in an incorrect format.
+pub fn get_crate_successor(crate_name: &str) -> Result<Option<String>> {
+    validate_crate_name(crate_name)?;
+
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality, where the crates ending in `-renamed` were renamed to the
+        // crate without that suffix.
+        let successor = crate_name.trim_end_matches("-renamed");
+        return Ok(if successor == crate_name {
+            None
+        } else {
+            Some(successor.to_owned())
+        });
+    }
+
+    let versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
+    Ok(versions
+        .krate
+        .as_ref()
+        .and_then(|krate| krate.description.as_ref())
+        .and_then(|description| read_successor(crate_name, description)))
+}
+
+/// Read the name of the crate that replaces `crate_name` from its description
+fn read_successor(crate_name: &str, description: &str) -> Option<String> {
+    // The name may be quoted, and follow "the" or "crate".
+    let markers = [
+        concat!(
+            r#"(?i)\b(?:renamed to|deprecated in favou?r of|superseded by|replaced by)\s+"#,
+            r#"(?:the\s+)?(?:crate\s+)?[`'"\[]*([a-zA-Z][-_0-9a-zA-Z]*)"#
+        ),
+        concat!(
+            r#"(?i)\buse\s+(?:the\s+)?(?:crate\s+)?[`'"\[]*([a-zA-Z][-_0-9a-zA-Z]*)"#,
+            r#"[`'"\]]*(?:\s+crate)?\s+instead\b"#
+        ),
+    ];
+    markers
+        .iter()
+        .filter_map(|marker| Regex::new(marker).expect("valid regex").captures(description))
+        .filter_map(|cap| cap.get(1).map(|successor| successor.as_str().to_owned()))
+        // The standard library is no crate to add.
+        .find(|successor| {
+            validate_crate_name(successor).is_ok()
+                && !successor.eq_ignore_ascii_case(crate_name)
+                && !["std", "core", "alloc"].contains(&successor.as_str())
+        })
 }
 
 /// Read latest version from Versions structure
 ///
 /// Assumes the version are sorted so that the first non-yanked version is the
-/// latest, and thus the one we want.
-fn read_latest_version(versions: &Versions, flag_allow_prerelease: bool) -> Result<Dependency> {
-    let latest = versions
+/// latest, and thus the one we want. Newer versions that were yanked are reported as a warning.
+fn read_latest_version(
+    versions: &Versions,
+    prereleases: Prereleases,
+    warnings: &mut Warnings,
+) -> Result<Dependency> {
+    let candidates: Vec<_> = versions
         .versions
         .iter()
-        .filter(|&v| flag_allow_prerelease || version_is_stable(v))
+        .filter(|&v| prereleases.accepts(&v.version))
+        .collect();
+    let latest = candidates
+        .iter()
         .find(|&v| !v.yanked)
-        .ok_or(ErrorKind::NoVersionsAvailable)?;
+        .ok_or(Error::NoVersionsAvailable)?;
+
+    let yanked: Vec<_> = candidates
+        .iter()
+        .take_while(|&v| v.yanked)
+        .map(|v| v.version.to_string())
+        .collect();
+    if !yanked.is_empty() {
+        warnings.push(
+            WarningKind::YankedVersion,
+            format!(
+                "Newer versions of `{}` were yanked ({}), using {}",
+                latest.name,
+                yanked.join(", "),
+                latest.version
+            ),
+        );
+    }
 
     let name = &latest.name;
     let version = latest.version.to_string();
     Ok(Dependency::new(name).set_version(&version))
 }
 
+/// Read the latest non-yanked version matching `req` from Versions structure, like
+/// `read_latest_version`.
+fn read_compatible_version(
+    versions: &Versions,
+    req: &semver::VersionReq,
+    prereleases: Prereleases,
+) -> Option<Dependency> {
+    versions
+        .versions
+        .iter()
+        .find(|&v| !v.yanked && prereleases.accepts(&v.version) && req.matches(&v.version))
+        .map(|v| Dependency::new(&v.name).set_version(&v.version.to_string()))
+}
+
+/// Read the latest version published on or before the day `cutoff` (in days since the Unix
+/// epoch), like `read_latest_version`. `max_age_days` is only used in the messages.
+fn read_latest_version_before(
+    versions: &Versions,
+    prereleases: Prereleases,
+    cutoff: u64,
+    max_age_days: u64,
+    warnings: &mut Warnings,
+) -> Result<Dependency> {
+    let (recent, old): (Vec<_>, Vec<_>) = versions
+        .versions
+        .iter()
+        .filter(|&v| prereleases.accepts(&v.version))
+        .filter(|&v| !v.yanked)
+        .partition(|&v| match v.created_at.as_ref().and_then(|d| parse_date(d)) {
+            Some(published) => published > cutoff,
+            None => true,
+        });
+    let name = versions.versions.first().map(|v| v.name.clone());
+    let latest = old.first().ok_or_else(|| Error::NoVersionsOldEnough {
+        name: name.unwrap_or_default(),
+        days: max_age_days,
+    })?;
+
+    if !recent.is_empty() {
+        let recent: Vec<_> = recent.iter().map(|v| v.version.to_string()).collect();
+        warnings.push(
+            WarningKind::RecentVersion,
+            format!(
+                "Newer versions of `{}` were published in the last {} days ({}), using {}",
+                latest.name,
+                max_age_days,
+                recent.join(", "),
+                latest.version
+            ),
+        );
+    }
+
+    Ok(Dependency::new(&latest.name).set_version(&latest.version.to_string()))
+}
+
+/// The number of whole days from the Unix epoch to `time`.
+fn days_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0)
+}
+
+/// Parse the day of a crates.io timestamp (e.g. `2017-09-06T14:23:13.215387+00:00`), in days since
+/// the Unix epoch.
+fn parse_date(timestamp: &str) -> Option<u64> {
+    let date = timestamp.get(..10)?;
+    let mut parts = date.split('-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days from civil, see http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    if days < 0 {
+        None
+    } else {
+        Some(days as u64)
+    }
+}
+
 #[test]
 fn get_latest_stable_version_from_json() {
     let versions: Versions = json::from_str(
@@ -98,7 +647,7 @@ fn get_latest_stable_version_from_json() {
     ).expect("crate version is correctly parsed");
 
     assert_eq!(
-        read_latest_version(&versions, false)
+        read_latest_version(&versions, Prereleases::None, &mut Warnings::new())
             .unwrap()
             .version()
             .unwrap(),
@@ -126,7 +675,7 @@ fn get_latest_unstable_or_stable_version_from_json() {
     ).expect("crate version is correctly parsed");
 
     assert_eq!(
-        read_latest_version(&versions, true)
+        read_latest_version(&versions, Prereleases::All, &mut Warnings::new())
             .unwrap()
             .version()
             .unwrap(),
@@ -134,6 +683,49 @@ fn get_latest_unstable_or_stable_version_from_json() {
     );
 }
 
+#[test]
+fn get_latest_version_of_prerelease_channel_from_json() {
+    use prerelease::PrereleaseChannel;
+
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "1.1.0-alpha.1",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "1.0.0-rc.2",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "1.0.0-beta.3",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "0.9.0",
+          "yanked": false
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let latest = |channel: PrereleaseChannel| {
+        read_latest_version(&versions, Prereleases::Channel(channel), &mut Warnings::new())
+            .unwrap()
+            .version()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(latest(PrereleaseChannel::Rc), "1.0.0-rc.2");
+    assert_eq!(latest(PrereleaseChannel::Beta), "1.0.0-rc.2");
+    assert_eq!(latest(PrereleaseChannel::Alpha), "1.1.0-alpha.1");
+}
+
 #[test]
 fn get_latest_version_from_json_test() {
     let versions: Versions = json::from_str(
@@ -154,7 +746,7 @@ fn get_latest_version_from_json_test() {
     ).expect("crate version is correctly parsed");
 
     assert_eq!(
-        read_latest_version(&versions, false)
+        read_latest_version(&versions, Prereleases::None, &mut Warnings::new())
             .unwrap()
             .version()
             .unwrap(),
@@ -162,6 +754,31 @@ fn get_latest_version_from_json_test() {
     );
 }
 
+#[test]
+fn warn_about_yanked_newer_versions() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "treexml",
+          "num": "0.3.1",
+          "yanked": true
+        },
+        {
+          "crate": "treexml",
+          "num": "0.3.0",
+          "yanked": false
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let mut warnings = Warnings::new();
+    read_latest_version(&versions, Prereleases::None, &mut warnings).unwrap();
+    let kinds: Vec<_> = warnings.iter().map(|w| w.kind).collect();
+    assert_eq!(kinds, vec![WarningKind::YankedVersion]);
+}
+
 #[test]
 fn get_no_latest_version_from_json_when_all_are_yanked() {
     let versions: Versions = json::from_str(
@@ -181,16 +798,318 @@ fn get_no_latest_version_from_json_when_all_are_yanked() {
     }"#,
     ).expect("crate version is correctly parsed");
 
-    assert!(read_latest_version(&versions, false).is_err());
+    assert!(read_latest_version(&versions, Prereleases::None, &mut Warnings::new()).is_err());
+}
+
+#[test]
+fn parse_crates_io_dates() {
+    assert_eq!(parse_date("1970-01-01T00:00:00+00:00"), Some(0));
+    assert_eq!(parse_date("2000-03-01T12:00:00.5+00:00"), Some(11_017));
+    assert_eq!(parse_date("2017-09-06T14:23:13.215387+00:00"), Some(17_415));
+    assert_eq!(parse_date("yesterday"), None);
+}
+
+#[test]
+fn skip_versions_published_within_max_age() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "0.3.2",
+          "yanked": false,
+          "created_at": "2017-09-06T14:23:13.215387+00:00"
+        },
+        {
+          "crate": "foo",
+          "num": "0.3.1",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "0.3.0",
+          "yanked": false,
+          "created_at": "2017-08-01T09:00:00.000000+00:00"
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let mut warnings = Warnings::new();
+    let cutoff = parse_date("2017-09-01").unwrap();
+    assert_eq!(
+        read_latest_version_before(&versions, Prereleases::None, cutoff, 7, &mut warnings)
+            .unwrap()
+            .version()
+            .unwrap(),
+        "0.3.0"
+    );
+    let messages: Vec<_> = warnings.iter().map(|w| w.message.clone()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Newer versions of `foo` were published in the last 7 days (0.3.2, 0.3.1), using \
+             0.3.0",
+        ]
+    );
+
+    let cutoff = parse_date("2017-07-01").unwrap();
+    let dep = read_latest_version_before(&versions, Prereleases::None, cutoff, 70, &mut warnings);
+    assert!(dep.is_err());
+}
+
+#[test]
+fn explain_verdicts_of_versions() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "1.0.0-rc.1",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "0.9.2",
+          "yanked": false,
+          "created_at": "2017-09-06T14:23:13.215387+00:00"
+        },
+        {
+          "crate": "foo",
+          "num": "0.9.1",
+          "yanked": true
+        },
+        {
+          "crate": "foo",
+          "num": "0.9.0",
+          "yanked": false,
+          "rust_version": "1.31",
+          "created_at": "2017-08-01T09:00:00.000000+00:00"
+        },
+        {
+          "crate": "foo",
+          "num": "0.8.0",
+          "yanked": false,
+          "created_at": "2017-06-01T09:00:00.000000+00:00"
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let verdicts = |explanation: VersionExplanation| -> Vec<VersionVerdict> {
+        explanation.versions.iter().map(|v| v.verdict).collect()
+    };
+    assert_eq!(
+        verdicts(explain_versions(&versions, Prereleases::None, None)),
+        vec![
+            VersionVerdict::Prerelease,
+            VersionVerdict::Picked,
+            VersionVerdict::Yanked,
+            VersionVerdict::Older,
+            VersionVerdict::Older,
+        ]
+    );
+
+    let cutoff = parse_date("2017-09-01");
+    let explanation = explain_versions(&versions, Prereleases::All, cutoff);
+    let picked = explanation.picked().unwrap();
+    assert_eq!(picked.version, "0.9.0");
+    assert_eq!(picked.published, Some("2017-08-01".to_owned()));
+    assert_eq!(picked.rust_version, Some("1.31".to_owned()));
+    assert_eq!(
+        verdicts(explanation),
+        vec![
+            VersionVerdict::TooRecent,
+            VersionVerdict::TooRecent,
+            VersionVerdict::Yanked,
+            VersionVerdict::Picked,
+            VersionVerdict::Older,
+        ]
+    );
 }
 
-fn fetch_cratesio(path: &str) -> Result<Versions> {
-    let url = format!("{host}/api/v1{path}", host = REGISTRY_HOST, path = path);
-    let response =
-        get_with_timeout(&url, get_default_timeout()).chain_err(|| ErrorKind::FetchVersionFailure)?;
-    let versions: Versions =
-        json::from_reader(response).chain_err(|| ErrorKind::InvalidCratesIoJson)?;
-    Ok(versions)
+#[test]
+fn get_crate_info_from_json() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "0.6.0",
+          "yanked": false,
+          "license": "MIT",
+          "rust_version": "1.56"
+        },
+        {
+          "crate": "foo",
+          "num": "0.5.1",
+          "yanked": true,
+          "license": "MIT"
+        },
+        {
+          "crate": "foo",
+          "num": "0.5.0",
+          "yanked": false,
+          "license": "MIT OR Apache-2.0"
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let req = semver::VersionReq::parse("0.5").unwrap();
+    assert_eq!(
+        read_crate_info(&versions, &req),
+        Some(CrateInfo {
+            license: Some("MIT OR Apache-2.0".into()),
+            rust_version: None,
+            edition: None,
+        })
+    );
+}
+
+#[test]
+fn read_compatible_version_from_json() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "0.6.0",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "0.5.2",
+          "yanked": true
+        },
+        {
+          "crate": "foo",
+          "num": "0.5.1",
+          "yanked": false
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let req = semver::VersionReq::parse("0.5").unwrap();
+    assert_eq!(
+        read_compatible_version(&versions, &req, Prereleases::None)
+            .and_then(|dep| dep.version().map(String::from)),
+        Some("0.5.1".to_string())
+    );
+    let req = semver::VersionReq::parse("0.4").unwrap();
+    assert!(read_compatible_version(&versions, &req, Prereleases::None).is_none());
+}
+
+#[test]
+fn read_checksum_from_json() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "0.6.0",
+          "yanked": false,
+          "checksum": "b5d3ab2f3a1b2c4e"
+        },
+        {
+          "crate": "foo",
+          "num": "0.5.0",
+          "yanked": false
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let version = |v| semver::Version::parse(v).unwrap();
+    assert_eq!(
+        read_checksum(&versions, &version("0.6.0")),
+        Some(Some("b5d3ab2f3a1b2c4e".to_owned()))
+    );
+    assert_eq!(read_checksum(&versions, &version("0.5.0")), Some(None));
+    assert_eq!(read_checksum(&versions, &version("0.4.0")), None);
+}
+
+#[test]
+fn read_successor_from_description() {
+    let successor = |description| read_successor("foo", description);
+    assert_eq!(
+        successor("This crate was renamed to `bar`."),
+        Some("bar".to_owned())
+    );
+    assert_eq!(
+        successor("DEPRECATED in favour of the bar-baz crate"),
+        Some("bar-baz".to_owned())
+    );
+    assert_eq!(
+        successor("Deprecated, use 'bar_baz' instead"),
+        Some("bar_baz".to_owned())
+    );
+    assert_eq!(successor("Superseded by std::sync::LazyLock"), None);
+    assert_eq!(successor("Renamed to Foo, as it was"), None);
+    assert_eq!(successor("Easy to use parser combinators"), None);
+}
+
+#[test]
+fn get_latest_version_for_edition_from_json() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "2.0.0",
+          "yanked": false,
+          "edition": "2021"
+        },
+        {
+          "crate": "foo",
+          "num": "1.1.0",
+          "yanked": true,
+          "edition": "2018"
+        },
+        {
+          "crate": "foo",
+          "num": "1.0.0",
+          "yanked": false,
+          "edition": "2018"
+        },
+        {
+          "crate": "foo",
+          "num": "0.9.0",
+          "yanked": false
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    assert_eq!(
+        read_latest_version_for_edition(&versions, "2021", Prereleases::None),
+        Some("2.0.0".to_owned())
+    );
+    assert_eq!(
+        read_latest_version_for_edition(&versions, "2018", Prereleases::None),
+        Some("1.0.0".to_owned())
+    );
+    assert_eq!(
+        read_latest_version_for_edition(&versions, "2015", Prereleases::None),
+        None
+    );
+}
+
+thread_local! {
+    /// The responses of the crates.io API so far, by path.
+    static RESPONSES: Cache<Versions> = Cache::new();
+}
+
+fn fetch_cratesio(path: &str) -> Result<Rc<Versions>> {
+    RESPONSES.with(|responses| {
+        responses.get_or_fetch(path, || {
+            let url = format!("{host}/api/v1{path}", host = REGISTRY_HOST, path = path);
+            let response = get_with_timeout(&url, get_default_timeout())
+                .map_err(Error::network("Failed to fetch crate version from crates.io"))?;
+            json::from_reader(response).map_err(Error::InvalidCratesIoJson)
+        })
+    })
 }
 
 fn get_crate_name_from_repository<T>(repo: &str, matcher: &Regex, url_template: T) -> Result<String>
@@ -199,15 +1118,14 @@ where
 {
     matcher
         .captures(repo)
-        .ok_or_else(|| "Unable to parse git repo URL".into())
         .and_then(|cap| match (cap.get(1), cap.get(2)) {
-            (Some(user), Some(repo)) => {
-                let url = url_template(user.as_str(), repo.as_str());
-                let data: Result<Manifest> = get_cargo_toml_from_git_url(&url)
-                    .and_then(|m| m.parse().chain_err(|| ErrorKind::ParseCargoToml));
-                data.and_then(|ref manifest| get_name_from_manifest(manifest))
-            }
-            _ => Err("Git repo url seems incomplete".into()),
+            (Some(user), Some(repo)) => Some(url_template(user.as_str(), repo.as_str())),
+            _ => None,
+        })
+        .ok_or_else(|| Error::InvalidGitUrl(repo.into()))
+        .and_then(|url| {
+            let data: Result<Manifest> = get_cargo_toml_from_git_url(&url).and_then(|m| m.parse());
+            data.and_then(|ref manifest| get_name_from_manifest(manifest))
         })
 }
 
@@ -255,9 +1173,7 @@ pub fn get_crate_name_from_gitlab(repo: &str) -> Result<String> {
 /// Cargo.toml is not present in the root of the path.
 pub fn get_crate_name_from_path(path: &str) -> Result<String> {
     let cargo_file = Path::new(path).join("Cargo.toml");
-    Manifest::open(&Some(cargo_file))
-        .chain_err(|| "Unable to open local Cargo.toml")
-        .and_then(|ref manifest| get_name_from_manifest(manifest))
+    Manifest::open(&Some(cargo_file)).and_then(|ref manifest| get_name_from_manifest(manifest))
 }
 
 fn get_name_from_manifest(manifest: &Manifest) -> Result<String> {
@@ -266,29 +1182,74 @@ fn get_name_from_manifest(manifest: &Manifest) -> Result<String> {
         .as_table()
         .get("package")
         .and_then(|m| m["name"].as_str().map(|s| s.to_string()))
-        .ok_or_else(|| ErrorKind::ParseCargoToml.into())
+        .ok_or(Error::ParseCargoToml)
 }
 
-fn get_default_timeout() -> Duration {
+pub(crate) fn get_default_timeout() -> Duration {
     Duration::from_secs(10)
 }
 
-fn get_with_timeout(url: &str, timeout: Duration) -> reqwest::Result<reqwest::Response> {
+lazy_static! {
+    /// The clients shared by all requests of the process, including the ones of the `--jobs`
+    /// workers, by the timeout they were built with. Connections to the registry and to git hosts
+    /// are kept alive and reused instead of opened per request or per thread.
+    static ref CLIENTS: Mutex<HashMap<Duration, reqwest::Client>> = Mutex::new(HashMap::new());
+}
+
+#[cfg(test)]
+lazy_static! {
+    /// The timeouts of the clients built so far, to check that they are shared.
+    static ref BUILT_CLIENTS: Mutex<Vec<Duration>> = Mutex::new(Vec::new());
+}
+
+/// Get the shared client with `timeout`, building it on first use.
+fn shared_client(timeout: Duration) -> reqwest::Result<reqwest::Client> {
+    // A panic while building a client leaves nothing half done, so a poisoned lock is fine.
+    let mut clients = CLIENTS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(client) = clients.get(&timeout) {
+        return Ok(client.clone());
+    }
+
     let client = reqwest::ClientBuilder::new()?
         .timeout(timeout)
         .proxy(reqwest::Proxy::custom(|url| {
             env_proxy::for_url(url).to_url()
         }))
         .build()?;
+    #[cfg(test)]
+    BUILT_CLIENTS.lock().unwrap().push(timeout);
+    clients.insert(timeout, client.clone());
+    Ok(client)
+}
+
+pub(crate) fn get_with_timeout(
+    url: &str,
+    timeout: Duration,
+) -> reqwest::Result<reqwest::Response> {
+    shared_client(timeout)?.get(url)?.send()
+}
+
+#[test]
+fn one_client_is_shared_by_all_threads() {
+    // A timeout no other request uses, so only this test builds its client.
+    let timeout = Duration::from_millis(12_345);
+    let workers: Vec<_> = (0..4)
+        .map(|_| ::std::thread::spawn(move || shared_client(timeout).is_ok()))
+        .collect();
+    for worker in workers {
+        assert!(worker.join().unwrap());
+    }
+    assert!(shared_client(timeout).is_ok());
 
-    client.get(url)?.send()
+    let built = BUILT_CLIENTS.lock().unwrap();
+    assert_eq!(built.iter().filter(|&&t| t == timeout).count(), 1);
 }
 
 fn get_cargo_toml_from_git_url(url: &str) -> Result<String> {
     let mut res = get_with_timeout(url, get_default_timeout())
-        .chain_err(|| "Failed to fetch crate from git")?;
+        .map_err(Error::network("Failed to fetch crate from git"))?;
     let mut body = String::new();
     res.read_to_string(&mut body)
-        .chain_err(|| "Git response not a valid `String`")?;
+        .map_err(Error::io("Git response not a valid `String`"))?;
     Ok(body)
 }
@@ -0,0 +1,202 @@
+//! The packages of a git repository, read with `git` without cloning the whole repository.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+use dependency::GitReference;
+use errors::*;
+use manifest::Manifest;
+use workspace::glob_matches;
+
+/// A package in a git repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitPackage {
+    /// Name of the package
+    pub name: String,
+    /// Directory of the package in the repository, e.g. `regex-syntax` (empty for the root)
+    pub path: String,
+}
+
+/// A scratch repository, removed when dropped
+struct Scratch(PathBuf);
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run `git` in `dir` and return what it prints.
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(Error::io("Failed to run git"))?;
+    if !output.status.success() {
+        return Err(Error::GitCommand {
+            command: args.join(" "),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Check a repository URL and a reference before passing them to `git`. A value starting with `-`
+/// would be read as an option (e.g. `--upload-pack=<command>`, which runs the command), so it is
+/// rejected; the values are also passed after `--`.
+fn check_arguments(url: &str, reference: &str) -> Result<()> {
+    if url.starts_with('-') {
+        return Err(Error::InvalidGitUrl(url.to_owned()));
+    }
+    if reference.starts_with('-') {
+        return Err(Error::InvalidGitReference(reference.to_owned()));
+    }
+    Ok(())
+}
+
+/// Read a manifest of the fetched commit.
+fn fetched_manifest(dir: &Path, path: &str) -> Result<Manifest> {
+    git(dir, &["show", &format!("FETCH_HEAD:{}", path)])?.parse()
+}
+
+/// Get the commit a reference of the remote repository at `url` points to, with `git ls-remote`.
+/// The reference is `HEAD` (the head of the default branch), or a full name like
+/// `refs/heads/main` or `refs/tags/v1.0`; annotated tags are resolved to their commit.
+pub fn git_remote_commit(url: &str, reference: &str) -> Result<String> {
+    check_arguments(url, reference)?;
+    let peeled = format!("{}^{{}}", reference);
+    let refs = git(&env::temp_dir(), &["ls-remote", "--", url, reference, &peeled])?;
+    let mut commit = None;
+    for line in refs.lines() {
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next()) {
+            (Some(id), Some(name)) if name == peeled => return Ok(id.to_owned()),
+            (Some(id), Some(name)) if name == reference => commit = Some(id.to_owned()),
+            _ => {}
+        }
+    }
+    commit.ok_or_else(|| Error::GitCommand {
+        command: format!("ls-remote {} {}", url, reference),
+        message: format!("the repository has no `{}`", reference),
+    })
+}
+
+/// Find the packages of a git repository at `reference` (the default branch if `None`): the root
+/// package and the members of its workspace, if any.
+///
+/// Only the commit and its trees are fetched, without history; of the files, only the manifests
+/// are downloaded (if the server supports partial fetches). This needs `git` and fails, when
+///
+/// - `git` is not installed (an `Error::Io` of kind `NotFound`),
+/// - the repository can not be fetched, e.g. without an Internet connection,
+/// - or there is no `Cargo.toml` at the root of the repository.
+pub fn git_packages(url: &str, reference: Option<&GitReference>) -> Result<Vec<GitPackage>> {
+    let refspec = match reference {
+        Some(&GitReference::Branch(ref name))
+        | Some(&GitReference::Tag(ref name))
+        | Some(&GitReference::Rev(ref name)) => name.as_str(),
+        None => "HEAD",
+    };
+    check_arguments(url, refspec)?;
+
+    static SCRATCH_COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+    let dir = env::temp_dir().join(format!(
+        "cargo-edit-git-{}-{}",
+        process::id(),
+        SCRATCH_COUNT.fetch_add(1, Ordering::SeqCst)
+    ));
+    fs::create_dir_all(&dir).map_err(Error::io("Failed to create a scratch directory"))?;
+    let scratch = Scratch(dir);
+
+    git(&scratch.0, &["init", "--quiet"])?;
+    git(&scratch.0, &["remote", "add", "--", "origin", url])?;
+    git(
+        &scratch.0,
+        &[
+            "fetch",
+            "--quiet",
+            "--depth=1",
+            "--filter=blob:none",
+            "--",
+            "origin",
+            refspec,
+        ],
+    )?;
+
+    let root = fetched_manifest(&scratch.0, "Cargo.toml")?;
+    let mut packages = Vec::new();
+    if let Some(name) = root.data["package"]["name"].as_str() {
+        packages.push(GitPackage {
+            name: name.to_owned(),
+            path: String::new(),
+        });
+    }
+
+    let members: Vec<&str> = root.data["workspace"]["members"]
+        .as_array()
+        .map(|members| members.iter().filter_map(|m| m.as_str()).collect())
+        .unwrap_or_default();
+    if !members.is_empty() {
+        let files = git(&scratch.0, &["ls-tree", "-r", "--name-only", "FETCH_HEAD"])?;
+        for file in files.lines() {
+            let dir = match file.rfind('/') {
+                Some(end) if &file[end..] == "/Cargo.toml" => &file[..end],
+                _ => continue,
+            };
+            // `*` matches within a directory, not across them.
+            let depth = dir.matches('/').count();
+            if !members
+                .iter()
+                .any(|member| member.matches('/').count() == depth && glob_matches(member, dir))
+            {
+                continue;
+            }
+            let manifest = fetched_manifest(&scratch.0, file)?;
+            if let Some(name) = manifest.data["package"]["name"].as_str() {
+                packages.push(GitPackage {
+                    name: name.to_owned(),
+                    path: dir.to_owned(),
+                });
+            }
+        }
+    }
+
+    if packages.is_empty() {
+        return Err(Error::ParseCargoToml);
+    }
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_options_as_url_or_reference() {
+        let marker = env::temp_dir().join(format!("cargo-edit-git-injected-{}", process::id()));
+        let injected = format!("--upload-pack=touch {}", marker.display());
+
+        match git_remote_commit(&injected, "HEAD") {
+            Err(Error::InvalidGitUrl(ref url)) => assert_eq!(*url, injected),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        match git_remote_commit("https://example.com/repo.git", &injected) {
+            Err(Error::InvalidGitReference(ref reference)) => assert_eq!(*reference, injected),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        match git_packages(&injected, None) {
+            Err(Error::InvalidGitUrl(_)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+        let reference = GitReference::Branch(injected.clone());
+        match git_packages("https://example.com/repo.git", Some(&reference)) {
+            Err(Error::InvalidGitReference(_)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert!(!marker.exists(), "no command was run");
+    }
+}
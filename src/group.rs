@@ -0,0 +1,170 @@
+//! Comment groups in dependency tables: entries listed under a `# <label>` banner line.
+use conflict::header;
+
+/// Split a table header (e.g. `target.'cfg(unix)'.dependencies`) into its keys, without quotes.
+pub(crate) fn header_keys(header: &str) -> Vec<String> {
+    let mut keys = vec![String::new()];
+    let mut quote = None;
+    for c in header.chars() {
+        match (c, quote) {
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (_, Some(q)) if c == q => quote = None,
+            ('.', None) => keys.push(String::new()),
+            _ => keys.last_mut().expect("at least one key").push(c),
+        }
+    }
+    keys.iter().map(|key| key.trim().to_owned()).collect()
+}
+
+/// The key of a `key = value` line, without quotes.
+pub(crate) fn line_key(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let key = line.splitn(2, '=').next()?;
+    if key.len() == line.len() {
+        return None;
+    }
+    Some(key.trim().trim_matches('"').trim_matches('\''))
+}
+
+/// Move the (single line) entry `name` of the table at `table_path` below the `# <label>` banner in
+/// that table, after the entries already listed there. Without such a banner, one is added at the
+/// end of the table, separated by an empty line. The contents are returned unchanged if the table
+/// or the entry can not be found.
+pub(crate) fn move_into_group(
+    contents: &str,
+    table_path: &[String],
+    name: &str,
+    label: &str,
+) -> String {
+    let banner = format!("# {}", label.trim());
+    let mut lines: Vec<&str> = contents.lines().collect();
+
+    // The lines of the table, from its header to the next header.
+    let start = match lines
+        .iter()
+        .position(|line| header(line).map(|h| header_keys(&h)) == Some(table_path.to_vec()))
+    {
+        Some(start) => start + 1,
+        None => return contents.to_owned(),
+    };
+    let end = lines[start..]
+        .iter()
+        .position(|line| header(line).is_some())
+        .map_or(lines.len(), |len| start + len);
+
+    let entry = match (start..end).find(|&i| line_key(lines[i]) == Some(name)) {
+        Some(entry) => lines.remove(entry),
+        None => return contents.to_owned(),
+    };
+    let end = end - 1;
+
+    match (start..end).find(|&i| lines[i].trim() == banner) {
+        Some(line) => {
+            // The group ends at the first line that is not an entry.
+            let after = (line + 1..end)
+                .find(|&i| line_key(lines[i]).is_none())
+                .unwrap_or(end);
+            lines.insert(after, entry);
+        }
+        None => {
+            // Before the empty lines that separate the table from the next one
+            let mut after = end;
+            while after > start && lines[after - 1].trim().is_empty() {
+                after -= 1;
+            }
+            let has_entries = after > start;
+            lines.insert(after, entry);
+            lines.insert(after, &banner);
+            if has_entries {
+                lines.insert(after, "");
+            }
+        }
+    }
+
+    let mut moved = lines.join("\n");
+    if contents.ends_with('\n') {
+        moved.push('\n');
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn create_and_extend_groups() {
+        let input = r#"[dev-dependencies]
+# benches
+criterion = "0.2"
+
+# tests
+pretty_assertions = "0.5"
+bencher = "0.1"
+
+[features]
+std = []
+"#;
+        assert_eq!(
+            move_into_group(input, &path(&["dev-dependencies"]), "bencher", "benches"),
+            r#"[dev-dependencies]
+# benches
+criterion = "0.2"
+bencher = "0.1"
+
+# tests
+pretty_assertions = "0.5"
+
+[features]
+std = []
+"#
+        );
+        assert_eq!(
+            move_into_group(input, &path(&["dev-dependencies"]), "bencher", "examples"),
+            r#"[dev-dependencies]
+# benches
+criterion = "0.2"
+
+# tests
+pretty_assertions = "0.5"
+
+# examples
+bencher = "0.1"
+
+[features]
+std = []
+"#
+        );
+        // Unknown tables and entries are left alone
+        assert_eq!(
+            move_into_group(input, &path(&["dependencies"]), "bencher", "benches"),
+            input
+        );
+    }
+
+    #[test]
+    fn find_target_tables() {
+        assert_eq!(
+            header_keys("target.'cfg(unix)'.dev-dependencies"),
+            path(&["target", "cfg(unix)", "dev-dependencies"])
+        );
+
+        let input = "[target.\"cfg(unix)\".dev-dependencies]\nnix = \"0.11\"\n";
+        assert_eq!(
+            move_into_group(
+                input,
+                &path(&["target", "cfg(unix)", "dev-dependencies"]),
+                "nix",
+                "unix"
+            ),
+            "[target.\"cfg(unix)\".dev-dependencies]\n# unix\nnix = \"0.11\"\n"
+        );
+    }
+}
@@ -0,0 +1,985 @@
+//! Access to the crates.io registry index.
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, BufReader};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, str, thread};
+
+use reqwest;
+use semver;
+use serde_json as json;
+
+use Dependency;
+use cache::Cache;
+use crate_name::validate_crate_name;
+use errors::*;
+use fetch::{get_api_checksum, get_compatible_dependency_from_api, get_default_timeout,
+            get_latest_dependency_from_api, get_latest_dependency_with_warnings, get_with_timeout};
+use prerelease::Prereleases;
+use warnings::{WarningKind, Warnings};
+
+const INDEX_HOST: &str = "https://index.crates.io";
+
+/// Minimum time between two requests to the index.
+const REQUEST_INTERVAL_MS: u64 = 250;
+/// Maximum number of index files fetched at the same time, unless asked for more.
+const MAX_DEFAULT_JOBS: usize = 4;
+/// How often a request is retried when crates.io answers with "429 Too Many Requests".
+const MAX_RETRIES: u32 = 3;
+
+/// Index entry used for every crate when testing.
+const TEST_INDEX_ENTRY: &str = r#"{
+    "name": "test",
+    "vers": "1.0.0",
+    "deps": [{ "name": "test-derive", "optional": true }],
+    "features": { "default": ["std"], "std": [], "derive": ["test-derive"] }
+}"#;
+
+/// A published version of a crate, as listed in the registry index.
+#[derive(Debug, Deserialize)]
+pub(crate) struct IndexEntry {
+    pub name: String,
+    pub vers: semver::Version,
+    #[serde(default)] pub deps: Vec<IndexDependency>,
+    #[serde(default)] pub features: BTreeMap<String, Vec<String>>,
+    #[serde(default)] pub features2: Option<BTreeMap<String, Vec<String>>>,
+    #[serde(default)] pub yanked: bool,
+    #[serde(default)] pub cksum: Option<String>,
+    #[serde(default)] pub links: Option<String>,
+}
+
+/// Index files by lowercase crate name, with the name the crate was first given as, see
+/// `fetch_unique_indexes`.
+type UniqueIndexFiles = BTreeMap<String, (String, Option<Rc<IndexFile>>)>;
+
+/// The entries of an index file, with the `ETag` of the file, if the server sent one.
+#[derive(Debug)]
+struct IndexFile {
+    entries: Vec<IndexEntry>,
+    etag: Option<String>,
+    /// The (1-based) numbers of the lines that could not be read as an entry
+    malformed: Vec<usize>,
+}
+
+thread_local! {
+    /// The index files fetched so far, by lowercase crate name.
+    static INDEX_FILES: Cache<IndexFile> = Cache::new();
+}
+
+/// A dependency of an `IndexEntry`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct IndexDependency {
+    pub name: String,
+    #[serde(default)] pub optional: bool,
+}
+
+/// A feature offered by a crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureInfo {
+    /// Name of the feature
+    pub name: String,
+    /// Whether the feature is enabled by the crate's default features
+    pub default: bool,
+    /// Features (and `dep:`/`dep/feature` entries) this feature enables
+    pub enables: Vec<String>,
+    /// Optional dependencies this feature pulls in
+    pub optional_deps: Vec<String>,
+}
+
+/// Query the features of a crate from the registry index
+///
+/// The features of the latest non-yanked version matching `version_req` are returned, sorted by
+/// name. Optional dependencies that are not hidden behind `dep:` show up as features of their own.
+/// This will fail, when
+///
+/// - there is no Internet connection,
+/// - the index entries of the crate are in an incorrect format,
+/// - or when no published version of the crate matches the requirement.
+///
+/// Entries of the index file that can not be read are skipped with a warning.
+pub fn get_crate_features(
+    crate_name: &str,
+    version_req: &str,
+    warnings: &mut Warnings,
+) -> Result<Vec<FeatureInfo>> {
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality. Every crate offers the same features.
+        let entry: IndexEntry =
+            json::from_str(TEST_INDEX_ENTRY).expect("test index entry is correctly parsed");
+        return Ok(features_of(&entry));
+    }
+
+    let req = semver::VersionReq::parse(version_req).map_err(Error::InvalidVersionRequirement)?;
+    let file = fetch_index(crate_name, warnings)?;
+
+    select_entry(&file.entries, &req)
+        .map(features_of)
+        .ok_or_else(|| Error::NoMatchingVersion {
+            name: crate_name.into(),
+            req: version_req.into(),
+        })
+}
+
+/// Where a version of a crate was resolved from, so what the registry offered can be checked
+/// later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    /// The version the requirement resolved to
+    pub version: String,
+    /// The SHA-256 checksum of the `.crate` file of that version, as listed in the index
+    pub checksum: Option<String>,
+    /// The URL of the index file the version was looked up in
+    pub index_url: String,
+    /// The `ETag` of the index file, which identifies the state of the index that was seen
+    pub etag: Option<String>,
+}
+
+/// Look up where the latest non-yanked version of a crate matching `version_req` comes from in
+/// the registry index. Entries of the index file that can not be read are skipped with a warning.
+///
+/// The checksum is cross-checked with the one the crates.io API lists, which is served by another
+/// host: a mismatch (e.g. because a proxy altered a response) is reported as a warning. Nothing is
+/// checked if the API can not be reached or either side lists no checksum.
+pub fn get_provenance(
+    crate_name: &str,
+    version_req: &str,
+    warnings: &mut Warnings,
+) -> Result<Provenance> {
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality. Every crate has the same checksum.
+        return Ok(Provenance {
+            version: version_req.into(),
+            checksum: Some("0".repeat(64)),
+            index_url: index_url(crate_name),
+            etag: Some("\"test\"".into()),
+        });
+    }
+
+    let req = semver::VersionReq::parse(version_req).map_err(Error::InvalidVersionRequirement)?;
+    let file = fetch_index(crate_name, warnings)?;
+    let entry = select_entry(&file.entries, &req).ok_or_else(|| Error::NoMatchingVersion {
+        name: crate_name.into(),
+        req: version_req.into(),
+    })?;
+    if let Some(ref checksum) = entry.cksum {
+        cross_check_checksum(crate_name, &entry.vers, checksum, warnings);
+    }
+    Ok(Provenance {
+        version: entry.vers.to_string(),
+        checksum: entry.cksum.clone(),
+        index_url: index_url(crate_name),
+        etag: file.etag.clone(),
+    })
+}
+
+/// Compare the checksum the index lists for a version of a crate with the one of the crates.io
+/// API, and warn if they differ.
+fn cross_check_checksum(
+    crate_name: &str,
+    version: &semver::Version,
+    index_checksum: &str,
+    warnings: &mut Warnings,
+) {
+    if let Ok(Some(api_checksum)) = get_api_checksum(crate_name, version) {
+        if !api_checksum.eq_ignore_ascii_case(index_checksum) {
+            warnings.push(
+                WarningKind::ChecksumMismatch,
+                format!(
+                    "The registry index and the crates.io API list different checksums for \
+                     `{}` {} ({} and {}). A proxy may have altered one of the responses.",
+                    crate_name, version, index_checksum, api_checksum
+                ),
+            );
+        }
+    }
+}
+
+/// Query the native library a crate links to (its `links` key, e.g. `ssl` for `openssl-sys`) from
+/// the registry index
+///
+/// The key of the latest non-yanked version matching `version_req` is returned, `None` if that
+/// version links to no native library. Entries of the index file that can not be read are skipped
+/// with a warning.
+pub fn get_crate_links(
+    crate_name: &str,
+    version_req: &str,
+    warnings: &mut Warnings,
+) -> Result<Option<String>> {
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality. `-sys` crates link to the library they are named after,
+        // e.g. both `libsqlite3-sys` and `sqlite3-sys` to `sqlite3`.
+        return Ok(if crate_name.ends_with("-sys") {
+            let library = crate_name.trim_end_matches("-sys");
+            Some(library.trim_start_matches("lib").to_owned())
+        } else {
+            None
+        });
+    }
+
+    let req = semver::VersionReq::parse(version_req).map_err(Error::InvalidVersionRequirement)?;
+    let file = fetch_index(crate_name, warnings)?;
+    let entry = select_entry(&file.entries, &req).ok_or_else(|| Error::NoMatchingVersion {
+        name: crate_name.into(),
+        req: version_req.into(),
+    })?;
+    Ok(entry.links.clone())
+}
+
+/// Query the latest versions of several crates from the registry index
+///
+/// Each index file is fetched only once per invocation, however often its crate is named. Up to `jobs` index
+/// files are fetched at the same time (by default, see `default_jobs`), and the requests are
+/// spaced out (with some jitter) to stay within the rate limits of crates.io. Crates that are not
+/// found in the index (e.g. because of a `-`/`_` mix-up) are looked up with the crates.io API,
+/// like `get_latest_dependency` does. The dependencies are returned in the order of `crate_names`.
+/// Any prerelease may be picked for the crates in `prerelease_crates`.
+pub fn get_latest_dependencies<S: AsRef<str>>(
+    crate_names: &[S],
+    prereleases: Prereleases,
+    prerelease_crates: &[String],
+    jobs: Option<usize>,
+) -> Result<Vec<Dependency>> {
+    let mut warnings = Warnings::new();
+    let deps = get_latest_dependencies_with_warnings(
+        crate_names,
+        prereleases,
+        prerelease_crates,
+        jobs,
+        &mut warnings,
+    );
+    warnings.print();
+    deps
+}
+
+/// Query the latest versions of several crates, like `get_latest_dependencies`, but collect the
+/// warnings instead of printing them.
+pub fn get_latest_dependencies_with_warnings<S: AsRef<str>>(
+    crate_names: &[S],
+    prereleases: Prereleases,
+    prerelease_crates: &[String],
+    jobs: Option<usize>,
+    warnings: &mut Warnings,
+) -> Result<Vec<Dependency>> {
+    for name in crate_names {
+        validate_crate_name(name.as_ref())?;
+    }
+
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // The simulated versions are the same as the ones of `get_latest_dependency`.
+        return crate_names
+            .iter()
+            .map(|name| {
+                let prereleases = prereleases.for_crate(name.as_ref(), prerelease_crates);
+                get_latest_dependency_with_warnings(name.as_ref(), prereleases, warnings)
+            })
+            .collect();
+    }
+
+    let files = fetch_unique_indexes(crate_names, jobs, warnings)?;
+
+    let mut fetched: BTreeMap<String, Dependency> = BTreeMap::new();
+    for (key, (name, file)) in files {
+        let prereleases = prereleases.for_crate(&name, prerelease_crates);
+        let dep = match file {
+            Some(file) => latest_dependency(&name, &file.entries, prereleases, warnings)?,
+            None => get_latest_dependency_from_api(&name, prereleases, warnings)?,
+        };
+        fetched.insert(key, dep);
+    }
+
+    Ok(crate_names
+        .iter()
+        .map(|name| fetched[&name.as_ref().to_lowercase()].clone())
+        .collect())
+}
+
+/// Query the latest versions of several crates that their requirements allow, like `cargo update`
+/// would pick them. Each crate is named together with its requirement (e.g. `1.2`, or
+/// `>=0.9, <2.0`), and the dependencies are returned in the same order; a dependency is `None` if
+/// no published version matches its requirement. The index files are fetched like with
+/// `get_latest_dependencies`, and the warnings are collected in `warnings`.
+pub fn get_compatible_dependencies(
+    requirements: &[(String, String)],
+    prereleases: Prereleases,
+    prerelease_crates: &[String],
+    jobs: Option<usize>,
+    warnings: &mut Warnings,
+) -> Result<Vec<Option<Dependency>>> {
+    let mut reqs = Vec::new();
+    for &(ref name, ref req) in requirements {
+        validate_crate_name(name)?;
+        reqs.push(semver::VersionReq::parse(req).map_err(Error::InvalidVersionRequirement)?);
+    }
+
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality, where every requirement allows a newer version.
+        return Ok(requirements
+            .iter()
+            .map(|&(ref name, _)| {
+                let version = format!("{}--COMPATIBLE_VERSION_TEST", name);
+                Some(Dependency::new(name).set_version(&version))
+            })
+            .collect());
+    }
+
+    let names: Vec<&str> = requirements.iter().map(|&(ref name, _)| name.as_str()).collect();
+    let files = fetch_unique_indexes(&names, jobs, warnings)?;
+
+    let mut deps = Vec::new();
+    for (name, req) in names.iter().zip(reqs) {
+        let (ref name, ref file) = files[&name.to_lowercase()];
+        let prereleases = prereleases.for_crate(name, prerelease_crates);
+        deps.push(match *file {
+            Some(ref file) => latest_matching_entry(&file.entries, &req, prereleases)
+                .map(|entry| Dependency::new(&entry.name).set_version(&entry.vers.to_string())),
+            None => get_compatible_dependency_from_api(name, &req, prereleases, warnings)?,
+        });
+    }
+    Ok(deps)
+}
+
+/// Fetch the index files of several crates, like `fetch_indexes`, but once per crate however
+/// often (and in whatever case) it is named. The files are returned by the lowercase crate name,
+/// together with the name the crate was first given as. The file is `None` for crates that are
+/// not found in the index (e.g. because of a `-`/`_` mix-up), which are left to the crates.io
+/// API. Fails with the lookup that failed, rather than the ones that were cancelled because of it.
+fn fetch_unique_indexes<S: AsRef<str>>(
+    crate_names: &[S],
+    jobs: Option<usize>,
+    warnings: &mut Warnings,
+) -> Result<UniqueIndexFiles> {
+    let mut unique: BTreeMap<String, &str> = BTreeMap::new();
+    for name in crate_names {
+        unique
+            .entry(name.as_ref().to_lowercase())
+            .or_insert_with(|| name.as_ref());
+    }
+    let names: Vec<String> = unique.values().map(|name| name.to_string()).collect();
+    let jobs = jobs.unwrap_or_else(|| default_jobs(names.len()));
+
+    let mut files = fetch_indexes(&names, jobs, warnings);
+    let failed = files.iter().position(|file| match *file {
+        Ok(_) | Err(Error::CrateNotFound(_)) | Err(Error::LookupCancelled(_)) => false,
+        Err(_) => true,
+    });
+    if let Some(failed) = failed {
+        if let Err(e) = files.swap_remove(failed) {
+            return Err(e);
+        }
+    }
+
+    names
+        .into_iter()
+        .zip(files)
+        .map(|(name, file)| {
+            let file = match file {
+                Ok(file) => Some(file),
+                Err(Error::CrateNotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
+            Ok((name.to_lowercase(), (name, file)))
+        })
+        .collect()
+}
+
+/// Query the latest version of a crate from its index file, which lists every version in one
+/// request. Fails with `Error::CrateNotFound` if the crate has no index file.
+pub(crate) fn get_latest_dependency_from_index(
+    crate_name: &str,
+    prereleases: Prereleases,
+    warnings: &mut Warnings,
+) -> Result<Dependency> {
+    let file = fetch_index(crate_name, warnings)?;
+    latest_dependency(crate_name, &file.entries, prereleases, warnings)
+}
+
+/// The latest non-yanked version among `entries` of the crate `crate_name`, as a dependency.
+/// Newer versions that were yanked, and a crate name that differs in case, are reported as
+/// warnings.
+fn latest_dependency(
+    crate_name: &str,
+    entries: &[IndexEntry],
+    prereleases: Prereleases,
+    warnings: &mut Warnings,
+) -> Result<Dependency> {
+    let latest = latest_entry(entries, prereleases).ok_or(Error::NoVersionsAvailable)?;
+
+    let mut yanked: Vec<_> = entries
+        .iter()
+        .filter(|e| e.yanked && prereleases.accepts(&e.vers) && e.vers > latest.vers)
+        .map(|e| &e.vers)
+        .collect();
+    if !yanked.is_empty() {
+        yanked.sort_by(|a, b| b.cmp(a));
+        let yanked: Vec<_> = yanked.iter().map(|v| v.to_string()).collect();
+        warnings.push(
+            WarningKind::YankedVersion,
+            format!(
+                "Newer versions of `{}` were yanked ({}), using {}",
+                latest.name,
+                yanked.join(", "),
+                latest.vers
+            ),
+        );
+    }
+    if latest.name != crate_name {
+        warnings.push(
+            WarningKind::RenamedCrate,
+            format!("Added `{}` instead of `{}`", latest.name, crate_name),
+        );
+    }
+
+    Ok(Dependency::new(&latest.name).set_version(&latest.vers.to_string()))
+}
+
+/// The number of index files fetched at the same time by default: one per crate, up to
+/// `MAX_DEFAULT_JOBS`.
+pub fn default_jobs(crate_count: usize) -> usize {
+    crate_count.min(MAX_DEFAULT_JOBS).max(1)
+}
+
+/// Fetch the index files of several crates, like `fetch_index`, with up to `jobs` requests at the
+/// same time. The results are in the order of `crate_names`.
+fn fetch_indexes(
+    crate_names: &[String],
+    jobs: usize,
+    warnings: &mut Warnings,
+) -> Vec<Result<Rc<IndexFile>>> {
+    INDEX_FILES.with(|files| {
+        let cached: Vec<_> = crate_names
+            .iter()
+            .map(|name| files.get(&name.to_lowercase()))
+            .collect();
+        let missing: Vec<_> = crate_names
+            .iter()
+            .zip(&cached)
+            .filter(|&(_, file)| file.is_none())
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let mut fetched = fetch_index_files(&missing, jobs).into_iter();
+        crate_names
+            .iter()
+            .zip(cached)
+            .map(|(name, file)| match file {
+                Some(file) => Ok(file),
+                None => fetched
+                    .next()
+                    .expect("one lookup per missing index file")
+                    .map(|file| {
+                        warn_about_malformed_lines(name, &file, warnings);
+                        files.insert(&name.to_lowercase(), file)
+                    }),
+            })
+            .collect()
+    })
+}
+
+/// Fetch the index files of several crates from the registry, with up to `jobs` requests at the
+/// same time. The results are in the order of `crate_names`. Once a lookup fails (other than for
+/// a crate that is not in the index), the crates that are still waiting are not looked up, and
+/// fail with `Error::LookupCancelled`.
+fn fetch_index_files(crate_names: &[String], jobs: usize) -> Vec<Result<IndexFile>> {
+    let throttle = Arc::new(Throttle::new());
+    let queue = Arc::new(Mutex::new(
+        crate_names.iter().cloned().enumerate().collect::<Vec<_>>(),
+    ));
+
+    let workers: Vec<_> = (0..jobs.max(1).min(crate_names.len()))
+        .map(|_| {
+            let throttle = Arc::clone(&throttle);
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || {
+                let mut results = Vec::new();
+                loop {
+                    let next = queue.lock().expect("queue lock is not poisoned").pop();
+                    match next {
+                        Some((i, ref name)) if throttle.is_cancelled() => {
+                            results.push((i, Err(Error::LookupCancelled(name.clone()))))
+                        }
+                        Some((i, name)) => {
+                            let result = fetch_index_file(&name, &throttle);
+                            match result {
+                                Ok(_)
+                                | Err(Error::CrateNotFound(_))
+                                | Err(Error::LookupCancelled(_)) => {}
+                                Err(_) => throttle.cancel(),
+                            }
+                            results.push((i, result))
+                        }
+                        None => return results,
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let mut results: Vec<_> = workers
+        .into_iter()
+        .flat_map(|worker| worker.join().expect("index lookup does not panic"))
+        .collect();
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Spaces out requests to the registry index, also when they are sent from several threads, and
+/// stops them once the batch they belong to is cancelled.
+#[derive(Debug)]
+struct Throttle {
+    last: Mutex<Option<Instant>>,
+    cancelled: AtomicBool,
+}
+
+impl Throttle {
+    fn new() -> Self {
+        Throttle {
+            last: Mutex::new(None),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// Let the requests that are still to be sent fail with `Error::LookupCancelled`.
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the next request may be sent.
+    fn wait(&self) {
+        let mut last = self.last.lock().expect("throttle lock is not poisoned");
+        if let Some(last) = *last {
+            let interval = Duration::from_millis(REQUEST_INTERVAL_MS) + jitter();
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+        *last = Some(Instant::now());
+    }
+}
+
+/// A random delay of up to `REQUEST_INTERVAL_MS`, so concurrent runs do not stay in lockstep.
+fn jitter() -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    Duration::from_millis(u64::from(nanos) % REQUEST_INTERVAL_MS)
+}
+
+/// The delay asked for by the `Retry-After` header of a response, in seconds.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get_raw("Retry-After")
+        .and_then(|raw| raw.one())
+        .and_then(|value| str::from_utf8(value).ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// Fetch the index file of a crate, unless it was fetched before in this invocation.
+fn fetch_index(crate_name: &str, warnings: &mut Warnings) -> Result<Rc<IndexFile>> {
+    INDEX_FILES.with(|files| {
+        files.get_or_fetch(&crate_name.to_lowercase(), || {
+            let file = fetch_index_file(crate_name, &Throttle::new())?;
+            warn_about_malformed_lines(crate_name, &file, warnings);
+            Ok(file)
+        })
+    })
+}
+
+/// Report the lines of a freshly fetched index file that were skipped.
+fn warn_about_malformed_lines(crate_name: &str, file: &IndexFile, warnings: &mut Warnings) {
+    if file.malformed.is_empty() {
+        return;
+    }
+    let lines: Vec<_> = file.malformed.iter().map(|line| line.to_string()).collect();
+    warnings.push(
+        WarningKind::MalformedIndexEntry,
+        format!(
+            "Skipped unreadable entries in the index file of `{}` (lines {})",
+            crate_name,
+            lines.join(", ")
+        ),
+    );
+}
+
+/// Fetch the index file of a crate from the registry, waiting for `throttle` before every request.
+/// When crates.io answers with "429 Too Many Requests", the request is retried after a while.
+fn fetch_index_file(crate_name: &str, throttle: &Throttle) -> Result<IndexFile> {
+    validate_crate_name(crate_name)?;
+
+    let url = index_url(crate_name);
+    let mut retries = 0;
+    let response = loop {
+        throttle.wait();
+        let response = get_with_timeout(&url, get_default_timeout())
+            .map_err(Error::network("Failed to fetch crate version from crates.io"))?;
+        if response.status() != reqwest::StatusCode::TooManyRequests || retries == MAX_RETRIES {
+            break response;
+        }
+        // Do not wait out the backoff for a batch that failed already.
+        if throttle.is_cancelled() {
+            return Err(Error::LookupCancelled(crate_name.into()));
+        }
+        let backoff = retry_after(&response).unwrap_or_else(|| Duration::from_secs(1 << retries));
+        thread::sleep(backoff + jitter());
+        retries += 1;
+    };
+    check_index_status(crate_name, response.status())?;
+
+    let etag = response
+        .headers()
+        .get_raw("ETag")
+        .and_then(|raw| raw.one())
+        .and_then(|value| str::from_utf8(value).ok())
+        .map(String::from);
+    Ok(IndexFile {
+        etag: etag,
+        ..parse_index(BufReader::new(response))?
+    })
+}
+
+/// Check the status the registry answered a request for the index file of a crate with. Only "404
+/// Not Found" and "410 Gone" mean that there is no such crate; any other error (e.g. a server
+/// error) is reported with its status, rather than looking the crate up elsewhere.
+fn check_index_status(crate_name: &str, status: reqwest::StatusCode) -> Result<()> {
+    match status {
+        _ if status.is_success() => Ok(()),
+        reqwest::StatusCode::NotFound | reqwest::StatusCode::Gone => {
+            Err(Error::CrateNotFound(crate_name.into()))
+        }
+        _ => Err(Error::RegistryStatus {
+            name: crate_name.into(),
+            status: status.as_u16(),
+        }),
+    }
+}
+
+/// The URL of the index file of a crate
+fn index_url(crate_name: &str) -> String {
+    format!("{}/{}", INDEX_HOST, index_path(crate_name))
+}
+
+/// Parse the newline-delimited JSON of an index file.
+///
+/// Fields that are not known are ignored, and lines that can not be read as an entry at all (e.g.
+/// because the format of the index changed) are skipped and noted in `malformed`, so a single odd
+/// version does not make the whole crate unusable.
+fn parse_index<R: BufRead>(reader: R) -> Result<IndexFile> {
+    let mut file = IndexFile {
+        entries: Vec::new(),
+        etag: None,
+        malformed: Vec::new(),
+    };
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(Error::io("Failed to read registry index"))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match json::from_str(&line) {
+            Ok(entry) => file.entries.push(entry),
+            Err(_) => file.malformed.push(index + 1),
+        }
+    }
+    Ok(file)
+}
+
+/// Path of a crate's file in the index (e.g. `se/rd/serde`).
+fn index_path(crate_name: &str) -> String {
+    let name = crate_name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// Select the latest non-yanked entry matching `req`.
+///
+/// Index files are ordered by publication, not by version, so all entries have to be considered.
+fn select_entry<'a>(entries: &'a [IndexEntry], req: &semver::VersionReq) -> Option<&'a IndexEntry> {
+    entries
+        .iter()
+        .filter(|e| !e.yanked && req.matches(&e.vers))
+        .max_by(|a, b| a.vers.cmp(&b.vers))
+}
+
+/// Select the latest non-yanked entry, ignoring the prereleases `prereleases` does not accept.
+fn latest_entry(entries: &[IndexEntry], prereleases: Prereleases) -> Option<&IndexEntry> {
+    entries
+        .iter()
+        .filter(|e| !e.yanked && prereleases.accepts(&e.vers))
+        .max_by(|a, b| a.vers.cmp(&b.vers))
+}
+
+/// The latest non-yanked entry whose version matches `req`.
+fn latest_matching_entry<'a>(
+    entries: &'a [IndexEntry],
+    req: &semver::VersionReq,
+    prereleases: Prereleases,
+) -> Option<&'a IndexEntry> {
+    entries
+        .iter()
+        .filter(|e| !e.yanked && prereleases.accepts(&e.vers) && req.matches(&e.vers))
+        .max_by(|a, b| a.vers.cmp(&b.vers))
+}
+
+/// Work out the features of an index entry.
+fn features_of(entry: &IndexEntry) -> Vec<FeatureInfo> {
+    let mut features = entry.features.clone();
+    if let Some(ref features2) = entry.features2 {
+        features.extend(features2.clone());
+    }
+
+    let optional: BTreeSet<&str> = entry
+        .deps
+        .iter()
+        .filter(|d| d.optional)
+        .map(|d| d.name.as_str())
+        .collect();
+    features_from(features, &optional)
+}
+
+/// The features of a crate, from its `[features]` table and its optional dependencies.
+pub(crate) fn features_from(
+    mut features: BTreeMap<String, Vec<String>>,
+    optional: &BTreeSet<&str>,
+) -> Vec<FeatureInfo> {
+    // Optional dependencies get an implicit feature, unless they are only ever named as `dep:x`.
+    for dep in optional {
+        let dep_syntax = format!("dep:{}", dep);
+        let hidden = features
+            .values()
+            .flat_map(|enables| enables.iter())
+            .any(|f| *f == dep_syntax);
+        if !hidden && !features.contains_key(*dep) {
+            features.insert(dep.to_string(), vec![dep_syntax]);
+        }
+    }
+
+    let mut defaults = BTreeSet::new();
+    let mut todo = vec!["default"];
+    while let Some(feature) = todo.pop() {
+        if defaults.insert(feature) {
+            if let Some(enables) = features.get(feature) {
+                todo.extend(
+                    enables
+                        .iter()
+                        .filter(|f| features.contains_key(f.as_str()))
+                        .map(|f| f.as_str()),
+                );
+            }
+        }
+    }
+
+    features
+        .iter()
+        .map(|(name, enables)| {
+            let optional_deps = enables
+                .iter()
+                .filter_map(|f| {
+                    let dep = if f.starts_with("dep:") {
+                        &f[4..]
+                    } else if f.contains("?/") {
+                        // `dep?/feature` does not enable `dep` by itself.
+                        return None;
+                    } else {
+                        f.splitn(2, '/').next().unwrap_or(f)
+                    };
+                    if optional.contains(dep) {
+                        Some(dep.to_owned())
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            FeatureInfo {
+                name: name.clone(),
+                default: defaults.contains(name.as_str()),
+                enables: enables.clone(),
+                optional_deps: optional_deps,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(lines: &str) -> Vec<IndexEntry> {
+        parse_index(lines.as_bytes())
+            .expect("index entries are correctly parsed")
+            .entries
+    }
+
+    #[test]
+    fn only_missing_index_files_mean_crate_not_found() {
+        assert!(check_index_status("foo", reqwest::StatusCode::Ok).is_ok());
+        for status in &[reqwest::StatusCode::NotFound, reqwest::StatusCode::Gone] {
+            match check_index_status("foo", *status) {
+                Err(Error::CrateNotFound(ref name)) => assert_eq!(name, "foo"),
+                result => panic!("unexpected result for {}: {:?}", status, result),
+            }
+        }
+        for status in &[
+            reqwest::StatusCode::InternalServerError,
+            reqwest::StatusCode::ServiceUnavailable,
+            reqwest::StatusCode::TooManyRequests,
+        ] {
+            match check_index_status("foo", *status) {
+                Err(Error::RegistryStatus { ref name, status: code }) => {
+                    assert_eq!(name, "foo");
+                    assert_eq!(code, status.as_u16());
+                }
+                result => panic!("unexpected result for {}: {:?}", status, result),
+            }
+        }
+    }
+
+    #[test]
+    fn index_paths() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("ab"), "2/ab");
+        assert_eq!(index_path("abc"), "3/a/abc");
+        assert_eq!(index_path("Serde"), "se/rd/serde");
+    }
+
+    #[test]
+    fn select_latest_matching_entry() {
+        let entries = entries(
+            r#"{"name":"foo","vers":"0.5.0"}
+{"name":"foo","vers":"0.6.0"}
+{"name":"foo","vers":"0.5.2","yanked":true}
+
+{"name":"foo","vers":"0.5.1"}"#,
+        );
+
+        let req = semver::VersionReq::parse("0.5").unwrap();
+        assert_eq!(
+            select_entry(&entries, &req).unwrap().vers,
+            semver::Version::parse("0.5.1").unwrap()
+        );
+
+        let req = semver::VersionReq::parse("2.0").unwrap();
+        assert!(select_entry(&entries, &req).is_none());
+    }
+
+    #[test]
+    fn latest_entry_skips_yanked_and_prereleases() {
+        let entries = entries(
+            r#"{"name":"foo","vers":"0.5.0"}
+{"name":"foo","vers":"0.7.0-alpha"}
+{"name":"foo","vers":"0.6.0","yanked":true}"#,
+        );
+
+        let latest = |prereleases| latest_entry(&entries, prereleases).unwrap().vers.to_string();
+        assert_eq!(latest(Prereleases::None), "0.5.0");
+        assert_eq!(latest(Prereleases::All), "0.7.0-alpha");
+        assert!(latest_entry(&entries[2..], Prereleases::All).is_none());
+    }
+
+    #[test]
+    fn latest_matching_entry_stays_within_requirement() {
+        let entries = entries(
+            r#"{"name":"foo","vers":"1.2.0"}
+{"name":"foo","vers":"1.4.1"}
+{"name":"foo","vers":"1.5.0","yanked":true}
+{"name":"foo","vers":"2.0.0"}"#,
+        );
+
+        let latest = |req| {
+            let req = semver::VersionReq::parse(req).unwrap();
+            latest_matching_entry(&entries, &req, Prereleases::None).map(|e| e.vers.to_string())
+        };
+        assert_eq!(latest("1.2"), Some("1.4.1".to_owned()));
+        assert_eq!(latest(">=1.0, <3.0"), Some("2.0.0".to_owned()));
+        assert_eq!(latest("0.9"), None);
+    }
+
+    #[test]
+    fn latest_dependency_warns_about_newer_yanked_versions() {
+        let entries = entries(
+            r#"{"name":"Foo","vers":"0.5.0"}
+{"name":"Foo","vers":"0.7.0","yanked":true}
+{"name":"Foo","vers":"0.4.0","yanked":true}
+{"name":"Foo","vers":"0.6.0","yanked":true}"#,
+        );
+
+        let mut warnings = Warnings::new();
+        let dep = latest_dependency("foo", &entries, Prereleases::None, &mut warnings).unwrap();
+        assert_eq!(dep.name, "Foo");
+        assert_eq!(dep.version(), Some("0.5.0"));
+        let messages: Vec<_> = warnings.iter().map(|w| w.message.clone()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Newer versions of `Foo` were yanked (0.7.0, 0.6.0), using 0.5.0",
+                "Added `Foo` instead of `foo`",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_ignored_and_malformed_lines_skipped() {
+        let file = parse_index(
+            r#"{"name":"foo","vers":"0.5.0","v":2,"rust_version":"1.60"}
+{"name":"foo","vers":"not a version"}
+
+{"name":"foo","vers":"0.6.0","features":"unexpected"}
+{"name":"foo","vers":"0.7.0"}"#
+                .as_bytes(),
+        ).unwrap();
+
+        let versions: Vec<_> = file.entries.iter().map(|e| e.vers.to_string()).collect();
+        assert_eq!(versions, vec!["0.5.0", "0.7.0"]);
+        assert_eq!(file.malformed, vec![2, 4]);
+
+        let mut warnings = Warnings::new();
+        warn_about_malformed_lines("foo", &file, &mut warnings);
+        let messages: Vec<_> = warnings.iter().map(|w| w.message.clone()).collect();
+        assert_eq!(
+            messages,
+            vec!["Skipped unreadable entries in the index file of `foo` (lines 2, 4)"]
+        );
+    }
+
+    #[test]
+    fn features_with_defaults_and_optional_deps() {
+        let entries = entries(
+            r#"{"name":"foo","vers":"1.0.0","deps":[{"name":"bar","optional":true},{"name":"baz","optional":true},{"name":"serde","optional":false}],"features":{"default":["std"],"std":["alloc"],"alloc":[],"derive":["dep:baz","serde/derive"]}}"#,
+        );
+        let features = features_of(&entries[0]);
+
+        let names: Vec<_> = features.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["alloc", "bar", "default", "derive", "std"]);
+
+        let defaults: Vec<_> = features
+            .iter()
+            .filter(|f| f.default)
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(defaults, vec!["alloc", "default", "std"]);
+
+        let derive = features.iter().find(|f| f.name == "derive").unwrap();
+        assert_eq!(derive.optional_deps, vec!["baz".to_owned()]);
+        let bar = features.iter().find(|f| f.name == "bar").unwrap();
+        assert_eq!(bar.optional_deps, vec!["bar".to_owned()]);
+    }
+
+    #[test]
+    fn default_number_of_jobs() {
+        assert_eq!(default_jobs(0), 1);
+        assert_eq!(default_jobs(2), 2);
+        assert_eq!(default_jobs(40), MAX_DEFAULT_JOBS);
+    }
+}
@@ -0,0 +1,156 @@
+//! Simple interactive prompts on the terminal.
+use std::io::{self, BufRead, Write};
+
+use errors::*;
+
+/// An entry that can be picked from a `checklist`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecklistItem {
+    /// Heading the item is listed under (e.g. the dependency section)
+    pub group: String,
+    /// Text shown for the item
+    pub label: String,
+}
+
+impl ChecklistItem {
+    /// Create a new `ChecklistItem`
+    pub fn new(group: &str, label: &str) -> Self {
+        ChecklistItem {
+            group: group.into(),
+            label: label.into(),
+        }
+    }
+}
+
+/// Show a numbered checklist of `items` on stdout and let the user pick any number of them.
+///
+/// Items are listed under their group headings, in the order given. The indices of the picked
+/// items are returned in ascending order; an empty answer picks nothing.
+pub fn checklist(prompt: &str, items: &[ChecklistItem]) -> Result<Vec<usize>> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut input = stdin.lock();
+    let mut output = stdout.lock();
+    checklist_from(&mut input, &mut output, prompt, items)
+}
+
+fn checklist_from<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    prompt: &str,
+    items: &[ChecklistItem],
+) -> Result<Vec<usize>> {
+    let mut group = None;
+    for (i, item) in items.iter().enumerate() {
+        if group != Some(&item.group) {
+            writeln!(output, "{}:", item.group).map_err(Error::io("Failed to write checklist"))?;
+            group = Some(&item.group);
+        }
+        writeln!(output, "{:>4}) {}", i + 1, item.label)
+            .map_err(Error::io("Failed to write checklist"))?;
+    }
+
+    loop {
+        write!(
+            output,
+            "{} (e.g. `1 3 5-7` or `all`, leave empty to cancel): ",
+            prompt
+        ).map_err(Error::io("Failed to write prompt"))?;
+        output.flush().map_err(Error::io("Failed to write prompt"))?;
+
+        let mut answer = String::new();
+        if input
+            .read_line(&mut answer)
+            .map_err(Error::io("Failed to read selection"))? == 0
+        {
+            // EOF: treat like an empty answer.
+            return Ok(vec![]);
+        }
+
+        match parse_selection(&answer, items.len()) {
+            Ok(selection) => return Ok(selection),
+            Err(e) => writeln!(output, "{}", e).map_err(Error::io("Failed to write prompt"))?,
+        }
+    }
+}
+
+/// Parse an answer like `1 3, 5-7` into zero-based, sorted and deduplicated indices.
+fn parse_selection(answer: &str, len: usize) -> Result<Vec<usize>> {
+    let answer = answer.trim();
+    if answer == "all" || answer == "*" {
+        return Ok((0..len).collect());
+    }
+
+    let parse_number = |s: &str| -> Result<usize> {
+        match s.trim().parse::<usize>() {
+            Ok(n) if n >= 1 && n <= len => Ok(n - 1),
+            _ => Err(Error::InvalidSelection {
+                input: s.into(),
+                len: len,
+            }),
+        }
+    };
+
+    let mut selection = Vec::new();
+    for token in answer
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+    {
+        let range: Vec<_> = token.splitn(2, '-').collect();
+        if range.len() == 2 {
+            let (start, end) = (parse_number(range[0])?, parse_number(range[1])?);
+            if start > end {
+                return Err(Error::InvalidSelection {
+                    input: token.into(),
+                    len: len,
+                });
+            }
+            selection.extend(start..end + 1);
+        } else {
+            selection.push(parse_number(token)?);
+        }
+    }
+
+    selection.sort();
+    selection.dedup();
+    Ok(selection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_numbers_and_ranges() {
+        assert_eq!(parse_selection("1 3, 5-7\n", 8).unwrap(), vec![0, 2, 4, 5, 6]);
+        assert_eq!(parse_selection("2 2 1", 3).unwrap(), vec![0, 1]);
+        assert_eq!(parse_selection("all", 3).unwrap(), vec![0, 1, 2]);
+        assert!(parse_selection("", 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reject_invalid_selection() {
+        assert!(parse_selection("0", 3).is_err());
+        assert!(parse_selection("4", 3).is_err());
+        assert!(parse_selection("3-1", 3).is_err());
+        assert!(parse_selection("foo", 3).is_err());
+    }
+
+    #[test]
+    fn checklist_reprompts_on_invalid_input() {
+        let items = vec![
+            ChecklistItem::new("dependencies", "docopt"),
+            ChecklistItem::new("dependencies", "pad"),
+            ChecklistItem::new("dev-dependencies", "regex"),
+        ];
+        let mut input = &b"7\n1 3\n"[..];
+        let mut output = Vec::new();
+
+        let picked = checklist_from(&mut input, &mut output, "Remove", &items).unwrap();
+        assert_eq!(picked, vec![0, 2]);
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("dependencies:\n   1) docopt\n   2) pad\ndev-dependencies:\n"));
+        assert!(output.contains("Invalid selection `7`"));
+    }
+}
@@ -7,7 +7,7 @@
 extern crate cargo_metadata;
 extern crate env_proxy;
 #[macro_use]
-extern crate error_chain;
+extern crate lazy_static;
 extern crate regex;
 extern crate reqwest;
 extern crate semver;
@@ -15,18 +15,63 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+#[cfg(any(test, feature = "test-support"))]
+extern crate tempdir;
 extern crate termcolor;
 extern crate toml_edit;
 
+#[macro_use]
+mod messages;
+
+mod cache;
+mod change_file;
+mod command;
+mod conflict;
 mod crate_name;
+mod dep_table;
 mod dependency;
 mod errors;
 mod fetch;
+mod git;
+mod group;
+mod index;
+mod interactive;
+mod local_source;
+mod lock_file;
 mod manifest;
+mod prerelease;
+mod self_description;
+mod style;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+mod warnings;
+mod workspace;
 
-pub use crate_name::CrateName;
-pub use dependency::Dependency;
+pub use cache::{registry_cache_stats, CacheStats};
+pub use change_file::{record_changes, RecordedChange};
+pub use command::{command_args, find_plugin, find_plugins, untranslated_command_args, Plugin};
+pub use conflict::{check_conflicts, resolve_conflicts, ConflictSide};
+pub use crate_name::{canonical_crate_name, expand_git_shorthand, validate_crate_name, CrateName};
+pub use dep_table::{DepKind, DepTable};
+pub use dependency::{Dependency, GitReference};
 pub use errors::*;
-pub use fetch::{get_crate_name_from_github, get_crate_name_from_gitlab, get_crate_name_from_path,
-                get_latest_dependency};
-pub use manifest::{find, LocalManifest, Manifest};
+pub use fetch::{explain_latest_version, get_crate_info, get_crate_name_from_github,
+                get_crate_name_from_gitlab, get_crate_name_from_path, get_crate_successor,
+                get_latest_dependency, get_latest_dependency_with_max_age,
+                get_latest_dependency_with_warnings, get_latest_version_for_edition, CrateInfo,
+                VersionChoice, VersionExplanation, VersionVerdict};
+pub use git::{git_packages, git_remote_commit, GitPackage};
+pub use index::{default_jobs, get_compatible_dependencies, get_crate_features, get_crate_links,
+                get_latest_dependencies, get_latest_dependencies_with_warnings, get_provenance,
+                FeatureInfo, Provenance};
+pub use interactive::{checklist, ChecklistItem};
+pub use local_source::{find_local_source, parse_crate_file_name, LocalSource, LocalSourceKind};
+pub use lock_file::{find_lock_file, latest_locked_version, parse_lock_file, parse_locked_packages,
+                    read_lock_file, read_locked_packages, LockedPackage, LockedVersions};
+pub use manifest::{find, BinTarget, LocalManifest, Manifest, SectionPlacement};
+pub use messages::{message, message_arg, Language, MessageArg};
+pub use prerelease::{PrereleaseChannel, Prereleases};
+pub use self_description::SelfDescription;
+pub use warnings::{Warning, WarningKind, Warnings};
+pub use workspace::{excluded_members, find_workspace_root, ignored_upgrades, nested_packages,
+                    prerelease_crates, workspace_members, WorkspaceMember};
@@ -0,0 +1,199 @@
+//! Sources on disk that replace crates.io for offline or vendored builds, as configured in
+//! `.cargo/config.toml`: local registries (`local-registry`, a directory of `.crate` archives) and
+//! directory sources (`directory`, e.g. the output of `cargo vendor`).
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use toml_edit;
+
+use errors::*;
+
+/// How a local source stores its crates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalSourceKind {
+    /// `.crate` archives, named after the crate and its version (`local-registry`)
+    LocalRegistry,
+    /// A directory per crate, with its manifest (`directory`)
+    Directory,
+}
+
+/// A source on disk that replaces crates.io
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalSource {
+    /// Name of the source in the configuration, e.g. `vendored-sources`
+    pub name: String,
+    /// How the source stores its crates
+    pub kind: LocalSourceKind,
+    /// The directory of the source
+    pub path: PathBuf,
+}
+
+impl LocalSource {
+    /// The versions of the crates in the source. Entries that are not crates (e.g. the index of a
+    /// local registry) are skipped.
+    pub fn versions(&self) -> Result<HashMap<String, Vec<Version>>> {
+        let mut versions = HashMap::new();
+        let entries =
+            fs::read_dir(&self.path).map_err(Error::io("Failed to read the local source"))?;
+        for entry in entries {
+            let entry = entry.map_err(Error::io("Failed to read the local source"))?;
+            let krate = match self.kind {
+                LocalSourceKind::LocalRegistry => entry
+                    .file_name()
+                    .to_str()
+                    .and_then(parse_crate_file_name),
+                LocalSourceKind::Directory => read_package(&entry.path().join("Cargo.toml")),
+            };
+            if let Some((name, version)) = krate {
+                versions.entry(name).or_insert_with(Vec::new).push(version);
+            }
+        }
+        Ok(versions)
+    }
+}
+
+/// Split the file name of a `.crate` archive (e.g. `foo-bar-1.2.0-rc.1.crate`) into the name of the
+/// crate and its version.
+pub fn parse_crate_file_name(file_name: &str) -> Option<(String, Version)> {
+    if !file_name.ends_with(".crate") {
+        return None;
+    }
+    let stem = &file_name[..file_name.len() - ".crate".len()];
+    // Names and versions can both contain `-`, but only the version starts with a digit.
+    stem.match_indices('-')
+        .map(|(i, _)| i)
+        .filter(|&i| i > 0)
+        .filter_map(|i| {
+            Version::parse(&stem[i + 1..])
+                .ok()
+                .map(|version| (stem[..i].to_owned(), version))
+        })
+        .next()
+}
+
+/// The name and version of the package of a manifest, if it can be read.
+fn read_package(manifest_path: &Path) -> Option<(String, Version)> {
+    let contents = fs::read_to_string(manifest_path).ok()?;
+    let manifest: toml_edit::Document = contents.parse().ok()?;
+    let name = manifest["package"]["name"].as_str()?;
+    let version = Version::parse(manifest["package"]["version"].as_str()?).ok()?;
+    Some((name.to_owned(), version))
+}
+
+/// The directory of the cargo home, from `CARGO_HOME` or else `~/.cargo`.
+fn cargo_home() -> Option<PathBuf> {
+    env::var_os("CARGO_HOME").map(PathBuf::from).or_else(|| {
+        env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(|home| Path::new(&home).join(".cargo"))
+    })
+}
+
+/// The cargo configuration files that apply in `dir` (an absolute path), the most specific first:
+/// `.cargo/config.toml` (or `.cargo/config`) in `dir` and each directory above it, then the one in
+/// the cargo home.
+fn config_files(dir: &Path) -> Vec<PathBuf> {
+    let mut config_dirs: Vec<PathBuf> = dir.ancestors().map(|dir| dir.join(".cargo")).collect();
+    if let Some(home) = cargo_home() {
+        if !config_dirs.contains(&home) {
+            config_dirs.push(home);
+        }
+    }
+    config_dirs
+        .iter()
+        .filter_map(|config_dir| {
+            ["config.toml", "config"]
+                .iter()
+                .map(|file| config_dir.join(file))
+                .find(|path| path.is_file())
+        })
+        .collect()
+}
+
+/// Find the source that replaces crates.io for the package of the manifest, if it is a local
+/// registry or a directory source: `replace-with` of `[source.crates-io]` names it, following
+/// further replacements. The configuration files are read like cargo does, from the directory of
+/// the manifest upwards and then in the cargo home; the first file that sets a key wins, and
+/// relative paths are relative to the directory that contains the `.cargo` directory.
+pub fn find_local_source(manifest_path: &Path) -> Result<Option<LocalSource>> {
+    let dir = match manifest_path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let dir = fs::canonicalize(dir)
+        .map_err(Error::io("Failed to resolve the directory of the manifest"))?;
+
+    let mut configs = Vec::new();
+    for path in config_files(&dir) {
+        let contents = fs::read_to_string(&path)
+            .map_err(Error::io("Failed to read a cargo configuration file"))?;
+        let config: toml_edit::Document = contents.parse().map_err(Error::InvalidToml)?;
+        let base = path.parent()
+            .and_then(Path::parent)
+            .unwrap_or_else(|| Path::new(""))
+            .to_owned();
+        configs.push((config, base));
+    }
+
+    let mut name = "crates-io".to_owned();
+    let mut replaced = Vec::new();
+    loop {
+        let replacement = match configs
+            .iter()
+            .filter_map(|&(ref config, _)| config["source"][name.as_str()]["replace-with"].as_str())
+            .next()
+        {
+            Some(replacement) => replacement.to_owned(),
+            None => break,
+        };
+        // Cargo rejects cycles of replacements; they do not lead to a local source either way.
+        if replaced.contains(&name) {
+            return Ok(None);
+        }
+        replaced.push(name);
+        name = replacement;
+    }
+
+    for &(ref config, ref base) in &configs {
+        let source = &config["source"][name.as_str()];
+        let (kind, path) = if let Some(path) = source["local-registry"].as_str() {
+            (LocalSourceKind::LocalRegistry, path)
+        } else if let Some(path) = source["directory"].as_str() {
+            (LocalSourceKind::Directory, path)
+        } else if source.is_none() {
+            continue;
+        } else {
+            // Another kind of source, e.g. a mirror of the registry.
+            return Ok(None);
+        };
+        return Ok(Some(LocalSource {
+            name: name,
+            kind: kind,
+            path: base.join(path),
+        }));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_crate_file_names() {
+        assert_eq!(
+            parse_crate_file_name("libc-0.2.30.crate"),
+            Some(("libc".to_owned(), Version::parse("0.2.30").unwrap()))
+        );
+        assert_eq!(
+            parse_crate_file_name("foo-bar-1.2.0-rc.1.crate"),
+            Some(("foo-bar".to_owned(), Version::parse("1.2.0-rc.1").unwrap()))
+        );
+        assert_eq!(parse_crate_file_name("libc-0.2.30.tar.gz"), None);
+        assert_eq!(parse_crate_file_name("libc.crate"), None);
+        assert_eq!(parse_crate_file_name("-0.2.30.crate"), None);
+    }
+}
@@ -0,0 +1,209 @@
+//! Versions recorded in a lock file (`Cargo.lock`).
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use toml_edit;
+
+use errors::*;
+use prerelease::Prereleases;
+use workspace::find_workspace_root;
+
+/// The versions of each package recorded in a lock file
+pub type LockedVersions = HashMap<String, Vec<Version>>;
+
+/// A package recorded in a lock file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    /// Name of the package
+    pub name: String,
+    /// The locked version
+    pub version: Version,
+    /// Where the package comes from, e.g. `registry+https://github.com/rust-lang/crates.io-index`
+    /// or `git+https://github.com/me/fork#<commit>`. Members of the workspace and other path
+    /// dependencies have none.
+    pub source: Option<String>,
+    /// The SHA-256 checksum of registry packages, if the lock file records it next to the package
+    pub checksum: Option<String>,
+    /// The packages it depends on, as written in the lock file: `name`, `name version` or
+    /// `name version (source)`
+    pub dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    /// Whether a dependency as written in the `dependencies` of another package refers to this
+    /// package.
+    pub fn is_named_by(&self, dependency: &str) -> bool {
+        let mut parts = dependency.split_whitespace();
+        parts.next() == Some(self.name.as_str())
+            && parts
+                .next()
+                .map_or(true, |version| version == self.version.to_string())
+    }
+}
+
+/// Find the lock file of a package: `Cargo.lock` next to the root manifest of its workspace, or
+/// next to its own manifest if it is not part of a workspace.
+pub fn find_lock_file(manifest_path: &Path) -> PathBuf {
+    let root = find_workspace_root(manifest_path).unwrap_or_else(|_| manifest_path.to_owned());
+    root.parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join("Cargo.lock")
+}
+
+/// Read the contents of a lock file, if it exists.
+fn read_lock_file_data(path: &Path) -> Result<Option<String>> {
+    let mut data = String::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_string(&mut data)
+            .map_err(Error::io("Failed to read Cargo.lock"))?,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::io("Failed to open Cargo.lock")(e)),
+    };
+    Ok(Some(data))
+}
+
+/// Read the versions of the packages recorded in a lock file. A missing lock file records none.
+pub fn read_lock_file(path: &Path) -> Result<LockedVersions> {
+    match read_lock_file_data(path)? {
+        Some(data) => parse_lock_file(&data),
+        None => Ok(LockedVersions::new()),
+    }
+}
+
+/// Parse the versions of the packages recorded in the contents of a lock file.
+pub fn parse_lock_file(data: &str) -> Result<LockedVersions> {
+    let mut locked = LockedVersions::new();
+    for package in parse_locked_packages(data)? {
+        locked
+            .entry(package.name)
+            .or_insert_with(Vec::new)
+            .push(package.version);
+    }
+    Ok(locked)
+}
+
+/// Read the packages recorded in a lock file, if it exists.
+pub fn read_locked_packages(path: &Path) -> Result<Option<Vec<LockedPackage>>> {
+    match read_lock_file_data(path)? {
+        Some(data) => parse_locked_packages(&data).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Parse the packages recorded in the contents of a lock file. Packages without a name or a valid
+/// version are left out.
+pub fn parse_locked_packages(data: &str) -> Result<Vec<LockedPackage>> {
+    let lock_file: toml_edit::Document = data.parse().map_err(Error::InvalidToml)?;
+
+    let mut locked = Vec::new();
+    if let Some(packages) = lock_file["package"].as_array_of_tables() {
+        for package in packages.iter() {
+            let field = |key: &str| package.get(key).and_then(toml_edit::Item::as_str);
+            let version = field("version").and_then(|v| Version::parse(v).ok());
+            let (name, version) = match (field("name"), version) {
+                (Some(name), Some(version)) => (name, version),
+                _ => continue,
+            };
+            let dependencies = package
+                .get("dependencies")
+                .and_then(toml_edit::Item::as_array)
+                .map(|deps| {
+                    deps.iter()
+                        .filter_map(|dep| dep.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            locked.push(LockedPackage {
+                name: name.to_owned(),
+                version: version,
+                source: field("source").map(String::from),
+                checksum: field("checksum").map(String::from),
+                dependencies: dependencies,
+            });
+        }
+    }
+    Ok(locked)
+}
+
+/// The highest version of `name` recorded in a lock file. Prereleases are only considered if
+/// `prereleases` accepts them.
+pub fn latest_locked_version<'a>(
+    locked: &'a LockedVersions,
+    name: &str,
+    prereleases: Prereleases,
+) -> Option<&'a Version> {
+    locked
+        .get(name)?
+        .iter()
+        .filter(|v| prereleases.accepts(v))
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_locked() {
+        let locked = parse_lock_file(
+            r#"[[package]]
+name = "a"
+version = "0.2.7"
+
+[[package]]
+name = "a"
+version = "0.3.1"
+
+[[package]]
+name = "a"
+version = "0.4.0-beta.1"
+"#,
+        ).unwrap();
+
+        assert_eq!(
+            latest_locked_version(&locked, "a", Prereleases::None).map(|v| v.to_string()),
+            Some("0.3.1".to_owned())
+        );
+        assert_eq!(
+            latest_locked_version(&locked, "a", Prereleases::All).map(|v| v.to_string()),
+            Some("0.4.0-beta.1".to_owned())
+        );
+        assert_eq!(latest_locked_version(&locked, "b", Prereleases::None), None);
+    }
+
+    #[test]
+    fn locked_packages() {
+        let packages = parse_locked_packages(
+            r#"[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "a 0.2.7",
+ "b",
+]
+
+[[package]]
+name = "a"
+version = "0.2.7"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "0123abcd"
+
+[[package]]
+name = "b"
+version = "1.0.0"
+source = "git+https://github.com/me/b#0123abcd"
+"#,
+        ).unwrap();
+
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages[0].source, None);
+        assert_eq!(packages[0].dependencies, vec!["a 0.2.7", "b"]);
+        assert_eq!(packages[1].checksum, Some("0123abcd".to_owned()));
+        assert!(packages[1].is_named_by("a 0.2.7"));
+        assert!(!packages[1].is_named_by("a 0.3.0"));
+        assert!(packages[2].is_named_by("b"));
+    }
+}
@@ -1,17 +1,105 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::{self, File, OpenOptions};
-use std::io::{Read, Write};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::{env, str};
+use std::{env, fmt, mem, process, str};
 
 use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
 use toml_edit;
 
 use errors::*;
+use conflict::{check_conflicts, resolve_conflicts, ConflictSide};
+use crate_name::canonical_crate_name;
+use group::move_into_group;
+use style::{restyle_entry, Style};
+use dep_table::{DepKind, DepTable};
 use dependency::Dependency;
+use index::{features_from, FeatureInfo};
+use warnings::{WarningKind, Warnings};
 
 const MANIFEST_FILENAME: &str = "Cargo.toml";
 
+/// The UTF-8 byte order mark, which some Windows editors write at the start of a file
+const BOM: &str = "\u{feff}";
+
+/// Top-level keys that hold dependencies.
+const DEPENDENCY_SECTIONS: &[&str] = &[
+    "dependencies",
+    "dev-dependencies",
+    "build-dependencies",
+    "target",
+];
+
+/// Keys a dependency may keep next to `workspace = true`; everything else is inherited from the
+/// workspace root.
+const INHERITABLE_KEYS: &[&str] = &["features", "optional"];
+
+/// A binary target of a package, as declared in a `[[bin]]` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinTarget {
+    /// Name of the binary
+    pub name: String,
+    /// Features that must be enabled for the binary to be built (`required-features`)
+    pub required_features: Vec<String>,
+}
+
+/// Where to put a dependency section that does not exist yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionPlacement {
+    /// At the end of the manifest
+    End,
+    /// Right after the last existing dependency section (or at the end, if there is none)
+    AfterDependencies,
+    /// Right before `[features]` (or at the end, if there is none)
+    BeforeFeatures,
+}
+
+impl Default for SectionPlacement {
+    fn default() -> Self {
+        SectionPlacement::End
+    }
+}
+
+impl str::FromStr for SectionPlacement {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "end" => Ok(SectionPlacement::End),
+            "after-dependencies" => Ok(SectionPlacement::AfterDependencies),
+            "before-features" => Ok(SectionPlacement::BeforeFeatures),
+            _ => Err(Error::InvalidSectionPlacement(s.into())),
+        }
+    }
+}
+
+/// Insert an empty table named `key` into the root of the manifest, at `placement`.
+fn insert_section(root: &mut toml_edit::Table, key: &str, placement: SectionPlacement) {
+    let keys: Vec<String> = root.iter().map(|(k, _)| k.to_owned()).collect();
+    let index = match placement {
+        SectionPlacement::End => None,
+        SectionPlacement::AfterDependencies => keys.iter()
+            .rposition(|k| DEPENDENCY_SECTIONS.contains(&k.as_str()))
+            .map(|i| i + 1),
+        SectionPlacement::BeforeFeatures => keys.iter().position(|k| k == "features"),
+    };
+
+    // Tables are written in the order they were inserted, so everything that should come after
+    // the new section is taken out and inserted again.
+    let moved: Vec<_> = match index {
+        Some(index) => keys[index..]
+            .iter()
+            .filter_map(|k| root.remove(k).map(|item| (k, item)))
+            .collect(),
+        None => vec![],
+    };
+    root[key] = toml_edit::table();
+    for (k, item) in moved {
+        root[k.as_str()] = item;
+    }
+}
+
 /// A Cargo manifest
 #[derive(Debug, Clone)]
 pub struct Manifest {
@@ -28,13 +116,13 @@ pub fn find(specified: &Option<PathBuf>) -> Result<PathBuf> {
     match *specified {
         Some(ref path)
             if fs::metadata(&path)
-                .chain_err(|| "Failed to get cargo file metadata")?
+                .map_err(Error::io("Failed to get cargo file metadata"))?
                 .is_file() =>
         {
             Ok(path.to_owned())
         }
         Some(ref path) => search(path),
-        None => search(&env::current_dir().chain_err(|| "Failed to get current directory")?),
+        None => search(&env::current_dir().map_err(Error::io("Failed to get current directory"))?),
     }
 }
 
@@ -46,11 +134,96 @@ fn search(dir: &Path) -> Result<PathBuf> {
         Ok(manifest)
     } else {
         dir.parent()
-            .ok_or_else(|| ErrorKind::MissingManifest.into())
+            .ok_or(Error::ManifestNotFound)
             .and_then(|dir| search(dir))
     }
 }
 
+/// The byte order mark and line endings of a manifest file. Manifests are parsed without them, and
+/// a file keeps them when it is overwritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TextFormat {
+    /// The file starts with a byte order mark
+    bom: bool,
+    /// Lines end with `\r\n` (judging by the first line)
+    crlf: bool,
+}
+
+impl TextFormat {
+    fn detect(contents: &[u8]) -> Self {
+        let crlf = contents
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(false, |end| end > 0 && contents[end - 1] == b'\r');
+        TextFormat {
+            bom: contents.starts_with(BOM.as_bytes()),
+            crlf: crlf,
+        }
+    }
+
+    /// Write `contents` (with `\n` line endings, and without a byte order mark) in this format.
+    fn apply(&self, contents: &str) -> String {
+        let contents = if self.crlf {
+            contents.replace('\n', "\r\n")
+        } else {
+            contents.to_owned()
+        };
+        if self.bom {
+            format!("{}{}", BOM, contents)
+        } else {
+            contents
+        }
+    }
+}
+
+/// A staging file next to the manifest, removed when dropped unless it was renamed over the
+/// manifest already.
+struct StagingFile(PathBuf);
+
+impl Drop for StagingFile {
+    fn drop(&mut self) {
+        // After the rename there is nothing left to remove.
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Replace the file at `path` with `contents`, keeping its byte order mark, line endings and
+/// permissions. The contents are written to a new file in the same directory, which is then
+/// renamed over the old one with `rename` (`fs::rename`, except in tests), so the file is never
+/// seen half written.
+///
+/// The staging file (`.Cargo.toml.cargo-edit-<pid>`) is removed again if the write fails. If the
+/// process is killed before the rename, no cleanup runs and it stays behind next to the
+/// untouched manifest; it can be deleted, and is replaced by a later write of the same process
+/// id.
+fn write_atomically<F>(path: &Path, contents: &str, rename: F) -> Result<()>
+where
+    F: FnOnce(&Path, &Path) -> io::Result<()>,
+{
+    // Write through symlinks instead of replacing them.
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let old_contents = fs::read(&path).unwrap_or_default();
+    let contents = TextFormat::detect(&old_contents).apply(contents);
+
+    let file_name = match path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => MANIFEST_FILENAME.to_owned(),
+    };
+    let staging = StagingFile(
+        path.with_file_name(format!(".{}.cargo-edit-{}", file_name, process::id())),
+    );
+    let write = || -> io::Result<()> {
+        let mut file = File::create(&staging.0)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(&path) {
+            fs::set_permissions(&staging.0, metadata.permissions())?;
+        }
+        rename(staging.0.as_path(), path.as_path())
+    };
+    write().map_err(Error::io("Failed to write updated Cargo.toml"))
+}
+
 fn merge_inline_table(old_dep: &mut toml_edit::Item, new: &toml_edit::Item) {
     for (k, v) in new.as_inline_table()
         .expect("expected an inline table")
@@ -60,6 +233,11 @@ fn merge_inline_table(old_dep: &mut toml_edit::Item, new: &toml_edit::Item) {
     }
 }
 
+/// Whether a dependency entry is inherited from the workspace (`workspace = true`).
+fn is_inherited(item: &toml_edit::Item) -> bool {
+    item["workspace"].as_bool() == Some(true)
+}
+
 fn str_or_1_len_table(item: &toml_edit::Item) -> bool {
     item.is_str() || item.as_table_like().map(|t| t.len() == 1).unwrap_or(false)
 }
@@ -74,15 +252,29 @@ fn merge_dependencies(old_dep: &mut toml_edit::Item, new: &Dependency) {
         // The old dependency is just a version/git/path. We are safe to overwrite.
         *old_dep = new_toml;
     } else if old_dep.is_table_like() {
-        for key in &["version", "path", "git"] {
+        for key in &["version", "path", "git", "branch", "tag", "rev"] {
             // remove this key/value pairs
             old_dep[key] = toml_edit::Item::None;
         }
+        let features = merged_features(old_dep, new);
         if let Some(name) = new_toml.as_str() {
             old_dep["version"] = toml_edit::value(name);
         } else {
             merge_inline_table(old_dep, &new_toml);
         }
+        match features {
+            Some(ref features) if features.is_empty() => {
+                old_dep["features"] = toml_edit::Item::None;
+            }
+            Some(features) => {
+                let mut array = toml_edit::Array::default();
+                for feature in &features {
+                    array.push(feature.as_str());
+                }
+                old_dep["features"] = toml_edit::value(toml_edit::Value::Array(array));
+            }
+            None => {}
+        }
     } else {
         unreachable!("Invalid old dependency type");
     }
@@ -90,6 +282,32 @@ fn merge_dependencies(old_dep: &mut toml_edit::Item, new: &Dependency) {
     old_dep.as_inline_table_mut().map(|t| t.fmt());
 }
 
+/// The features an old entry enables, without the ones the new dependency removes, followed by
+/// the ones the new dependency enables that are not there yet. `None` if the new dependency
+/// neither enables nor removes features, so the old ones are left alone.
+fn merged_features(old_dep: &toml_edit::Item, new: &Dependency) -> Option<Vec<String>> {
+    let removed = new.removed_features();
+    if new.features().is_none() && removed.is_empty() {
+        return None;
+    }
+    let mut features: Vec<String> = old_dep["features"]
+        .as_array()
+        .map(|features| {
+            features
+                .iter()
+                .filter_map(|f| f.as_str().map(String::from))
+                .filter(|f| !removed.contains(f))
+                .collect()
+        })
+        .unwrap_or_default();
+    for feature in new.features().unwrap_or_default() {
+        if !features.contains(feature) {
+            features.push(feature.clone());
+        }
+    }
+    Some(features)
+}
+
 /// Print a message if the new dependency version is different from the old one.
 fn print_upgrade_if_necessary(
     crate_name: &str,
@@ -101,7 +319,7 @@ fn print_upgrade_if_necessary(
     } else if old_dep.is_table_like() {
         let version = old_dep["version"].clone();
         if version.is_none() {
-            return Err("Missing version field".into());
+            return Err(Error::MissingVersionField);
         }
         version
     } else {
@@ -116,19 +334,20 @@ fn print_upgrade_if_necessary(
         let mut buffer = bufwtr.buffer();
         buffer
             .set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))
-            .chain_err(|| "Failed to set output colour")?;
-        write!(&mut buffer, "    Upgrading ").chain_err(|| "Failed to write upgrade message")?;
+            .map_err(Error::io("Failed to set output colour"))?;
+        write!(&mut buffer, "    Upgrading ")
+            .map_err(Error::io("Failed to write upgrade message"))?;
         buffer
             .set_color(&ColorSpec::new())
-            .chain_err(|| "Failed to clear output colour")?;
+            .map_err(Error::io("Failed to clear output colour"))?;
         write!(
             &mut buffer,
             "{} v{} -> v{}\n",
             crate_name, old_version, new_version,
-        ).chain_err(|| "Failed to write upgrade versions")?;
+        ).map_err(Error::io("Failed to write upgrade versions"))?;
         bufwtr
             .print(&buffer)
-            .chain_err(|| "Failed to print upgrade message")?;
+            .map_err(Error::io("Failed to print upgrade message"))?;
     }
     Ok(())
 }
@@ -144,22 +363,47 @@ impl Manifest {
                 .read(true)
                 .write(true)
                 .open(path)
-                .chain_err(|| "Failed to find Cargo.toml")
+                .map_err(Error::io("Failed to find Cargo.toml"))
         })
     }
 
     /// Open the `Cargo.toml` for a path (or the process' `cwd`)
     pub fn open(path: &Option<PathBuf>) -> Result<Manifest> {
+        Manifest::open_resolving(path, None)
+    }
+
+    /// Open the `Cargo.toml` for a path (or the process' `cwd`). Unresolved merge conflicts in
+    /// dependency tables are resolved in favour of `side`, if one is given.
+    pub fn open_resolving(path: &Option<PathBuf>, side: Option<ConflictSide>) -> Result<Manifest> {
         let mut file = Manifest::find_file(path)?;
         let mut data = String::new();
         file.read_to_string(&mut data)
-            .chain_err(|| "Failed to read manifest contents")?;
+            .map_err(Error::io("Failed to read manifest contents"))?;
+
+        let data = match side {
+            Some(side) => resolve_conflicts(&data, side)?,
+            None => {
+                check_conflicts(&data)?;
+                data
+            }
+        };
 
-        data.parse().chain_err(|| "Unable to parse Cargo.toml")
+        data.parse()
+            .map_err(|e| Error::ManifestParse(Box::new(e)))
     }
 
     /// Get the specified table from the manifest.
     pub fn get_table<'a>(&'a mut self, table_path: &[String]) -> Result<&'a mut toml_edit::Item> {
+        self.get_table_at(table_path, SectionPlacement::End)
+    }
+
+    /// Get the specified table from the manifest. If its top-level section does not exist yet, it
+    /// is created at `placement`.
+    pub fn get_table_at<'a>(
+        &'a mut self,
+        table_path: &[String],
+        placement: SectionPlacement,
+    ) -> Result<&'a mut toml_edit::Item> {
         /// Descend into a manifest until the required table is found.
         fn descend<'a>(
             input: &'a mut toml_edit::Item,
@@ -171,27 +415,279 @@ impl Manifest {
                 if value.is_table_like() {
                     descend(value, &path[1..])
                 } else {
-                    Err(ErrorKind::NonExistentTable(segment.clone()).into())
+                    Err(Error::SectionMissing(segment.clone()))
                 }
             } else {
                 Ok(input)
             }
         }
 
+        if let Some(section) = table_path.get(0) {
+            if self.data[section].is_none() {
+                let root = self.data
+                    .root
+                    .as_table_mut()
+                    .expect("manifest root is a table");
+                insert_section(root, section, placement);
+            }
+        }
+
         descend(&mut self.data.root, table_path)
     }
 
+    /// Whether the package may be published, i.e. `package.publish` is neither `false` nor an empty
+    /// list of registries.
+    pub fn is_publishable(&self) -> bool {
+        let publish = &self.data["package"]["publish"];
+        match publish.as_bool() {
+            Some(publish) => publish,
+            None => publish.as_array().map(|a| a.len() > 0).unwrap_or(true),
+        }
+    }
+
+    /// Get the minimum supported Rust version declared in `package.rust-version`, if any.
+    pub fn rust_version(&self) -> Option<&str> {
+        self.data["package"]["rust-version"].as_str()
+    }
+
+    /// Get the Rust edition declared in `package.edition`, if any. An edition inherited from the
+    /// workspace is not resolved.
+    pub fn edition(&self) -> Option<&str> {
+        self.data["package"]["edition"].as_str()
+    }
+
+    /// Set `package.rust-version`.
+    pub fn set_rust_version(&mut self, version: &str) {
+        self.data["package"]["rust-version"] = toml_edit::value(version);
+    }
+
+    /// Get the crates for which any prerelease may be picked, as listed in
+    /// `package.metadata.cargo-edit.allow-prerelease` or
+    /// `workspace.metadata.cargo-edit.allow-prerelease`, e.g. `["bevy"]`.
+    pub fn prerelease_crates(&self) -> Vec<String> {
+        self.listed_crates("allow-prerelease")
+    }
+
+    /// Get the crates `cargo upgrade` leaves alone unless they are named, as listed in
+    /// `package.metadata.cargo-edit.ignore-upgrades` or
+    /// `workspace.metadata.cargo-edit.ignore-upgrades`, e.g. `["openssl"]`.
+    pub fn ignored_upgrades(&self) -> Vec<String> {
+        self.listed_crates("ignore-upgrades")
+    }
+
+    /// Get the crates listed in `key` of `package.metadata.cargo-edit` and
+    /// `workspace.metadata.cargo-edit`.
+    fn listed_crates(&self, key: &str) -> Vec<String> {
+        ["package", "workspace"]
+            .iter()
+            .filter_map(|&table| self.data[table]["metadata"]["cargo-edit"][key].as_array())
+            .flat_map(|crates| crates.iter().filter_map(|c| c.as_str().map(String::from)))
+            .collect()
+    }
+
+    /// Get the reason the crate `name` is held at its version, as given with `cargo add --reason`
+    /// and kept in `package.metadata.cargo-edit.pins` (or `workspace.metadata`).
+    pub fn pin_reason(&self, name: &str) -> Option<&str> {
+        ["package", "workspace"]
+            .iter()
+            .filter_map(|&table| self.data[table]["metadata"]["cargo-edit"]["pins"][name].as_str())
+            .next()
+    }
+
+    /// Declare the feature `name` in `[features]`, enabling `enables`, unless it exists already.
+    /// Returns whether it was added.
+    pub fn add_feature(&mut self, name: &str, enables: &[String]) -> Result<bool> {
+        let features = self.get_table(&["features".to_owned()])?;
+        if !features[name].is_none() {
+            return Ok(false);
+        }
+        let mut array = toml_edit::Array::default();
+        for feature in enables {
+            array.push(feature.as_str());
+        }
+        features[name] = toml_edit::value(toml_edit::Value::Array(array));
+        Ok(true)
+    }
+
+    /// Keep the reason the crate `name` is held at its version in
+    /// `package.metadata.cargo-edit.pins`, or in `workspace.metadata` of a virtual manifest.
+    pub fn set_pin_reason(&mut self, name: &str, reason: &str) -> Result<()> {
+        let table = if self.data["package"].is_none() && !self.data["workspace"].is_none() {
+            "workspace"
+        } else {
+            "package"
+        };
+        let pins: Vec<String> = [table, "metadata", "cargo-edit", "pins"]
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        self.get_table(&pins)?[name] = toml_edit::value(reason);
+        Ok(())
+    }
+
+    /// Forget the reason the crate `name` was held at its version, e.g. once it is removed. The
+    /// `pins` table is removed once it is empty.
+    pub fn remove_pin_reason(&mut self, name: &str) {
+        for &table in &["package", "workspace"] {
+            // Only look into tables that exist, as indexing creates them.
+            if self.data[table]["metadata"]["cargo-edit"]["pins"][name].is_none() {
+                continue;
+            }
+            let cargo_edit = &mut self.data[table]["metadata"]["cargo-edit"];
+            let empty = cargo_edit["pins"].as_table_mut().map_or(false, |pins| {
+                pins.remove(name);
+                pins.is_empty()
+            });
+            if empty {
+                cargo_edit.as_table_mut().map(|cargo_edit| cargo_edit.remove("pins"));
+            }
+        }
+    }
+
+    /// Collect warnings about how the dependency sections are written: sections that appear under
+    /// both spellings (`dev-dependencies` and `dev_dependencies`), the deprecated spelling, and
+    /// entries that name the same crate (see `duplicate_crate_names`).
+    pub fn lint(&self, warnings: &mut Warnings) {
+        for &(section, alias) in &[
+            ("dev-dependencies", "dev_dependencies"),
+            ("build-dependencies", "build_dependencies"),
+        ] {
+            if self.data[alias].is_none() {
+                continue;
+            }
+            if self.data[section].is_none() {
+                warnings.push(
+                    WarningKind::Style,
+                    format!("`[{}]` is a deprecated spelling of `[{}]`", alias, section),
+                );
+            } else {
+                warnings.push(
+                    WarningKind::DuplicateSection,
+                    format!(
+                        "Both `[{}]` and `[{}]` exist; merge them into `[{}]`",
+                        section, alias, section
+                    ),
+                );
+            }
+        }
+
+        for (section, names) in self.duplicate_crate_names() {
+            let names: Vec<_> = names.iter().map(|name| format!("`{}`", name)).collect();
+            warnings.push(
+                WarningKind::DuplicateCrate,
+                format!(
+                    "{} in {} are the same crate, as crates.io does not tell case, `-` and `_` \
+                     apart; merge them with `cargo dep dedupe`",
+                    names.join(" and "),
+                    section
+                ),
+            );
+        }
+    }
+
+    /// Find the entries of each dependency section whose names differ only in case, `-` and `_`,
+    /// which crates.io treats as the same crate. Returns the names of each group of such entries,
+    /// in the order of the section. Renamed entries (with `package`) are left out.
+    pub fn duplicate_crate_names(&self) -> Vec<(DepTable, Vec<String>)> {
+        let mut duplicates = Vec::new();
+        for (section, table) in self.get_sections() {
+            let table = table.as_table_like().expect("Unexpected non-table");
+            let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+            // Removed entries stay in the table as `Item::None`.
+            let entries = table
+                .iter()
+                .filter(|&(_, entry)| !entry.is_none() && entry["package"].is_none());
+            for (name, _) in entries {
+                let canonical = canonical_crate_name(name);
+                match groups.iter().position(|&(ref other, _)| *other == canonical) {
+                    Some(i) => groups[i].1.push(name.to_owned()),
+                    None => groups.push((canonical, vec![name.to_owned()])),
+                }
+            }
+            duplicates.extend(
+                groups
+                    .into_iter()
+                    .filter(|&(_, ref names)| names.len() > 1)
+                    .map(|(_, names)| (section.clone(), names)),
+            );
+        }
+        duplicates
+    }
+
+    /// Get the features the package offers: the `[features]` table, and the implicit features of
+    /// its optional dependencies, like `get_crate_features` lists them for a crate of the registry.
+    pub fn features(&self) -> Vec<FeatureInfo> {
+        let features: BTreeMap<String, Vec<String>> = self.data["features"]
+            .as_table_like()
+            .map(|table| {
+                table
+                    .iter()
+                    .map(|(name, enables)| {
+                        let enables = enables
+                            .as_array()
+                            .map(|enables| {
+                                enables
+                                    .iter()
+                                    .filter_map(|f| f.as_str().map(String::from))
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+                        (name.to_owned(), enables)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut optional = BTreeSet::new();
+        for (_, table) in self.get_sections() {
+            let table = table.as_table_like().expect("Unexpected non-table");
+            for (name, entry) in table.iter() {
+                if entry["optional"].as_bool() == Some(true) {
+                    optional.insert(name.to_owned());
+                }
+            }
+        }
+        let optional = optional.iter().map(String::as_str).collect();
+        features_from(features, &optional)
+    }
+
+    /// Get the binary targets declared with `[[bin]]`. Entries without a name are skipped.
+    pub fn bin_targets(&self) -> Vec<BinTarget> {
+        let bins = match self.data["bin"].as_array_of_tables() {
+            Some(bins) => bins,
+            None => return vec![],
+        };
+        bins.iter()
+            .filter_map(|bin| {
+                let name = bin.get("name").and_then(toml_edit::Item::as_str)?;
+                let required_features = bin.get("required-features")
+                    .and_then(toml_edit::Item::as_array)
+                    .map(|features| {
+                        features
+                            .iter()
+                            .filter_map(|f| f.as_str().map(String::from))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Some(BinTarget {
+                    name: name.to_owned(),
+                    required_features: required_features,
+                })
+            })
+            .collect()
+    }
+
     /// Get all sections in the manifest that exist and might contain dependencies.
     /// The returned items are always `Table` or `InlineTable`.
-    pub fn get_sections(&self) -> Vec<(Vec<String>, toml_edit::Item)> {
+    pub fn get_sections(&self) -> Vec<(DepTable, toml_edit::Item)> {
         let mut sections = Vec::new();
 
-        for dependency_type in &["dev-dependencies", "build-dependencies", "dependencies"] {
+        for &kind in DepKind::ALL {
+            let kind_table = kind.kind_table();
             // Dependencies can be in the three standard sections...
-            if self.data[dependency_type].is_table_like() {
+            if self.data[kind_table].is_table_like() {
                 sections.push((
-                    vec![dependency_type.to_string()],
-                    self.data[dependency_type].clone(),
+                    DepTable::new().set_kind(kind),
+                    self.data[kind_table].clone(),
                 ))
             }
 
@@ -203,14 +699,10 @@ impl Manifest {
                 .into_iter()
                 .flat_map(|t| t.iter())
                 .filter_map(|(target_name, target_table)| {
-                    let dependency_table = &target_table[dependency_type];
+                    let dependency_table = &target_table[kind_table];
                     dependency_table.as_table_like().map(|_| {
                         (
-                            vec![
-                                "target".to_string(),
-                                target_name.to_string(),
-                                dependency_type.to_string(),
-                            ],
+                            DepTable::new().set_kind(kind).set_target(target_name),
                             dependency_table.clone(),
                         )
                     })
@@ -222,35 +714,166 @@ impl Manifest {
         sections
     }
 
-    /// Overwrite a file with TOML data.
-    pub fn write_to_file(&self, file: &mut File) -> Result<()> {
+    /// Check that the manifest can be edited as the one of a package: it has a `[package]` table,
+    /// and its dependency sections are tables. This only looks at the parsed manifest, so commands
+    /// check it before they look anything up, instead of failing once they write the manifest.
+    pub fn validate(&self) -> Result<()> {
+        self.check_package()?;
+        let targets = &self.data["target"];
+        if !targets.is_none() && !targets.is_table_like() {
+            return Err(Error::InvalidSection("target".to_owned()));
+        }
+        let target_names: Vec<&str> = targets
+            .as_table_like()
+            .map(|targets| targets.iter().map(|(name, _)| name).collect())
+            .unwrap_or_default();
+        for &kind in DepKind::ALL {
+            let kind_table = kind.kind_table();
+            let mut sections = vec![(kind_table.to_owned(), &self.data[kind_table])];
+            for &target in &target_names {
+                sections.push((
+                    format!("target.{}.{}", target, kind_table),
+                    &targets[target][kind_table],
+                ));
+            }
+            for (name, section) in sections {
+                if !section.is_none() && !section.is_table_like() {
+                    return Err(Error::InvalidSection(name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the manifest is one of a package, as `write_to_file` needs.
+    fn check_package(&self) -> Result<()> {
         if self.data["package"].is_none() && self.data["project"].is_none() {
             if !self.data["workspace"].is_none() {
-                Err(ErrorKind::UnexpectedRootManifest)?;
+                return Err(Error::UnexpectedRootManifest);
             } else {
-                Err(ErrorKind::InvalidManifest)?;
+                return Err(Error::InvalidManifest);
             }
         }
+        Ok(())
+    }
+
+    /// Check that the manifest is one of a workspace root, as `write_workspace_to_file` needs.
+    fn check_workspace(&self) -> Result<()> {
+        if self.data["workspace"].is_none() {
+            return Err(Error::NotWorkspaceRoot);
+        }
+        Ok(())
+    }
+
+    /// Overwrite a file with TOML data.
+    ///
+    /// The file is written in place, so an interrupted write may leave it half written: an atomic
+    /// write needs a staging file in the same directory to rename over the old one, and a `File`
+    /// does not tell which path (if any) it was opened from. Use `write_to_path` where the path is
+    /// known.
+    pub fn write_to_file(&self, file: &mut File) -> Result<()> {
+        self.check_package()?;
+        self.write_unchecked(file)
+    }
+
+    /// Overwrite a file with the TOML data of a workspace root, which may be a virtual manifest.
+    /// Like `write_to_file`, this is not atomic.
+    pub fn write_workspace_to_file(&self, file: &mut File) -> Result<()> {
+        self.check_workspace()?;
+        self.write_unchecked(file)
+    }
+
+    /// Overwrite the `Cargo.toml` for a path (or the process' `cwd`, see `find`) with TOML data.
+    ///
+    /// The write is atomic: the data goes to a new file next to the manifest first, which then
+    /// replaces it. If the process is interrupted (e.g. with Ctrl-C), the manifest is either left
+    /// untouched or fully updated.
+    pub fn write_to_path(&self, path: &Option<PathBuf>) -> Result<()> {
+        self.check_package()?;
+        write_atomically(&find(path)?, &self.to_string(), |from, to| fs::rename(from, to))
+    }
 
-        let s = self.data.to_string();
+    /// Overwrite the `Cargo.toml` of a workspace root, which may be a virtual manifest, with TOML
+    /// data. The write is atomic, like the one of `write_to_path`.
+    pub fn write_workspace_to_path(&self, path: &Option<PathBuf>) -> Result<()> {
+        self.check_workspace()?;
+        write_atomically(&find(path)?, &self.to_string(), |from, to| fs::rename(from, to))
+    }
+
+    fn write_unchecked(&self, file: &mut File) -> Result<()> {
+        // Keep the byte order mark and line endings of the file. A new file, or one opened only for
+        // writing, gets neither.
+        let mut old_contents = Vec::new();
+        let _ = file.read_to_end(&mut old_contents);
+        file.seek(SeekFrom::Start(0))
+            .map_err(Error::io("Failed to write updated Cargo.toml"))?;
+        let s = TextFormat::detect(&old_contents).apply(&self.to_string());
         let new_contents_bytes = s.as_bytes();
 
         // We need to truncate the file, otherwise the new contents
         // will be mixed up with the old ones.
         file.set_len(new_contents_bytes.len() as u64)
-            .chain_err(|| "Failed to truncate Cargo.toml")?;
+            .map_err(Error::io("Failed to truncate Cargo.toml"))?;
         file.write_all(new_contents_bytes)
-            .chain_err(|| "Failed to write updated Cargo.toml")
+            .map_err(Error::io("Failed to write updated Cargo.toml"))
     }
 
     /// Add entry to a Cargo.toml.
-    pub fn insert_into_table(&mut self, table_path: &[String], dep: &Dependency) -> Result<()> {
-        let table = self.get_table(table_path)?;
+    pub fn insert_into_table(&mut self, table: &DepTable, dep: &Dependency) -> Result<()> {
+        self.insert_into_table_at(table, dep, SectionPlacement::End)
+    }
+
+    /// Add entry to a Cargo.toml. If the section does not exist yet, it is created at `placement`.
+    pub fn insert_into_table_at(
+        &mut self,
+        table: &DepTable,
+        dep: &Dependency,
+        placement: SectionPlacement,
+    ) -> Result<()> {
+        self.insert_at(&table.to_table(), dep, placement)
+    }
+
+    /// Add entry to a Cargo.toml, like `insert_into_table_at`. A new entry is listed below the
+    /// `# <label>` comment in the table (see `cargo add --group`), which is added if missing; an
+    /// existing entry stays where it is.
+    pub fn insert_into_group(
+        &mut self,
+        table: &DepTable,
+        dep: &Dependency,
+        placement: SectionPlacement,
+        label: &str,
+    ) -> Result<()> {
+        let table_path = table.to_table();
+        let is_new = self.get_table_at(&table_path, placement)?[&dep.name].is_none();
+        self.insert_at(&table_path, dep, placement)?;
+        if is_new {
+            *self = move_into_group(&self.to_string(), &table_path, &dep.name, label).parse()?;
+        }
+        Ok(())
+    }
+
+    /// Add entry to `[workspace.dependencies]` of a workspace root.
+    pub fn insert_into_workspace(&mut self, dep: &Dependency) -> Result<()> {
+        self.insert_at(&workspace_dependencies(), dep, SectionPlacement::End)
+    }
+
+    fn insert_at(
+        &mut self,
+        table_path: &[String],
+        dep: &Dependency,
+        placement: SectionPlacement,
+    ) -> Result<()> {
+        let style = Style::detect(&self.to_string());
+        let table = self.get_table_at(table_path, placement)?;
 
         if table[&dep.name].is_none() {
             // insert a new entry
             let (ref name, ref mut new_dependency) = dep.to_toml();
             table[name] = new_dependency.clone();
+            // in the style of the entries around it
+            if style != Style::default() {
+                *self = restyle_entry(&self.to_string(), table_path, name, &style).parse()?;
+            }
         } else {
             // update an existing entry
             merge_dependencies(&mut table[&dep.name], dep);
@@ -262,15 +885,25 @@ impl Manifest {
     /// Update an entry in Cargo.toml.
     pub fn update_table_entry(
         &mut self,
-        table_path: &[String],
+        table: &DepTable,
         dep: &Dependency,
         dry_run: bool,
     ) -> Result<()> {
+        self.update_at(&table.to_table(), dep, dry_run)
+    }
+
+    /// Update an entry in `[workspace.dependencies]` of a workspace root.
+    pub fn update_workspace_entry(&mut self, dep: &Dependency, dry_run: bool) -> Result<()> {
+        self.update_at(&workspace_dependencies(), dep, dry_run)
+    }
+
+    fn update_at(&mut self, table_path: &[String], dep: &Dependency, dry_run: bool) -> Result<()> {
         let table = self.get_table(table_path)?;
         let new_dep = dep.to_toml().1;
 
-        // If (and only if) there is an old entry, merge the new one in.
-        if !table[&dep.name].is_none() {
+        // If (and only if) there is an old entry, merge the new one in. Entries inherited from the
+        // workspace get their version from the workspace root.
+        if !table[&dep.name].is_none() && !is_inherited(&table[&dep.name]) {
             if let Err(e) = print_upgrade_if_necessary(&dep.name, &table[&dep.name], &new_dep) {
                 eprintln!("Error while displaying upgrade message, {}", e);
             }
@@ -283,7 +916,38 @@ impl Manifest {
         Ok(())
     }
 
-    /// Remove entry from a Cargo.toml.
+    /// Let an entry inherit its dependency from `[workspace.dependencies]` of the workspace root.
+    ///
+    /// The source and version of the entry are replaced by `workspace = true`; the features it
+    /// adds and whether it is optional are kept.
+    pub fn inherit_dependency(&mut self, table: &DepTable, name: &str) -> Result<()> {
+        let table_path = table.to_table();
+        let table = self.get_table(&table_path)?;
+        if table[name].is_none() {
+            return Err(Error::DependencyMissing(name.into(), table_path.join(".")));
+        }
+
+        let mut inherited = toml_edit::InlineTable::default();
+        inherited.get_or_insert("workspace", true);
+        if let Some(old) = table[name].as_table_like() {
+            for (key, value) in old.iter() {
+                match value.as_value() {
+                    Some(value) if INHERITABLE_KEYS.contains(&key) => {
+                        inherited.get_or_insert(key, value.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        inherited.fmt();
+
+        table[name] = toml_edit::value(toml_edit::Value::InlineTable(inherited));
+        table.as_inline_table_mut().map(|t| t.fmt());
+        Ok(())
+    }
+
+    /// Remove entry from a Cargo.toml, returning the removed entry. Tables left empty are removed,
+    /// too.
     ///
     /// # Examples
     ///
@@ -291,40 +955,45 @@ impl Manifest {
     /// # extern crate cargo_edit;
     /// # extern crate toml_edit;
     /// # fn main() {
-    ///     use cargo_edit::{Dependency, Manifest};
+    ///     use cargo_edit::{DepTable, Dependency, Manifest};
     ///     use toml_edit;
     ///
     ///     let mut manifest = Manifest { data: toml_edit::Document::new() };
     ///     let dep = Dependency::new("cargo-edit").set_version("0.1.0");
-    ///     let _ = manifest.insert_into_table(&vec!["dependencies".to_owned()], &dep);
-    ///     assert!(manifest.remove_from_table("dependencies", &dep.name).is_ok());
-    ///     assert!(manifest.remove_from_table("dependencies", &dep.name).is_err());
+    ///     let table = DepTable::new();
+    ///     let _ = manifest.insert_into_table(&table, &dep);
+    ///     let removed = manifest.remove_from_table(&table, &dep.name).unwrap();
+    ///     assert_eq!(removed.as_str(), Some("0.1.0"));
+    ///     assert!(manifest.remove_from_table(&table, &dep.name).is_err());
     ///     assert!(manifest.data["dependencies"].is_none());
     /// # }
     /// ```
-    pub fn remove_from_table(&mut self, table: &str, name: &str) -> Result<()> {
-        if !self.data[table].is_table_like() {
-            Err(ErrorKind::NonExistentTable(table.into()))?;
-        } else {
-            {
-                let dep = &mut self.data[table][name];
-                if dep.is_none() {
-                    Err(ErrorKind::NonExistentDependency(name.into(), table.into()))?;
-                }
-                // remove the dependency
-                *dep = toml_edit::Item::None;
-            }
+    pub fn remove_from_table(&mut self, table: &DepTable, name: &str) -> Result<toml_edit::Item> {
+        let table_path = table.to_table();
+        let section = table_path.iter().fold(&self.data.root, |item, key| &item[key.as_str()]);
+        if !section.is_table_like() {
+            return Err(Error::SectionMissing(table_path.join(".")));
+        }
+        if section[name].is_none() {
+            return Err(Error::DependencyMissing(name.into(), table_path.join(".")));
+        }
+
+        let removed = mem::replace(&mut self.get_table(&table_path)?[name], toml_edit::Item::None);
 
-            // remove table if empty
-            if self.data[table].as_table_like().unwrap().is_empty() {
-                self.data[table] = toml_edit::Item::None;
+        // remove the tables left empty, e.g. `[target.'cfg(unix)'.dependencies]` and `[target]`
+        for depth in (1..table_path.len() + 1).rev() {
+            let table = self.get_table(&table_path[..depth])?;
+            if table.as_table_like().map(|t| t.is_empty()).unwrap_or(false) {
+                *table = toml_edit::Item::None;
+            } else {
+                break;
             }
         }
-        Ok(())
+        Ok(removed)
     }
 
     /// Add multiple dependencies to manifest
-    pub fn add_deps(&mut self, table: &[String], deps: &[Dependency]) -> Result<()> {
+    pub fn add_deps(&mut self, table: &DepTable, deps: &[Dependency]) -> Result<()> {
         deps.iter()
             .map(|dep| self.insert_into_table(table, dep))
             .collect::<Result<Vec<_>>>()?;
@@ -333,17 +1002,51 @@ impl Manifest {
     }
 }
 
+/// Path of `[workspace.dependencies]`
+fn workspace_dependencies() -> Vec<String> {
+    vec!["workspace".to_owned(), "dependencies".to_owned()]
+}
+
+/// Parse a manifest from a string, without touching the file system.
+///
+/// The formatting of the input (comments, whitespace, order of tables) is preserved, so turning
+/// the manifest back into a string with `to_string` gives back the input unless it was edited.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate cargo_edit;
+/// # fn main() {
+///     use cargo_edit::{DepTable, Dependency, Manifest};
+///
+///     let input = "[package]\nname = \"foo\" # the name\n\n[dependencies]\n";
+///     let mut manifest: Manifest = input.parse().unwrap();
+///     assert_eq!(manifest.to_string(), input);
+///
+///     let dep = Dependency::new("regex").set_version("0.2");
+///     manifest.insert_into_table(&DepTable::new(), &dep).unwrap();
+///     assert!(manifest.to_string().ends_with("[dependencies]\nregex = \"0.2\"\n"));
+/// # }
+/// ```
 impl str::FromStr for Manifest {
     type Err = Error;
 
-    /// Read manifest data from string
+    /// Read manifest data from string, without its byte order mark and with `\n` line endings
     fn from_str(input: &str) -> ::std::result::Result<Self, Self::Err> {
-        let d: toml_edit::Document = input.parse().chain_err(|| "Manifest not valid TOML")?;
+        let input = input.trim_start_matches(BOM).replace("\r\n", "\n");
+        let d: toml_edit::Document = input.parse().map_err(Error::InvalidToml)?;
 
         Ok(Manifest { data: d })
     }
 }
 
+/// Write the manifest as TOML, preserving the formatting it was parsed with.
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.data)
+    }
+}
+
 /// A Cargo manifest that is available locally.
 #[derive(Debug)]
 pub struct LocalManifest {
@@ -378,27 +1081,62 @@ impl LocalManifest {
         })
     }
 
-    /// Get the `File` corresponding to this manifest.
-    fn get_file(&self) -> Result<File> {
-        Manifest::find_file(&Some(self.path.clone()))
+    /// Write the manifest back to its file, atomically (see `Manifest::write_to_path`).
+    fn write(&self) -> Result<()> {
+        self.write_to_path(&Some(self.path.clone()))
     }
 
     /// Instruct this manifest to upgrade a single dependency. If this manifest does not have that
     /// dependency, it does nothing.
     pub fn upgrade(&mut self, dependency: &Dependency, dry_run: bool) -> Result<()> {
+        self.upgrade_where(dependency, dry_run, |_| true)
+    }
+
+    /// Like `upgrade`, but only the entries for which `include` returns `true` are upgraded (e.g.
+    /// to leave git dependencies alone).
+    pub fn upgrade_where<F>(
+        &mut self,
+        dependency: &Dependency,
+        dry_run: bool,
+        include: F,
+    ) -> Result<()>
+    where
+        F: Fn(&toml_edit::Item) -> bool,
+    {
         for (table_path, table) in self.get_sections() {
             let table_like = table.as_table_like().expect("Unexpected non-table");
-            for (name, _old_value) in table_like.iter() {
-                if name == dependency.name {
+            for (name, old_value) in table_like.iter() {
+                if name == dependency.name && include(old_value) {
                     self.manifest
                         .update_table_entry(&table_path, dependency, dry_run)?;
                 }
             }
         }
 
-        let mut file = self.get_file()?;
-        self.write_to_file(&mut file)
-            .chain_err(|| "Failed to write new manifest contents")
+        self.write()
+    }
+
+    /// Pin the git dependency `name` in `table` to the commit `rev`, replacing its old `rev`.
+    pub fn set_git_rev(
+        &mut self,
+        table: &DepTable,
+        name: &str,
+        rev: &str,
+        dry_run: bool,
+    ) -> Result<()> {
+        let table_path = table.to_table();
+        {
+            let entry = &mut self.manifest.get_table(&table_path)?[name];
+            if entry["git"].is_none() {
+                return Err(Error::DependencyMissing(name.into(), table_path.join(".")));
+            }
+            entry["rev"] = toml_edit::value(rev);
+            entry.as_inline_table_mut().map(|t| t.fmt());
+        }
+        if dry_run {
+            return Ok(());
+        }
+        self.write()
     }
 }
 
@@ -406,8 +1144,53 @@ impl LocalManifest {
 mod tests {
     use dependency::Dependency;
     use super::*;
+    use tempdir::TempDir;
     use toml_edit;
 
+    const OLD_MANIFEST: &str = "[package]\nname = \"old\"\n";
+    const NEW_MANIFEST: &str = "[package]\nname = \"new\"\n";
+
+    /// The names of the files in `dir`.
+    fn file_names(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn write_atomically_through_staging_file() {
+        let dir = TempDir::new("cargo-edit-test").unwrap();
+        let path = dir.path().join("Cargo.toml");
+        fs::write(&path, OLD_MANIFEST).unwrap();
+
+        write_atomically(&path, NEW_MANIFEST, |from, to| {
+            // The manifest is untouched until the staging file replaces it.
+            assert_eq!(fs::read_to_string(from).unwrap(), NEW_MANIFEST);
+            assert_eq!(fs::read_to_string(to).unwrap(), OLD_MANIFEST);
+            fs::rename(from, to)
+        }).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), NEW_MANIFEST);
+        assert_eq!(file_names(dir.path()), vec!["Cargo.toml"]);
+    }
+
+    #[test]
+    fn failed_write_leaves_manifest_intact() {
+        let dir = TempDir::new("cargo-edit-test").unwrap();
+        let path = dir.path().join("Cargo.toml");
+        fs::write(&path, OLD_MANIFEST).unwrap();
+
+        let result = write_atomically(&path, NEW_MANIFEST, |_, _| {
+            Err(io::Error::new(io::ErrorKind::Other, "interrupted"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), OLD_MANIFEST);
+        // The staging file is removed again.
+        assert_eq!(file_names(dir.path()), vec!["Cargo.toml"]);
+    }
+
     #[test]
     fn add_remove_dependency() {
         let mut manifest = Manifest {
@@ -415,10 +1198,10 @@ mod tests {
         };
         let clone = manifest.clone();
         let dep = Dependency::new("cargo-edit").set_version("0.1.0");
-        let _ = manifest.insert_into_table(&["dependencies".to_owned()], &dep);
+        let _ = manifest.insert_into_table(&DepTable::new(), &dep);
         assert!(
             manifest
-                .remove_from_table("dependencies", &dep.name)
+                .remove_from_table(&DepTable::new(), &dep.name)
                 .is_ok()
         );
         assert_eq!(manifest.data.to_string(), clone.data.to_string());
@@ -431,12 +1214,12 @@ mod tests {
         };
         let dep = Dependency::new("cargo-edit").set_version("0.1.0");
         manifest
-            .insert_into_table(&["dependencies".to_owned()], &dep)
+            .insert_into_table(&DepTable::new(), &dep)
             .unwrap();
 
         let new_dep = Dependency::new("cargo-edit").set_version("0.2.0");
         manifest
-            .update_table_entry(&["dependencies".to_owned()], &new_dep, false)
+            .update_table_entry(&DepTable::new(), &new_dep, false)
             .unwrap();
     }
 
@@ -447,13 +1230,13 @@ mod tests {
         };
         let dep = Dependency::new("cargo-edit").set_version("0.1.0");
         manifest
-            .insert_into_table(&["dependencies".to_owned()], &dep)
+            .insert_into_table(&DepTable::new(), &dep)
             .unwrap();
         let original = manifest.clone();
 
         let new_dep = Dependency::new("wrong-dep").set_version("0.2.0");
         manifest
-            .update_table_entry(&["dependencies".to_owned()], &new_dep, false)
+            .update_table_entry(&DepTable::new(), &new_dep, false)
             .unwrap();
 
         assert_eq!(manifest.data.to_string(), original.data.to_string());
@@ -467,7 +1250,7 @@ mod tests {
         let dep = Dependency::new("cargo-edit").set_version("0.1.0");
         assert!(
             manifest
-                .remove_from_table("dependencies", &dep.name)
+                .remove_from_table(&DepTable::new(), &dep.name)
                 .is_err()
         );
     }
@@ -479,11 +1262,329 @@ mod tests {
         };
         let dep = Dependency::new("cargo-edit").set_version("0.1.0");
         let other_dep = Dependency::new("other-dep").set_version("0.1.0");
-        let _ = manifest.insert_into_table(&["dependencies".to_owned()], &other_dep);
+        let _ = manifest.insert_into_table(&DepTable::new(), &other_dep);
         assert!(
             manifest
-                .remove_from_table("dependencies", &dep.name)
+                .remove_from_table(&DepTable::new(), &dep.name)
                 .is_err()
         );
     }
+
+    #[test]
+    fn insert_keeps_keys_of_existing_entry() {
+        let mut manifest: Manifest = r#"[package]
+name = "foo"
+
+[dependencies]
+bar = { version = "0.8", features = ["derive"], default-features = false, optional = true }
+
+[dependencies.baz]
+version = "0.1"
+features = ["std"]
+"#.parse()
+            .unwrap();
+        let section = DepTable::new();
+
+        let bar = Dependency::new("bar").set_version("0.9");
+        manifest.insert_into_table(&section, &bar).unwrap();
+        let baz = Dependency::new("baz")
+            .set_version("0.2")
+            .set_features(Some(vec!["alloc".into(), "std".into()]));
+        manifest.insert_into_table(&section, &baz).unwrap();
+
+        let bar = &manifest.data["dependencies"]["bar"];
+        assert_eq!(bar["version"].as_str(), Some("0.9"));
+        assert_eq!(bar["default-features"].as_bool(), Some(false));
+        assert_eq!(bar["optional"].as_bool(), Some(true));
+        assert_eq!(bar["features"].as_array().map(|a| a.len()), Some(1));
+        let baz = &manifest.data["dependencies"]["baz"];
+        assert_eq!(baz["version"].as_str(), Some("0.2"));
+        let features: Vec<_> = baz["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|f| f.as_str())
+            .collect();
+        assert_eq!(features, vec!["std", "alloc"]);
+    }
+
+    #[test]
+    fn inherit_dependency_keeps_features() {
+        let mut manifest: Manifest = r#"[package]
+name = "foo"
+
+[dependencies]
+bar = "0.1"
+baz = { version = "0.2", features = ["std"], optional = true }
+"#.parse()
+            .unwrap();
+        let section = DepTable::new();
+
+        manifest.inherit_dependency(&section, "bar").unwrap();
+        manifest.inherit_dependency(&section, "baz").unwrap();
+        assert!(manifest.inherit_dependency(&section, "qux").is_err());
+
+        let bar = &manifest.data["dependencies"]["bar"];
+        assert_eq!(bar["workspace"].as_bool(), Some(true));
+        let baz = &manifest.data["dependencies"]["baz"];
+        assert_eq!(baz["workspace"].as_bool(), Some(true));
+        assert!(baz["version"].is_none());
+        assert_eq!(baz["optional"].as_bool(), Some(true));
+        assert_eq!(baz["features"].as_array().map(|a| a.len()), Some(1));
+
+        // Inherited entries are upgraded in the workspace root, not in the member.
+        let new_dep = Dependency::new("bar").set_version("0.3");
+        manifest.update_table_entry(&section, &new_dep, false).unwrap();
+        assert!(manifest.data["dependencies"]["bar"]["version"].is_none());
+    }
+
+    #[test]
+    fn remove_target_dependency() {
+        let mut manifest: Manifest = r#"[package]
+name = "foo"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+"#.parse()
+            .unwrap();
+        let unix = DepTable::new().set_target("cfg(unix)");
+
+        let sections: Vec<_> = manifest.get_sections().into_iter().map(|(t, _)| t).collect();
+        assert_eq!(sections, vec![unix.clone()]);
+
+        let removed = manifest.remove_from_table(&unix, "libc").unwrap();
+        assert_eq!(removed.as_str(), Some("0.2"));
+        assert!(manifest.data["target"].is_none());
+        assert!(manifest.remove_from_table(&unix, "libc").is_err());
+    }
+
+    #[test]
+    fn lint_section_spellings() {
+        let manifest: Manifest = r#"[package]
+name = "foo"
+
+[dev-dependencies]
+a = "1.0"
+
+[dev_dependencies]
+b = "1.0"
+
+[build_dependencies]
+c = "1.0"
+"#.parse()
+            .unwrap();
+
+        let mut warnings = Warnings::new();
+        manifest.lint(&mut warnings);
+        let kinds: Vec<_> = warnings.iter().map(|w| w.kind).collect();
+        assert_eq!(kinds, vec![WarningKind::DuplicateSection, WarningKind::Style]);
+    }
+
+    #[test]
+    fn list_package_features() {
+        let manifest: Manifest = r#"[package]
+name = "foo"
+
+[features]
+default = ["std"]
+std = []
+serde = ["dep:serde"]
+
+[dependencies]
+serde = { version = "1.0", optional = true }
+log = { version = "0.4", optional = true }
+"#.parse()
+            .unwrap();
+
+        let features = manifest.features();
+        let names: Vec<_> = features.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["default", "log", "serde", "std"]);
+        assert!(features[3].default);
+        assert_eq!(features[2].optional_deps, vec!["serde"]);
+    }
+
+    #[test]
+    fn validate_manifests() {
+        let valid: Manifest = r#"[package]
+name = "foo"
+
+[dependencies]
+libc = "0.2"
+
+[target.'cfg(unix)'.dev-dependencies]
+tempdir = "0.3"
+"#.parse()
+            .unwrap();
+        assert!(valid.validate().is_ok());
+
+        let virtual_manifest: Manifest = "[workspace]\nmembers = [\"foo\"]\n".parse().unwrap();
+        match virtual_manifest.validate() {
+            Err(Error::UnexpectedRootManifest) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let invalid: Manifest = r#"[package]
+name = "foo"
+
+[target.'cfg(unix)']
+dependencies = "libc"
+"#.parse()
+            .unwrap();
+        match invalid.validate() {
+            Err(Error::InvalidSection(ref table)) => {
+                assert_eq!(table, "target.cfg(unix).dependencies")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn find_duplicate_crate_names() {
+        let manifest: Manifest = r#"[package]
+name = "foo"
+
+[dependencies]
+Inflector = "0.11"
+serde_json = "1.0"
+inflector = { version = "0.11", features = ["heavyweight"] }
+serde-json = "1.0"
+json = { package = "serde_json", version = "1.0" }
+
+[dev-dependencies]
+serde_json = "1.0"
+"#.parse()
+            .unwrap();
+
+        assert_eq!(
+            manifest.duplicate_crate_names(),
+            vec![
+                (
+                    DepTable::new(),
+                    vec!["Inflector".to_owned(), "inflector".to_owned()],
+                ),
+                (
+                    DepTable::new(),
+                    vec!["serde_json".to_owned(), "serde-json".to_owned()],
+                ),
+            ]
+        );
+
+        let mut warnings = Warnings::new();
+        manifest.lint(&mut warnings);
+        let messages: Vec<_> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            messages[0],
+            "`Inflector` and `inflector` in dependencies are the same crate, as crates.io does \
+             not tell case, `-` and `_` apart; merge them with `cargo dep dedupe`"
+        );
+    }
+
+    #[test]
+    fn list_bin_targets() {
+        let manifest: Manifest = r#"[package]
+name = "foo"
+
+[[bin]]
+name = "server"
+
+[[bin]]
+name = "cli"
+required-features = ["cli", "color"]
+"#.parse()
+            .unwrap();
+
+        assert_eq!(
+            manifest.bin_targets(),
+            vec![
+                BinTarget {
+                    name: "server".into(),
+                    required_features: vec![],
+                },
+                BinTarget {
+                    name: "cli".into(),
+                    required_features: vec!["cli".into(), "color".into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn publishable_packages() {
+        let manifest = |publish: &str| -> Manifest {
+            format!("[package]\nname = \"foo\"\n{}", publish)
+                .parse()
+                .unwrap()
+        };
+        assert!(manifest("").is_publishable());
+        assert!(manifest("publish = true\n").is_publishable());
+        assert!(manifest("publish = [\"my-registry\"]\n").is_publishable());
+        assert!(!manifest("publish = false\n").is_publishable());
+        assert!(!manifest("publish = []\n").is_publishable());
+    }
+
+    #[test]
+    fn place_new_sections() {
+        let input = "[package]\nname = \"foo\"\n\n[dependencies]\nbar = \"0.1\"\n\n\
+                     [features]\ndefault = []\n";
+        let dep = Dependency::new("baz").set_version("0.2");
+        let dev = DepTable::new().set_kind(DepKind::Development);
+
+        let mut manifest: Manifest = input.parse().unwrap();
+        manifest.insert_into_table(&dev, &dep).unwrap();
+        let output = manifest.data.to_string();
+        assert!(output.find("[features]") < output.find("[dev-dependencies]"));
+
+        for placement in &[
+            SectionPlacement::AfterDependencies,
+            SectionPlacement::BeforeFeatures,
+        ] {
+            let mut manifest: Manifest = input.parse().unwrap();
+            manifest.insert_into_table_at(&dev, &dep, *placement).unwrap();
+            let output = manifest.data.to_string();
+            assert!(output.find("[dependencies]") < output.find("[dev-dependencies]"));
+            assert!(output.find("[dev-dependencies]") < output.find("[features]"));
+        }
+    }
+
+    #[test]
+    fn parse_section_placement() {
+        assert_eq!(
+            "before-features".parse::<SectionPlacement>().unwrap(),
+            SectionPlacement::BeforeFeatures
+        );
+        assert!("middle".parse::<SectionPlacement>().is_err());
+    }
+
+    #[test]
+    fn string_round_trip_preserves_formatting() {
+        let input = r#"# A comment on top
+[package]
+name   = "foo"  # aligned
+version = "0.1.0"
+
+[dependencies]
+bar = { version = "0.1", features = ["std"] }  # keep this
+"#;
+        let manifest: Manifest = input.parse().unwrap();
+        assert_eq!(manifest.to_string(), input);
+    }
+
+    #[test]
+    fn keep_byte_order_mark_and_line_endings() {
+        let input = "\u{feff}[package]\r\nname = \"foo\"\r\n";
+        let manifest: Manifest = input.parse().unwrap();
+        assert_eq!(manifest.to_string(), "[package]\nname = \"foo\"\n");
+
+        let format = TextFormat::detect(input.as_bytes());
+        assert_eq!(
+            format,
+            TextFormat {
+                bom: true,
+                crlf: true,
+            }
+        );
+        assert_eq!(format.apply(&manifest.to_string()), input);
+        let unix = TextFormat::detect(b"[package]\nname = \"foo\"\r\n");
+        assert_eq!(unix.apply("a = 1\n"), "a = 1\n");
+    }
 }
@@ -0,0 +1,655 @@
+//! The message catalog: user-facing text by message ID, in the language the user picked.
+//!
+//! The language is taken from `CARGO_EDIT_LANG`, or else from the locale (`LC_ALL`,
+//! `LC_MESSAGES` or `LANG`), e.g. `de` or `de_DE.UTF-8`. Messages without a translation, and
+//! languages without a catalog, fall back to English. Messages take named arguments, which are
+//! filled in for `{name}`; translations must use the same ones.
+use std::env;
+use std::fmt;
+
+/// An argument of a message, see `message`.
+pub type MessageArg<'a> = &'a dyn fmt::Display;
+
+/// Pass a value as an argument of a message.
+pub fn message_arg<T: fmt::Display>(value: &T) -> MessageArg {
+    value
+}
+
+/// Look up a message of the catalog with `message` and fill in its arguments, e.g.
+/// `message!("section-missing", table = name)`.
+#[macro_export]
+macro_rules! message {
+    ($id:expr) => {
+        $crate::message($id, &[])
+    };
+    ($id:expr, $($key:ident = $value:expr),+) => {
+        $crate::message($id, &[$((stringify!($key), $crate::message_arg(&$value))),+])
+    };
+}
+
+/// A language the messages can be shown in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// English, the language of the source text
+    English,
+    /// German
+    German,
+}
+
+impl Language {
+    /// The language of a language tag or locale, e.g. `de`, `de-AT` or `de_DE.UTF-8`. English for
+    /// languages without a catalog.
+    pub fn parse(tag: &str) -> Language {
+        let language = tag.split(|c| c == '_' || c == '-' || c == '.' || c == '@')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        match language.as_str() {
+            "de" => Language::German,
+            _ => Language::English,
+        }
+    }
+
+    /// The language picked with `CARGO_EDIT_LANG`, or else with the locale.
+    pub fn from_env() -> Language {
+        ["CARGO_EDIT_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Language::English, |tag| Language::parse(&tag))
+    }
+}
+
+/// A message of the catalog, with its translations (empty if there is none)
+struct Entry {
+    id: &'static str,
+    english: &'static str,
+    german: &'static str,
+}
+
+impl Entry {
+    fn text(&self, language: Language) -> &'static str {
+        match language {
+            Language::German if !self.german.is_empty() => self.german,
+            _ => self.english,
+        }
+    }
+}
+
+static CATALOG: &[Entry] = &[
+    Entry {
+        id: "invalid-crates-io-json",
+        english: "Invalid JSON (the crate may not exist)",
+        german: "Ungültiges JSON (die Crate existiert möglicherweise nicht)",
+    },
+    Entry {
+        id: "no-versions-available",
+        english: "No available versions exist. Either all were yanked or only prerelease \
+                  versions exist. Trying with the --allow-prerelease flag might solve the issue.",
+        german: "Es gibt keine verfügbaren Versionen. Entweder wurden alle zurückgezogen (yanked), \
+                 oder es gibt nur Vorabversionen. Die Option --allow-prerelease könnte das \
+                 Problem lösen.",
+    },
+    Entry {
+        id: "no-matching-version",
+        english: "No version of `{name}` matching `{req}` found on crates.io",
+        german: "Keine Version von `{name}` passend zu `{req}` auf crates.io gefunden",
+    },
+    Entry {
+        id: "no-versions-old-enough",
+        english: "No available version of `{name}` was published more than {days} days ago",
+        german: "Keine verfügbare Version von `{name}` wurde vor mehr als {days} Tagen \
+                 veröffentlicht",
+    },
+    Entry {
+        id: "crate-not-found",
+        english: "The crate `{name}` could not be found in the registry index",
+        german: "Die Crate `{name}` wurde im Registry-Index nicht gefunden",
+    },
+    Entry {
+        id: "registry-status",
+        english: "The registry answered the lookup of `{name}` with the status {status}",
+        german: "Die Registry beantwortete die Abfrage von `{name}` mit dem Status {status}",
+    },
+    Entry {
+        id: "lookup-cancelled",
+        english: "The lookup of `{name}` was cancelled, as another lookup failed",
+        german: "Die Abfrage von `{name}` wurde abgebrochen, da eine andere Abfrage fehlschlug",
+    },
+    Entry {
+        id: "invalid-crate-name",
+        english: "Invalid crate name `{name}`: {reason}",
+        german: "Ungültiger Crate-Name `{name}`: {reason}",
+    },
+    Entry {
+        id: "crate-name-empty",
+        english: "the name is empty",
+        german: "der Name ist leer",
+    },
+    Entry {
+        id: "crate-name-too-long",
+        english: "the name is longer than 64 characters",
+        german: "der Name ist länger als 64 Zeichen",
+    },
+    Entry {
+        id: "crate-name-invalid-character",
+        english: "only letters, digits, `-` and `_` are allowed",
+        german: "nur Buchstaben, Ziffern, `-` und `_` sind erlaubt",
+    },
+    Entry {
+        id: "crate-name-not-ascii",
+        english: "only ASCII characters are allowed",
+        german: "nur ASCII-Zeichen sind erlaubt",
+    },
+    Entry {
+        id: "crate-name-leading-character",
+        english: "the name must start with a letter",
+        german: "der Name muss mit einem Buchstaben beginnen",
+    },
+    Entry {
+        id: "crate-name-reserved",
+        english: "the name is reserved",
+        german: "der Name ist reserviert",
+    },
+    Entry {
+        id: "invalid-version-requirement",
+        english: "Invalid crate version requirement",
+        german: "Ungültige Versionsanforderung",
+    },
+    Entry {
+        id: "unresolvable-crate",
+        english: "Unable to obtain crate informations from `{spec}`.\n",
+        german: "Aus `{spec}` lassen sich keine Informationen über die Crate ermitteln.\n",
+    },
+    Entry {
+        id: "invalid-git-url",
+        english: "Unable to parse git repo URL `{url}`",
+        german: "Die URL des Git-Repositorys `{url}` kann nicht gelesen werden",
+    },
+    Entry {
+        id: "invalid-git-reference",
+        english: "`{reference}` is not a valid git branch, tag or revision",
+        german: "`{reference}` ist kein gültiger Git-Branch, -Tag oder keine gültige Revision",
+    },
+    Entry {
+        id: "git-command",
+        english: "`git {command}` failed: {message}",
+        german: "`git {command}` ist fehlgeschlagen: {message}",
+    },
+    Entry {
+        id: "parse-cargo-toml",
+        english: "Unable to parse external Cargo.toml",
+        german: "Die externe Cargo.toml kann nicht gelesen werden",
+    },
+    Entry {
+        id: "manifest-not-found",
+        english: "Unable to find Cargo.toml",
+        german: "Cargo.toml wurde nicht gefunden",
+    },
+    Entry {
+        id: "manifest-parse",
+        english: "Unable to parse Cargo.toml",
+        german: "Cargo.toml kann nicht gelesen werden",
+    },
+    Entry {
+        id: "invalid-toml",
+        english: "Manifest not valid TOML",
+        german: "Das Manifest ist kein gültiges TOML",
+    },
+    Entry {
+        id: "invalid-manifest",
+        english: "Cargo.toml missing expected `package` or `project` fields",
+        german: "In Cargo.toml fehlt das erwartete Feld `package` oder `project`",
+    },
+    Entry {
+        id: "invalid-section",
+        english: "`{table}` in Cargo.toml is not a table",
+        german: "`{table}` in Cargo.toml ist keine Tabelle",
+    },
+    Entry {
+        id: "unexpected-root-manifest",
+        english: "Found virtual manifest, but this command requires running against an actual \
+                  package in this workspace.",
+        german: "Virtuelles Manifest gefunden, aber dieser Befehl muss auf ein Paket des \
+                 Workspace angewendet werden.",
+    },
+    Entry {
+        id: "not-workspace-root",
+        english: "This command requires the root manifest of a workspace (with a `[workspace]` \
+                  table).",
+        german: "Dieser Befehl benötigt das Wurzelmanifest eines Workspace (mit einer \
+                 `[workspace]`-Tabelle).",
+    },
+    Entry {
+        id: "workspace-root-not-found",
+        english: "Unable to find the root manifest of the workspace",
+        german: "Das Wurzelmanifest des Workspace wurde nicht gefunden",
+    },
+    Entry {
+        id: "section-missing",
+        english: "The table `{table}` could not be found.",
+        german: "Die Tabelle `{table}` wurde nicht gefunden.",
+    },
+    Entry {
+        id: "dependency-missing",
+        english: "The dependency `{name}` could not be found in `{table}`.",
+        german: "Die Abhängigkeit `{name}` wurde in `{table}` nicht gefunden.",
+    },
+    Entry {
+        id: "missing-version-field",
+        english: "Missing version field",
+        german: "Das Feld `version` fehlt",
+    },
+    Entry {
+        id: "invalid-section-placement",
+        english: "Invalid section placement `{placement}`, expected `end`, `after-dependencies` \
+                  or `before-features`",
+        german: "Ungültige Platzierung `{placement}`, erwartet wird `end`, `after-dependencies` \
+                 oder `before-features`",
+    },
+    Entry {
+        id: "invalid-prerelease-channel",
+        english: "Invalid prerelease channel `{channel}`, expected `alpha`, `beta` or `rc`",
+        german: "Ungültiger Kanal für Vorabversionen `{channel}`, erwartet wird `alpha`, `beta` \
+                 oder `rc`",
+    },
+    Entry {
+        id: "merge-conflicts",
+        english: "Cargo.toml contains unresolved merge conflicts (lines {lines}). Resolve them, \
+                  or pass `--ours` or `--theirs` to pick a side for conflicting dependencies.",
+        german: "Cargo.toml enthält ungelöste Merge-Konflikte (Zeilen {lines}). Löse sie auf, \
+                 oder wähle mit `--ours` oder `--theirs` eine Seite für widersprüchliche \
+                 Abhängigkeiten.",
+    },
+    Entry {
+        id: "unresolvable-conflict",
+        english: "The merge conflict at line {line} is not within a dependency table and has to \
+                  be resolved by hand",
+        german: "Der Merge-Konflikt in Zeile {line} liegt nicht in einer Abhängigkeitstabelle \
+                 und muss von Hand gelöst werden",
+    },
+    Entry {
+        id: "workspace-metadata",
+        english: "Failed to get workspace metadata",
+        german: "Die Metadaten des Workspace konnten nicht ermittelt werden",
+    },
+    Entry {
+        id: "unknown-workspace-member",
+        english: "No member of the workspace is named `{name}`",
+        german: "Kein Mitglied des Workspace heißt `{name}`",
+    },
+    Entry {
+        id: "invalid-selection",
+        english: "Invalid selection `{input}`: expected numbers or ranges from 1 to {len}",
+        german: "Ungültige Auswahl `{input}`: erwartet werden Zahlen oder Bereiche von 1 bis \
+                 {len}",
+    },
+    Entry {
+        id: "add-invalid-version",
+        english: "Invalid dependency version requirement",
+        german: "Ungültige Versionsanforderung der Abhängigkeit",
+    },
+    Entry {
+        id: "add-empty-target",
+        english: "Target specification may not be empty",
+        german: "Die Angabe der Zielplattform darf nicht leer sein",
+    },
+    Entry {
+        id: "add-no-crates",
+        english: "No crate to add was given (pass <crate>, `--dev-crates` or `--build-crates`)",
+        german: "Es wurde keine Crate zum Hinzufügen angegeben (übergib <crate>, `--dev-crates` \
+                 oder `--build-crates`)",
+    },
+    Entry {
+        id: "add-optional-dev-or-build",
+        english: "`--optional` can not be combined with `--dev` or `--build`: cargo does not \
+                  support optional dev-dependencies or build-dependencies",
+        german: "`--optional` kann nicht mit `--dev` oder `--build` kombiniert werden: cargo \
+                 unterstützt keine optionalen dev-dependencies oder build-dependencies",
+    },
+    Entry {
+        id: "add-optional-dev-hint",
+        english: "To use a crate only in some tests, add it as an optional dependency that a \
+                  feature enables, and gate the tests with `#[cfg(feature = \"...\")]`; pass \
+                  `--emulate-optional-dev` instead of `--optional` to set this up. \
+                  (`[target.'cfg(test)'.dependencies]` does not work, as cargo does not set \
+                  `cfg(test)` for dependency tables.)",
+        german: "Um eine Crate nur in einigen Tests zu verwenden, füge sie als optionale \
+                 Abhängigkeit hinzu, die ein Feature aktiviert, und schränke die Tests mit \
+                 `#[cfg(feature = \"...\")]` ein; übergib dafür `--emulate-optional-dev` statt \
+                 `--optional`. (`[target.'cfg(test)'.dependencies]` funktioniert nicht, da cargo \
+                 `cfg(test)` für Abhängigkeitstabellen nicht setzt.)",
+    },
+    Entry {
+        id: "add-optional-build-hint",
+        english: "A build script can read the enabled features from the `CARGO_FEATURE_<name>` \
+                  environment variables instead.",
+        german: "Ein Build-Skript kann die aktivierten Features stattdessen aus den \
+                 Umgebungsvariablen `CARGO_FEATURE_<name>` lesen.",
+    },
+    Entry {
+        id: "add-git-reference-without-git",
+        english: "`--branch`, `--tag` and `--rev` can only be used with a git dependency (pass \
+                  `--git` or a git repository URL)",
+        german: "`--branch`, `--tag` und `--rev` können nur mit einer Git-Abhängigkeit verwendet \
+                 werden (übergib `--git` oder die URL eines Git-Repositorys)",
+    },
+    Entry {
+        id: "add-conflicting-git-references",
+        english: "Only one of `--branch`, `--tag` and `--rev` may be given",
+        german: "Nur eine der Optionen `--branch`, `--tag` und `--rev` darf angegeben werden",
+    },
+    Entry {
+        id: "add-ambiguous-git-package",
+        english: "The repository `{url}` has several packages ({packages}). Pick one, e.g. with \
+                  `cargo add {url} --crate {first}`",
+        german: "Das Repository `{url}` enthält mehrere Pakete ({packages}). Wähle eines aus, \
+                 z. B. mit `cargo add {url} --crate {first}`",
+    },
+    Entry {
+        id: "add-unknown-git-package",
+        english: "The repository `{url}` has no package `{name}`. Its packages are: {packages}",
+        german: "Das Repository `{url}` enthält kein Paket `{name}`. Seine Pakete sind: \
+                 {packages}",
+    },
+    Entry {
+        id: "add-crate-without-git-repository",
+        english: "`--crate` picks a package of a git repository, so it needs `--git` or a \
+                  repository URL instead of a crate name",
+        german: "`--crate` wählt ein Paket eines Git-Repositorys aus und braucht daher `--git` \
+                 oder die URL eines Repositorys statt eines Crate-Namens",
+    },
+    Entry {
+        id: "add-no-matching-members",
+        english: "No workspace member matches `{pattern}`",
+        german: "Kein Mitglied des Workspace passt zu `{pattern}`",
+    },
+    Entry {
+        id: "add-without-members",
+        english: "`{flag}` can only be used together with `--members`",
+        german: "`{flag}` kann nur zusammen mit `--members` verwendet werden",
+    },
+    Entry {
+        id: "add-members-failed",
+        english: "Failed to add the dependency to {failed} of {total} workspace members",
+        german: "Die Abhängigkeit konnte zu {failed} von {total} Mitgliedern des Workspace \
+                 nicht hinzugefügt werden",
+    },
+    Entry {
+        id: "add-invalid-path",
+        english: "Unable to resolve path `{path}`",
+        german: "Der Pfad `{path}` konnte nicht aufgelöst werden",
+    },
+    Entry {
+        id: "add-contradictory-features",
+        english: "`--no-default-features` contradicts enabling {features} of `{name}`, which \
+                  only enable the default features",
+        german: "`--no-default-features` widerspricht dem Aktivieren von {features} von \
+                 `{name}`, die nur die Standard-Features aktivieren",
+    },
+    Entry {
+        id: "add-not-in-lock-file",
+        english: "The crate `{name}` is not in Cargo.lock, so its version can not be found \
+                  offline. Pass a version (e.g. `{name}@1.0`) or run without `--offline`.",
+        german: "Die Crate `{name}` ist nicht in Cargo.lock, daher kann ihre Version offline \
+                 nicht ermittelt werden. Übergib eine Version (z. B. `{name}@1.0`) oder führe \
+                 den Befehl ohne `--offline` aus.",
+    },
+    Entry {
+        id: "add-not-in-local-source",
+        english: "The crate `{name}` is not in the source `{source}` ({path}), which replaces \
+                  crates.io. Add it there, or pass a version (e.g. `{name}@1.0`).",
+        german: "Die Crate `{name}` ist nicht in der Quelle `{source}` ({path}), die crates.io \
+                 ersetzt. Füge sie dort hinzu, oder übergib eine Version (z. B. `{name}@1.0`).",
+    },
+    Entry {
+        id: "add-invalid-crate-file",
+        english: "`{path}` is not named like a crate archive, e.g. `foo-1.2.0.crate`",
+        german: "`{path}` ist nicht wie ein Crate-Archiv benannt, z. B. `foo-1.2.0.crate`",
+    },
+    Entry {
+        id: "add-locked-missing",
+        english: "it is not in Cargo.lock",
+        german: "sie ist nicht in Cargo.lock",
+    },
+    Entry {
+        id: "add-locked-versions",
+        english: "Cargo.lock has {versions}",
+        german: "Cargo.lock enthält {versions}",
+    },
+    Entry {
+        id: "add-not-satisfied-by-lock-file",
+        english: "`{name}` {version} is not met by a version in Cargo.lock ({locked}), so adding \
+                  it would change the locked versions. Pass a requirement a locked version \
+                  meets, or update Cargo.lock first and run without `--locked`.",
+        german: "Keine Version in Cargo.lock erfüllt `{name}` {version} ({locked}), daher würde \
+                 das Hinzufügen die gesperrten Versionen ändern. Übergib eine Anforderung, die \
+                 eine gesperrte Version erfüllt, oder aktualisiere zuerst Cargo.lock und führe \
+                 den Befehl ohne `--locked` aus.",
+    },
+    Entry {
+        id: "add-needs-registry-interactive-features",
+        english: "`--interactive-features` needs the registry, so it can not be combined with \
+                  `--offline`",
+        german: "`--interactive-features` braucht die Registry und kann daher nicht mit \
+                 `--offline` kombiniert werden",
+    },
+    Entry {
+        id: "add-needs-registry-max-age",
+        english: "`--max-age` needs the publication dates from the registry, so it can not be \
+                  combined with `--offline`",
+        german: "`--max-age` braucht die Veröffentlichungsdaten aus der Registry und kann daher \
+                 nicht mit `--offline` kombiniert werden",
+    },
+    Entry {
+        id: "add-needs-registry-bump-msrv",
+        english: "`--bump-msrv` needs the MSRV of the crates from the registry, so it can not be \
+                  combined with `--offline`",
+        german: "`--bump-msrv` braucht die MSRV der Crates aus der Registry und kann daher nicht \
+                 mit `--offline` kombiniert werden",
+    },
+    Entry {
+        id: "add-needs-registry-explain",
+        english: "`--explain` needs the published versions from the registry, so it can not be \
+                  combined with `--offline`",
+        german: "`--explain` braucht die veröffentlichten Versionen aus der Registry und kann \
+                 daher nicht mit `--offline` kombiniert werden",
+    },
+    Entry {
+        id: "add-needs-registry-compat-edition",
+        english: "`--compat-edition` needs the editions of the crates from the registry, so it \
+                  can not be combined with `--offline`",
+        german: "`--compat-edition` braucht die Editionen der Crates aus der Registry und kann \
+                 daher nicht mit `--offline` kombiniert werden",
+    },
+    Entry {
+        id: "add-unknown-features",
+        english: "Unknown features {unknown} for `{name}` {source}.\nValid features are: \
+                  {available}\n(Pass `--no-verify-features` to skip this check.)",
+        german: "Unbekannte Features {unknown} für `{name}` {source}.\nGültige Features sind: \
+                 {available}\n(Übergib `--no-verify-features`, um diese Prüfung zu \
+                 überspringen.)",
+    },
+    Entry {
+        id: "rm-no-dependencies",
+        english: "There are no dependencies to remove.",
+        german: "Es gibt keine Abhängigkeiten zum Entfernen.",
+    },
+    Entry {
+        id: "rm-no-crate-specified",
+        english: "No crate specified. Pass the name of the crate to remove, or run `cargo rm` in \
+                  an interactive terminal to pick from the current dependencies.",
+        german: "Keine Crate angegeben. Übergib den Namen der zu entfernenden Crate, oder führe \
+                 `cargo rm` in einem interaktiven Terminal aus, um aus den aktuellen \
+                 Abhängigkeiten zu wählen.",
+    },
+    Entry {
+        id: "rm-no-dependencies-in-target",
+        english: "There are no dependencies for the target `{target}` to remove.",
+        german: "Es gibt keine Abhängigkeiten der Zielplattform `{target}` zum Entfernen.",
+    },
+    Entry {
+        id: "upgrade-virtual-manifest",
+        english: "Found virtual manifest, but this command requires running against an actual \
+                  package in this workspace. Try adding `--all`.",
+        german: "Ein virtuelles Manifest wurde gefunden, aber dieser Befehl muss auf einem \
+                 tatsächlichen Paket des Workspace ausgeführt werden. Versuche es mit `--all`.",
+    },
+    Entry {
+        id: "upgrade-hoist-without-consolidate",
+        english: "`--hoist` can only be used together with `--consolidate`",
+        german: "`--hoist` kann nur zusammen mit `--consolidate` verwendet werden",
+    },
+    Entry {
+        id: "upgrade-without-all",
+        english: "`{flag}` can only be used together with `--all`",
+        german: "`{flag}` kann nur zusammen mit `--all` verwendet werden",
+    },
+    Entry {
+        id: "upgrade-conflicting-flags",
+        english: "`{flag}` can not be combined with `{other}`",
+        german: "`{flag}` kann nicht mit `{other}` kombiniert werden",
+    },
+    Entry {
+        id: "upgrade-invalid-git-target",
+        english: "Invalid `--to-rev` `{target}`, expected `head`, `branch:<name>` or \
+                  `tag:<name>`",
+        german: "Ungültiges `--to-rev` `{target}`, erwartet wird `head`, `branch:<name>` oder \
+                 `tag:<name>`",
+    },
+    Entry {
+        id: "upgrade-fetch-new-version",
+        english: "Failed to get new version",
+        german: "Die neue Version konnte nicht ermittelt werden",
+    },
+    Entry {
+        id: "dep-not-inherited",
+        english: "The dependency `{name}` is not inherited from the workspace (with `workspace = \
+                  true`)",
+        german: "Die Abhängigkeit `{name}` wird nicht vom Workspace geerbt (mit `workspace = \
+                 true`)",
+    },
+    Entry {
+        id: "dep-unsupported-entry",
+        english: "The entry of `{name}` in `[workspace.dependencies]` is not understood",
+        german: "Der Eintrag von `{name}` in `[workspace.dependencies]` wird nicht verstanden",
+    },
+    Entry {
+        id: "dep-publish-blocked",
+        english: "{count} dependency entries keep the package from being published",
+        german: "{count} Abhängigkeitseinträge verhindern die Veröffentlichung des Pakets",
+    },
+    Entry {
+        id: "dep-workspace-inconsistent",
+        english: "{count} dependencies are declared in different ways by the members of the \
+                  workspace",
+        german: "{count} Abhängigkeiten werden von den Mitgliedern des Workspace \
+                 unterschiedlich deklariert",
+    },
+    Entry {
+        id: "dep-missing-lock-file",
+        english: "There is no lock file at {path}, create one with `cargo generate-lockfile`",
+        german: "Unter {path} gibt es keine Lock-Datei, erstelle eine mit \
+                 `cargo generate-lockfile`",
+    },
+    Entry {
+        id: "dep-invalid-change-file",
+        english: "Invalid change file: {reason}",
+        german: "Ungültige Änderungsdatei: {reason}",
+    },
+    Entry {
+        id: "unhandled-error",
+        english: "Command failed due to unhandled error: {error}",
+        german: "Der Befehl ist wegen eines unbehandelten Fehlers fehlgeschlagen: {error}",
+    },
+    Entry {
+        id: "caused-by",
+        english: "Caused by: {cause}",
+        german: "Ursache: {cause}",
+    },
+];
+
+/// Fill in the arguments of a message text.
+fn fill(text: &str, args: &[(&str, MessageArg)]) -> String {
+    args.iter().fold(text.to_owned(), |text, &(key, value)| {
+        text.replace(&format!("{{{}}}", key), &value.to_string())
+    })
+}
+
+/// The message `id` of the catalog in the language of `Language::from_env`, with its arguments
+/// filled in. Usually called through the `message!` macro. An unknown ID is returned as it is.
+pub fn message(id: &str, args: &[(&str, MessageArg)]) -> String {
+    match CATALOG.iter().find(|entry| entry.id == id) {
+        Some(entry) => fill(entry.text(Language::from_env()), args),
+        None => id.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_languages() {
+        assert_eq!(Language::parse("de"), Language::German);
+        assert_eq!(Language::parse("de_DE.UTF-8"), Language::German);
+        assert_eq!(Language::parse("DE-at"), Language::German);
+        assert_eq!(Language::parse("en_US.UTF-8"), Language::English);
+        assert_eq!(Language::parse("C"), Language::English);
+        assert_eq!(Language::parse("fr_FR"), Language::English);
+    }
+
+    #[test]
+    fn fill_in_arguments() {
+        let entry = CATALOG
+            .iter()
+            .find(|entry| entry.id == "dependency-missing")
+            .unwrap();
+        let name = "serde".to_owned();
+        let args: &[(&str, MessageArg)] = &[("name", &name), ("table", &"dev-dependencies")];
+        assert_eq!(
+            fill(entry.text(Language::English), args),
+            "The dependency `serde` could not be found in `dev-dependencies`."
+        );
+        assert_eq!(
+            fill(entry.text(Language::German), args),
+            "Die Abhängigkeit `serde` wurde in `dev-dependencies` nicht gefunden."
+        );
+    }
+
+    #[test]
+    fn render_crate_name_reason_in_german() {
+        let text = |id: &str| {
+            let entry = CATALOG.iter().find(|entry| entry.id == id).unwrap();
+            entry.text(Language::German)
+        };
+        let args: &[(&str, MessageArg)] =
+            &[("name", &"nul"), ("reason", &text("crate-name-reserved"))];
+        assert_eq!(
+            fill(text("invalid-crate-name"), args),
+            "Ungültiger Crate-Name `nul`: der Name ist reserviert"
+        );
+    }
+
+    #[test]
+    fn translations_have_the_same_arguments() {
+        let arguments = |text: &str| {
+            let mut arguments: Vec<String> = text.split('{')
+                .skip(1)
+                .filter_map(|rest| rest.split('}').next())
+                .map(String::from)
+                .collect();
+            arguments.sort();
+            arguments
+        };
+        for entry in CATALOG {
+            if !entry.german.is_empty() {
+                assert_eq!(arguments(entry.german), arguments(entry.english), "{}", entry.id);
+            }
+        }
+        let mut ids: Vec<_> = CATALOG.iter().map(|entry| entry.id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), CATALOG.len());
+    }
+}
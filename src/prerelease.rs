@@ -0,0 +1,136 @@
+//! Which prerelease versions may be picked.
+use std::str;
+
+use semver::{Identifier, Version};
+
+use errors::*;
+
+/// A prerelease channel. Channels are ordered by how close to a release they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PrereleaseChannel {
+    /// e.g. `1.0.0-alpha.1`
+    Alpha,
+    /// e.g. `1.0.0-beta.2`
+    Beta,
+    /// A release candidate, e.g. `1.0.0-rc.3`
+    Rc,
+}
+
+impl PrereleaseChannel {
+    /// The channel of a prerelease version, from its first identifier, e.g. `rc` in `1.0.0-rc.2`
+    /// or `1.0.0-rc2`. Unknown labels (e.g. `1.0.0-dev`) have no channel.
+    pub fn of(version: &Version) -> Option<PrereleaseChannel> {
+        match version.pre.first() {
+            Some(&Identifier::AlphaNumeric(ref label)) => label
+                .trim_end_matches(|c: char| c.is_ascii_digit())
+                .parse()
+                .ok(),
+            _ => None,
+        }
+    }
+}
+
+impl str::FromStr for PrereleaseChannel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "alpha" => Ok(PrereleaseChannel::Alpha),
+            "beta" => Ok(PrereleaseChannel::Beta),
+            "rc" => Ok(PrereleaseChannel::Rc),
+            _ => Err(Error::InvalidPrereleaseChannel(s.into())),
+        }
+    }
+}
+
+/// Which prerelease versions may be picked when looking for the latest version of a crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prereleases {
+    /// Only stable versions
+    None,
+    /// Stable versions and prereleases of this channel or a later one, e.g. `beta` allows
+    /// `1.0.0-beta.2` and `1.0.0-rc.1`, but not `1.0.0-alpha.3`
+    Channel(PrereleaseChannel),
+    /// Any version
+    All,
+}
+
+impl Prereleases {
+    /// Whether `version` may be picked.
+    pub fn accepts(self, version: &Version) -> bool {
+        if !version.is_prerelease() {
+            return true;
+        }
+        match self {
+            Prereleases::None => false,
+            Prereleases::Channel(min) => PrereleaseChannel::of(version).map_or(false, |c| c >= min),
+            Prereleases::All => true,
+        }
+    }
+
+    /// The prereleases that may be picked for `crate_name`: any, if it is one of
+    /// `prerelease_crates` (see `Manifest::prerelease_crates`), otherwise these.
+    pub fn for_crate(self, crate_name: &str, prerelease_crates: &[String]) -> Prereleases {
+        if prerelease_crates
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(crate_name))
+        {
+            Prereleases::All
+        } else {
+            self
+        }
+    }
+}
+
+impl Default for Prereleases {
+    fn default() -> Self {
+        Prereleases::None
+    }
+}
+
+impl From<bool> for Prereleases {
+    /// `true` allows all prereleases (like `--allow-prerelease`), `false` none.
+    fn from(allow_prerelease: bool) -> Self {
+        if allow_prerelease {
+            Prereleases::All
+        } else {
+            Prereleases::None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepts(prereleases: Prereleases, version: &str) -> bool {
+        prereleases.accepts(&Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn channels() {
+        let rc = Prereleases::Channel(PrereleaseChannel::Rc);
+        assert!(accepts(rc, "1.0.0"));
+        assert!(accepts(rc, "1.0.0-rc.2"));
+        assert!(accepts(rc, "1.0.0-rc2"));
+        assert!(!accepts(rc, "1.0.0-beta.1"));
+        assert!(!accepts(rc, "1.0.0-alpha"));
+        assert!(!accepts(rc, "1.0.0-dev"));
+
+        let beta = Prereleases::Channel(PrereleaseChannel::Beta);
+        assert!(accepts(beta, "1.0.0-beta.1"));
+        assert!(accepts(beta, "1.0.0-RC.1"));
+        assert!(!accepts(beta, "1.0.0-alpha.3"));
+
+        assert!(!accepts(Prereleases::None, "1.0.0-rc.1"));
+        assert!(accepts(Prereleases::All, "1.0.0-dev"));
+        assert!("gamma".parse::<PrereleaseChannel>().is_err());
+
+        let prerelease_crates = vec!["bevy".to_owned()];
+        assert_eq!(
+            Prereleases::None.for_crate("Bevy", &prerelease_crates),
+            Prereleases::All
+        );
+        assert_eq!(beta.for_crate("serde", &prerelease_crates), beta);
+    }
+}
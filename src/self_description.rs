@@ -0,0 +1,90 @@
+//! The description of a command that `--version --json` prints, so tools that drive cargo-edit can
+//! check what the installed binaries support instead of parsing `--help`.
+use std::collections::BTreeMap;
+
+use serde_json;
+
+/// Version of the layout of the description itself. Bumped when keys are removed or change their
+/// meaning; new keys may be added without a bump.
+const DESCRIPTION_FORMAT_VERSION: u32 = 1;
+
+/// What a command is and supports, as printed by `--version --json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SelfDescription {
+    format_version: u32,
+    name: String,
+    version: &'static str,
+    features: Vec<&'static str>,
+    output_formats: BTreeMap<&'static str, u32>,
+}
+
+impl SelfDescription {
+    /// The description of the command `name` (e.g. `cargo-add`), without features or output
+    /// formats.
+    pub fn new(name: &str) -> Self {
+        SelfDescription {
+            format_version: DESCRIPTION_FORMAT_VERSION,
+            name: name.to_owned(),
+            version: env!("CARGO_PKG_VERSION"),
+            features: Vec::new(),
+            output_formats: BTreeMap::new(),
+        }
+    }
+
+    /// Declare the features the command supports, e.g. `sparse-index` or `offline`.
+    pub fn set_features(mut self, features: &[&'static str]) -> Self {
+        for feature in features {
+            if !self.features.contains(feature) {
+                self.features.push(feature);
+            }
+        }
+        self.features.sort();
+        self
+    }
+
+    /// Declare the machine-readable outputs of the command (e.g. `add --output json`), with the
+    /// version of their layout. The version is bumped when the layout changes in a way that can
+    /// break a consumer.
+    pub fn set_output_formats(mut self, formats: &[(&'static str, u32)]) -> Self {
+        self.output_formats.extend(formats.iter().cloned());
+        self
+    }
+
+    /// Add the features and output formats of another command, e.g. one built into `cargo edit`.
+    pub fn merge(self, other: &SelfDescription) -> Self {
+        let formats: Vec<_> = other.output_formats.iter().map(|(&k, &v)| (k, v)).collect();
+        self.set_features(&other.features).set_output_formats(&formats)
+    }
+
+    /// The description as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("description is correctly serialized")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_commands() {
+        let rm = SelfDescription::new("cargo-rm")
+            .set_features(&["workspace", "record-changes"])
+            .set_output_formats(&[("rm --output json", 1)]);
+        let json: serde_json::Value = serde_json::from_str(&rm.to_json()).unwrap();
+        assert_eq!(json["format-version"], 1);
+        assert_eq!(json["name"], "cargo-rm");
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["features"][0], "record-changes");
+        assert_eq!(json["features"][1], "workspace");
+        assert_eq!(json["output-formats"]["rm --output json"], 1);
+
+        let add = SelfDescription::new("cargo-add")
+            .set_features(&["offline", "workspace"])
+            .set_output_formats(&[("add --output json", 1)]);
+        let edit = SelfDescription::new("cargo-edit").merge(&add).merge(&rm);
+        assert_eq!(edit.features, vec!["offline", "record-changes", "workspace"]);
+        assert_eq!(edit.output_formats.len(), 2);
+    }
+}
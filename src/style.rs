@@ -0,0 +1,303 @@
+//! The formatting conventions of a manifest, which new entries follow so that they look like the
+//! entries around them.
+use conflict::header;
+use group::{header_keys, line_key};
+
+/// How many entries of a manifest format a detail one way or the other.
+#[derive(Debug, Default, Clone, Copy)]
+struct Tally {
+    yes: usize,
+    no: usize,
+}
+
+impl Tally {
+    fn add(&mut self, yes: bool) {
+        if yes {
+            self.yes += 1;
+        } else {
+            self.no += 1;
+        }
+    }
+
+    /// The way most entries go, or `default` if there is no majority.
+    fn verdict(&self, default: bool) -> bool {
+        if self.yes == self.no {
+            default
+        } else {
+            self.yes > self.no
+        }
+    }
+}
+
+/// The formatting conventions of a manifest. The default is how `toml_edit` formats a new entry,
+/// e.g. `serde = { version = "1.0", features = ["derive"] }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Style {
+    /// `key = value` rather than `key=value`
+    spaced_equals: bool,
+    /// `'1.0'` rather than `"1.0"`
+    single_quotes: bool,
+    /// `["a", "b"]` rather than `["a","b"]`
+    spaced_commas: bool,
+    /// `[ "a" ]` rather than `["a"]`
+    padded_brackets: bool,
+    /// `{ version = "1.0" }` rather than `{version = "1.0"}`
+    padded_braces: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            spaced_equals: true,
+            single_quotes: false,
+            spaced_commas: true,
+            padded_brackets: false,
+            padded_braces: true,
+        }
+    }
+}
+
+/// The length of the string that starts `rest` (with its quotes), which ends at the end of the
+/// line if it is not closed.
+fn string_len(rest: &str) -> usize {
+    let quote = rest.chars().next().expect("a quote");
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        match c {
+            '\\' if quote == '"' && !escaped => escaped = true,
+            _ if c == quote && !escaped => return i + 1,
+            _ => escaped = false,
+        }
+    }
+    rest.len()
+}
+
+impl Style {
+    /// Detect the conventions most entries of the manifest follow. Details the manifest does not
+    /// settle, e.g. the padding of arrays in a manifest without arrays, stay the default.
+    pub(crate) fn detect(contents: &str) -> Self {
+        let mut equals = Tally::default();
+        let mut quotes = Tally::default();
+        let mut commas = Tally::default();
+        let mut brackets = Tally::default();
+        let mut braces = Tally::default();
+        let mut in_multiline_string = false;
+        for line in contents.lines() {
+            // The lines of multi-line strings are text, not TOML.
+            let delimiters = line.matches("\"\"\"").count() + line.matches("'''").count();
+            if delimiters % 2 == 1 {
+                in_multiline_string = !in_multiline_string;
+                continue;
+            }
+            let trimmed = line.trim();
+            if in_multiline_string || trimmed.starts_with('#') || header(line).is_some() {
+                continue;
+            }
+
+            let mut rest = trimmed;
+            while let Some(c) = rest.chars().next() {
+                let after = &rest[c.len_utf8()..];
+                let next = after.chars().next();
+                match c {
+                    '#' => break,
+                    '"' | '\'' => {
+                        quotes.add(c == '\'');
+                        rest = &rest[string_len(rest)..];
+                        continue;
+                    }
+                    '=' => {
+                        let before = trimmed.len() - rest.len();
+                        let spaced = trimmed[..before].ends_with(char::is_whitespace)
+                            || next.map_or(false, char::is_whitespace);
+                        equals.add(spaced);
+                    }
+                    // Empty arrays and tables, line breaks and trailing commas settle nothing.
+                    ',' | '[' | '{' => match next {
+                        None | Some(']') | Some('}') => {}
+                        Some(next) => {
+                            let tally = match c {
+                                ',' => &mut commas,
+                                '[' => &mut brackets,
+                                _ => &mut braces,
+                            };
+                            tally.add(next.is_whitespace());
+                        }
+                    },
+                    _ => {}
+                }
+                rest = after;
+            }
+        }
+
+        let default = Style::default();
+        Style {
+            spaced_equals: equals.verdict(default.spaced_equals),
+            single_quotes: quotes.verdict(default.single_quotes),
+            spaced_commas: commas.verdict(default.spaced_commas),
+            padded_brackets: brackets.verdict(default.padded_brackets),
+            padded_braces: braces.verdict(default.padded_braces),
+        }
+    }
+
+    /// Format a (single line) `key = value` entry in this style. Strings that can not be written
+    /// with single quotes, e.g. because they contain escapes, keep their double quotes.
+    fn apply(&self, line: &str) -> String {
+        let mut formatted = String::new();
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            let mut after = &rest[c.len_utf8()..];
+            match c {
+                '#' => {
+                    formatted.push_str(rest);
+                    break;
+                }
+                '"' | '\'' => {
+                    let string = &rest[..string_len(rest)];
+                    let contents = string.trim_matches(c);
+                    if c == '"'
+                        && self.single_quotes
+                        && string.len() >= 2
+                        && !contents.contains(|ch| ch == '\\' || ch == '\'')
+                    {
+                        formatted.push_str(&format!("'{}'", contents));
+                    } else {
+                        formatted.push_str(string);
+                    }
+                    after = &rest[string.len()..];
+                }
+                '=' => {
+                    let len = formatted.trim_end().len();
+                    formatted.truncate(len);
+                    formatted.push_str(if self.spaced_equals { " = " } else { "=" });
+                    after = after.trim_start();
+                }
+                ',' => {
+                    formatted.push(',');
+                    after = after.trim_start();
+                    let closing = after.starts_with(']') || after.starts_with('}');
+                    if self.spaced_commas && !after.is_empty() && !closing {
+                        formatted.push(' ');
+                    }
+                }
+                '[' | '{' => {
+                    let (padded, close) = if c == '[' {
+                        (self.padded_brackets, ']')
+                    } else {
+                        (self.padded_braces, '}')
+                    };
+                    formatted.push(c);
+                    after = after.trim_start();
+                    if padded && !after.is_empty() && !after.starts_with(close) {
+                        formatted.push(' ');
+                    }
+                }
+                ']' | '}' => {
+                    let (padded, open) = if c == ']' {
+                        (self.padded_brackets, '[')
+                    } else {
+                        (self.padded_braces, '{')
+                    };
+                    let len = formatted.trim_end().len();
+                    formatted.truncate(len);
+                    if padded && !formatted.ends_with(open) {
+                        formatted.push(' ');
+                    }
+                    formatted.push(c);
+                }
+                _ => formatted.push(c),
+            }
+            rest = after;
+        }
+        formatted
+    }
+}
+
+/// Format the (single line) entry `name` of the table at `table_path` in `style`. The contents are
+/// returned unchanged if the table or the entry can not be found.
+pub(crate) fn restyle_entry(
+    contents: &str,
+    table_path: &[String],
+    name: &str,
+    style: &Style,
+) -> String {
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+    let start = match lines
+        .iter()
+        .position(|line| header(line).map(|h| header_keys(&h)) == Some(table_path.to_vec()))
+    {
+        Some(start) => start + 1,
+        None => return contents.to_owned(),
+    };
+    let entry = lines[start..]
+        .iter()
+        .take_while(|line| header(line).is_none())
+        .position(|line| line_key(line) == Some(name));
+    match entry {
+        Some(entry) => lines[start + entry] = style.apply(&lines[start + entry]),
+        None => return contents.to_owned(),
+    }
+
+    let mut restyled = lines.join("\n");
+    if contents.ends_with('\n') {
+        restyled.push('\n');
+    }
+    restyled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_and_follow_style() {
+        let input = r#"[package]
+name='demo'
+description = """
+A demo, with "quotes" = and [ brackets ]
+"""
+keywords=[ 'a','b' ]
+
+[dependencies]
+log='0.4'
+serde={version='1.0',features=[ 'derive' ]}
+rand={version='0.5'}
+url = { path = "../url" }
+"#;
+        let style = Style::detect(input);
+        assert_eq!(
+            style,
+            Style {
+                spaced_equals: false,
+                single_quotes: true,
+                spaced_commas: false,
+                padded_brackets: true,
+                padded_braces: false,
+            }
+        );
+        assert_eq!(
+            style.apply(r#"regex = { version = "1.0", features = ["std", "unicode"] }"#),
+            r#"regex={version='1.0',features=[ 'std','unicode' ]}"#
+        );
+        // Strings that need double quotes keep them.
+        assert_eq!(
+            style.apply(r#"it = { path = "it's", registry = "a\\b" }"#),
+            r#"it={path="it's",registry="a\\b"}"#
+        );
+
+        let restyled = restyle_entry(
+            "[dependencies]\nlog='0.4'\nregex = \"1.0\"\n",
+            &["dependencies".to_owned()],
+            "regex",
+            &style,
+        );
+        assert_eq!(restyled, "[dependencies]\nlog='0.4'\nregex='1.0'\n");
+    }
+
+    #[test]
+    fn default_style() {
+        let input = "[dependencies]\nlog = \"0.4\"\nserde = { version = \"1.0\", features = \
+                     [\"derive\"] }\n";
+        assert_eq!(Style::detect(input), Style::default());
+        assert_eq!(Style::detect(""), Style::default());
+    }
+}
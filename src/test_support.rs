@@ -0,0 +1,43 @@
+//! Helpers for testing tools that edit manifests, available with the `test-support` feature.
+//!
+//! Fixture manifests are copied into temporary directories, which are created in the directory
+//! named by `CARGO_EDIT_TEST_TMPDIR` if it is set (and in the system's temporary directory
+//! otherwise), so tests do not depend on where the `target` directory is.
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use tempdir::TempDir;
+use toml_edit;
+
+/// Create an empty temporary directory.
+pub fn temp_dir() -> TempDir {
+    match env::var_os("CARGO_EDIT_TEST_TMPDIR") {
+        Some(base) => {
+            fs::create_dir_all(&base)
+                .unwrap_or_else(|err| panic!("could not create test directory: {}", err));
+            TempDir::new_in(base, "cargo-edit-test")
+        }
+        None => TempDir::new("cargo-edit-test"),
+    }.expect("failed to construct temporary directory")
+}
+
+/// Copy the fixture manifest `source` to `Cargo.toml` in a new temporary directory. Returns the
+/// directory (which is removed when it is dropped) and the path of the manifest.
+pub fn clone_out_test(source: &str) -> (TempDir, String) {
+    let tmpdir = temp_dir();
+    let path = tmpdir.path().join("Cargo.toml");
+    fs::copy(source, &path).unwrap_or_else(|err| panic!("could not copy test manifest: {}", err));
+
+    (tmpdir, path.to_string_lossy().into_owned())
+}
+
+/// Parse a manifest file as TOML
+pub fn get_toml<P: AsRef<Path>>(manifest_path: P) -> toml_edit::Document {
+    let mut s = String::new();
+    fs::File::open(manifest_path)
+        .and_then(|mut f| f.read_to_string(&mut s))
+        .unwrap_or_else(|err| panic!("could not read manifest: {}", err));
+    s.parse().expect("toml parse error")
+}
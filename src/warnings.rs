@@ -0,0 +1,125 @@
+//! Non-fatal findings, collected while an operation runs and reported at the end.
+use std::fmt;
+use std::io::{self, Write};
+use std::slice;
+
+/// What a warning is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningKind {
+    /// A differently named crate was found than the one asked for (e.g. `-` instead of `_`)
+    RenamedCrate,
+    /// Newer versions of a crate were yanked
+    YankedVersion,
+    /// Newer versions of a crate were passed over, as they were published too recently
+    RecentVersion,
+    /// A section appears under two spellings (e.g. `dev-dependencies` and `dev_dependencies`)
+    DuplicateSection,
+    /// Entries of a section whose names differ only in case, `-` and `_`, i.e. the same crate
+    DuplicateCrate,
+    /// The manifest deviates from the usual style
+    Style,
+    /// A version was taken from the lock file instead of the registry (e.g. offline)
+    LockedVersion,
+    /// The package can not be published as it is
+    Unpublishable,
+    /// A dependency needs a newer Rust than the package declares in `rust-version`
+    RustVersion,
+    /// A dependency uses a newer edition than the package
+    Edition,
+    /// A crate says it was renamed, or deprecated in favour of another crate
+    Superseded,
+    /// Two dependencies link to the same native library, which cargo does not allow
+    NativeLibrary,
+    /// The registry index and the crates.io API list different checksums for a version
+    ChecksumMismatch,
+    /// Entries of the registry index could not be read and were skipped
+    MalformedIndexEntry,
+    /// Something else worth knowing
+    Other,
+}
+
+/// A non-fatal finding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+    /// What the warning is about
+    pub kind: WarningKind,
+    /// The message shown to the user
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+/// Collects warnings, so an operation can complete and leave reporting them to the caller.
+///
+/// Serialized as a list of `{ "kind": ..., "message": ... }` objects.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Warnings(Vec<Warning>);
+
+impl Warnings {
+    /// An empty collection
+    pub fn new() -> Self {
+        Warnings(Vec::new())
+    }
+
+    /// Add a warning
+    pub fn push<S: Into<String>>(&mut self, kind: WarningKind, message: S) {
+        self.0.push(Warning {
+            kind: kind,
+            message: message.into(),
+        });
+    }
+
+    /// Whether no warnings were collected
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the warnings, in the order they were collected
+    pub fn iter(&self) -> slice::Iter<Warning> {
+        self.0.iter()
+    }
+
+    /// Write the warnings as `WARN: ...` lines
+    pub fn render<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        for warning in &self.0 {
+            writeln!(output, "WARN: {}", warning)?;
+        }
+        Ok(())
+    }
+
+    /// Print the warnings to stdout, for callers that do not collect them.
+    pub(crate) fn print(&self) {
+        for warning in &self.0 {
+            println!("WARN: {}", warning);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn render_and_serialize() {
+        let mut warnings = Warnings::new();
+        assert!(warnings.is_empty());
+        warnings.push(WarningKind::Style, "`[dev_dependencies]` is a deprecated spelling");
+
+        let mut rendered = Vec::new();
+        warnings.render(&mut rendered).unwrap();
+        assert_eq!(
+            String::from_utf8(rendered).unwrap(),
+            "WARN: `[dev_dependencies]` is a deprecated spelling\n"
+        );
+        assert_eq!(
+            serde_json::to_string(&warnings).unwrap(),
+            r#"[{"kind":"style","message":"`[dev_dependencies]` is a deprecated spelling"}]"#
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Members of a cargo workspace.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cargo_metadata;
+
+use errors::*;
+use manifest::{find, Manifest};
+
+/// A package that is a member of a workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceMember {
+    /// Name of the package
+    pub name: String,
+    /// Version of the package
+    pub version: String,
+    /// Path to the manifest of the package
+    pub manifest_path: PathBuf,
+    /// Directory of the package, relative to the workspace root (e.g. `crates/foo`), with `/` as
+    /// separator
+    pub path: String,
+}
+
+/// List the members of the workspace the manifest belongs to.
+///
+/// `manifest_path` works like for `find`; the returned paths are relative to the directory of the
+/// manifest found, which is expected to be the workspace root.
+pub fn workspace_members(manifest_path: &Option<PathBuf>) -> Result<Vec<WorkspaceMember>> {
+    let root_manifest = find(manifest_path)?;
+    let root = root_manifest.parent().unwrap_or_else(|| Path::new(""));
+
+    let metadata = cargo_metadata::metadata(Some(root_manifest.as_path()))
+        .map_err(Error::WorkspaceMetadata)?;
+
+    Ok(metadata
+        .packages
+        .into_iter()
+        .map(|package| {
+            let manifest_path = PathBuf::from(package.manifest_path);
+            let path = manifest_path
+                .parent()
+                .and_then(|dir| dir.strip_prefix(root).ok())
+                .map(|dir| {
+                    dir.components()
+                        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                        .collect::<Vec<_>>()
+                        .join("/")
+                })
+                .unwrap_or_default();
+            WorkspaceMember {
+                name: package.name,
+                version: package.version,
+                manifest_path: manifest_path,
+                path: path,
+            }
+        })
+        .collect())
+}
+
+/// The root manifest of the workspace the manifest belongs to, with the directories its
+/// `[workspace]` table excludes (`exclude`). `None` if the manifest does not belong to a workspace.
+fn workspace_exclusions(
+    manifest_path: &Option<PathBuf>,
+) -> Result<Option<(PathBuf, Vec<PathBuf>)>> {
+    let root_manifest = match find_workspace_root(&find(manifest_path)?) {
+        Ok(root_manifest) => root_manifest,
+        Err(Error::WorkspaceRootNotFound) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let root = root_manifest.parent().unwrap_or_else(|| Path::new(""));
+    let root_data = Manifest::open(&Some(root_manifest.clone()))?.data;
+    let excluded = root_data["workspace"]["exclude"]
+        .as_array()
+        .map(|excluded| {
+            excluded
+                .iter()
+                .filter_map(|e| e.as_str())
+                .map(|dir| root.join(dir))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Some((root_manifest.clone(), excluded)))
+}
+
+/// List the packages in the directories that the workspace the manifest belongs to excludes with
+/// `exclude` of its `[workspace]` table, e.g. fuzz targets or example projects with workspaces of
+/// their own. Empty if the manifest does not belong to a workspace.
+///
+/// The packages are found from their manifests alone, searching the excluded directories and the
+/// directories below them, except for `target` and hidden directories. Their paths are relative to
+/// the workspace root.
+pub fn excluded_members(manifest_path: &Option<PathBuf>) -> Result<Vec<WorkspaceMember>> {
+    let (root_manifest, excluded) = match workspace_exclusions(manifest_path)? {
+        Some(exclusions) => exclusions,
+        None => return Ok(vec![]),
+    };
+    let mut manifests = Vec::new();
+    for dir in &excluded {
+        find_manifests(dir, &mut manifests)?;
+    }
+    read_packages(manifests, &root_manifest, false)
+}
+
+/// List the packages below the root of the workspace the manifest belongs to that are neither
+/// members of the workspace nor excluded by it, e.g. the crates of test fixtures. Cargo does not
+/// consider them part of the workspace, so operations on all members skip them. Empty if the
+/// manifest does not belong to a workspace.
+///
+/// The directories are searched like for `excluded_members`. Manifests that can not be read, e.g.
+/// the fixtures of broken manifests, are left out.
+pub fn nested_packages(manifest_path: &Option<PathBuf>) -> Result<Vec<WorkspaceMember>> {
+    let (root_manifest, excluded) = match workspace_exclusions(manifest_path)? {
+        Some(exclusions) => exclusions,
+        None => return Ok(vec![]),
+    };
+    let resolve = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let mut known: Vec<PathBuf> = workspace_members(&Some(root_manifest.clone()))?
+        .iter()
+        .map(|member| resolve(&member.manifest_path))
+        .collect();
+    known.push(resolve(&root_manifest));
+
+    let mut manifests = Vec::new();
+    find_manifests(
+        root_manifest.parent().unwrap_or_else(|| Path::new("")),
+        &mut manifests,
+    )?;
+    manifests.retain(|manifest| {
+        !known.contains(&resolve(manifest)) && !excluded.iter().any(|dir| manifest.starts_with(dir))
+    });
+    read_packages(manifests, &root_manifest, true)
+}
+
+/// Read the packages of `manifests` (skipping virtual manifests), with their paths relative to the
+/// directory of `root_manifest`. With `skip_unreadable`, manifests that can not be read are left
+/// out instead of failing.
+fn read_packages(
+    mut manifests: Vec<PathBuf>,
+    root_manifest: &Path,
+    skip_unreadable: bool,
+) -> Result<Vec<WorkspaceMember>> {
+    let root = root_manifest.parent().unwrap_or_else(|| Path::new(""));
+    manifests.sort();
+    manifests.dedup();
+
+    let mut packages = Vec::new();
+    for manifest_path in manifests {
+        let manifest = match Manifest::open(&Some(manifest_path.clone())) {
+            Ok(manifest) => manifest,
+            Err(_) if skip_unreadable => continue,
+            Err(e) => return Err(e),
+        };
+        let name = match manifest.data["package"]["name"].as_str() {
+            Some(name) => name.to_owned(),
+            None => continue,
+        };
+        let path = manifest_path
+            .parent()
+            .and_then(|dir| dir.strip_prefix(root).ok())
+            .map(|dir| {
+                dir.components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .unwrap_or_default();
+        packages.push(WorkspaceMember {
+            name: name,
+            // Cargo takes a package without a version to be 0.0.0.
+            version: manifest.data["package"]["version"]
+                .as_str()
+                .unwrap_or("0.0.0")
+                .to_owned(),
+            manifest_path: manifest_path,
+            path: path,
+        });
+    }
+    Ok(packages)
+}
+
+/// Add the manifests in `dir` and the directories below it to `manifests`, skipping `target` and
+/// hidden directories. Symbolic links are not followed, so a link to a parent directory can not
+/// send the search in circles. A missing directory has no manifests.
+fn find_manifests(dir: &Path, manifests: &mut Vec<PathBuf>) -> Result<()> {
+    let manifest = dir.join("Cargo.toml");
+    if manifest.is_file() {
+        manifests.push(manifest);
+    }
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+    for entry in entries {
+        let entry = entry.map_err(Error::io("Failed to read a directory of the workspace"))?;
+        let is_dir = entry
+            .file_type()
+            .map(|file_type| file_type.is_dir())
+            .unwrap_or(false);
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !is_dir || name == "target" || name.starts_with('.') {
+            continue;
+        }
+        find_manifests(&entry.path(), manifests)?;
+    }
+    Ok(())
+}
+
+/// Find the root manifest of the workspace a package belongs to: the closest manifest with a
+/// `[workspace]` table, starting with the package's own manifest and going up the directory tree.
+pub fn find_workspace_root(manifest_path: &Path) -> Result<PathBuf> {
+    let dir = match manifest_path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let dir = dir.canonicalize()
+        .map_err(Error::io("Failed to resolve the directory of the manifest"))?;
+    for dir in dir.ancestors() {
+        let candidate = dir.join("Cargo.toml");
+        if !candidate.is_file() {
+            continue;
+        }
+        if !Manifest::open(&Some(candidate.clone()))?.data["workspace"].is_none() {
+            return Ok(candidate);
+        }
+    }
+    Err(Error::WorkspaceRootNotFound)
+}
+
+/// Get the crates for which any prerelease may be picked, from the manifest and the root manifest
+/// of its workspace, if any (see `Manifest::prerelease_crates`).
+pub fn prerelease_crates(manifest_path: &Path) -> Result<Vec<String>> {
+    listed_crates(manifest_path, Manifest::prerelease_crates)
+}
+
+/// Get the crates `cargo upgrade` leaves alone unless they are named, from the manifest and the
+/// root manifest of its workspace, if any (see `Manifest::ignored_upgrades`).
+pub fn ignored_upgrades(manifest_path: &Path) -> Result<Vec<String>> {
+    listed_crates(manifest_path, Manifest::ignored_upgrades)
+}
+
+/// Get the crates a list of the manifest and the root manifest of its workspace name.
+fn listed_crates(manifest_path: &Path, list: fn(&Manifest) -> Vec<String>) -> Result<Vec<String>> {
+    let mut crates = list(&Manifest::open(&Some(manifest_path.to_path_buf()))?);
+    match find_workspace_root(manifest_path) {
+        Ok(root) => crates.extend(list(&Manifest::open(&Some(root))?)),
+        Err(Error::WorkspaceRootNotFound) => {}
+        Err(e) => return Err(e),
+    }
+    Ok(crates)
+}
+
+impl WorkspaceMember {
+    /// Whether the package name or its path matches a glob `pattern` (e.g. `crates/*-client`).
+    ///
+    /// In a pattern, `*` matches any (possibly empty) string and `?` any single character.
+    pub fn matches(&self, pattern: &str) -> bool {
+        glob_matches(pattern, &self.name) || glob_matches(pattern, &self.path)
+    }
+}
+
+pub(crate) fn glob_matches(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[char], text: &[char]) -> bool {
+        match pattern.first() {
+            None => text.is_empty(),
+            Some(&'*') => (0..text.len() + 1).any(|i| matches(&pattern[1..], &text[i..])),
+            Some(&'?') => !text.is_empty() && matches(&pattern[1..], &text[1..]),
+            Some(c) => text.first() == Some(c) && matches(&pattern[1..], &text[1..]),
+        }
+    }
+
+    let pattern: Vec<_> = pattern.chars().collect();
+    let text: Vec<_> = text.chars().collect();
+    matches(&pattern, &text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_globs() {
+        assert!(glob_matches("crates/*-client", "crates/http-client"));
+        assert!(glob_matches("*-client", "-client"));
+        assert!(glob_matches("one", "one"));
+        assert!(glob_matches("t?o", "two"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("crates/*-client", "crates/http-server"));
+        assert!(!glob_matches("t?o", "to"));
+    }
+
+    #[test]
+    fn match_name_or_path() {
+        let member = WorkspaceMember {
+            name: "four".into(),
+            version: "0.1.0".into(),
+            manifest_path: PathBuf::from("explicit/four/Cargo.toml"),
+            path: "explicit/four".into(),
+        };
+        assert!(member.matches("four"));
+        assert!(member.matches("explicit/*"));
+        assert!(!member.matches("one"));
+    }
+}
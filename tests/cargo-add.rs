@@ -3,9 +3,11 @@ extern crate assert_cli;
 extern crate pretty_assertions;
 extern crate toml_edit;
 
+use std::io::Write;
 use std::process;
 mod utils;
-use utils::{clone_out_test, execute_command, get_toml};
+use utils::{add_excluded_package, clone_out_test, commit_git_repository, copy_workspace_test,
+            execute_command, get_toml};
 
 /// Some of the tests need to have a crate name that does not exist on crates.io. Hence this rather
 /// silly constant. Tests _will_ fail, though, if a crate is ever published with this name.
@@ -50,6 +52,55 @@ fn adds_prerelease_dependency() {
     assert_eq!(val.as_str().unwrap(), "my-package--PRERELEASE_VERSION_TEST");
 }
 
+#[test]
+fn adds_prerelease_of_crate_listed_in_manifest() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.prerelease-crates");
+
+    execute_command(&["add", "my-prerelease-package", "my-package"], &manifest);
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-prerelease-package"];
+    assert_eq!(
+        val.as_str().unwrap(),
+        "my-prerelease-package--PRERELEASE_VERSION_TEST"
+    );
+    let val = &toml["dependencies"]["my-package"];
+    assert_eq!(val.as_str().unwrap(), "my-package--CURRENT_VERSION_TEST");
+}
+
+#[test]
+fn adds_prerelease_dependency_of_channel() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "my-package", "--prerelease", "rc"], &manifest);
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package"];
+    assert_eq!(val.as_str().unwrap(), "my-package--PRERELEASE_VERSION_TEST");
+}
+
+#[test]
+fn fails_to_add_prerelease_of_unknown_channel() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "my-package",
+        "--prerelease",
+        "nightly",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: Invalid prerelease channel `nightly`, \
+             expected `alpha`, `beta` or `rc`",
+        )
+        .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"].is_none());
+}
+
 fn upgrade_test_helper(upgrade_method: &str, expected_prefix: &str) {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
@@ -109,6 +160,97 @@ fn adds_multiple_dependencies() {
     assert_eq!(val.as_str().unwrap(), "my-package2--CURRENT_VERSION_TEST");
 }
 
+#[test]
+fn adds_multiple_dependencies_with_own_operators() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &[
+            "add",
+            "my-package1@=",
+            "my-package2@~",
+            "my-package3",
+            "--upgrade=all",
+        ],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package1"];
+    assert_eq!(val.as_str().unwrap(), "=my-package1--CURRENT_VERSION_TEST");
+    let val = &toml["dependencies"]["my-package2"];
+    assert_eq!(val.as_str().unwrap(), "~my-package2--CURRENT_VERSION_TEST");
+    let val = &toml["dependencies"]["my-package3"];
+    assert_eq!(val.as_str().unwrap(), ">=my-package3--CURRENT_VERSION_TEST");
+}
+
+#[test]
+fn adds_multiple_dependencies_with_jobs() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "my-package1", "my-package2", "--jobs=1"], &manifest);
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package1"];
+    assert_eq!(val.as_str().unwrap(), "my-package1--CURRENT_VERSION_TEST");
+    let val = &toml["dependencies"]["my-package2"];
+    assert_eq!(val.as_str().unwrap(), "my-package2--CURRENT_VERSION_TEST");
+}
+
+#[test]
+fn adds_dependencies_to_several_sections() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &[
+            "add",
+            "my-package",
+            "--dev-crates",
+            "my-dev-package1,my-dev-package2@0.3",
+            "--build-crates",
+            "my-build-package",
+        ],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["my-package"].as_str(),
+        Some("my-package--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(
+        toml["dev-dependencies"]["my-dev-package1"].as_str(),
+        Some("my-dev-package1--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(
+        toml["dev-dependencies"]["my-dev-package2"].as_str(),
+        Some("0.3")
+    );
+    assert_eq!(
+        toml["build-dependencies"]["my-build-package"].as_str(),
+        Some("my-build-package--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn adds_only_dev_and_build_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "--dev-crates", "my-dev-package"], &manifest);
+    execute_command(&["add", "--build-crates", "my-build-package"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"].is_none());
+    assert_eq!(
+        toml["dev-dependencies"]["my-dev-package"].as_str(),
+        Some("my-dev-package--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(
+        toml["build-dependencies"]["my-build-package"].as_str(),
+        Some("my-build-package--CURRENT_VERSION_TEST")
+    );
+}
+
 #[test]
 fn adds_dev_build_dependency() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -317,6 +459,240 @@ fn adds_git_source_using_flag() {
     assert_eq!(val["git"].as_str(), Some("http://site/gp.git"));
 }
 
+#[test]
+fn adds_git_source_using_shorthand() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "regex", "--git", "rust-lang/regex"], &manifest);
+    execute_command(&["add", "polly", "--git", "gl:Polly-lang/Polly"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["regex"]["git"].as_str(),
+        Some("https://github.com/rust-lang/regex")
+    );
+    assert_eq!(
+        toml["dependencies"]["polly"]["git"].as_str(),
+        Some("https://gitlab.com/Polly-lang/Polly")
+    );
+}
+
+#[test]
+fn adds_git_source_named_by_its_manifest() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let (repo, _) = clone_out_test("tests/fixtures/add/local/Cargo.toml.sample");
+    let url = commit_git_repository(repo.path());
+
+    execute_command(&["add", &url], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["foo-crate"]["git"].as_str(),
+        Some(url.as_str())
+    );
+}
+
+/// Commit a workspace with the members `crates/one` and `crates/two` to a new git repository in
+/// `dir`, and return its `file://` URL.
+fn commit_git_workspace(dir: &std::path::Path) -> String {
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/*\"]\n",
+    ).unwrap();
+    for name in &["one", "two"] {
+        let member = dir.join("crates").join(name);
+        std::fs::create_dir_all(&member).unwrap();
+        std::fs::write(
+            member.join("Cargo.toml"),
+            format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name),
+        ).unwrap();
+    }
+    commit_git_repository(dir)
+}
+
+#[test]
+fn fails_to_pick_package_of_git_workspace_without_terminal() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let (repo, _) = clone_out_test("tests/fixtures/add/local/Cargo.toml.sample");
+    let url = commit_git_workspace(repo.path());
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        &url,
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(format!(
+            "Command failed due to unhandled error: The repository `{}` has several packages \
+             (one, two). Pick one, e.g. with `cargo add {} --crate one`",
+            url, url
+        ))
+        .unwrap();
+
+    // Naming the package works.
+    execute_command(&["add", "two", "--git", &url], &manifest);
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["two"]["git"].as_str(), Some(url.as_str()));
+}
+
+#[test]
+fn adds_member_of_git_workspace_with_crate() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let (repo, _) = clone_out_test("tests/fixtures/add/local/Cargo.toml.sample");
+    let url = commit_git_workspace(repo.path());
+
+    execute_command(&["add", &url, "--crate", "one"], &manifest);
+    execute_command(&["add", "--git", &url, "--crate", "two", "--dev"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["one"]["git"].as_str(), Some(url.as_str()));
+    assert_eq!(toml["dev-dependencies"]["two"]["git"].as_str(), Some(url.as_str()));
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "--git",
+        &url,
+        "--crate",
+        "three",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(format!(
+            "Command failed due to unhandled error: The repository `{}` has no package \
+             `three`. Its packages are: one, two",
+            url
+        ))
+        .unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "serde",
+        "--crate",
+        "one",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--crate` picks a package of a git \
+             repository, so it needs `--git` or a repository URL instead of a crate name",
+        )
+        .unwrap();
+}
+
+#[test]
+fn adds_git_source_with_version() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &[
+            "add",
+            "git-package",
+            "--git",
+            "http://localhost/git-package.git",
+            "--vers",
+            "1.0",
+        ],
+        &manifest,
+    );
+    execute_command(
+        &["add", "local", "--path", "/path/to/pkg", "--vers=0.2"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["git-package"];
+    assert_eq!(val["version"].as_str(), Some("1.0"));
+    assert_eq!(
+        val["git"].as_str(),
+        Some("http://localhost/git-package.git")
+    );
+    let val = &toml["dependencies"]["local"];
+    assert_eq!(val["version"].as_str(), Some("0.2"));
+    assert_eq!(val["path"].as_str(), Some("/path/to/pkg"));
+}
+
+#[test]
+fn adds_git_source_with_reference() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &["add", "git-package", "--git", "http://localhost/git-package.git", "--tag", "v1.0"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["git-package"];
+    assert_eq!(val["git"].as_str(), Some("http://localhost/git-package.git"));
+    assert_eq!(val["tag"].as_str(), Some("v1.0"));
+
+    // the reference is replaced along with the repository
+    execute_command(
+        &["add", "git-package", "--git", "http://localhost/git-package.git", "--rev", "abc123"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["git-package"];
+    assert!(val["tag"].is_none());
+    assert_eq!(val["rev"].as_str(), Some("abc123"));
+}
+
+#[test]
+fn fails_to_add_git_reference_without_git() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "my-package",
+        "--branch",
+        "master",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--branch`, `--tag` and `--rev` can only be \
+             used with a git dependency (pass `--git` or a git repository URL)",
+        )
+        .unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "my-package",
+        "--git",
+        "http://localhost/git-package.git",
+        "--branch",
+        "master",
+        "--tag",
+        "v1.0",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: Only one of `--branch`, `--tag` and `--rev` \
+             may be given",
+        )
+        .unwrap();
+}
+
+#[test]
+#[cfg(feature = "test-external-apis")]
+fn adds_git_source_without_flag_with_branch() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &["add", "https://github.com/killercup/cargo-edit.git", "--branch", "master"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["cargo-edit"];
+    assert_eq!(
+        val["git"].as_str(),
+        Some("https://github.com/killercup/cargo-edit.git")
+    );
+    assert_eq!(val["branch"].as_str(), Some("master"));
+}
+
 #[test]
 fn adds_local_source_using_flag() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -436,41 +812,209 @@ fn package_kinds_are_mutually_exclusive() {
 }
 
 #[test]
-fn adds_optional_dependency() {
+fn adds_dependency_with_features() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
-    // dependency not present beforehand
-    let toml = get_toml(&manifest);
-    assert!(toml["dependencies"].is_none());
-
-    execute_command(
-        &[
-            "add",
-            "versioned-package",
-            "--vers",
-            ">=0.1.1",
-            "--optional",
-        ],
-        &manifest,
-    );
+    execute_command(&["add", "my-package", "--features", "derive,std"], &manifest);
 
-    // dependency present afterwards
     let toml = get_toml(&manifest);
-    let val = &toml["dependencies"]["versioned-package"]["optional"];
-    assert_eq!(val.as_bool().expect("optional not a bool"), true);
+    let val = &toml["dependencies"]["my-package"];
+    let features = val["features"].as_array().expect("features not an array");
+    let features: Vec<_> = features.iter().map(|f| f.as_str().unwrap()).collect();
+    assert_eq!(features, vec!["derive", "std"]);
 }
 
 #[test]
-fn adds_multiple_optional_dependencies() {
+fn adds_dependencies_with_inline_features() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
-    // dependencies not present beforehand
-    let toml = get_toml(&manifest);
-    assert!(toml["dependencies"].is_none());
+    execute_command(&["add", "my-package1+derive", "my-package2+std+derive@1.0"], &manifest);
 
-    execute_command(
-        &["add", "--optional", "my-package1", "my-package2"],
-        &manifest,
+    let toml = get_toml(&manifest);
+    let features = |name: &str| -> Vec<String> {
+        toml["dependencies"][name]["features"]
+            .as_array()
+            .expect("features not an array")
+            .iter()
+            .map(|f| f.as_str().unwrap().to_owned())
+            .collect()
+    };
+    assert_eq!(
+        toml["dependencies"]["my-package1"]["version"].as_str(),
+        Some("my-package1--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(features("my-package1"), vec!["derive"]);
+    assert_eq!(
+        toml["dependencies"]["my-package2"]["version"].as_str(),
+        Some("1.0")
+    );
+    assert_eq!(features("my-package2"), vec!["std", "derive"]);
+}
+
+#[test]
+fn adds_dependency_without_default_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &["add", "my-package", "--no-default-features", "--features=derive"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package"];
+    assert_eq!(val["default-features"].as_bool(), Some(false));
+    assert_eq!(val["features"].as_array().map(|f| f.len()), Some(1));
+}
+
+#[test]
+fn fails_to_reenable_default_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "my-package", "--no-default-features", "--features=default"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(!call.status.success());
+    assert!(String::from_utf8_lossy(&call.stderr).contains(
+        "`--no-default-features` contradicts enabling `default` of `my-package`, which only \
+         enable the default features"
+    ));
+    assert!(get_toml(&manifest)["dependencies"].is_none());
+}
+
+#[test]
+fn fails_to_add_dependency_with_unknown_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "my-package", "--features=derive,does-not-exist"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(!call.status.success());
+    assert!(String::from_utf8_lossy(&call.stderr).contains(
+        "Unknown features `does-not-exist` for `my-package` my-package--CURRENT_VERSION_TEST.
+Valid features are: default, derive, std, test-derive"
+    ));
+
+    // nothing has been added
+    assert!(get_toml(&manifest)["dependencies"].is_none());
+
+    // ... unless the check is skipped
+    execute_command(
+        &[
+            "add",
+            "my-package",
+            "--features=does-not-exist",
+            "--no-verify-features",
+        ],
+        &manifest,
+    );
+    assert!(!get_toml(&manifest)["dependencies"]["my-package"].is_none());
+}
+
+#[test]
+fn checks_features_of_path_dependency_against_its_manifest() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let sibling = tmpdir.path().join("sibling");
+    std::fs::create_dir(&sibling).unwrap();
+    std::fs::write(
+        sibling.join("Cargo.toml"),
+        "[package]\nname = \"sibling\"\nversion = \"0.1.0\"\n\n[features]\nfast = []\n",
+    ).unwrap();
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "sibling", "--path=sibling", "--features=fast,slow", "--offline"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(!call.status.success());
+    assert!(String::from_utf8_lossy(&call.stderr).contains(
+        "Unknown features `slow` for `sibling` at `sibling`.
+Valid features are: fast"
+    ));
+    assert!(get_toml(&manifest)["dependencies"].is_none());
+
+    execute_command(&["add", "sibling", "--path=sibling", "--features=fast"], &manifest);
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["sibling"]["features"]
+            .as_array()
+            .map(|features| features.len()),
+        Some(1)
+    );
+}
+
+#[test]
+fn adds_interactively_picked_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let mut call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "my-package", "--interactive-features"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The fake feature list is `derive`, `std`, `test-derive` (`default` is not offered).
+    call.stdin.as_mut().unwrap().write_all(b"2\n").unwrap();
+    assert!(call.wait_with_output().unwrap().status.success());
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package"];
+    assert_eq!(
+        val["version"].as_str(),
+        Some("my-package--CURRENT_VERSION_TEST")
+    );
+    let features = val["features"].as_array().expect("features not an array");
+    let features: Vec<_> = features.iter().map(|f| f.as_str().unwrap()).collect();
+    assert_eq!(features, vec!["std"]);
+}
+
+#[test]
+fn adds_optional_dependency() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    // dependency not present beforehand
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"].is_none());
+
+    execute_command(
+        &[
+            "add",
+            "versioned-package",
+            "--vers",
+            ">=0.1.1",
+            "--optional",
+        ],
+        &manifest,
+    );
+
+    // dependency present afterwards
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["versioned-package"]["optional"];
+    assert_eq!(val.as_bool().expect("optional not a bool"), true);
+}
+
+#[test]
+fn adds_multiple_optional_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    // dependencies not present beforehand
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"].is_none());
+
+    execute_command(
+        &["add", "--optional", "my-package1", "my-package2"],
+        &manifest,
     );
 
     // dependencies present afterwards
@@ -520,6 +1064,27 @@ fn adds_dependency_with_target_cfg() {
     assert_eq!(val.as_str().unwrap(), "my-package1--CURRENT_VERSION_TEST");
 }
 
+#[test]
+fn adds_build_dependency_with_target_cfg() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &["add", "--build", "--target", "cfg(windows)", "my-package1"],
+        &manifest,
+    );
+    execute_command(
+        &["add", "--dev", "--target", "cfg(unix)", "my-package2"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    assert!(toml["build-dependencies"].is_none());
+    let val = &toml["target"]["cfg(windows)"]["build-dependencies"]["my-package1"];
+    assert_eq!(val.as_str(), Some("my-package1--CURRENT_VERSION_TEST"));
+    let val = &toml["target"]["cfg(unix)"]["dev-dependencies"]["my-package2"];
+    assert_eq!(val.as_str(), Some("my-package2--CURRENT_VERSION_TEST"));
+}
+
 #[test]
 fn adds_dependency_with_custom_target() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -560,12 +1125,22 @@ fn adds_dependency_normalized_name() {
 }
 
 #[test]
-#[should_panic]
 fn fails_to_add_dependency_with_empty_target() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
     // Fails because target parameter must be a valid target
-    execute_command(&["add", "--target", "", "my-package1"], &manifest);
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "--target",
+        "",
+        "my-package1",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: Target specification may not be empty",
+        )
+        .unwrap();
 }
 
 #[test]
@@ -591,6 +1166,67 @@ fn fails_to_add_optional_dev_dependency() {
     );
 }
 
+#[test]
+fn optional_dev_dependency_error() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "my-package",
+        "--build",
+        "--optional",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error(
+            "`--optional` can not be combined with `--dev` or `--build`: cargo does not support \
+             optional dev-dependencies or build-dependencies",
+        )
+        .unwrap();
+
+    assert!(get_toml(&manifest)["build-dependencies"].is_none());
+}
+
+#[test]
+fn optional_dev_dependency_error_suggests_emulation() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "my-package",
+        "--dev",
+        "--optional",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error("pass `--emulate-optional-dev` instead of `--optional` to set this up")
+        .unwrap();
+
+    assert!(get_toml(&manifest)["dev-dependencies"].is_none());
+}
+
+#[test]
+fn emulates_optional_dev_dependency() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &["add", "my-package", "--dev", "--emulate-optional-dev"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    assert!(toml["dev-dependencies"].is_none());
+    let dep = &toml["dependencies"]["my-package"];
+    assert_eq!(dep["optional"].as_bool(), Some(true));
+    let enables: Vec<_> = toml["features"]["test-my-package"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|f| f.as_str().map(String::from))
+        .collect();
+    assert_eq!(enables, vec!["dep:my-package"]);
+}
+
 #[test]
 #[should_panic]
 fn fails_to_add_multiple_optional_dev_dependencies() {
@@ -669,6 +1305,38 @@ versioned-package = { version = "versioned-package--CURRENT_VERSION_TEST", optio
     )
 }
 
+#[test]
+fn overwrite_version_keeps_features() {
+    overwrite_dependency_test(
+        &[
+            "add",
+            "versioned-package",
+            "--vers",
+            "0.1.1",
+            "--features",
+            "derive",
+            "--no-default-features",
+        ],
+        &["add", "versioned-package", "--features", "std"],
+        r#"
+[dependencies]
+versioned-package = { version = "versioned-package--CURRENT_VERSION_TEST", default-features = false, features = ["derive", "std"] }
+"#,
+    )
+}
+
+#[test]
+fn overwrite_features_with_added_and_removed_ones() {
+    overwrite_dependency_test(
+        &["add", "versioned-package", "--features", "std", "--optional"],
+        &["add", "versioned-package", "--features=-std,+derive"],
+        r#"
+[dependencies]
+versioned-package = { version = "versioned-package--CURRENT_VERSION_TEST", features = ["derive"], optional = true }
+"#,
+    )
+}
+
 #[test]
 fn overwrite_version_with_git() {
     overwrite_dependency_test(
@@ -731,26 +1399,32 @@ fn no_argument() {
             r"Invalid arguments.
 
 Usage:
-    cargo add <crate> [--dev|--build|--optional] [--vers=<ver>|--git=<uri>|--path=<uri>] [options]
-    cargo add <crates>... [--dev|--build|--optional] [options]
+    cargo add <crate> [--dev|--build] [--optional] [--vers=<ver>] [--git=<uri>|--path=<uri>] [options]
+    cargo add --git=<uri> --crate=<member> [--dev|--build] [--optional] [--vers=<ver>] [options]
+    cargo add --path-to-crate=<file> [--dev|--build] [--optional] [options]
+    cargo add <crates>... [--dev|--build] [--optional] [options]
+    cargo add (--dev-crates=<crates>|--build-crates=<crates>) [options]
     cargo add (-h|--help)
-    cargo add --version",
+    cargo add --version [--json]",
         )
         .unwrap();
 }
 
 #[test]
 fn unknown_flags() {
-    assert_cli::Assert::command(&["target/debug/cargo-add", "add", "foo", "--flag"])
+    assert_cli::Assert::command(&["target/debug/cargo-add", "add", "foo", "--frobnicate"])
         .fails_with(1)
         .prints_error_exactly(
-            r"Unknown flag: '--flag'
+            r"Unknown flag: '--frobnicate'
 
 Usage:
-    cargo add <crate> [--dev|--build|--optional] [--vers=<ver>|--git=<uri>|--path=<uri>] [options]
-    cargo add <crates>... [--dev|--build|--optional] [options]
+    cargo add <crate> [--dev|--build] [--optional] [--vers=<ver>] [--git=<uri>|--path=<uri>] [options]
+    cargo add --git=<uri> --crate=<member> [--dev|--build] [--optional] [--vers=<ver>] [options]
+    cargo add --path-to-crate=<file> [--dev|--build] [--optional] [options]
+    cargo add <crates>... [--dev|--build] [--optional] [options]
+    cargo add (--dev-crates=<crates>|--build-crates=<crates>) [options]
     cargo add (-h|--help)
-    cargo add --version",
+    cargo add --version [--json]",
         )
         .unwrap();
 }
@@ -820,3 +1494,844 @@ fn add_prints_message_for_build_deps() {
         .prints_exactly("Adding hello-world v0.1.0 to build-dependencies")
         .unwrap();
 }
+
+#[test]
+fn add_prints_message_for_target_build_deps() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "hello-world",
+        "--build",
+        "--target=cfg(windows)",
+        "--vers",
+        "0.1.0",
+        &format!("--manifest-path={}", manifest),
+    ]).succeeds()
+        .prints_exactly("Adding hello-world v0.1.0 to build-dependencies for target `cfg(windows)`")
+        .unwrap();
+}
+
+#[test]
+fn add_prints_message_once_for_several_targets() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "libc",
+        "--target",
+        "cfg(unix), cfg(windows)",
+        "--vers",
+        "0.2.30",
+        &format!("--manifest-path={}", manifest),
+    ]).succeeds()
+        .prints_exactly(
+            "Adding libc v0.2.30 to dependencies for targets `cfg(unix)`, `cfg(windows)`",
+        )
+        .unwrap();
+
+    let toml = get_toml(&manifest);
+    for target in &["cfg(unix)", "cfg(windows)"] {
+        let val = &toml["target"][target]["dependencies"]["libc"];
+        assert_eq!(val.as_str(), Some("0.2.30"));
+    }
+}
+
+#[test]
+fn add_verbose_prints_license_and_msrv() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    // The license and MSRV are the ones of the simulated crates.io.
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "docopt", "--vers", "0.6.0", "--verbose"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&call.stdout).trim(),
+        "Adding docopt v0.6.0 to dependencies (license: MIT OR Apache-2.0, MSRV: 1.31)"
+    );
+}
+
+#[test]
+fn add_warns_about_newer_rust_version() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.rust-version");
+
+    // The simulated crates.io says every crate needs Rust 1.31.
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "docopt", "--vers", "0.6.0"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(String::from_utf8_lossy(&call.stdout).contains(
+        "WARN: `docopt` needs Rust 1.31, which is newer than the `rust-version` of the \
+         package (1.20). Pass `--bump-msrv` to raise it."
+    ));
+    assert_eq!(
+        get_toml(&manifest)["package"]["rust-version"].as_str(),
+        Some("1.20")
+    );
+}
+
+#[test]
+fn add_bumps_rust_version() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.rust-version");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "docopt", "--vers", "0.6.0", "--bump-msrv"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(
+        String::from_utf8_lossy(&call.stdout)
+            .contains("Updating rust-version from 1.20 to 1.31 (needed by docopt)")
+    );
+    assert_eq!(
+        get_toml(&manifest)["package"]["rust-version"].as_str(),
+        Some("1.31")
+    );
+}
+
+#[test]
+fn add_warns_about_newer_edition() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.edition");
+
+    // The simulated crates.io says every crate uses the 2018 edition.
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "docopt", "--vers", "0.6.0"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(String::from_utf8_lossy(&call.stdout).contains(
+        "WARN: `docopt` 0.6.0 uses the 2018 edition, which is newer than the edition of the \
+         package (2015). Its latest version for the 2015 edition is \
+         docopt--EDITION_2015_TEST; pass `--compat-edition` to add that one."
+    ));
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["docopt"].as_str(),
+        Some("0.6.0")
+    );
+}
+
+#[test]
+fn add_picks_version_for_edition() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.edition");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "docopt", "--vers", "=0.6.0", "--compat-edition"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(String::from_utf8_lossy(&call.stdout).contains(
+        "Downgrading docopt from =0.6.0 to =docopt--EDITION_2015_TEST (for the 2015 edition)"
+    ));
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["docopt"].as_str(),
+        Some("=docopt--EDITION_2015_TEST")
+    );
+}
+
+#[test]
+fn add_follows_manifest_style() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.style");
+
+    execute_command(
+        &["add", "docopt", "--vers", "0.6.0", "--features", "derive,std"],
+        &manifest,
+    );
+    execute_command(&["add", "regex", "--vers", "1.0"], &manifest);
+    let contents = std::fs::read_to_string(&manifest).unwrap();
+    assert!(contents.contains("docopt={version='0.6.0',features=[ 'derive','std' ]}\n"));
+    assert!(contents.contains("regex='1.0'\n"));
+}
+
+#[test]
+fn add_keeps_byte_order_mark_and_line_endings() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let contents = std::fs::read_to_string(&manifest).unwrap();
+    let windows = format!("\u{feff}{}", contents.replace('\n', "\r\n"));
+    std::fs::write(&manifest, &windows).unwrap();
+
+    execute_command(&["add", "docopt", "--vers", "0.6.0"], &manifest);
+    let contents = std::fs::read_to_string(&manifest).unwrap();
+    assert!(contents.starts_with("\u{feff}[package]\r\n"));
+    assert!(contents.ends_with("docopt = \"0.6.0\"\r\n"));
+    assert_eq!(contents.matches('\n').count(), contents.matches("\r\n").count());
+}
+
+#[test]
+fn add_dry_run_leaves_manifest_alone() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let original = std::fs::read_to_string(&manifest).unwrap();
+
+    execute_command(&["add", "docopt", "--vers", "0.6.0", "--dry-run"], &manifest);
+
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), original);
+}
+
+#[test]
+fn add_warns_about_renamed_crate() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    // On the simulated crates.io, `<crate>-renamed` was renamed to `<crate>`.
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "docopt-renamed"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(String::from_utf8_lossy(&call.stdout).contains(
+        "WARN: `docopt-renamed` says it was renamed or deprecated in favour of `docopt`; \
+         pass `--follow-renames` to add that crate instead."
+    ));
+    assert!(!get_toml(&manifest)["dependencies"]["docopt-renamed"].is_none());
+}
+
+#[test]
+fn add_follows_renamed_crate() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&[
+            "add",
+            "docopt-renamed@=0.6",
+            "--features",
+            "derive",
+            "--follow-renames",
+        ])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(String::from_utf8_lossy(&call.stdout).contains(
+        "Following the rename of docopt-renamed to docopt =docopt--CURRENT_VERSION_TEST"
+    ));
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"]["docopt-renamed"].is_none());
+    let docopt = &toml["dependencies"]["docopt"];
+    assert_eq!(
+        docopt["version"].as_str(),
+        Some("=docopt--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(docopt["features"][0].as_str(), Some("derive"));
+}
+
+#[test]
+fn add_prints_affected_binary_targets() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.bins");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "docopt",
+        "--vers",
+        "0.6.0",
+        "--optional",
+        &format!("--manifest-path={}", manifest),
+    ]).succeeds()
+        .prints_exactly(
+            "Adding docopt v0.6.0 to optional dependencies
+note: all binary targets (`server`, `cli`) can use the new dependencies
+note: `cli` is only built with `--features cli`
+note: `docopt` is optional, the binaries only get it with `--features docopt`",
+        )
+        .unwrap();
+}
+
+#[test]
+fn add_prints_only_json_when_quiet() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "docopt", "--vers=0.6.0", "--quiet", "--output=json"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&call.stdout),
+        "{\"added\":[{\"name\":\"docopt\",\"version\":\"0.6.0\",\"git\":null,\"path\":null,\
+         \"optional\":false,\"features\":null,\"section\":[\"dependencies\"]}],\
+         \"warnings\":[]}\n"
+    );
+    assert!(call.stderr.is_empty());
+}
+
+#[test]
+fn add_reports_warnings_in_json() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "local", "--path", "/path/to/pkg", "--quiet", "--output=json"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    let stdout = String::from_utf8_lossy(&call.stdout);
+    assert!(stdout.contains(
+        "\"warnings\":[{\"kind\":\"unpublishable\",\"message\":\"`local` is a path dependency \
+         without a version"
+    ));
+    // The warnings are also shown to humans, on stderr
+    assert!(String::from_utf8_lossy(&call.stderr).contains("WARN: `local` is a path dependency"));
+}
+
+#[test]
+fn add_prints_messages_to_stderr_with_json_output() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "docopt", "--vers=0.6.0", "--output=json"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(String::from_utf8_lossy(&call.stdout).starts_with("{\"added\":[{\"name\":\"docopt\""));
+    assert!(String::from_utf8_lossy(&call.stderr).contains("Adding docopt v0.6.0 to dependencies"));
+}
+
+#[test]
+fn add_records_provenance_in_json() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "docopt", "--vers=0.6.0", "--quiet", "--output=json", "--provenance"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    let stdout = String::from_utf8_lossy(&call.stdout);
+    assert!(stdout.contains(&format!(
+        "\"provenance\":{{\"version\":\"0.6.0\",\"checksum\":\"{}\",\
+         \"index_url\":\"https://index.crates.io/do/co/docopt\",\"etag\":\"\\\"test\\\"\"}}",
+        "0".repeat(64)
+    )));
+}
+
+#[test]
+fn warns_about_sys_crates_linking_to_the_same_library() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "libsqlite3-sys"], &manifest);
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "sqlite3-sys"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    let stdout = String::from_utf8_lossy(&call.stdout);
+    assert!(stdout.contains(
+        "note: `sqlite3-sys` links to the native library `sqlite3`, which has to be installed \
+         (with its development files) where the package is built"
+    ));
+    assert!(stdout.contains(
+        "WARN: `sqlite3-sys` links to the native library `sqlite3`, like `libsqlite3-sys` \
+         already does."
+    ));
+    assert!(!get_toml(&manifest)["dependencies"]["sqlite3-sys"].is_none());
+}
+
+#[test]
+fn adds_new_section_before_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.with_features");
+
+    execute_command(
+        &[
+            "add",
+            "regex",
+            "--dev",
+            "--vers=0.2",
+            "--section-placement=before-features",
+        ],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest).to_string();
+    let dev = toml.find("[dev-dependencies]").expect("section was not added");
+    assert!(toml.find("[dependencies]").unwrap() < dev);
+    assert!(dev < toml.find("[features]").unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn adds_local_source_through_symlink() {
+    use std::os::unix::fs::symlink;
+    use std::path::Path;
+
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    std::fs::create_dir_all(tmpdir.path().join("real/pkg")).unwrap();
+    symlink(tmpdir.path().join("real"), tmpdir.path().join("link")).unwrap();
+    let typed = tmpdir.path().join("link/pkg");
+    let typed = typed.to_str().unwrap();
+
+    // The path is written as typed ...
+    execute_command(&["add", "local", "--path", typed], &manifest);
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["local"]["path"].as_str(), Some(typed));
+
+    // ... unless it should be resolved.
+    execute_command(&["add", "local", "--path", typed, "--canonicalize"], &manifest);
+    let toml = get_toml(&manifest);
+    let path = toml["dependencies"]["local"]["path"].as_str().unwrap();
+    assert_eq!(Path::new(path), Path::new("real/pkg"));
+}
+
+#[test]
+fn adds_dependency_to_matching_workspace_members() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+
+    // A real version, as `cargo metadata` reads the manifests again in the second call.
+    execute_command(
+        &["add", "my-package", "--vers=0.1.0", "--members", "t*"],
+        &root_manifest,
+    );
+
+    // `two` and `three` match by name, `four` does not match
+    for (member, added) in workspace_manifests
+        .iter()
+        .zip(&[false, true, true, false])
+    {
+        let toml = get_toml(member);
+        assert_eq!(!toml["dependencies"]["my-package"].is_none(), *added, "{}", member);
+    }
+
+    execute_command(
+        &["add", "my-package", "--vers=0.1.0", "--members", "explicit/*"],
+        &root_manifest,
+    );
+    let toml = get_toml(&workspace_manifests[3]);
+    assert!(!toml["dependencies"]["my-package"].is_none());
+}
+
+#[test]
+fn adds_dependency_to_excluded_packages() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    let fuzz_manifest = add_excluded_package(&root_manifest);
+
+    // Excluded packages are only considered on request.
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "my-package",
+        "--members",
+        "fuzz*",
+        &format!("--manifest-path={}", root_manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: No workspace member matches `fuzz*`",
+        )
+        .unwrap();
+
+    execute_command(
+        &["add", "my-package", "--members", "*", "--include-excluded"],
+        &root_manifest,
+    );
+    assert!(!get_toml(&fuzz_manifest)["dependencies"]["my-package"].is_none());
+    for member in &workspace_manifests {
+        assert!(!get_toml(member)["dependencies"]["my-package"].is_none());
+    }
+}
+
+#[test]
+fn adds_workspace_member_as_path_dependency() {
+    let (_tmpdir, _, workspace_manifests) = copy_workspace_test();
+
+    // `four` is a member of the workspace, so it is not looked up on crates.io
+    execute_command(&["add", "four"], &workspace_manifests[1]);
+
+    let toml = get_toml(&workspace_manifests[1]);
+    let four = &toml["dependencies"]["four"];
+    assert_eq!(four["path"].as_str(), Some("../explicit/four"));
+    assert_eq!(four["version"].as_str(), Some("0.1.0"));
+}
+
+#[test]
+fn fails_to_add_dependency_without_matching_members() {
+    let (_tmpdir, root_manifest, _) = copy_workspace_test();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "my-package",
+        "--members",
+        "*-client",
+        &format!("--manifest-path={}", root_manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: No workspace member matches `*-client`",
+        )
+        .unwrap();
+}
+
+#[test]
+fn fails_on_virtual_manifest_before_looking_up_crates() {
+    let (_tmpdir, root_manifest, _) = copy_workspace_test();
+
+    // The crate does not exist, but the manifest is checked first.
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        BOGUS_CRATE_NAME,
+        &format!("--manifest-path={}", root_manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: Found virtual manifest, but this command \
+             requires running against an actual package in this workspace.",
+        )
+        .unwrap();
+}
+
+#[test]
+fn warns_about_path_dependency_without_version() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "local", "--path", "/path/to/pkg"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(String::from_utf8_lossy(&call.stdout).contains(
+        "WARN: `local` is a path dependency without a version, so this package can not be \
+         published."
+    ));
+}
+
+#[test]
+fn unpublished_package_takes_path_dependency_without_version() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.unpublished");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "local", "--path", "/path/to/pkg"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(!String::from_utf8_lossy(&call.stdout).contains("WARN"));
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["local"]["path"].as_str(),
+        Some("/path/to/pkg")
+    );
+}
+
+#[test]
+fn add_offline_takes_version_from_lock_file() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    std::fs::write(
+        tmpdir.path().join("Cargo.lock"),
+        r#"[[package]]
+name = "libc"
+version = "0.2.40"
+
+[[package]]
+name = "libc"
+version = "0.2.42"
+"#,
+    ).unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "libc",
+        "--offline",
+        &format!("--manifest-path={}", manifest),
+    ]).succeeds()
+        .prints("WARN: using version 0.2.42 of `libc` from Cargo.lock, not from the registry")
+        .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["libc"].as_str(), Some("0.2.42"));
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        BOGUS_CRATE_NAME,
+        "--offline",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error(format!(
+            "The crate `{}` is not in Cargo.lock, so its version can not be found offline.",
+            BOGUS_CRATE_NAME
+        ).as_str())
+        .unwrap();
+}
+
+#[test]
+fn add_takes_version_from_local_source() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let config_dir = tmpdir.path().join(".cargo");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(
+        config_dir.join("config.toml"),
+        r#"[source.crates-io]
+replace-with = "local"
+
+[source.local]
+local-registry = "registry"
+"#,
+    ).unwrap();
+    let registry = tmpdir.path().join("registry");
+    std::fs::create_dir_all(registry.join("index")).unwrap();
+    for archive in &["libc-0.2.40.crate", "libc-0.2.42.crate", "libc-0.3.0-alpha.1.crate"] {
+        std::fs::write(registry.join(archive), "").unwrap();
+    }
+
+    execute_command(&["add", "libc", "--offline"], &manifest);
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["libc"].as_str(), Some("0.2.42"));
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        BOGUS_CRATE_NAME,
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error(format!(
+            "The crate `{}` is not in the source `local`",
+            BOGUS_CRATE_NAME
+        ).as_str())
+        .unwrap();
+
+    // A directory source, e.g. from `cargo vendor`, has the manifests of the crates.
+    std::fs::write(
+        config_dir.join("config.toml"),
+        r#"[source.crates-io]
+replace-with = "vendored-sources"
+
+[source.vendored-sources]
+directory = "vendor"
+"#,
+    ).unwrap();
+    let vendored = tmpdir.path().join("vendor").join("docopt");
+    std::fs::create_dir_all(&vendored).unwrap();
+    std::fs::write(
+        vendored.join("Cargo.toml"),
+        "[package]\nname = \"docopt\"\nversion = \"0.8.3\"\n",
+    ).unwrap();
+
+    execute_command(&["add", "docopt", "--offline"], &manifest);
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["docopt"].as_str(), Some("0.8.3"));
+}
+
+#[test]
+fn add_crate_archive() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let archive = tmpdir.path().join("foo-bar-1.2.0.crate");
+    std::fs::write(&archive, "").unwrap();
+
+    execute_command(
+        &["add", "--path-to-crate", archive.to_str().unwrap(), "--dev"],
+        &manifest,
+    );
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dev-dependencies"]["foo-bar"].as_str(), Some("1.2.0"));
+
+    let misnamed = tmpdir.path().join("foo.tar.gz");
+    std::fs::write(&misnamed, "").unwrap();
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "--path-to-crate",
+        misnamed.to_str().unwrap(),
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error("is not named like a crate archive")
+        .unwrap();
+}
+
+#[test]
+fn add_locked_needs_a_matching_locked_version() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    std::fs::write(
+        tmpdir.path().join("Cargo.lock"),
+        r#"[[package]]
+name = "libc"
+version = "0.2.42"
+"#,
+    ).unwrap();
+
+    execute_command(&["add", "libc@0.2", "--locked"], &manifest);
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["libc"].as_str(), Some("0.2"));
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "libc@0.3",
+        "--locked",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error(
+            "`libc` 0.3 is not met by a version in Cargo.lock (Cargo.lock has 0.2.42), so adding \
+             it would change the locked versions.",
+        )
+        .unwrap();
+    assert_eq!(get_toml(&manifest)["dependencies"]["libc"].as_str(), Some("0.2"));
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "serde@1.0",
+        "--locked",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error("`serde` 1.0 is not met by a version in Cargo.lock (it is not in Cargo.lock)")
+        .unwrap();
+    assert!(get_toml(&manifest)["dependencies"]["serde"].is_none());
+}
+
+#[test]
+fn errors_in_german() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let error = |args: &[&str]| {
+        let output = process::Command::new("target/debug/cargo-add")
+            .args(args)
+            .arg(format!("--manifest-path={}", manifest))
+            .env("CARGO_IS_TEST", "1")
+            .env("CARGO_EDIT_LANG", "de")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    };
+
+    assert_eq!(
+        error(&["add", "serde", "--max-age", "14", "--offline"]).trim(),
+        "Der Befehl ist wegen eines unbehandelten Fehlers fehlgeschlagen: `--max-age` braucht \
+         die Veröffentlichungsdaten aus der Registry und kann daher nicht mit `--offline` \
+         kombiniert werden"
+    );
+    let reserved = "Ungültiger Crate-Name `nul`: der Name ist reserviert";
+    assert!(error(&["add", "nul"]).contains(reserved));
+}
+
+#[test]
+fn invalid_crate_name_fails_before_lookup() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "nul",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error("Invalid crate name `nul`: the name is reserved")
+        .unwrap();
+
+    assert!(get_toml(&manifest)["dependencies"].is_none());
+}
+
+#[test]
+fn max_age_can_not_be_combined_with_offline() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "libc",
+        "--max-age",
+        "14",
+        "--offline",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error("`--max-age` needs the publication dates from the registry")
+        .unwrap();
+}
+
+#[test]
+fn explain_prints_decisions_without_editing() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "my-package", "docopt@0.6", "--explain"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&call.stdout),
+        "  Explaining my-package (prereleases: none, yanked: skipped, max-age: none)
+             1.0.0-rc.1  skipped: prerelease
+             0.9.1       skipped: yanked
+             0.9.0       picked
+             and 1 older version(s)
+  Explaining docopt: not picked from the registry (the version, a git repository or a path was \
+         given, or it is a member of the workspace)
+"
+    );
+    assert!(get_toml(&manifest)["dependencies"].is_none());
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "my-package", "--explain", "--prerelease=rc", "--output=json"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    let stdout = String::from_utf8_lossy(&call.stdout);
+    assert!(stdout.starts_with(
+        "{\"rust_version\":null,\"crates\":[{\"name\":\"my-package\",\"looked_up\":true,\
+         \"prereleases\":\"rc\",\"picked\":\"1.0.0-rc.1\",\"versions\":[{\"version\":\
+         \"1.0.0-rc.1\",\"verdict\":\"picked\""
+    ));
+    assert!(get_toml(&manifest)["dependencies"].is_none());
+}
+
+#[test]
+fn adds_dependencies_below_group_comment() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &["add", "criterion", "bencher", "--dev", "--group", "benches"],
+        &manifest,
+    );
+    execute_command(&["add", "proptest", "--dev", "--group", "tests"], &manifest);
+    execute_command(&["add", "test_bench", "--dev", "--group", "benches"], &manifest);
+
+    let contents = std::fs::read_to_string(&manifest).unwrap();
+    assert!(contents.contains(
+        r#"[dev-dependencies]
+# benches
+criterion = "criterion--CURRENT_VERSION_TEST"
+bencher = "bencher--CURRENT_VERSION_TEST"
+test_bench = "test_bench--CURRENT_VERSION_TEST"
+
+# tests
+proptest = "proptest--CURRENT_VERSION_TEST"
+"#
+    ));
+}
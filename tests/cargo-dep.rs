@@ -0,0 +1,580 @@
+extern crate assert_cli;
+
+mod utils;
+use utils::{add_excluded_package, clone_out_test, copy_workspace_test, execute_command, get_toml};
+
+#[test]
+fn dedupe_removes_redundant_entries() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.dedupe");
+
+    execute_command(&["dep", "dedupe"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert!(toml["dev-dependencies"]["docopt"].is_none());
+    assert!(toml["dev-dependencies"]["serde"].is_none());
+    assert!(toml["target"].is_none());
+    assert_eq!(toml["dependencies"]["libc"].as_str(), Some("0.2"));
+
+    // optional dependencies are not available to tests
+    assert_eq!(toml["dev-dependencies"]["pad"].as_str(), Some("0.1"));
+    assert_eq!(toml["dev-dependencies"]["regex"].as_str(), Some("0.2"));
+}
+
+#[test]
+fn dedupe_prints_changes() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.dedupe");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "dedupe",
+        &format!("--manifest-path={}", manifest),
+    ]).succeeds()
+        .prints_exactly(
+            "Removing docopt from dev-dependencies (same as in dependencies)
+    Removing serde from dev-dependencies (same as in dependencies)
+    Removing docopt from dependencies for target `cfg(unix)` (same as in dependencies)
+     Merging libc for targets `cfg(unix)` and `cfg(not(unix))` into dependencies",
+        )
+        .unwrap();
+}
+
+#[test]
+fn dedupe_dry_run() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.dedupe");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "dedupe",
+        "--dry-run",
+        &format!("--manifest-path={}", manifest),
+    ]).succeeds()
+        .unwrap();
+
+    // nothing has been changed
+    let toml = get_toml(&manifest);
+    assert!(!toml["dev-dependencies"]["docopt"].is_none());
+    assert!(!toml["target"].is_none());
+}
+
+#[test]
+fn hoist_workspace_dependencies() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+
+    execute_command(&["dep", "hoist"], &root_manifest);
+
+    assert_eq!(
+        get_toml(&root_manifest)["workspace"]["dependencies"]["libc"].as_str(),
+        Some("0.2.28")
+    );
+    for workspace_member in &workspace_manifests {
+        let toml = get_toml(workspace_member);
+        assert_eq!(toml["dependencies"]["libc"]["workspace"].as_bool(), Some(true));
+    }
+    // Path dependencies are relative to the member, and stay where they are.
+    let one = get_toml(&workspace_manifests[0]);
+    assert_eq!(
+        one["dependencies"]["three"]["path"].as_str(),
+        Some("../implicit/three")
+    );
+}
+
+#[test]
+fn verify_workspace_fails_and_fixes() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    execute_command(&["add", "libc@0.2.30"], &workspace_manifests[1]);
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "verify-workspace",
+        &format!("--manifest-path={}", root_manifest),
+    ]).fails_with(1)
+        .prints("Mismatch libc: different versions")
+        .prints_error(
+            "1 dependencies are declared in different ways by the members of the workspace",
+        )
+        .unwrap();
+
+    execute_command(&["dep", "verify-workspace", "--fix"], &root_manifest);
+
+    for workspace_member in &workspace_manifests {
+        assert_eq!(
+            get_toml(workspace_member)["dependencies"]["libc"].as_str(),
+            Some("0.2.30")
+        );
+    }
+    execute_command(&["dep", "verify-workspace"], &root_manifest);
+}
+
+#[test]
+fn hoist_min_members() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+
+    execute_command(&["dep", "hoist", "--min-members=5"], &root_manifest);
+
+    assert!(get_toml(&root_manifest)["workspace"]["dependencies"].is_none());
+    assert_eq!(
+        get_toml(&workspace_manifests[0])["dependencies"]["libc"].as_str(),
+        Some("0.2.28")
+    );
+}
+
+#[test]
+fn unhoist_inherited_dependencies() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    execute_command(&["dep", "hoist"], &root_manifest);
+
+    execute_command(&["dep", "unhoist", "libc"], &workspace_manifests[3]);
+
+    assert_eq!(
+        get_toml(&workspace_manifests[3])["dependencies"]["libc"].as_str(),
+        Some("0.2.28")
+    );
+    // The other members still inherit the dependency.
+    assert_eq!(
+        get_toml(&workspace_manifests[0])["dependencies"]["libc"]["workspace"].as_bool(),
+        Some(true)
+    );
+}
+
+#[test]
+fn unhoist_fails_for_dependencies_not_inherited() {
+    let (_tmpdir, _root_manifest, workspace_manifests) = copy_workspace_test();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "unhoist",
+        "libc",
+        &format!("--manifest-path={}", workspace_manifests[0]),
+    ]).fails_with(1)
+        .prints_error(
+            "The dependency `libc` is not inherited from the workspace (with `workspace = true`)",
+        )
+        .unwrap();
+}
+
+#[test]
+fn patch_add_and_rm() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.dedupe");
+
+    execute_command(
+        &[
+            "dep",
+            "patch",
+            "add",
+            "serde",
+            "--git=https://github.com/serde-rs/serde",
+            "--branch=master",
+        ],
+        &manifest,
+    );
+    {
+        let toml = get_toml(&manifest);
+        let patch = &toml["patch"]["crates-io"]["serde"];
+        assert_eq!(patch["git"].as_str(), Some("https://github.com/serde-rs/serde"));
+        assert_eq!(patch["branch"].as_str(), Some("master"));
+    }
+
+    execute_command(&["dep", "patch", "rm", "serde"], &manifest);
+    assert!(get_toml(&manifest)["patch"].is_none());
+}
+
+#[test]
+fn patch_workspace_root() {
+    let (_tmpdir, root_manifest, _workspace_manifests) = copy_workspace_test();
+
+    execute_command(
+        &["dep", "patch", "add", "libc", "--path=../libc", "--registry=my-registry"],
+        &root_manifest,
+    );
+
+    assert_eq!(
+        get_toml(&root_manifest)["patch"]["my-registry"]["libc"]["path"].as_str(),
+        Some("../libc")
+    );
+}
+
+#[test]
+fn migrate_replace_to_patch() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.replace");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "migrate-replace",
+        &format!("--manifest-path={}", manifest),
+    ]).succeeds()
+        .prints_exactly(
+            "Migrating `docopt:0.8.3` to patch.crates-io
+    Skipping `libc:0.2.40`: patches need a `git` or `path` source",
+        )
+        .unwrap();
+
+    let toml = get_toml(&manifest);
+    let patch = &toml["patch"]["crates-io"]["docopt"];
+    assert_eq!(patch["rev"].as_str(), Some("d2ab1a1"));
+    assert!(toml["replace"]["docopt:0.8.3"].is_none());
+    assert_eq!(toml["replace"]["libc:0.2.40"].as_str(), Some("0.2.41"));
+}
+
+#[test]
+fn publish_check_lists_blocking_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.publish");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "publish-check",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_exactly(
+            "Blocking libc in dependencies: wildcard version requirement, which crates.io does \
+             not accept
+    Blocking local in dependencies: path dependency without a version
+    Blocking fork in dependencies: git dependency without a version",
+        )
+        .unwrap();
+}
+
+#[test]
+fn publish_check_skips_unpublished_packages() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.unpublished");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "publish-check",
+        &format!("--manifest-path={}", manifest),
+    ]).succeeds()
+        .unwrap();
+}
+
+#[test]
+fn fix_wildcards_pins_requirements() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.wildcards");
+
+    execute_command(&["dep", "fix-wildcards", "--exclude=log"], &manifest);
+
+    let toml = get_toml(&manifest);
+    let deps = &toml["dependencies"];
+    assert_eq!(deps["docopt"].as_str(), Some("docopt--CURRENT_VERSION_TEST"));
+    assert_eq!(
+        deps["libc"]["version"].as_str(),
+        Some("libc--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(deps["libc"]["optional"].as_bool(), Some(true));
+    assert_eq!(deps["log"].as_str(), Some("0.4.*"));
+    assert_eq!(deps["regex"].as_str(), Some("0.2"));
+}
+
+#[test]
+fn fix_wildcards_dry_run() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.wildcards");
+
+    execute_command(&["dep", "fix-wildcards", "--dry-run"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["docopt"].as_str(), Some("*"));
+    assert_eq!(toml["dependencies"]["log"].as_str(), Some("0.4.*"));
+}
+
+#[test]
+fn features_report_lists_default_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.publish");
+
+    let call = std::process::Command::new("target/debug/cargo-dep")
+        .args(&["dep", "features-report"])
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    // Every crate offers the same features in tests, `std` is the default one.
+    let stdout = String::from_utf8_lossy(&call.stdout);
+    assert!(stdout.contains("Defaults libc * in dependencies: default features std"));
+    assert!(!stdout.contains("Suggestion"));
+}
+
+#[test]
+fn licenses_are_listed_as_spdx_document() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.publish");
+
+    let call = std::process::Command::new("target/debug/cargo-dep")
+        .args(&["dep", "licenses", "--output=json"])
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    // Every crate on crates.io has the same license in tests; the path dependencies do not exist.
+    // The dev-dependencies are listed first, like `Manifest::get_sections` does.
+    let unknown = |name: &str, version: &str| {
+        format!(
+            r#"{{"SPDXID":"SPDXRef-Package-{0}","name":"{0}","versionInfo":"{1}","downloadLocation":"NOASSERTION","licenseDeclared":"NOASSERTION","comment":"unknown license"}}"#,
+            name, version
+        )
+    };
+    let expected = format!(
+        r#"{{"spdxVersion":"SPDX-2.3","dataLicense":"CC0-1.0","SPDXID":"SPDXRef-DOCUMENT","name":"cargo-list-test-fixture","packages":[{},{{"SPDXID":"SPDXRef-Package-docopt","name":"docopt","versionInfo":"0.8","downloadLocation":"https://crates.io/crates/docopt","licenseDeclared":"MIT OR Apache-2.0"}},{{"SPDXID":"SPDXRef-Package-libc","name":"libc","versionInfo":"*","downloadLocation":"https://crates.io/crates/libc","licenseDeclared":"MIT OR Apache-2.0"}},{},{},{}]}}"#,
+        unknown("helper", "NOASSERTION"),
+        unknown("local", "NOASSERTION"),
+        unknown("fork", "NOASSERTION"),
+        unknown("published", "0.1")
+    );
+    assert_eq!(String::from_utf8_lossy(&call.stdout).trim(), expected);
+}
+
+#[test]
+fn sbom_lists_locked_packages() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.wildcards");
+    std::fs::write(
+        tmpdir.path().join("Cargo.lock"),
+        r#"[[package]]
+name = "cargo-list-test-fixture"
+version = "0.0.0"
+dependencies = [
+ "docopt",
+ "libc",
+]
+
+[[package]]
+name = "docopt"
+version = "0.8.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "d8acd393692c503b168471874953a2531df0e9ab77d0b6bbc582395743300e4a"
+dependencies = [
+ "libc",
+]
+
+[[package]]
+name = "libc"
+version = "0.2.42"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+    ).unwrap();
+
+    let call = std::process::Command::new("target/debug/cargo-dep")
+        .args(&["dep", "sbom", "--format", "cyclonedx"])
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    let stdout = String::from_utf8_lossy(&call.stdout);
+    assert!(stdout.starts_with(r#"{"bomFormat":"CycloneDX","specVersion":"1.5","version":1,"#));
+    assert!(stdout.contains(
+        r#"{"type":"library","bom-ref":"libc@0.2.42","name":"libc","version":"0.2.42","scope":"optional","purl":"pkg:cargo/libc@0.2.42"}"#
+    ));
+    assert!(stdout.contains(
+        r#"{"ref":"cargo-list-test-fixture@0.0.0","dependsOn":["docopt@0.8.3","libc@0.2.42"]}"#
+    ));
+}
+
+#[test]
+fn sbom_needs_lock_file() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.wildcards");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "sbom",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error("create one with `cargo generate-lockfile`")
+        .unwrap();
+}
+
+#[test]
+fn fmt_formats_only_dependency_sections() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/dep/Cargo.toml.fmt");
+
+    execute_command(&["dep", "fmt", "--sort", "--align"], &manifest);
+
+    let contents = std::fs::read_to_string(&manifest).unwrap();
+    assert!(contents.starts_with("[package]\nname = 'cargo-list-test-fixture'\n"));
+    assert!(contents.contains(
+        r#"[dependencies]
+docopt = "0.8" # command line
+libc   = "0.2"
+serde  = { version = "1.0", features = ["derive"] }
+"#
+    ));
+    assert!(contents.contains(r#"nix = { version = "0.11", optional = false }"#));
+}
+
+#[test]
+fn apply_changes_to_several_manifests() {
+    let (tmpdir, _root_manifest, workspace_manifests) = copy_workspace_test();
+    let changes = tmpdir.path().join("changes.toml");
+    std::fs::write(
+        &changes,
+        r#"manifests = ["one", "two/Cargo.toml"]
+
+[[change]]
+op = "add"
+crate = "serde"
+version = "1.0"
+section = "dev-dependencies"
+
+[[change]]
+op = "upgrade"
+crate = "libc"
+version = "0.2.30"
+
+[[change]]
+op = "rm"
+crate = "three"
+"#,
+    ).unwrap();
+
+    let call = std::process::Command::new("target/debug/cargo-dep")
+        .args(&["dep", "apply", changes.to_str().unwrap()])
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    // Each step names the manifest it changes.
+    let stdout = String::from_utf8_lossy(&call.stdout);
+    assert!(stdout.contains("one/Cargo.toml: libc v0.2.28 -> v0.2.30 in dependencies"));
+    assert!(stdout.contains("two/Cargo.toml: three: not in dependencies"));
+
+    for workspace_member in &workspace_manifests[..2] {
+        let toml = get_toml(workspace_member);
+        assert_eq!(toml["dev-dependencies"]["serde"].as_str(), Some("1.0"));
+        assert_eq!(toml["dependencies"]["libc"].as_str(), Some("0.2.30"));
+        assert!(toml["dependencies"]["three"].is_none());
+    }
+}
+
+#[test]
+fn apply_changes_all_or_nothing() {
+    let (tmpdir, _root_manifest, workspace_manifests) = copy_workspace_test();
+    let changes = tmpdir.path().join("changes.toml");
+    std::fs::write(
+        &changes,
+        r#"manifests = ["one", "missing/Cargo.toml"]
+
+[[change]]
+op = "rm"
+crate = "libc"
+"#,
+    ).unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "apply",
+        changes.to_str().unwrap(),
+    ]).fails_with(1)
+        .unwrap();
+
+    assert_eq!(
+        get_toml(&workspace_manifests[0])["dependencies"]["libc"].as_str(),
+        Some("0.2.28")
+    );
+}
+
+#[test]
+fn apply_recorded_changes() {
+    let (tmpdir, _root_manifest, workspace_manifests) = copy_workspace_test();
+    let changes = tmpdir.path().join("changes.toml");
+    let record = format!("--record={}", changes.to_str().unwrap());
+
+    execute_command(&["add", "serde@1.0", "--dev", &record], &workspace_manifests[0]);
+    execute_command(&["rm", "libc", &record], &workspace_manifests[0]);
+    assert_eq!(
+        std::fs::read_to_string(&changes).unwrap(),
+        r#"manifests = ["one/Cargo.toml"]
+
+[[change]]
+op = "add"
+crate = "serde"
+section = "dev-dependencies"
+version = "1.0"
+
+[[change]]
+op = "rm"
+crate = "libc"
+"#
+    );
+
+    // Replay the changes on another manifest.
+    std::fs::write(
+        &changes,
+        std::fs::read_to_string(&changes)
+            .unwrap()
+            .replace("one/Cargo.toml", "two/Cargo.toml"),
+    ).unwrap();
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "apply",
+        changes.to_str().unwrap(),
+    ]).unwrap();
+
+    let two = get_toml(&workspace_manifests[1]);
+    assert_eq!(two["dev-dependencies"]["serde"].as_str(), Some("1.0"));
+    assert!(two["dependencies"]["libc"].is_none());
+}
+
+#[test]
+fn graph_draws_workspace_members() {
+    let (_tmpdir, root_manifest, _) = copy_workspace_test();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "graph",
+        &format!("--manifest-path={}", root_manifest),
+    ]).prints("\"three\" [shape=box];")
+        .prints("\"libc\";")
+        .prints("\"one\" -> \"three\" [label=\"path\"];")
+        .prints("\"two\" -> \"libc\" [label=\"0.2.28\"];")
+        .unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "graph",
+        "--mermaid",
+        &format!("--manifest-path={}", root_manifest),
+    ]).prints("flowchart LR")
+        .prints("(\"libc\")")
+        .prints("-->|\"0.2.28\"|")
+        .unwrap();
+}
+
+#[test]
+fn bot_config_covers_workspace_packages() {
+    let (_tmpdir, root_manifest, _) = copy_workspace_test();
+    add_excluded_package(&root_manifest);
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "bot-config",
+        "--dependabot",
+        &format!("--manifest-path={}", root_manifest),
+    ]).prints("directory: \"/\"")
+        .prints("directory: \"/one\"")
+        .prints("directory: \"/fuzz\"")
+        .unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-dep",
+        "dep",
+        "bot-config",
+        "--renovate",
+        &format!("--manifest-path={}", root_manifest),
+    ]).prints("\"enabledManagers\"")
+        .prints("\"Cargo.toml\"")
+        .prints("\"implicit/three/Cargo.toml\"")
+        .prints("\"fuzz/Cargo.toml\"")
+        .unwrap();
+}
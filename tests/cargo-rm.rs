@@ -1,7 +1,9 @@
 extern crate assert_cli;
 
+use std::process;
+
 mod utils;
-use utils::{clone_out_test, execute_command, get_toml};
+use utils::{clone_out_test, copy_workspace_test, execute_command, get_toml};
 
 #[test]
 fn remove_existing_dependency() {
@@ -47,6 +49,60 @@ fn remove_section_after_removed_last_dependency() {
     assert!(toml["dev-dependencies"].is_none());
 }
 
+#[test]
+fn remove_pin_reason_with_last_entry() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    execute_command(&["add", "foo@1.0", "--reason", "2.0 is broken"], &manifest);
+    execute_command(&["add", "foo@1.0", "--dev"], &manifest);
+
+    execute_command(&["rm", "foo"], &manifest);
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["package"]["metadata"]["cargo-edit"]["pins"]["foo"].as_str(),
+        Some("2.0 is broken")
+    );
+
+    execute_command(&["rm", "--dev", "foo"], &manifest);
+    let toml = get_toml(&manifest);
+    assert!(toml["package"]["metadata"]["cargo-edit"]["pins"].is_none());
+}
+
+#[test]
+fn remove_all_dependencies_of_target() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    execute_command(&["add", "foo@1.0", "--target", "cfg(windows)"], &manifest);
+    execute_command(&["add", "bar@1.0.7", "--target", "cfg(windows)", "--dev"], &manifest);
+    execute_command(&["add", "baz@0.3", "--target", "cfg(unix)"], &manifest);
+
+    execute_command(&["rm", "--target", "cfg(windows)", "--all-in-target"], &manifest);
+    let toml = get_toml(&manifest);
+    assert!(toml["target"]["cfg(windows)"].is_none());
+    assert!(!toml["target"]["cfg(unix)"]["dependencies"]["baz"].is_none());
+    assert!(!toml["dependencies"]["docopt"].is_none());
+
+    execute_command(&["rm", "--target", "cfg(unix)", "baz"], &manifest);
+    let toml = get_toml(&manifest);
+    assert!(toml["target"].is_none());
+}
+
+#[test]
+fn remove_all_dependencies_of_unknown_target() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-rm",
+        "rm",
+        "--target=cfg(windows)",
+        "--all-in-target",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: There are no dependencies for the target \
+             `cfg(windows)` to remove.",
+        )
+        .unwrap();
+}
+
 // https://github.com/killercup/cargo-edit/issues/32
 #[test]
 fn issue_32() {
@@ -125,18 +181,24 @@ fn invalid_dependency_in_section() {
 }
 
 #[test]
-fn no_argument() {
-    assert_cli::Assert::command(&["target/debug/cargo-rm", "rm"])
-        .fails_with(1)
-        .prints_error_exactly(
-            r"Invalid arguments.
+fn no_argument_without_terminal() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
 
-Usage:
-    cargo rm <crate> [--dev|--build] [options]
-    cargo rm (-h|--help)
-    cargo rm --version",
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-rm",
+        "rm",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: No crate specified. Pass the name of the crate \
+             to remove, or run `cargo rm` in an interactive terminal to pick from the current \
+             dependencies.",
         )
         .unwrap();
+
+    // nothing has been removed
+    let toml = get_toml(&manifest);
+    assert!(!toml["dependencies"]["docopt"].is_none());
 }
 
 #[test]
@@ -147,13 +209,23 @@ fn unknown_flags() {
             r"Unknown flag: '--flag'
 
 Usage:
-    cargo rm <crate> [--dev|--build] [options]
+    cargo rm [<crate>] [--dev|--build] [--target=<target>] [options]
+    cargo rm --target=<target> --all-in-target [options]
     cargo rm (-h|--help)
-    cargo rm --version",
+    cargo rm --version [--json]",
         )
         .unwrap();
 }
 
+#[test]
+fn version_as_json() {
+    assert_cli::Assert::command(&["target/debug/cargo-rm", "rm", "--version", "--json"])
+        .succeeds()
+        .prints(r#""name": "cargo-rm""#)
+        .prints(r#""rm --output json": 1"#)
+        .unwrap();
+}
+
 #[test]
 fn rm_prints_message() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
@@ -164,6 +236,166 @@ fn rm_prints_message() {
         "semver",
         &format!("--manifest-path={}", manifest),
     ]).succeeds()
-        .prints_exactly("Removing semver from dependencies")
+        .prints_exactly("Removing semver = \"0.1\" from dependencies")
+        .unwrap();
+}
+
+#[test]
+fn rm_prints_removed_entry_as_json() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-rm")
+        .args(&["rm", "semver", "--quiet", "--output=json"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .output()
         .unwrap();
+
+    assert!(call.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&call.stdout),
+        "{\"removed\":[{\"name\":\"semver\",\"section\":[\"dependencies\"],\
+         \"spec\":\"semver = \\\"0.1\\\"\",\"version\":\"0.1\",\"git\":null,\
+         \"path\":null,\"optional\":false,\"features\":null}]}\n"
+    );
+    assert!(call.stderr.is_empty());
+}
+
+#[test]
+fn rm_reports_merge_conflicts() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.conflict");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-rm",
+        "rm",
+        "docopt",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: Cargo.toml contains unresolved merge \
+             conflicts (lines 7-13). Resolve them, or pass `--ours` or `--theirs` to pick a side \
+             for conflicting dependencies.",
+        )
+        .unwrap();
+}
+
+#[test]
+fn rm_resolves_merge_conflicts() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.conflict");
+
+    execute_command(&["rm", "docopt", "--theirs"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"]["docopt"].is_none());
+    assert_eq!(toml["dependencies"]["semver"].as_str(), Some("0.9"));
+    assert_eq!(toml["dependencies"]["pad"].as_str(), Some("0.1"));
+    assert_eq!(toml["dependencies"]["regex"].as_str(), Some("0.2"));
+}
+
+#[test]
+fn rm_notes_other_users_of_path_dependency() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    let (one, four) = (&workspace_manifests[0], &workspace_manifests[3]);
+    execute_command(&["add", "two"], one);
+    execute_command(&["add", "two"], four);
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-rm",
+        "rm",
+        "two",
+        &format!("--manifest-path={}", one),
+    ]).prints_exactly(
+        "Removing two = { version = \"0.1.0\", path = \"../two\" } from dependencies
+note: `two` is still a path dependency of four",
+    )
+        .unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-rm",
+        "rm",
+        "two",
+        &format!("--manifest-path={}", four),
+    ]).prints_exactly(
+        "Removing two = { version = \"0.1.0\", path = \"../../two\" } from dependencies
+note: no other workspace member depends on `two` any more; pass `--gc-workspace` to also remove \
+         `two` from the workspace members",
+    )
+        .unwrap();
+
+    assert!(
+        get_toml(&root_manifest)["workspace"]["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|m| m.as_str() == Some("two"))
+    );
+}
+
+#[test]
+fn rm_gc_workspace_removes_unused_member() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    let one = &workspace_manifests[0];
+    execute_command(&["add", "two"], one);
+
+    execute_command(&["rm", "two", "--gc-workspace"], one);
+
+    let members: Vec<_> = get_toml(&root_manifest)["workspace"]["members"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|m| m.as_str().map(String::from))
+        .collect();
+    assert_eq!(members, vec!["one", "explicit/*"]);
+}
+
+#[test]
+fn rm_dry_run_leaves_manifest_alone() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    let original = std::fs::read_to_string(&manifest).unwrap();
+
+    execute_command(&["rm", "docopt", "--dry-run"], &manifest);
+
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), original);
+}
+
+#[test]
+fn rm_from_upstream_package_flag() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+
+    // The spelling of upstream cargo-edit picks the member to remove from.
+    execute_command(&["rm", "--package", "one", "libc"], &root_manifest);
+
+    assert!(get_toml(&workspace_manifests[0])["dependencies"]["libc"].is_none());
+    assert!(!get_toml(&workspace_manifests[1])["dependencies"]["libc"].is_none());
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-rm",
+        "rm",
+        "-p",
+        "nobody",
+        "libc",
+        &format!("--manifest-path={}", root_manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: No member of the workspace is named `nobody`",
+        )
+        .unwrap();
+}
+
+#[test]
+fn rm_notes_ignored_upstream_flags() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-rm",
+        "rm",
+        "docopt",
+        "--pinned",
+        "--quiet",
+        &format!("--manifest-path={}", manifest),
+    ]).prints_error_exactly(
+        "note: ignoring `--pinned`: only `cargo upgrade` upgrades pinned requirements",
+    )
+        .unwrap();
+
+    assert!(get_toml(&manifest)["dependencies"]["docopt"].is_none());
 }
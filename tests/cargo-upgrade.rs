@@ -1,60 +1,14 @@
 extern crate assert_cli;
 #[macro_use]
 extern crate pretty_assertions;
-extern crate tempdir;
 extern crate toml_edit;
 
-use std::fs;
+use std::io::Write;
+use std::process;
 
 mod utils;
-use utils::{clone_out_test, execute_command, get_toml};
-
-/// Helper function that copies the workspace test into a temporary directory.
-pub fn copy_workspace_test() -> (tempdir::TempDir, String, Vec<String>) {
-    // Create a temporary directory and copy in the root manifest, the dummy rust file, and
-    // workspace member manifests.
-    let tmpdir = tempdir::TempDir::new("upgrade_workspace")
-        .expect("failed to construct temporary directory");
-
-    let (root_manifest_path, workspace_manifest_paths) = {
-        // Helper to copy in files to the temporary workspace. The standard library doesn't have a
-        // good equivalent of `cp -r`, hence this oddity.
-        let copy_in = |dir, file| {
-            let file_path = tmpdir
-                .path()
-                .join(dir)
-                .join(file)
-                .to_str()
-                .unwrap()
-                .to_string();
-
-            fs::create_dir_all(tmpdir.path().join(dir)).unwrap();
-
-            fs::copy(
-                format!("tests/fixtures/workspace/{}/{}", dir, file),
-                &file_path,
-            ).unwrap_or_else(|err| panic!("could not copy test file: {}", err));
-
-            file_path
-        };
-
-        let root_manifest_path = copy_in(".", "Cargo.toml");
-        copy_in(".", "dummy.rs");
-
-        let workspace_manifest_paths = ["one", "two", "implicit/three", "explicit/four"]
-            .iter()
-            .map(|member| copy_in(member, "Cargo.toml"))
-            .collect::<Vec<_>>();
-
-        (root_manifest_path, workspace_manifest_paths)
-    };
-
-    (
-        tmpdir,
-        root_manifest_path,
-        workspace_manifest_paths.to_owned(),
-    )
-}
+use utils::{add_excluded_package, clone_out_test, commit_git_repository, copy_workspace_test,
+            execute_command, get_toml};
 
 // Verify that an upgraded Cargo.toml matches what we expect.
 #[test]
@@ -103,6 +57,41 @@ fn upgrade_all_allow_prerelease() {
     );
 }
 
+#[test]
+fn upgrade_all_compatible() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    // Setup manifest with `docopt`
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+
+    // Now, upgrade `docopt` to the latest version `0.8` allows
+    execute_command(&["upgrade", "--compatible"], &manifest);
+
+    // Verify that `docopt` has been updated successfully.
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["docopt"].as_str(),
+        Some("docopt--COMPATIBLE_VERSION_TEST")
+    );
+}
+
+#[test]
+fn compatible_excludes_consolidate() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--compatible",
+        "--consolidate",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--compatible` can not be combined with \
+             `--consolidate`",
+        )
+        .unwrap();
+}
+
 #[test]
 fn upgrade_all_dry_run() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -208,6 +197,31 @@ fn upgrade_at() {
     );
 }
 
+#[test]
+fn upgrade_save_exact() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    // Setup manifest
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+    execute_command(&["add", "serde", "--vers", "0.9"], &manifest);
+    execute_command(&["add", "pad", "--vers", "0.1"], &manifest);
+
+    // Upgrade to a specified version, to a specified range, and to the latest (last, as
+    // `cargo metadata` can not read the test version).
+    execute_command(&["upgrade", "--save-exact", "serde@1.0.2"], &manifest);
+    execute_command(&["upgrade", "--save-exact", "pad@>=0.1, <0.3"], &manifest);
+    execute_command(&["upgrade", "--save-exact", "docopt"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["docopt"].as_str(),
+        Some("=docopt--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(toml["dependencies"]["serde"].as_str(), Some("=1.0.2"));
+    // Requirements that are not plain versions are not pinned.
+    assert_eq!(toml["dependencies"]["pad"].as_str(), Some(">=0.1, <0.3"));
+}
+
 #[test]
 fn upgrade_workspace() {
     let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
@@ -223,6 +237,84 @@ fn upgrade_workspace() {
     }
 }
 
+#[test]
+fn upgrade_workspace_with_excluded_packages() {
+    let (_tmpdir, root_manifest, _workspace_manifests) = copy_workspace_test();
+    let fuzz_manifest = add_excluded_package(&root_manifest);
+
+    // A real version, as `cargo metadata` reads the members again in the second call.
+    execute_command(&["upgrade", "libc@0.2.30", "--all"], &root_manifest);
+    assert_eq!(
+        get_toml(&fuzz_manifest)["dependencies"]["libc"].as_str(),
+        Some("0.2.28")
+    );
+
+    execute_command(
+        &["upgrade", "libc@0.2.30", "--all", "--include-excluded"],
+        &root_manifest,
+    );
+    assert_eq!(
+        get_toml(&fuzz_manifest)["dependencies"]["libc"].as_str(),
+        Some("0.2.30")
+    );
+}
+
+#[test]
+fn upgrade_workspace_with_nested_packages() {
+    let (tmpdir, root_manifest, _workspace_manifests) = copy_workspace_test();
+    let fuzz_manifest = add_excluded_package(&root_manifest);
+    // A crate of a test fixture, which is neither a member nor excluded (and so has to be a
+    // workspace of its own for cargo)
+    let fixture_dir = tmpdir.path().join("one/tests/fixture");
+    std::fs::create_dir_all(&fixture_dir).unwrap();
+    let fixture_manifest = fixture_dir.join("Cargo.toml");
+    std::fs::write(
+        &fixture_manifest,
+        r#"[package]
+name = "fixture"
+version = "0.1.0"
+
+[lib]
+path = "../../../dummy.rs"
+
+[dependencies]
+libc = "0.2.28"
+
+[workspace]
+"#,
+    ).unwrap();
+    let fixture_manifest = fixture_manifest.to_str().unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--all",
+        &format!("--manifest-path={}", root_manifest),
+    ]).prints(
+        "note: skipping `fuzz-targets` at fuzz, which the workspace excludes; pass \
+         `--include-excluded` to include it",
+    )
+        .prints(
+            "note: skipping `fixture` at one/tests/fixture, which is not a member of the \
+             workspace; pass `--include-nested` to include it",
+        )
+        .unwrap();
+    assert_eq!(
+        get_toml(fixture_manifest)["dependencies"]["libc"].as_str(),
+        Some("0.2.28")
+    );
+
+    execute_command(&["upgrade", "--all", "--include-nested"], &root_manifest);
+    assert_eq!(
+        get_toml(fixture_manifest)["dependencies"]["libc"].as_str(),
+        Some("libc--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(
+        get_toml(&fuzz_manifest)["dependencies"]["libc"].as_str(),
+        Some("0.2.28")
+    );
+}
+
 /// Detect if attempting to run against a workspace root and give a helpful warning.
 #[test]
 fn detect_workspace() {
@@ -290,7 +382,7 @@ fn unknown_flags() {
 Usage:
     cargo upgrade [options] [<dependency>]...
     cargo upgrade (-h | --help)
-    cargo upgrade (-V | --version)",
+    cargo upgrade (-V | --version) [--json]",
         )
         .unwrap();
 }
@@ -308,3 +400,316 @@ fn upgrade_prints_messages() {
         .prints("docopt v0.8 -> v")
         .unwrap();
 }
+
+#[test]
+fn upgrade_skips_pinned_crates() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(
+        &["add", "docopt", "--vers", "0.8", "--reason", "0.9 drops rustc-serialize"],
+        &manifest,
+    );
+    assert_eq!(
+        get_toml(&manifest)["package"]["metadata"]["cargo-edit"]["pins"]["docopt"].as_str(),
+        Some("0.9 drops rustc-serialize")
+    );
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        &format!("--manifest-path={}", manifest),
+    ]).succeeds()
+        .prints("docopt, pinned in cargo-list-test-fixture: 0.9 drops rustc-serialize")
+        .unwrap();
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["docopt"].as_str(),
+        Some("0.8")
+    );
+
+    // Named crates are upgraded anyway.
+    let call = process::Command::new("target/debug/cargo-upgrade")
+        .args(&["upgrade", "docopt"])
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(
+        String::from_utf8_lossy(&call.stdout)
+            .contains("docopt in cargo-list-test-fixture: 0.9 drops rustc-serialize")
+    );
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["docopt"].as_str(),
+        Some("docopt--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn upgrade_holds_back_ignored_crates() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+    let mut contents = std::fs::read_to_string(&manifest).unwrap();
+    contents.push_str("\n[package.metadata.cargo-edit]\nignore-upgrades = [\"docopt\"]\n");
+    std::fs::write(&manifest, contents).unwrap();
+
+    let call = process::Command::new("target/debug/cargo-upgrade")
+        .args(&["upgrade", "--report", "markdown"])
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    let stdout = String::from_utf8_lossy(&call.stdout);
+    assert!(stdout.contains(
+        "docopt 0.8 in cargo-list-test-fixture, listed in `ignore-upgrades` \
+         (docopt--CURRENT_VERSION_TEST is available)"
+    ));
+    assert!(stdout.contains("- `docopt` `0.8` (`docopt--CURRENT_VERSION_TEST` is available)"));
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["docopt"].as_str(),
+        Some("0.8")
+    );
+
+    // Named crates are upgraded anyway.
+    execute_command(&["upgrade", "docopt"], &manifest);
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["docopt"].as_str(),
+        Some("docopt--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn upgrade_prints_markdown_report() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+
+    let call = process::Command::new("target/debug/cargo-upgrade")
+        .args(&["upgrade", "--report", "markdown"])
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    let stdout = String::from_utf8_lossy(&call.stdout);
+    assert!(stdout.contains("| Crate | From | To | Links |\n|---|---|---|---|\n"));
+    assert!(stdout.contains(
+        "| `docopt` | `0.8` | `docopt--CURRENT_VERSION_TEST` | \
+         [diff](https://diff.rs/docopt/0.8/docopt--CURRENT_VERSION_TEST) · \
+         [docs](https://docs.rs/docopt/docopt--CURRENT_VERSION_TEST) |\n"
+    ));
+}
+
+#[test]
+fn upgrade_interactively_picked() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+    execute_command(&["add", "pad", "--vers", "0.1.2"], &manifest);
+    execute_command(&["add", "serde", "--vers", "1.0"], &manifest);
+
+    let mut call = process::Command::new("target/debug/cargo-upgrade")
+        .args(&["upgrade", "--interactive", "docopt@0.9", "pad@0.1.4", "serde@1.2"])
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    call.stdin.as_mut().unwrap().write_all(b"1 3\n").unwrap();
+    let output = call.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(
+        "Patch upgrades:\n   1) pad 0.1.2 -> 0.1.4\nMinor upgrades:\n   2) serde 1.0 -> 1.2\n\
+         Breaking upgrades:\n   3) docopt 0.8 -> 0.9\n"
+    ));
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["pad"].as_str(), Some("0.1.4"));
+    assert_eq!(toml["dependencies"]["serde"].as_str(), Some("1.0"));
+    assert_eq!(toml["dependencies"]["docopt"].as_str(), Some("0.9"));
+}
+
+#[test]
+fn upgrade_workspace_consolidate() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    execute_command(&["add", "libc@0.2.30"], &workspace_manifests[0]);
+
+    execute_command(&["upgrade", "--all", "--consolidate"], &root_manifest);
+
+    // All members now require the highest version any of them did, without a lookup.
+    for workspace_member in workspace_manifests {
+        assert_eq!(
+            get_toml(&workspace_member)["dependencies"]["libc"].as_str(),
+            Some("0.2.30")
+        );
+    }
+}
+
+#[test]
+fn upgrade_workspace_consolidate_hoist() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    execute_command(&["add", "libc@0.2.30"], &workspace_manifests[1]);
+
+    execute_command(&["upgrade", "--all", "--consolidate", "--hoist"], &root_manifest);
+
+    assert_eq!(
+        get_toml(&root_manifest)["workspace"]["dependencies"]["libc"].as_str(),
+        Some("0.2.30")
+    );
+    for workspace_member in workspace_manifests {
+        let toml = get_toml(&workspace_member);
+        let libc = &toml["dependencies"]["libc"];
+        assert_eq!(libc["workspace"].as_bool(), Some(true));
+        assert!(libc["version"].is_none());
+    }
+}
+
+#[test]
+fn hoist_requires_consolidate() {
+    let (_tmpdir, root_manifest, _workspace_manifests) = copy_workspace_test();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--all",
+        "--hoist",
+        "--manifest-path",
+        &root_manifest,
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--hoist` can only be used together with \
+             `--consolidate`",
+        )
+        .unwrap();
+}
+
+#[test]
+fn upgrade_skips_git_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+    // Cargo does not accept a crate from different sources, so the git one is another crate.
+    execute_command(
+        &[
+            "add",
+            "pad",
+            "--vers",
+            "0.1",
+            "--git",
+            "http://localhost/pad.git",
+            "--dev",
+        ],
+        &manifest,
+    );
+
+    execute_command(&["upgrade", "docopt", "pad", "--skip-git"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["docopt"].as_str(),
+        Some("docopt--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(toml["dev-dependencies"]["pad"]["version"].as_str(), Some("0.1"));
+}
+
+#[test]
+fn upgrade_git_revisions_to_head() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let (repo, _) = clone_out_test("tests/fixtures/add/local/Cargo.toml.sample");
+    let url = commit_git_repository(repo.path());
+    let head = process::Command::new("git")
+        .arg("-C")
+        .arg(repo.path())
+        .args(&["rev-parse", "HEAD"])
+        .output()
+        .unwrap()
+        .stdout;
+    let head = String::from_utf8_lossy(&head).trim().to_owned();
+    execute_command(
+        &["add", "foo-crate", "--git", &url, "--rev", "0123456"],
+        &manifest,
+    );
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+
+    execute_command(&["upgrade", "--git-only", "--to-rev", "head"], &manifest);
+
+    // The abbreviated revision stays abbreviated, and crates.io dependencies are left alone.
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["foo-crate"]["rev"].as_str(),
+        Some(&head[..7])
+    );
+    assert_eq!(toml["dependencies"]["docopt"].as_str(), Some("0.8"));
+}
+
+#[test]
+fn upgrade_git_revisions_to_branch_with_registry_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let (repo, _) = clone_out_test("tests/fixtures/add/local/Cargo.toml.sample");
+    let url = commit_git_repository(repo.path());
+    let git = |args: &[&str]| {
+        let output = process::Command::new("git")
+            .arg("-C")
+            .arg(repo.path())
+            .args(&["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "`git {}` failed", args.join(" "));
+        String::from_utf8_lossy(&output.stdout).trim().to_owned()
+    };
+    let first = git(&["rev-parse", "HEAD"]);
+    git(&["checkout", "--quiet", "-b", "feature"]);
+    git(&["commit", "--quiet", "--allow-empty", "-m", "Feature"]);
+    let feature = git(&["rev-parse", "HEAD"]);
+    git(&["checkout", "--quiet", "-"]);
+
+    execute_command(
+        &["add", "foo-crate", "--git", &url, "--rev", &first],
+        &manifest,
+    );
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+
+    let call = process::Command::new("target/debug/cargo-upgrade")
+        .args(&["upgrade", "--update-revs", "--to-rev", "branch:feature"])
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+    assert!(call.status.success());
+    assert!(String::from_utf8_lossy(&call.stdout).contains(&format!(
+        "foo-crate {} -> {}",
+        &first[..7],
+        &feature[..7]
+    )));
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["foo-crate"]["rev"].as_str(),
+        Some(feature.as_str())
+    );
+    assert_eq!(
+        toml["dependencies"]["docopt"].as_str(),
+        Some("docopt--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn git_only_excludes_skip_git() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--git-only",
+        "--skip-git",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--git-only` can not be combined with \
+             `--skip-git`",
+        )
+        .unwrap();
+}
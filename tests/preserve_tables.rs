@@ -0,0 +1,110 @@
+//! Regression corpus: editing dependencies must never disturb `[profile.*]` and `[lints.*]`.
+use std::fs;
+
+mod utils;
+use utils::{clone_out_test, execute_command, get_toml};
+
+/// toml_edit writes nested tables right after their parent, so the `[profile.*]` tables of a
+/// fixture are kept together; the dependency sections may come between them and `[lints.*]`.
+const FIXTURES: &[&str] = &[
+    "tests/fixtures/preserve/Cargo.toml.tail",
+    "tests/fixtures/preserve/Cargo.toml.interleaved",
+];
+
+/// Get the `[profile.*]` and `[lints.*]` tables of a manifest, each including the comments in
+/// front of its header, in the order they appear.
+fn preserved_tables(manifest: &str) -> Vec<String> {
+    let mut tables = Vec::new();
+    let mut current: Option<String> = None;
+    // Comment lines belong to whatever follows them.
+    let mut comments = String::new();
+
+    for line in manifest.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+            comments.push_str(line);
+            comments.push('\n');
+            continue;
+        }
+
+        if trimmed.starts_with('[') {
+            tables.extend(current.take());
+            if trimmed.starts_with("[profile") || trimmed.starts_with("[lints") {
+                current = Some(String::new());
+            }
+        }
+        if let Some(ref mut table) = current {
+            table.push_str(&comments);
+            table.push_str(line);
+            table.push('\n');
+        }
+        comments.clear();
+    }
+    tables.extend(current);
+
+    // Trailing blank lines depend on what follows a table, which may change.
+    tables
+        .into_iter()
+        .map(|table| table.trim_end().to_owned())
+        .collect()
+}
+
+fn read(path: &str) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+/// Run `command` against each fixture and check the preserved tables did not change.
+fn check_preserved(command: &[&str]) {
+    for fixture in FIXTURES {
+        let (_tmpdir, manifest) = clone_out_test(fixture);
+        let before = preserved_tables(&read(&manifest));
+        assert!(!before.is_empty());
+
+        execute_command(command, &manifest);
+
+        assert!(!get_toml(&manifest)["profile"].is_none());
+        let after = preserved_tables(&read(&manifest));
+        assert_eq!(before, after, "`{}` disturbed {}", command.join(" "), fixture);
+    }
+}
+
+#[test]
+fn add_to_existing_section_preserves_tables() {
+    check_preserved(&["add", "regex", "--vers=0.2"]);
+}
+
+#[test]
+fn add_new_section_preserves_tables() {
+    check_preserved(&["add", "regex", "--dev", "--vers=0.2"]);
+}
+
+#[test]
+fn add_new_target_section_preserves_tables() {
+    check_preserved(&["add", "winapi", "--target=cfg(windows)", "--vers=0.3"]);
+}
+
+#[test]
+fn add_new_section_with_placement_preserves_tables() {
+    check_preserved(&[
+        "add",
+        "regex",
+        "--dev",
+        "--vers=0.2",
+        "--section-placement=after-dependencies",
+    ]);
+}
+
+#[test]
+fn add_with_features_preserves_tables() {
+    check_preserved(&["add", "pad", "--vers=0.1", "--features=std"]);
+}
+
+#[test]
+fn rm_preserves_tables() {
+    check_preserved(&["rm", "docopt"]);
+}
+
+#[test]
+fn upgrade_preserves_tables() {
+    check_preserved(&["upgrade"]);
+}
@@ -1,26 +1,11 @@
+extern crate cargo_edit;
 extern crate tempdir;
-extern crate toml_edit;
 
 use std::{fs, process};
 use std::ffi::OsStr;
-use std::io::prelude::*;
-
-/// Create temporary working directory with Cargo.toml manifest
-pub fn clone_out_test(source: &str) -> (tempdir::TempDir, String) {
-    let tmpdir =
-        tempdir::TempDir::new("cargo-edit-test").expect("failed to construct temporary directory");
-    fs::copy(source, tmpdir.path().join("Cargo.toml"))
-        .unwrap_or_else(|err| panic!("could not copy test manifest: {}", err));
-    let path = tmpdir
-        .path()
-        .join("Cargo.toml")
-        .to_str()
-        .unwrap()
-        .to_string()
-        .clone();
-
-    (tmpdir, path)
-}
+use std::path::Path;
+
+pub use self::cargo_edit::test_support::{clone_out_test, get_toml};
 
 /// Execute localc cargo command, includes `--manifest-path`
 pub fn execute_command<S>(command: &[S], manifest: &str)
@@ -44,10 +29,105 @@ where
     }
 }
 
-/// Parse a manifest file as TOML
-pub fn get_toml(manifest_path: &str) -> toml_edit::Document {
-    let mut f = fs::File::open(manifest_path).unwrap();
-    let mut s = String::new();
-    f.read_to_string(&mut s).unwrap();
-    s.parse().expect("toml parse error")
+/// Helper function that copies the workspace test into a temporary directory.
+#[allow(dead_code)]
+pub fn copy_workspace_test() -> (tempdir::TempDir, String, Vec<String>) {
+    // Create a temporary directory and copy in the root manifest, the dummy rust file, and
+    // workspace member manifests.
+    let tmpdir = cargo_edit::test_support::temp_dir();
+
+    let (root_manifest_path, workspace_manifest_paths) = {
+        // Helper to copy in files to the temporary workspace. The standard library doesn't have a
+        // good equivalent of `cp -r`, hence this oddity.
+        let copy_in = |dir, file| {
+            let file_path = tmpdir
+                .path()
+                .join(dir)
+                .join(file)
+                .to_str()
+                .unwrap()
+                .to_string();
+
+            fs::create_dir_all(tmpdir.path().join(dir)).unwrap();
+
+            fs::copy(
+                format!("tests/fixtures/workspace/{}/{}", dir, file),
+                &file_path,
+            ).unwrap_or_else(|err| panic!("could not copy test file: {}", err));
+
+            file_path
+        };
+
+        let root_manifest_path = copy_in(".", "Cargo.toml");
+        copy_in(".", "dummy.rs");
+
+        let workspace_manifest_paths = ["one", "two", "implicit/three", "explicit/four"]
+            .iter()
+            .map(|member| copy_in(member, "Cargo.toml"))
+            .collect::<Vec<_>>();
+
+        (root_manifest_path, workspace_manifest_paths)
+    };
+
+    (
+        tmpdir,
+        root_manifest_path,
+        workspace_manifest_paths.to_owned(),
+    )
+}
+
+/// Add a package in a `fuzz` directory next to the root manifest of a workspace from
+/// `copy_workspace_test`, which the workspace excludes. Returns the path of its manifest.
+#[allow(dead_code)]
+pub fn add_excluded_package(root_manifest: &str) -> String {
+    let root = Path::new(root_manifest).parent().unwrap();
+    let mut contents = fs::read_to_string(root_manifest).unwrap();
+    contents.push_str("\nexclude = [\"fuzz\"]\n");
+    fs::write(root_manifest, contents).unwrap();
+
+    fs::create_dir_all(root.join("fuzz")).unwrap();
+    let manifest = root.join("fuzz/Cargo.toml");
+    fs::write(
+        &manifest,
+        r#"[package]
+name = "fuzz-targets"
+version = "0.0.1"
+
+[lib]
+path = "../dummy.rs"
+
+[dependencies]
+libc = "0.2.28"
+"#,
+    ).unwrap();
+    manifest.to_str().unwrap().to_string()
+}
+
+/// Commit everything in `dir` to a new git repository there, and return its `file://` URL.
+#[allow(dead_code)]
+pub fn commit_git_repository(dir: &Path) -> String {
+    let commands: &[&[&str]] = &[
+        &["init", "--quiet"],
+        &["add", "."],
+        &[
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "--quiet",
+            "-m",
+            "Initial commit",
+        ],
+    ];
+    for args in commands {
+        let status = process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(*args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "`git {}` failed", args.join(" "));
+    }
+    format!("file://{}", dir.display())
 }